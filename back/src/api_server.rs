@@ -0,0 +1,260 @@
+//! Optional local API server driving the backend from other tools.
+//!
+//! `POST /api/<command>` runs a command of the `commands` table with the same JSON
+//! arguments as `invoke` (snake_case or camelCase keys) and returns its result.
+//! `GET /api/events?job_id=&after_seq=` is a WebSocket streaming job events,
+//! replayed from `after_seq` first. Every request needs `Authorization: Bearer <token>`.
+
+use crate::commands::{self, CommandError};
+use crate::config::CONFIG;
+use crate::job_events::{job_events, JobEvent};
+use crate::services::local_cache;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use log::{error, info, warn};
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::broadcast::error::RecvError;
+
+const TOKEN_NAMESPACE: &str = "api_server";
+const TOKEN_KEY: &str = "token";
+
+#[derive(Clone)]
+struct ApiState {
+    app: AppHandle,
+    token: Arc<String>,
+}
+
+#[derive(Debug)]
+enum ApiError {
+    Unauthorized,
+    UnknownCommand(String),
+    BadRequest(String),
+    /// The command ran and returned an error
+    Failed(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
+            ApiError::UnknownCommand(command) => (
+                StatusCode::NOT_FOUND,
+                format!("unknown command {}", command),
+            ),
+            ApiError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            ApiError::Failed(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+        };
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+impl From<CommandError> for ApiError {
+    fn from(e: CommandError) -> Self {
+        match e {
+            CommandError::UnknownCommand(command) => ApiError::UnknownCommand(command),
+            CommandError::BadRequest(e) => ApiError::BadRequest(e),
+            CommandError::Failed(e) => ApiError::Failed(e),
+        }
+    }
+}
+
+/// Configured token, or one generated on first start and kept in the data dir
+fn api_token() -> anyhow::Result<String> {
+    if !CONFIG.api_server.token.expose().is_empty() {
+        return Ok(CONFIG.api_server.token.expose().to_string());
+    }
+    if let Some(token) = local_cache::load::<String>(TOKEN_NAMESPACE, TOKEN_KEY) {
+        return Ok(token);
+    }
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    local_cache::store(TOKEN_NAMESPACE, TOKEN_KEY, &token)?;
+    info!(
+        "Generated API server token in {}",
+        local_cache::namespace_dir(TOKEN_NAMESPACE).display()
+    );
+    Ok(token)
+}
+
+/// Compare without leaking where the first difference is
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn authorize(token: &str, headers: &HeaderMap) -> Result<(), ApiError> {
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+async fn invoke(
+    State(state): State<ApiState>,
+    Path(command): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<Value>>,
+) -> Result<Json<Value>, ApiError> {
+    authorize(&state.token, &headers)?;
+    let args = body.map(|Json(args)| args).unwrap_or(Value::Null);
+    let result = commands::dispatch(&state.app, &command, &args)
+        .await
+        .map_err(ApiError::from);
+    if let Err(ApiError::Failed(e)) = &result {
+        warn!("API command {} failed: {}", command, e);
+    }
+    result.map(Json)
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    job_id: Option<String>,
+    after_seq: Option<u64>,
+}
+
+async fn events(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    authorize(&state.token, &headers)?;
+    Ok(ws.on_upgrade(move |socket| {
+        stream_events(socket, query.job_id, query.after_seq.unwrap_or(0))
+    }))
+}
+
+async fn send_event(socket: &mut WebSocket, event: &JobEvent) -> Result<(), ()> {
+    let json = serde_json::to_string(event).map_err(|_| ())?;
+    socket.send(Message::Text(json)).await.map_err(|_| ())
+}
+
+/// Replay events after `last_seq`, then follow live ones
+async fn stream_events(mut socket: WebSocket, job_id: Option<String>, mut last_seq: u64) {
+    // Subscribe before replaying so no event falls between the two
+    let mut live = job_events().subscribe();
+    let mut backlog = job_events().replay(job_id.as_deref(), last_seq);
+
+    loop {
+        for event in backlog.drain(..) {
+            last_seq = event.seq;
+            if send_event(&mut socket, &event).await.is_err() {
+                return;
+            }
+        }
+
+        tokio::select! {
+            received = live.recv() => match received {
+                Ok(event) => {
+                    let wanted = job_id.as_deref().is_none_or(|id| event.job_id == id);
+                    if wanted && event.seq > last_seq {
+                        backlog.push(event);
+                    }
+                }
+                // Missed live events are still in the history
+                Err(RecvError::Lagged(_)) => {
+                    backlog = job_events().replay(job_id.as_deref(), last_seq);
+                }
+                Err(RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn serve(app: AppHandle) -> anyhow::Result<()> {
+    let addr: SocketAddr = CONFIG
+        .api_server
+        .bind_addr
+        .parse()
+        .map_err(|e| anyhow::anyhow!("invalid API_SERVER_ADDR: {}", e))?;
+    if !addr.ip().is_loopback() {
+        anyhow::bail!("API server only binds to localhost, not {}", addr);
+    }
+
+    let state = ApiState {
+        app,
+        token: Arc::new(api_token()?),
+    };
+    let router = Router::new()
+        .route("/api/events", get(events))
+        .route("/api/:command", post(invoke))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("API server listening on http://{}", addr);
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// Start the API server in the background when enabled in the config
+pub fn start(app: &AppHandle) {
+    if !CONFIG.api_server.enabled {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(app).await {
+            error!("API server stopped: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn headers(authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = authorization {
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn authorize_needs_the_exact_bearer_token() {
+        assert!(authorize(TOKEN, &headers(Some(&format!("Bearer {}", TOKEN)))).is_ok());
+
+        for rejected in [
+            None,
+            Some(TOKEN),
+            Some("Bearer "),
+            Some("Bearer 0123456789abcde"),
+            Some("Bearer 0123456789abcdeF"),
+            Some("Bearer 0123456789abcdef0"),
+            Some("bearer 0123456789abcdef"),
+            Some("Basic 0123456789abcdef"),
+        ] {
+            assert!(
+                matches!(
+                    authorize(TOKEN, &headers(rejected)),
+                    Err(ApiError::Unauthorized)
+                ),
+                "{:?} was accepted",
+                rejected
+            );
+        }
+    }
+}
@@ -0,0 +1,663 @@
+//! Headless access to the service layer, for scripts and servers without a GUI.
+//!
+//! Reads the same environment config and data dir (keystore, caches) as the app.
+
+mod output;
+
+use app_lib::{
+    job_queue::signer_pubkey,
+    scheduler::{self as schedules, Schedule},
+    services::{
+        self,
+        balance_snapshot::GroupBalanceSnapshot,
+        common::{
+            CreateTokenReq, CreateTokenTransactionRes, QuickBuyReq, QuickSellReq, TransactionRes,
+        },
+        create_meme_token, keystore, launch_preview,
+        launch_preview::LaunchPlan,
+        local_cache,
+    },
+};
+use clap::{Parser, Subcommand};
+use output::{fields, opt, utc, Format, Table};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{signature::Keypair, signer::Signer};
+use std::fs;
+use std::future::Future;
+use std::io::BufRead;
+use std::process::ExitCode;
+
+const JOBS_NAMESPACE: &str = "cli_jobs";
+const KEYSTORE_PASSWORD_ENV: &str = "MEMECORE_KEYSTORE_PASSWORD";
+
+const KEY_HELP: &str = "env:VAR, file:PATH (JSON byte array or base58), keystore:PUBKEY \
+                        (password from MEMECORE_KEYSTORE_PASSWORD) or - to read a line of stdin; \
+                        bare keys are refused as they end up in shell history";
+
+#[derive(Parser)]
+#[command(
+    name = "memecore-cli",
+    version,
+    about = "Headless wallet and token operations"
+)]
+struct Cli {
+    /// Output format
+    #[arg(long, short, value_enum, global = true, default_value = "table")]
+    output: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// SOL and token balances of wallets
+    Balances {
+        /// Wallet public keys
+        #[arg(required = true)]
+        wallets: Vec<String>,
+        #[arg(long)]
+        max_concurrency: Option<usize>,
+    },
+    /// Split SOL from one wallet across many
+    Distribute {
+        #[arg(long, help = KEY_HELP)]
+        from: String,
+        /// Total SOL to split
+        #[arg(long)]
+        amount: f64,
+        /// Destination public keys
+        #[arg(required = true)]
+        wallets: Vec<String>,
+    },
+    /// Send the SOL of wallets back to one address
+    Refund {
+        /// Destination public key
+        #[arg(long)]
+        to: String,
+        /// SOL to send from each wallet (everything but the fee reserve by default)
+        #[arg(long)]
+        amount: Option<f64>,
+        #[arg(required = true, help = KEY_HELP)]
+        keys: Vec<String>,
+    },
+    /// Close empty token accounts and reclaim their rent
+    CloseAccounts {
+        #[arg(help = KEY_HELP)]
+        key: String,
+        /// Rent destination (the wallet itself by default)
+        #[arg(long)]
+        rent_to: Option<String>,
+    },
+    /// Burn a share of a token balance
+    Burn {
+        #[arg(help = KEY_HELP)]
+        key: String,
+        #[arg(long)]
+        mint: String,
+        /// Percentage of the balance to burn
+        #[arg(long, default_value_t = 100.0)]
+        percent: f64,
+    },
+    /// Buy a token on its bonding curve
+    Buy {
+        #[arg(help = KEY_HELP)]
+        key: String,
+        #[arg(long)]
+        mint: String,
+        /// SOL to spend
+        #[arg(long)]
+        amount: f64,
+        #[arg(long)]
+        slippage_bps: Option<u16>,
+    },
+    /// Sell a share of a token balance
+    Sell {
+        #[arg(help = KEY_HELP)]
+        key: String,
+        #[arg(long)]
+        mint: String,
+        #[arg(long, default_value_t = 100)]
+        percent: u8,
+        #[arg(long)]
+        slippage_bps: Option<u64>,
+    },
+    /// Preview or launch a token from a create request JSON file
+    #[command(subcommand)]
+    Create(CreateCommand),
+    /// Jobs run by this CLI, and schedules of the app
+    #[command(subcommand)]
+    Jobs(JobsCommand),
+}
+
+#[derive(Subcommand)]
+enum CreateCommand {
+    /// Pin the image, estimate costs and print the plan to confirm
+    Preview {
+        /// Create request JSON file
+        #[arg(long)]
+        request: String,
+    },
+    /// Confirm a previewed plan and launch
+    Launch {
+        #[arg(long)]
+        request: String,
+        #[arg(long)]
+        plan_id: String,
+        /// Plan hash printed by the preview, confirming it was reviewed
+        #[arg(long)]
+        plan_hash: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum JobsCommand {
+    /// Jobs run by this CLI, newest first
+    List {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// One job run by this CLI
+    Status { job_id: String },
+    /// Scheduled and recurring jobs of the app
+    Schedules,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum CliJobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Record of an operation run by the CLI, kept in the data dir
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CliJob {
+    job_id: String,
+    command: String,
+    status: CliJobStatus,
+    started_at: i64,
+    finished_at: Option<i64>,
+    result: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+fn job_table(jobs: &[CliJob]) -> Table {
+    Table::new(&["job", "command", "status", "started", "finished", "error"]).rows(jobs.iter().map(
+        |job| {
+            vec![
+                job.job_id.clone(),
+                job.command.clone(),
+                format!("{:?}", job.status).to_lowercase(),
+                utc(job.started_at),
+                job.finished_at.map(utc).unwrap_or_else(|| "-".to_string()),
+                opt(&job.error),
+            ]
+        },
+    ))
+}
+
+fn store_job(job: &CliJob) {
+    if let Err(e) = local_cache::store(JOBS_NAMESPACE, &job.job_id, job) {
+        log::warn!("Failed to record job {}: {:#}", job.job_id, e);
+    }
+}
+
+/// Transaction failures of buy and sell, as errors
+fn checked(res: TransactionRes, action: &str) -> Result<TransactionRes, String> {
+    if res.ok {
+        Ok(res)
+    } else {
+        Err(res.error.unwrap_or_else(|| format!("{} failed", action)))
+    }
+}
+
+/// Run an operation as a recorded job, so `jobs status` can report it later
+async fn run_job<T, F>(command: &str, operation: F) -> Result<T, String>
+where
+    T: Serialize,
+    F: Future<Output = Result<T, String>>,
+{
+    let mut job = CliJob {
+        job_id: format!(
+            "cli_{}_{}",
+            command.replace('-', "_"),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ),
+        command: command.to_string(),
+        status: CliJobStatus::Running,
+        started_at: chrono::Utc::now().timestamp(),
+        finished_at: None,
+        result: None,
+        error: None,
+    };
+    store_job(&job);
+    eprintln!("job {}", job.job_id);
+
+    let result = operation.await;
+    job.finished_at = Some(chrono::Utc::now().timestamp());
+    match &result {
+        Ok(value) => {
+            job.status = CliJobStatus::Completed;
+            job.result = serde_json::to_value(value).ok();
+        }
+        Err(e) => {
+            job.status = CliJobStatus::Failed;
+            job.error = Some(e.clone());
+        }
+    }
+    store_job(&job);
+    result
+}
+
+fn list_jobs() -> Vec<CliJob> {
+    let Ok(entries) = fs::read_dir(local_cache::namespace_dir(JOBS_NAMESPACE)) else {
+        return vec![];
+    };
+    let mut jobs: Vec<CliJob> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            local_cache::load(JOBS_NAMESPACE, path.file_stem()?.to_str()?)
+        })
+        .collect();
+    jobs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    jobs
+}
+
+fn keystore_password() -> Result<String, String> {
+    std::env::var(KEYSTORE_PASSWORD_ENV)
+        .map_err(|_| format!("{} is not set", KEYSTORE_PASSWORD_ENV))
+}
+
+/// Base58 private key from a key argument (see KEY_HELP)
+fn resolve_key(spec: &str) -> Result<String, String> {
+    resolve_key_from(spec, &mut std::io::stdin().lock())
+}
+
+/// `resolve_key` reading `-` keys from `stdin`, one line each
+fn resolve_key_from(spec: &str, stdin: &mut dyn BufRead) -> Result<String, String> {
+    if spec == "-" {
+        let mut line = String::new();
+        stdin
+            .read_line(&mut line)
+            .map_err(|e| format!("stdin: {}", e))?;
+        let key = line.trim();
+        if key.is_empty() {
+            return Err("stdin: no key to read".to_string());
+        }
+        return Ok(key.to_string());
+    }
+    if let Some(var) = spec.strip_prefix("env:") {
+        return std::env::var(var).map_err(|_| format!("{} is not set", var));
+    }
+    if let Some(path) = spec.strip_prefix("file:") {
+        let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let raw = raw.trim();
+        if raw.starts_with('[') {
+            let bytes: Vec<u8> = serde_json::from_str(raw)
+                .map_err(|e| format!("{}: not a JSON byte array: {}", path, e))?;
+            let keypair = Keypair::try_from(&bytes[..])
+                .map_err(|e| format!("{}: invalid keypair: {}", path, e))?;
+            return Ok(keypair.to_base58_string());
+        }
+        return Ok(raw.to_string());
+    }
+    if let Some(pubkey) = spec.strip_prefix("keystore:") {
+        let keypair = keystore::load_keypair(pubkey, &keystore_password()?)
+            .map_err(|e| format!("{:#}", e))?;
+        return Ok(keypair.to_base58_string());
+    }
+    // Never echo the argument: it is most likely a private key
+    Err("key must be env:VAR, file:PATH, keystore:PUBKEY or -, \
+         bare private keys are refused"
+        .to_string())
+}
+
+fn read_request(path: &str) -> Result<CreateTokenReq, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("{}: invalid create request: {}", path, e))
+}
+
+#[derive(Serialize)]
+/// Summary message of a transfer, close or burn
+struct Outcome {
+    result: String,
+}
+
+fn outcome_table(res: &Outcome) -> Table {
+    fields([("result", res.result.clone())])
+}
+
+fn plan_table(plan: &LaunchPlan) -> Table {
+    let mut table = fields([
+        ("plan_id", plan.plan_id.clone()),
+        ("plan_hash", plan.plan_hash.clone()),
+        (
+            "token",
+            format!(
+                "{} ({})",
+                plan.launch_metadata.name, plan.launch_metadata.symbol
+            ),
+        ),
+        ("image", plan.image.uri.clone()),
+        ("dev_wallet", plan.dev_wallet.clone()),
+        (
+            "dev_wallet_balance_sol",
+            format!("{:.6}", plan.dev_wallet_balance_lamports as f64 / 1e9),
+        ),
+        (
+            "dev_wallet_needs_sol",
+            format!("{:.6}", plan.cost.dev_wallet_total_lamports as f64 / 1e9),
+        ),
+        ("total_cost_sol", format!("{:.6}", plan.cost.total_sol)),
+        ("expires", utc(plan.expires_at)),
+    ]);
+    for warning in &plan.warnings {
+        table = table.row(["warning".to_string(), warning.clone()]);
+    }
+    table
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let format = cli.output;
+    match cli.command {
+        Command::Balances {
+            wallets,
+            max_concurrency,
+        } => {
+            let snapshot = run_job("balances", async {
+                services::balance_snapshot::get_group_balance_snapshot(
+                    wallets,
+                    max_concurrency,
+                    None,
+                )
+                .await
+            })
+            .await?;
+            output::print(format, &snapshot, |s: &GroupBalanceSnapshot| {
+                Table::new(&["wallet", "sol", "tokens", "error"])
+                    .rows(s.wallets.iter().map(|w| {
+                        vec![
+                            w.wallet.clone(),
+                            format!("{:.6}", w.sol),
+                            w.tokens.len().to_string(),
+                            opt(&w.error),
+                        ]
+                    }))
+                    .row([
+                        "total".to_string(),
+                        format!("{:.6}", s.total_sol),
+                        s.mint_totals.len().to_string(),
+                        String::new(),
+                    ])
+            });
+        }
+        Command::Distribute {
+            from,
+            amount,
+            wallets,
+        } => {
+            let source = resolve_key(&from)?;
+            let result = run_job("distribute", async {
+                services::distribute_sol::distribute_sol(source, wallets, amount, None).await
+            })
+            .await?;
+            output::print(format, &Outcome { result }, outcome_table);
+        }
+        Command::Refund { to, amount, keys } => {
+            let pks = keys
+                .iter()
+                .map(|key| resolve_key(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            let result = run_job("refund", async {
+                match amount {
+                    Some(amount) => {
+                        services::refund_wallets::refunds_amount_to(pks, to, amount, None).await
+                    }
+                    None => {
+                        services::refund_wallets::refunds_to(pks, to, String::new(), None).await
+                    }
+                }
+            })
+            .await?;
+            output::print(format, &Outcome { result }, outcome_table);
+        }
+        Command::CloseAccounts { key, rent_to } => {
+            let pk = resolve_key(&key)?;
+            let result = run_job("close-accounts", async {
+                services::close_accounts::close_accounts(pk, rent_to).await
+            })
+            .await?;
+            output::print(format, &Outcome { result }, outcome_table);
+        }
+        Command::Burn { key, mint, percent } => {
+            let pk = resolve_key(&key)?;
+            let result = run_job("burn", async {
+                services::burn_tokens::burn_tokens(pk, mint, percent).await
+            })
+            .await?;
+            output::print(format, &Outcome { result }, outcome_table);
+        }
+        Command::Buy {
+            key,
+            mint,
+            amount,
+            slippage_bps,
+        } => {
+            let req = QuickBuyReq {
+                pk: resolve_key(&key)?.into(),
+                amount_sol: amount,
+                mint,
+                slippage_bps,
+            };
+            let res = run_job("buy", async {
+                checked(services::quick_buy::quick_buy(req).await?, "buy")
+            })
+            .await?;
+            output::print(format, &res, |r| fields([("tx_sig", opt(&r.tx_sig))]));
+        }
+        Command::Sell {
+            key,
+            mint,
+            percent,
+            slippage_bps,
+        } => {
+            let pk = resolve_key(&key)?;
+            let req = QuickSellReq {
+                wallet: signer_pubkey(&pk).ok_or("invalid private key")?,
+                pk: pk.into(),
+                percent,
+                mint,
+                slippage_bps,
+            };
+            let res = run_job("sell", async {
+                checked(services::quick_sell::quick_sell(req).await, "sell")
+            })
+            .await?;
+            output::print(format, &res, |r| fields([("tx_sig", opt(&r.tx_sig))]));
+        }
+        Command::Create(CreateCommand::Preview { request }) => {
+            let req = read_request(&request)?;
+            let plan = launch_preview::preview_launch(req).await?;
+            output::print(format, &plan, plan_table);
+            eprintln!(
+                "Review the plan, then launch with: create launch --request {} --plan-id {} --plan-hash {}",
+                request, plan.plan_id, plan.plan_hash
+            );
+        }
+        Command::Create(CreateCommand::Launch {
+            request,
+            plan_id,
+            plan_hash,
+        }) => {
+            let mut req = read_request(&request)?;
+            launch_preview::confirm_launch(plan_id.clone(), plan_hash)?;
+            req.plan_id = Some(plan_id);
+            let password = req
+                .vanity_mint
+                .as_ref()
+                .map(|_| keystore_password())
+                .transpose()?;
+            let mint_keypair = create_meme_token::mint_keypair_for(&req, password.as_deref())
+                .map_err(|e| format!("{:#}", e))?;
+            let mint = mint_keypair.pubkey().to_string();
+            let res = run_job("create", async {
+                create_meme_token::create_meme_token(req, mint_keypair)
+                    .await
+                    .map(|tx_sig| CreateTokenTransactionRes {
+                        ok: true,
+                        tx_sig: Some(tx_sig),
+                        mint: Some(mint),
+                        error: None,
+                    })
+                    .map_err(|e| format!("{:#}", e))
+            })
+            .await?;
+            output::print(format, &res, |r| {
+                fields([("mint", opt(&r.mint)), ("tx_sig", opt(&r.tx_sig))])
+            });
+        }
+        Command::Jobs(JobsCommand::List { limit }) => {
+            let jobs: Vec<CliJob> = list_jobs().into_iter().take(limit).collect();
+            output::print(format, &jobs, |jobs| job_table(jobs));
+        }
+        Command::Jobs(JobsCommand::Status { job_id }) => {
+            let job: CliJob = local_cache::load(JOBS_NAMESPACE, &job_id)
+                .ok_or_else(|| format!("Unknown job {}", job_id))?;
+            output::print(format, &job, |job| {
+                let mut table = job_table(std::slice::from_ref(job));
+                if let Some(result) = &job.result {
+                    table = table.row(["result".to_string(), result.to_string()]);
+                }
+                table
+            });
+        }
+        Command::Jobs(JobsCommand::Schedules) => {
+            let schedules = schedules::scheduler().list();
+            output::print(format, &schedules, |schedules: &Vec<Schedule>| {
+                Table::new(&[
+                    "schedule", "name", "enabled", "jobs", "next run", "last run",
+                ])
+                .rows(schedules.iter().map(|s| {
+                    vec![
+                        s.id.clone(),
+                        s.name.clone(),
+                        s.enabled.to_string(),
+                        s.job_kinds.join(","),
+                        s.next_run_at.map(utc).unwrap_or_else(|| "-".to_string()),
+                        s.runs
+                            .last()
+                            .map(|r| format!("{:?}", r.status).to_lowercase())
+                            .unwrap_or_else(|| "-".to_string()),
+                    ]
+                }))
+            });
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Logs go to stderr so stdout stays parseable
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn no_stdin() -> Cursor<&'static [u8]> {
+        Cursor::new(&[])
+    }
+
+    fn temp_file(contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("memecore-key-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn resolve_key_refuses_bare_and_unknown_keys_without_echoing_them() {
+        let key = Keypair::new().to_base58_string();
+        for spec in [key.as_str(), "", "stdin", "vault:abc"] {
+            let err = resolve_key_from(spec, &mut no_stdin()).unwrap_err();
+            assert!(err.contains("bare private keys are refused"));
+            assert!(!err.contains(&key));
+        }
+    }
+
+    #[test]
+    fn resolve_key_reads_env_vars() {
+        let key = Keypair::new().to_base58_string();
+        let var = format!("MEMECORE_TEST_KEY_{}", uuid::Uuid::new_v4().simple());
+        std::env::set_var(&var, &key);
+        assert_eq!(
+            resolve_key_from(&format!("env:{}", var), &mut no_stdin()),
+            Ok(key)
+        );
+        std::env::remove_var(&var);
+        assert!(resolve_key_from(&format!("env:{}", var), &mut no_stdin())
+            .unwrap_err()
+            .contains("is not set"));
+    }
+
+    #[test]
+    fn resolve_key_reads_base58_and_json_key_files() {
+        let keypair = Keypair::new();
+        let base58 = temp_file(&format!("{}\n", keypair.to_base58_string()));
+        let json = temp_file(&serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap());
+        let truncated = temp_file("[1, 2, 3]");
+
+        for path in [&base58, &json] {
+            assert_eq!(
+                resolve_key_from(&format!("file:{}", path), &mut no_stdin()),
+                Ok(keypair.to_base58_string())
+            );
+        }
+        assert!(
+            resolve_key_from(&format!("file:{}", truncated), &mut no_stdin())
+                .unwrap_err()
+                .contains("invalid keypair")
+        );
+        for path in [base58, json, truncated] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn resolve_key_reads_one_stdin_line_per_dash() {
+        let (first, second) = (Keypair::new(), Keypair::new());
+        let input = format!(
+            "{}\n  {}  \n",
+            first.to_base58_string(),
+            second.to_base58_string()
+        );
+        let mut stdin = Cursor::new(input.into_bytes());
+        assert_eq!(
+            resolve_key_from("-", &mut stdin),
+            Ok(first.to_base58_string())
+        );
+        assert_eq!(
+            resolve_key_from("-", &mut stdin),
+            Ok(second.to_base58_string())
+        );
+        assert_eq!(
+            resolve_key_from("-", &mut stdin),
+            Err("stdin: no key to read".to_string())
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+/// Rows printed with columns padded to their widest cell
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Table {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row<I: IntoIterator<Item = String>>(mut self, cells: I) -> Self {
+        self.rows.push(cells.into_iter().collect());
+        self
+    }
+
+    pub fn rows<I: IntoIterator<Item = Vec<String>>>(mut self, rows: I) -> Self {
+        self.rows.extend(rows);
+        self
+    }
+
+    fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.len()).collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(cell.chars().count());
+                }
+            }
+        }
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        let headers: Vec<String> = self.headers.iter().map(|h| h.to_string()).collect();
+        let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        let mut out = vec![line(&headers), line(&separator)];
+        out.extend(self.rows.iter().map(|row| line(row)));
+        out.join("\n")
+    }
+}
+
+/// Key/value table of a single result
+pub fn fields<I: IntoIterator<Item = (&'static str, String)>>(fields: I) -> Table {
+    Table::new(&["field", "value"]).rows(
+        fields
+            .into_iter()
+            .map(|(name, value)| vec![name.to_string(), value]),
+    )
+}
+
+/// Print `value` as pretty JSON, or as the table built from it
+pub fn print<T: Serialize>(format: Format, value: &T, table: impl FnOnce(&T) -> Table) {
+    match format {
+        Format::Json => match serde_json::to_string_pretty(value) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("error: failed to serialize output: {}", e),
+        },
+        Format::Table => println!("{}", table(value).render()),
+    }
+}
+
+pub fn opt(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".to_string())
+}
+
+pub fn utc(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
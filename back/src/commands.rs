@@ -0,0 +1,512 @@
+//! Commands of the desktop UI and the local API server.
+//!
+//! `dispatch` is the one table of commands: the Tauri IPC handler and
+//! `POST /api/<command>` both run it with the JSON arguments `invoke` sends
+//! (snake_case or camelCase keys), so a command only has to be added here.
+
+use crate::job_commands;
+use crate::job_events::job_events;
+use crate::job_queue::job_scheduler;
+use crate::jobs::JobManager;
+use crate::scheduler;
+use crate::services::{
+    self,
+    common::{CreateTokenReq, QuickBuyReq, QuickSellReq},
+    create_meme_token,
+    get_token_balance::GetTokenBalanceResult,
+    long_polling::LongPollingService,
+};
+use crate::solana::tokens::TOKEN_COLLECTION;
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use tauri::ipc::{Invoke, InvokeBody, InvokeError};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug)]
+pub enum CommandError {
+    UnknownCommand(String),
+    BadRequest(String),
+    /// The command ran and returned an error
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand(command) => write!(f, "unknown command {}", command),
+            CommandError::BadRequest(e) | CommandError::Failed(e) => f.write_str(e),
+        }
+    }
+}
+
+/// The manager shared with the desktop UI
+fn job_manager(app: &AppHandle) -> Result<JobManager, CommandError> {
+    if let Some(manager) = app.try_state::<JobManager>() {
+        return Ok(manager.inner().clone());
+    }
+    crate::job_manager_instance()
+        .map(|manager| (*manager).clone())
+        .ok_or_else(|| CommandError::Failed("job manager not ready".to_string()))
+}
+
+fn camel_case(name: &str) -> String {
+    let mut parts = name.split('_');
+    let mut camel = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+/// Command argument by its Rust name, or the camelCase name `invoke` sends
+fn arg<T: DeserializeOwned>(args: &Value, name: &str) -> Result<T, CommandError> {
+    let value = args
+        .get(name)
+        .or_else(|| args.get(camel_case(name)))
+        .cloned()
+        .unwrap_or(Value::Null);
+    serde_json::from_value(value)
+        .map_err(|e| CommandError::BadRequest(format!("invalid argument {}: {}", name, e)))
+}
+
+fn ok<T: Serialize>(value: T) -> Result<Value, CommandError> {
+    serde_json::to_value(value).map_err(|e| CommandError::Failed(e.to_string()))
+}
+
+fn done<T: Serialize>(result: Result<T, String>) -> Result<Value, CommandError> {
+    ok(result.map_err(CommandError::Failed)?)
+}
+
+/// Run a command with its JSON arguments
+pub async fn dispatch(app: &AppHandle, command: &str, args: &Value) -> Result<Value, CommandError> {
+    let a = args;
+    match command {
+        "ping" => ok("pong"),
+        "quick_buy" => {
+            let req: QuickBuyReq = arg(a, "req")?;
+            info!("quick_buy request: {:?}", req);
+            done(services::quick_buy::quick_buy(req).await)
+        }
+        "quick_sell" => {
+            let req: QuickSellReq = arg(a, "req")?;
+            info!("quick_sell request: {:?}", req);
+            ok(services::quick_sell::quick_sell(req).await)
+        }
+        "create_token" => {
+            let req: CreateTokenReq = arg(a, "req")?;
+            info!("create_token request: {:?}", req);
+            ok(create_meme_token::create_token(req, arg(a, "keystore_password")?).await)
+        }
+        "create_token_spawn" => ok(job_commands::create_token_spawn(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "req")?,
+            arg(a, "keystore_password")?,
+            arg(a, "priority")?,
+        )),
+        "list_jobs" => ok(job_manager(app)?.list_all()),
+        "get_job_status" => {
+            let job_id: String = arg(a, "job_id")?;
+            ok(job_manager(app)?.get_info(&job_id))
+        }
+        "get_sol_balance" => {
+            done(services::get_sol_balance::get_sol_balance(arg(a, "wallet")?).await)
+        }
+        "get_token_balance" => done(
+            services::get_token_balance::get_single_token_balance(
+                arg(a, "wallet")?,
+                Some(arg(a, "mint")?),
+            )
+            .await,
+        ),
+        "get_tokens_balances" => {
+            done(services::get_token_balance::get_tokens_balances(arg(a, "wallet")?).await)
+        }
+        "get_tokens" => done(services::get_token_balance::get_tokens(arg(a, "wallet")?).await),
+        "refund_wallet" => done(
+            services::refund_wallets::refunds_to(
+                arg(a, "pks")?,
+                arg(a, "refund_to")?,
+                arg(a, "funding_pk")?,
+                None,
+            )
+            .await,
+        ),
+        "refund_specific_amount" => done(
+            services::refund_wallets::refund_amount_to(
+                arg(a, "source_pk")?,
+                arg(a, "refund_to")?,
+                arg(a, "amount_sol")?,
+                None,
+            )
+            .await,
+        ),
+        "refund_wallets_specific_amount" => done(
+            services::refund_wallets::refunds_amount_to(
+                arg(a, "pks")?,
+                arg(a, "refund_to")?,
+                arg(a, "amount_sol")?,
+                None,
+            )
+            .await,
+        ),
+        "distribute_sol" => done(
+            services::distribute_sol::distribute_sol(
+                arg(a, "src")?,
+                arg(a, "wallets")?,
+                arg(a, "total_amount_sol")?,
+                None,
+            )
+            .await,
+        ),
+        "close_accounts" => done(
+            services::close_accounts::close_accounts(
+                arg(a, "wallet_pk")?,
+                arg(a, "rent_destination")?,
+            )
+            .await,
+        ),
+        "close_token_account" => done(
+            services::close_token_account::close_token_account(
+                arg(a, "wallet_pk")?,
+                arg(a, "token_mint")?,
+                arg(a, "rent_destination")?,
+                None,
+            )
+            .await,
+        ),
+        "burn_tokens" => done(
+            services::burn_tokens::burn_tokens(
+                arg(a, "wallet_pk")?,
+                arg(a, "mint_address")?,
+                arg(a, "burn_percentage")?,
+            )
+            .await,
+        ),
+        "burn_each_tokens" => done(
+            services::burn_tokens::burn_each_tokens(
+                arg(a, "wallet_pk")?,
+                arg(a, "mint_addresses")?,
+                arg(a, "burn_percentage")?,
+            )
+            .await,
+        ),
+        "refund_wallets_job" => ok(job_commands::refund_wallets_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "pks")?,
+            arg(a, "refund_to")?,
+            arg(a, "funding_pk")?,
+            arg(a, "priority")?,
+        )),
+        "refund_wallets_specific_amount_job" => {
+            ok(job_commands::refund_wallets_specific_amount_job(
+                &job_manager(app)?,
+                app.clone(),
+                arg(a, "pks")?,
+                arg(a, "refund_to")?,
+                arg(a, "amount_sol")?,
+                arg(a, "priority")?,
+            ))
+        }
+        "distribute_sol_job" => ok(job_commands::distribute_sol_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "src")?,
+            arg(a, "wallets")?,
+            arg(a, "total_amount_sol")?,
+            arg(a, "priority")?,
+        )),
+        "close_accounts_job" => ok(job_commands::close_accounts_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "rent_destination")?,
+            arg(a, "priority")?,
+        )),
+        "close_token_account_job" => ok(job_commands::close_token_account_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "token_mint")?,
+            arg(a, "rent_destination")?,
+            arg(a, "priority")?,
+        )),
+        "close_token_accounts_batch_job" => ok(job_commands::close_token_accounts_batch_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "token_mints")?,
+            arg(a, "rent_destination")?,
+            arg(a, "priority")?,
+        )),
+        "burn_tokens_job" => ok(job_commands::burn_tokens_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "mint_address")?,
+            arg(a, "burn_percentage")?,
+            arg(a, "priority")?,
+        )),
+        "burn_tokens_batch_job" => ok(job_commands::burn_tokens_batch_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "token_mints")?,
+            arg(a, "priority")?,
+        )),
+        "burn_each_tokens_job" => ok(job_commands::burn_each_tokens_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "mint_addresses")?,
+            arg(a, "burn_percentage")?,
+            arg(a, "priority")?,
+        )),
+        "get_tokens_balances_job" => ok(job_commands::get_tokens_balances_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet")?,
+            arg(a, "priority")?,
+        )),
+        "get_wallet_history" => done(
+            services::tx_history::get_wallet_history(
+                arg(a, "wallet")?,
+                arg::<Option<bool>>(a, "refresh")?.unwrap_or(false),
+                arg(a, "filter")?,
+                None,
+            )
+            .await,
+        ),
+        "clear_wallet_history" => {
+            let wallet: String = arg(a, "wallet")?;
+            done(services::tx_history::clear_wallet_history(&wallet))
+        }
+        "import_group_history_job" => ok(job_commands::import_group_history_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallets")?,
+            arg(a, "priority")?,
+        )),
+        "export_jobs" => done(services::export::export_jobs(
+            &job_manager(app)?.list_all(),
+            &arg(a, "options")?,
+        )),
+        "export_token_balances" => {
+            let snapshots: Vec<GetTokenBalanceResult> = arg(a, "snapshots")?;
+            done(services::export::export_token_balances(
+                &snapshots,
+                &arg(a, "options")?,
+            ))
+        }
+        "export_trades" => {
+            done(services::export::export_trades(arg(a, "wallets")?, arg(a, "options")?).await)
+        }
+        "export_transfers" => {
+            done(services::export::export_transfers(arg(a, "wallets")?, arg(a, "options")?).await)
+        }
+        "import_wallets" => {
+            let source = arg(a, "source")?;
+            let existing: Option<Vec<String>> = arg(a, "existing_pubkeys")?;
+            let result = tokio::task::spawn_blocking(move || {
+                services::wallet_import::import_wallets(source, existing.unwrap_or_default())
+            })
+            .await
+            .map_err(|e| CommandError::Failed(format!("join error: {}", e)))?;
+            done(result)
+        }
+        "export_group_backup" => {
+            let (group_name, pks, password, path) = (
+                arg(a, "group_name")?,
+                arg(a, "pks")?,
+                arg(a, "password")?,
+                arg(a, "path")?,
+            );
+            let result = tokio::task::spawn_blocking(move || {
+                services::wallet_import::export_group_bundle(group_name, pks, password, path)
+            })
+            .await
+            .map_err(|e| CommandError::Failed(format!("join error: {}", e)))?;
+            done(result)
+        }
+        "get_group_balance_snapshot" => done(
+            services::balance_snapshot::get_group_balance_snapshot(
+                arg(a, "wallets")?,
+                arg(a, "max_concurrency")?,
+                None,
+            )
+            .await,
+        ),
+        "get_tokens_metadata" => done(
+            services::token_metadata::get_tokens_metadata(
+                arg(a, "mints")?,
+                arg::<Option<bool>>(a, "refresh")?.unwrap_or(false),
+            )
+            .await,
+        ),
+        "plan_cleanup" => done(
+            services::cleanup::plan_cleanup(
+                arg(a, "wallets")?,
+                arg::<Option<_>>(a, "options")?.unwrap_or_default(),
+            )
+            .await,
+        ),
+        "execute_cleanup_plan_job" => ok(job_commands::execute_cleanup_plan_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "plan")?,
+            arg(a, "pks")?,
+            arg(a, "rent_destination")?,
+            arg(a, "priority")?,
+        )),
+        "get_rent_report" => done(
+            services::rent_report::get_rent_report(arg(a, "wallets")?, arg(a, "max_concurrency")?)
+                .await,
+        ),
+        "burn_and_close_job" => ok(job_commands::burn_and_close_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "wallet_pk")?,
+            arg(a, "mints")?,
+            arg(a, "rent_destination")?,
+            arg(a, "priority")?,
+        )),
+        "list_token_templates" => ok(TOKEN_COLLECTION.templates()),
+        "preview_launch" => done(services::launch_preview::preview_launch(arg(a, "req")?).await),
+        "confirm_launch" => done(services::launch_preview::confirm_launch(
+            arg(a, "plan_id")?,
+            arg(a, "plan_hash")?,
+        )),
+        "estimate_vanity" => {
+            done(services::vanity::estimate_vanity(arg(a, "pattern")?, arg(a, "threads")?).await)
+        }
+        "grind_vanity_mint_job" => ok(job_commands::grind_vanity_mint_job(
+            &job_manager(app)?,
+            app.clone(),
+            arg(a, "pattern")?,
+            arg(a, "threads")?,
+            arg(a, "password")?,
+            arg(a, "priority")?,
+        )),
+        "cancel_vanity_search" => {
+            let job_id: String = arg(a, "job_id")?;
+            ok(services::vanity::cancel_vanity_search(&job_id))
+        }
+        "list_keystore" => ok(services::keystore::list_keypairs()),
+        "subscribe_job" => {
+            // Live events follow on the job_event channel once the app handle is attached
+            job_events().attach(app);
+            let job_id: Option<String> = arg(a, "job_id")?;
+            let after_seq: Option<u64> = arg(a, "after_seq")?;
+            ok(job_events().replay(job_id.as_deref(), after_seq.unwrap_or(0)))
+        }
+        "get_job_queue" => {
+            let scheduler = job_scheduler();
+            ok((scheduler.queue_status(), scheduler.queued_jobs()))
+        }
+        "configure_job_queue" => {
+            let queue: String = arg(a, "queue")?;
+            job_scheduler().configure_queue(&queue, arg(a, "concurrency")?);
+            ok(job_scheduler().queue_status())
+        }
+        "start_batch" => done(LongPollingService::start_controlled_batch(
+            arg(a, "request")?,
+            &job_manager(app)?,
+            app.clone(),
+        )),
+        "get_batch" => {
+            let batch_id: String = arg(a, "batch_id")?;
+            ok(services::long_polling::get_batch(&batch_id))
+        }
+        "list_batches" => ok(services::long_polling::list_batches()),
+        "create_schedule" => done(scheduler::create_schedule(arg(a, "spec")?)),
+        "list_schedules" => ok(scheduler::scheduler().list()),
+        "set_schedule_enabled" => {
+            let schedule_id: String = arg(a, "schedule_id")?;
+            done(scheduler::scheduler().set_enabled(&schedule_id, arg(a, "enabled")?))
+        }
+        "delete_schedule" => {
+            let schedule_id: String = arg(a, "schedule_id")?;
+            ok(scheduler::scheduler().remove(&schedule_id))
+        }
+        "unlock_schedules" => done(scheduler::unlock_schedules(
+            arg(a, "password")?,
+            job_manager(app)?,
+            app.clone(),
+        )),
+        "lock_schedules" => {
+            scheduler::lock_schedules();
+            ok(())
+        }
+        _ => Err(CommandError::UnknownCommand(command.to_string())),
+    }
+}
+
+/// Tauri IPC handler running every `invoke` through `dispatch`
+pub fn invoke_handler() -> impl Fn(Invoke) -> bool + Send + Sync + 'static {
+    |invoke: Invoke| {
+        let app = invoke.message.webview().app_handle().clone();
+        let command = invoke.message.command().to_string();
+        let args = match invoke.message.payload() {
+            InvokeBody::Json(args) => args.clone(),
+            InvokeBody::Raw(_) => Value::Null,
+        };
+        invoke.resolver.respond_async(async move {
+            dispatch(&app, &command, &args)
+                .await
+                .map_err(|e| InvokeError::from(e.to_string()))
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn camel_case_matches_invoke_argument_names() {
+        assert_eq!(camel_case("wallet"), "wallet");
+        assert_eq!(camel_case("wallet_pk"), "walletPk");
+        assert_eq!(camel_case("keystore_password"), "keystorePassword");
+        assert_eq!(camel_case("refund_to_address"), "refundToAddress");
+        assert_eq!(camel_case(""), "");
+    }
+
+    #[test]
+    fn arg_reads_snake_or_camel_case_keys() {
+        let args = json!({
+            "wallet_pk": "snake",
+            "walletPk": "camel",
+            "tokenMints": ["a", "b"],
+            "amount_sol": 1.5,
+        });
+        // The Rust name wins when both are sent
+        assert_eq!(arg::<String>(&args, "wallet_pk").unwrap(), "snake");
+        assert_eq!(
+            arg::<Vec<String>>(&args, "token_mints").unwrap(),
+            ["a", "b"]
+        );
+        assert_eq!(arg::<f64>(&args, "amount_sol").unwrap(), 1.5);
+
+        // Missing arguments read as null, so only optional ones may be left out
+        assert_eq!(arg::<Option<u64>>(&args, "priority").unwrap(), None);
+        assert_eq!(arg::<Option<String>>(&Value::Null, "job_id").unwrap(), None);
+        match arg::<String>(&args, "refund_to") {
+            Err(CommandError::BadRequest(e)) => assert!(e.contains("refund_to"), "{}", e),
+            other => panic!("expected a bad request, got {:?}", other),
+        }
+        assert!(matches!(
+            arg::<u64>(&args, "amount_sol"),
+            Err(CommandError::BadRequest(_))
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::secret::SecretString;
 use log::{info, warn};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -14,15 +15,15 @@ pub struct ApiConfig {
 // Configuration for transactions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionConfig {
-    pub private_key1: String,
-    pub private_key2: String,
-    pub private_key3: String,
-    pub private_key4: String,
-    pub private_key5: String,
-    pub private_key6: String,
-    pub private_key7: String,
+    pub private_key1: SecretString,
+    pub private_key2: SecretString,
+    pub private_key3: SecretString,
+    pub private_key4: SecretString,
+    pub private_key5: SecretString,
+    pub private_key6: SecretString,
+    pub private_key7: SecretString,
     pub sol_to_spend: f64,
-    pub jwt_token: String,
+    pub jwt_token: SecretString,
     pub slippage: f64,
 }
 
@@ -69,6 +70,33 @@ pub struct TradingConfig {
     pub sell_iteration_sleep: u64,
 }
 
+// Configuration for local storage (caches, exports, keystore)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    pub data_dir: String,
+}
+
+// Configuration for IPFS pinning (token images and metadata JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpfsConfig {
+    /// "pinata" or "kubo"
+    pub provider: String,
+    pub pinata_jwt: SecretString,
+    pub pinata_api_url: String,
+    pub kubo_api_url: String,
+    pub gateway_url: String,
+}
+
+// Configuration for the optional local API server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiServerConfig {
+    pub enabled: bool,
+    /// Loopback address only, e.g. "127.0.0.1:8787"
+    pub bind_addr: String,
+    /// Bearer token clients must send; generated and kept in the data dir when empty
+    pub token: SecretString,
+}
+
 // Main structure grouping all configurations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -76,6 +104,9 @@ pub struct AppConfig {
     pub transaction: TransactionConfig,
     pub metrics: MetricsConfig,
     pub trading: TradingConfig,
+    pub storage: StorageConfig,
+    pub ipfs: IpfsConfig,
+    pub api_server: ApiServerConfig,
 }
 
 // Globally accessible configuration singleton
@@ -107,38 +138,54 @@ impl AppConfig {
                     .unwrap_or_else(|_| "mongodb://localhost:27017".to_string()),
             },
             transaction: TransactionConfig {
-                jwt_token: env::var("JWT_TOKEN").unwrap_or_else(|_| {
-                    warn!("JWT_TOKEN not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key1: env::var("PRIVATE_KEY").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key2: env::var("PRIVATE_KEY2").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY2 not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key3: env::var("PRIVATE_KEY3").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY3 not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key4: env::var("PRIVATE_KEY4").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY4 not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key5: env::var("PRIVATE_KEY5").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY5 not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key6: env::var("PRIVATE_KEY6").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY6 not set in environment variables!");
-                    "".to_string()
-                }),
-                private_key7: env::var("PRIVATE_KEY7").unwrap_or_else(|_| {
-                    warn!("PRIVATE_KEY7 not set in environment variables!");
-                    "".to_string()
-                }),
+                jwt_token: env::var("JWT_TOKEN")
+                    .unwrap_or_else(|_| {
+                        warn!("JWT_TOKEN not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key1: env::var("PRIVATE_KEY")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key2: env::var("PRIVATE_KEY2")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY2 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key3: env::var("PRIVATE_KEY3")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY3 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key4: env::var("PRIVATE_KEY4")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY4 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key5: env::var("PRIVATE_KEY5")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY5 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key6: env::var("PRIVATE_KEY6")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY6 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
+                private_key7: env::var("PRIVATE_KEY7")
+                    .unwrap_or_else(|_| {
+                        warn!("PRIVATE_KEY7 not set in environment variables!");
+                        "".to_string()
+                    })
+                    .into(),
                 sol_to_spend: env::var("SOL_TO_SPEND")
                     .unwrap_or_else(|_| "0.01".to_string())
                     .parse::<f64>()
@@ -252,6 +299,30 @@ impl AppConfig {
                     .parse::<u64>()
                     .unwrap_or(12),
             },
+            storage: StorageConfig {
+                data_dir: env::var("DATA_DIR").unwrap_or_else(|_| "./data".to_string()),
+            },
+            ipfs: IpfsConfig {
+                provider: env::var("IPFS_PROVIDER").unwrap_or_else(|_| "pinata".to_string()),
+                pinata_jwt: env::var("PINATA_JWT")
+                    .or_else(|_| env::var("JWT_TOKEN"))
+                    .unwrap_or_default()
+                    .into(),
+                pinata_api_url: env::var("PINATA_API_URL")
+                    .unwrap_or_else(|_| "https://api.pinata.cloud".to_string()),
+                kubo_api_url: env::var("KUBO_API_URL")
+                    .unwrap_or_else(|_| "http://127.0.0.1:5001".to_string()),
+                gateway_url: env::var("IPFS_GATEWAY_URL")
+                    .unwrap_or_else(|_| "https://ipfs.io/ipfs".to_string()),
+            },
+            api_server: ApiServerConfig {
+                enabled: env::var("API_SERVER_ENABLED")
+                    .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                    .unwrap_or(false),
+                bind_addr: env::var("API_SERVER_ADDR")
+                    .unwrap_or_else(|_| "127.0.0.1:8787".to_string()),
+                token: env::var("API_SERVER_TOKEN").unwrap_or_default().into(),
+            },
         };
 
         info!("Configuration loaded successfully");
@@ -267,14 +338,14 @@ impl AppConfig {
                 mongodb_uri: "mongodb://localhost:27017".to_string(),
             },
             transaction: TransactionConfig {
-                jwt_token: "".to_string(),
-                private_key1: "".to_string(),
-                private_key2: "".to_string(),
-                private_key3: "".to_string(),
-                private_key4: "".to_string(),
-                private_key5: "".to_string(),
-                private_key6: "".to_string(),
-                private_key7: "".to_string(),
+                jwt_token: SecretString::default(),
+                private_key1: SecretString::default(),
+                private_key2: SecretString::default(),
+                private_key3: SecretString::default(),
+                private_key4: SecretString::default(),
+                private_key5: SecretString::default(),
+                private_key6: SecretString::default(),
+                private_key7: SecretString::default(),
                 sol_to_spend: 0.01,
                 slippage: 0.05,
             },
@@ -309,6 +380,46 @@ impl AppConfig {
                 min_holders: 12,
                 lost_profit_pourcentage: 10.0,
             },
+            storage: StorageConfig {
+                data_dir: "./data".to_string(),
+            },
+            ipfs: IpfsConfig {
+                provider: "pinata".to_string(),
+                pinata_jwt: SecretString::default(),
+                pinata_api_url: "https://api.pinata.cloud".to_string(),
+                kubo_api_url: "http://127.0.0.1:5001".to_string(),
+                gateway_url: "https://ipfs.io/ipfs".to_string(),
+            },
+            api_server: ApiServerConfig {
+                enabled: false,
+                bind_addr: "127.0.0.1:8787".to_string(),
+                token: SecretString::default(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_redacts_tokens_and_keys() {
+        let mut config = AppConfig::default();
+        config.api_server.token = "api-server-bearer-token".into();
+        config.ipfs.pinata_jwt = "pinata-jwt-value".into();
+        config.transaction.jwt_token = "transaction-jwt-value".into();
+        config.transaction.private_key1 = "private-key-value".into();
+
+        let debug = format!("{:?}", config);
+        for secret in [
+            "api-server-bearer-token",
+            "pinata-jwt-value",
+            "transaction-jwt-value",
+            "private-key-value",
+        ] {
+            assert!(!debug.contains(secret), "{} leaked in {}", secret, debug);
         }
+        assert!(debug.contains("[REDACTED]"), "{}", debug);
     }
 }
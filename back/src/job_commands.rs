@@ -0,0 +1,376 @@
+//! Job commands of the `commands` table, so the UI and the local API server
+//! start the same jobs on the same `JobManager`.
+
+use crate::job_queue::{
+    signer_pubkeys, spawn_queued, JobPriority, JobRequest, QUEUE_CPU, QUEUE_LAUNCH, QUEUE_READS,
+    QUEUE_TRANSACTIONS,
+};
+use crate::jobs::JobManager;
+use crate::secret::SecretString;
+use crate::services::{
+    self, cleanup::CleanupPlan, common::CreateTokenReq, create_meme_token, vanity::VanityPattern,
+};
+use serde::Serialize;
+use std::future::Future;
+use tauri::AppHandle;
+
+/// Spawn a queued job whose result is stored on the job as JSON
+fn spawn_with_result<T, F, Fut>(
+    manager: &JobManager,
+    name: &str,
+    request: JobRequest,
+    app_handle: AppHandle,
+    job: F,
+) -> String
+where
+    T: Serialize,
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, String>> + Send + 'static,
+{
+    let manager_clone = manager.clone();
+    spawn_queued(
+        manager,
+        name,
+        request,
+        app_handle,
+        move |job_id| async move {
+            let result = job(job_id.clone()).await?;
+            let json = serde_json::to_string(&result).map_err(|e| e.to_string())?;
+            manager_clone.set_job_result(&job_id, json);
+            Ok(())
+        },
+    )
+}
+
+fn transactions(priority: Option<JobPriority>, wallets: Vec<String>) -> JobRequest {
+    JobRequest {
+        queue: QUEUE_TRANSACTIONS.to_string(),
+        priority: priority.unwrap_or(JobPriority::Normal),
+        wallets,
+    }
+}
+
+pub fn create_token_spawn(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    req: CreateTokenReq,
+    keystore_password: Option<SecretString>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = JobRequest {
+        queue: QUEUE_LAUNCH.to_string(),
+        priority: priority.unwrap_or(JobPriority::High),
+        wallets: signer_pubkeys(&[req.dev_wallet.expose(), req.sniper_wallet_one.expose()]),
+    };
+    spawn_queued(
+        manager,
+        "create_token",
+        request,
+        app_handle,
+        move |_| async move {
+            let mint_keypair = create_meme_token::mint_keypair_for(
+                &req,
+                keystore_password.as_ref().map(SecretString::expose),
+            )
+            .map_err(|e| format!("{:#}", e))?;
+            create_meme_token::create_meme_token(req, mint_keypair.insecure_clone())
+                .await
+                .map(|_tx_sig| ())
+                .map_err(|e| e.to_string())
+        },
+    )
+}
+
+pub fn refund_wallets_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    pks: Vec<String>,
+    refund_to: String,
+    funding_pk: String,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&pks));
+    spawn_with_result(
+        manager,
+        "refund_wallets",
+        request,
+        app_handle,
+        move |job_id| {
+            services::refund_wallets::refunds_to(pks, refund_to, funding_pk, Some(job_id))
+        },
+    )
+}
+
+pub fn refund_wallets_specific_amount_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    pks: Vec<String>,
+    refund_to: String,
+    amount_sol: f64,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&pks));
+    spawn_with_result(
+        manager,
+        "refund_wallets_specific_amount",
+        request,
+        app_handle,
+        move |job_id| {
+            services::refund_wallets::refunds_amount_to(pks, refund_to, amount_sol, Some(job_id))
+        },
+    )
+}
+
+pub fn distribute_sol_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    src: String,
+    wallets: Vec<String>,
+    total_amount_sol: f64,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&src]));
+    spawn_with_result(
+        manager,
+        "distribute_sol",
+        request,
+        app_handle,
+        move |job_id| {
+            services::distribute_sol::distribute_sol(src, wallets, total_amount_sol, Some(job_id))
+        },
+    )
+}
+
+pub fn close_accounts_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    rent_destination: Option<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_with_result(manager, "close_accounts", request, app_handle, move |_| {
+        services::close_accounts::close_accounts(wallet_pk, rent_destination)
+    })
+}
+
+pub fn close_token_account_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    token_mint: String,
+    rent_destination: Option<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_queued(
+        manager,
+        "close_token_account",
+        request,
+        app_handle,
+        move |job_id| async move {
+            services::close_token_account::close_token_account(
+                wallet_pk,
+                token_mint,
+                rent_destination,
+                Some(job_id),
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+        },
+    )
+}
+
+pub fn close_token_accounts_batch_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    token_mints: Vec<String>,
+    rent_destination: Option<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    let batch = services::close_token_account::CloseTokenAccountBatchRequest {
+        wallet_pk: wallet_pk.into(),
+        token_mints,
+        rent_destination,
+    };
+    spawn_with_result(
+        manager,
+        "close_token_accounts_batch",
+        request,
+        app_handle,
+        move |job_id| {
+            services::close_token_account::close_token_accounts_batch(batch, Some(job_id))
+        },
+    )
+}
+
+pub fn burn_tokens_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    mint_address: String,
+    burn_percentage: f64,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_queued(
+        manager,
+        "burn_tokens",
+        request,
+        app_handle,
+        move |_| async move {
+            services::burn_tokens::burn_tokens(wallet_pk, mint_address, burn_percentage)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        },
+    )
+}
+
+/// Burn the whole balance of each mint; the batch variant of `burn_tokens_job`
+pub fn burn_tokens_batch_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    token_mints: Vec<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_with_result(
+        manager,
+        "burn_tokens_batch",
+        request,
+        app_handle,
+        move |_| services::burn_tokens::burn_each_tokens(wallet_pk, token_mints, 100.0),
+    )
+}
+
+pub fn burn_each_tokens_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    mint_addresses: Vec<String>,
+    burn_percentage: f64,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_with_result(
+        manager,
+        "burn_each_tokens",
+        request,
+        app_handle,
+        move |_| {
+            services::burn_tokens::burn_each_tokens(wallet_pk, mint_addresses, burn_percentage)
+        },
+    )
+}
+
+pub fn get_tokens_balances_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet: String,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = JobRequest {
+        queue: QUEUE_READS.to_string(),
+        priority: priority.unwrap_or(JobPriority::Low),
+        wallets: vec![],
+    };
+    spawn_queued(
+        manager,
+        "get_tokens_balances",
+        request,
+        app_handle,
+        move |_| async move {
+            services::get_token_balance::get_tokens_balances(wallet)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        },
+    )
+}
+
+pub fn import_group_history_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallets: Vec<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = JobRequest {
+        queue: QUEUE_READS.to_string(),
+        priority: priority.unwrap_or(JobPriority::Low),
+        wallets: vec![],
+    };
+    spawn_queued(
+        manager,
+        "import_group_history",
+        request,
+        app_handle,
+        move |job_id| async move {
+            services::tx_history::import_group_history(wallets, Some(job_id))
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        },
+    )
+}
+
+pub fn execute_cleanup_plan_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    plan: CleanupPlan,
+    pks: Vec<String>,
+    rent_destination: Option<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&pks));
+    spawn_with_result(manager, "cleanup", request, app_handle, move |job_id| {
+        services::cleanup::execute_cleanup_plan(plan, pks, rent_destination, Some(job_id))
+    })
+}
+
+pub fn burn_and_close_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    wallet_pk: String,
+    mints: Vec<String>,
+    rent_destination: Option<String>,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = transactions(priority, signer_pubkeys(&[&wallet_pk]));
+    spawn_with_result(
+        manager,
+        "burn_and_close",
+        request,
+        app_handle,
+        move |job_id| {
+            services::burn_and_close::burn_and_close(
+                wallet_pk,
+                mints,
+                rent_destination,
+                Some(job_id),
+            )
+        },
+    )
+}
+
+pub fn grind_vanity_mint_job(
+    manager: &JobManager,
+    app_handle: AppHandle,
+    pattern: VanityPattern,
+    threads: Option<usize>,
+    password: SecretString,
+    priority: Option<JobPriority>,
+) -> String {
+    let request = JobRequest {
+        queue: QUEUE_CPU.to_string(),
+        priority: priority.unwrap_or(JobPriority::Normal),
+        wallets: vec![],
+    };
+    spawn_with_result(manager, "vanity_mint", request, app_handle, move |job_id| {
+        services::vanity::grind_vanity_mint(pattern, threads, password, job_id)
+    })
+}
@@ -0,0 +1,270 @@
+use crate::jobs::JobManager;
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{LazyLock, OnceLock};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{broadcast, oneshot};
+
+/// Tauri event carrying every [`JobEvent`]
+pub const JOB_EVENT_NAME: &str = "job_event";
+/// Events kept for replay; older ones are dropped
+const HISTORY_CAPACITY: usize = 10_000;
+const CHANNEL_CAPACITY: usize = 1_024;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEventKind {
+    Created {
+        name: String,
+    },
+    /// Waiting in a scheduler queue; sent again whenever the position changes
+    Queued {
+        queue: String,
+        position: usize,
+    },
+    Started,
+    Progress {
+        completed: u32,
+        total: u32,
+        step: Option<String>,
+    },
+    ItemCompleted {
+        item: String,
+        success: bool,
+        message: Option<String>,
+    },
+    Finished,
+    Failed {
+        error: String,
+    },
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobEvent {
+    /// Increases by one with every event, across all jobs
+    pub seq: u64,
+    pub job_id: String,
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub kind: JobEventKind,
+}
+
+struct History {
+    next_seq: u64,
+    events: VecDeque<JobEvent>,
+}
+
+/// Sequenced job events, pushed to the frontend and kept for replay
+pub struct JobEventBus {
+    history: Mutex<History>,
+    sender: broadcast::Sender<JobEvent>,
+    app: OnceLock<AppHandle>,
+}
+
+static JOB_EVENTS: LazyLock<JobEventBus> = LazyLock::new(|| JobEventBus {
+    history: Mutex::new(History {
+        next_seq: 1,
+        events: VecDeque::with_capacity(HISTORY_CAPACITY),
+    }),
+    sender: broadcast::channel(CHANNEL_CAPACITY).0,
+    app: OnceLock::new(),
+});
+
+pub fn job_events() -> &'static JobEventBus {
+    &JOB_EVENTS
+}
+
+impl JobEventBus {
+    /// Emit events to the frontend through this app (first call wins)
+    pub fn attach(&self, app: &AppHandle) {
+        let _ = self.app.set(app.clone());
+    }
+
+    pub fn publish(&self, job_id: &str, kind: JobEventKind) -> JobEvent {
+        // Sequence numbers are assigned under the lock so history stays ordered
+        let event = {
+            let mut history = self.history.lock();
+            let event = JobEvent {
+                seq: history.next_seq,
+                job_id: job_id.to_string(),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+                kind,
+            };
+            history.next_seq += 1;
+            if history.events.len() == HISTORY_CAPACITY {
+                history.events.pop_front();
+            }
+            history.events.push_back(event.clone());
+            event
+        };
+
+        // No receiver is not an error
+        let _ = self.sender.send(event.clone());
+        if let Some(app) = self.app.get() {
+            if let Err(e) = app.emit(JOB_EVENT_NAME, &event) {
+                warn!("Failed to emit job event {}: {}", event.seq, e);
+            }
+        }
+        event
+    }
+
+    /// Events after `after_seq`, for one job or all of them
+    pub fn replay(&self, job_id: Option<&str>, after_seq: u64) -> Vec<JobEvent> {
+        self.history
+            .lock()
+            .events
+            .iter()
+            .filter(|e| e.seq > after_seq && job_id.is_none_or(|id| e.job_id == id))
+            .cloned()
+            .collect()
+    }
+
+    /// Live events, for in-process consumers
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Whether the job published a cancelled event
+    pub fn is_cancelled(&self, job_id: &str) -> bool {
+        self.history
+            .lock()
+            .events
+            .iter()
+            .any(|e| e.job_id == job_id && matches!(e.kind, JobEventKind::Cancelled))
+    }
+
+    /// Sequence number of the latest event (0 if none yet)
+    pub fn last_seq(&self) -> u64 {
+        self.history.lock().next_seq - 1
+    }
+}
+
+/// Run a tracked job once its id arrives, then publish finished or failed;
+/// a job that reported itself cancelled does not also report a failure
+async fn run_tracked<F, Fut>(id: oneshot::Receiver<String>, job: F) -> Result<(), String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    // Start once created is published, so events of the job follow it
+    let job_id = id.await.map_err(|_| "job id unavailable".to_string())?;
+    let result = job(job_id.clone()).await;
+    let bus = job_events();
+    match &result {
+        Ok(()) => {
+            bus.publish(&job_id, JobEventKind::Finished);
+        }
+        Err(_) if bus.is_cancelled(&job_id) => {}
+        Err(error) => {
+            bus.publish(
+                &job_id,
+                JobEventKind::Failed {
+                    error: error.clone(),
+                },
+            );
+        }
+    }
+    result
+}
+
+/// Publish created for a spawned job and hand the job its id
+fn announce(job_id: &str, name: &str, id: oneshot::Sender<String>) {
+    job_events().publish(
+        job_id,
+        JobEventKind::Created {
+            name: name.to_string(),
+        },
+    );
+    let _ = id.send(job_id.to_string());
+}
+
+/// Spawn a job through the manager, publishing created, then finished or failed.
+/// The manager assigns the id, so `job` receives it once spawned and must use it,
+/// not `name`, for progress and results
+pub fn spawn_tracked<F, Fut>(
+    manager: &JobManager,
+    name: &str,
+    app_handle: AppHandle,
+    job: F,
+) -> String
+where
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let (id_tx, id_rx) = oneshot::channel::<String>();
+    let job_id = manager.spawn_job(name, app_handle, run_tracked(id_rx, job));
+    announce(&job_id, name, id_tx);
+    job_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(job_id: &str) -> Vec<String> {
+        job_events()
+            .replay(Some(job_id), 0)
+            .into_iter()
+            .map(|e| match e.kind {
+                JobEventKind::Created { .. } => "created".to_string(),
+                JobEventKind::Progress {
+                    completed, total, ..
+                } => {
+                    format!("progress {}/{}", completed, total)
+                }
+                JobEventKind::Finished => "finished".to_string(),
+                JobEventKind::Failed { error } => format!("failed {}", error),
+                JobEventKind::Cancelled => "cancelled".to_string(),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tracked_job_events_replay_in_order_under_its_id() {
+        let job_id = format!("job_{}", uuid::Uuid::new_v4());
+        let (id_tx, id_rx) = oneshot::channel();
+        let run = tokio::spawn(run_tracked(id_rx, |job_id: String| async move {
+            crate::update_job_progress(&job_id, 1, 2, "first half".to_string());
+            crate::update_job_progress(&job_id, 2, 2, "second half".to_string());
+            Ok(())
+        }));
+        announce(&job_id, "test_job", id_tx);
+        run.await.unwrap().unwrap();
+
+        assert_eq!(
+            kinds(&job_id),
+            ["created", "progress 1/2", "progress 2/2", "finished"]
+        );
+        assert!(kinds("test_job").is_empty());
+
+        let events = job_events().replay(Some(&job_id), 0);
+        assert!(events.windows(2).all(|w| w[0].seq < w[1].seq));
+        let after_first = job_events().replay(Some(&job_id), events[0].seq);
+        assert_eq!(after_first.len(), events.len() - 1);
+    }
+
+    #[tokio::test]
+    async fn cancelled_job_does_not_also_fail() {
+        let job_id = format!("job_{}", uuid::Uuid::new_v4());
+        let (id_tx, id_rx) = oneshot::channel();
+        let run = tokio::spawn(run_tracked(id_rx, |job_id: String| async move {
+            job_events().publish(&job_id, JobEventKind::Cancelled);
+            Err("stopped".to_string())
+        }));
+        announce(&job_id, "test_job", id_tx);
+        assert!(run.await.unwrap().is_err());
+        assert_eq!(kinds(&job_id), ["created", "cancelled"]);
+
+        let job_id = format!("job_{}", uuid::Uuid::new_v4());
+        let (id_tx, id_rx) = oneshot::channel();
+        let run = tokio::spawn(run_tracked(id_rx, |_| async { Err("boom".to_string()) }));
+        announce(&job_id, "test_job", id_tx);
+        assert!(run.await.unwrap().is_err());
+        assert_eq!(kinds(&job_id), ["created", "failed boom"]);
+    }
+}
@@ -0,0 +1,556 @@
+use crate::job_events::{job_events, spawn_tracked, JobEventKind};
+use crate::jobs::JobManager;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{bs58, signature::Keypair, signer::Signer};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::LazyLock;
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+pub const QUEUE_LAUNCH: &str = "launch";
+pub const QUEUE_TRANSACTIONS: &str = "transactions";
+pub const QUEUE_READS: &str = "reads";
+pub const QUEUE_CPU: &str = "cpu";
+
+/// Jobs of a queue allowed to run at once, unless configured otherwise
+const DEFAULT_CONCURRENCY: &[(&str, usize)] = &[
+    (QUEUE_LAUNCH, 1),
+    (QUEUE_TRANSACTIONS, 2),
+    (QUEUE_READS, 4),
+    (QUEUE_CPU, 1),
+];
+const UNKNOWN_QUEUE_CONCURRENCY: usize = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Where a job runs and which wallets it signs with
+#[derive(Debug, Clone)]
+pub struct JobRequest {
+    pub queue: String,
+    pub priority: JobPriority,
+    /// Public keys locked for the whole job
+    pub wallets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueuedJob {
+    pub job_id: String,
+    pub queue: String,
+    pub priority: JobPriority,
+    /// 1 for the next job of the queue to start
+    pub position: usize,
+    pub wallets: Vec<String>,
+    /// Wallets held by running jobs that this job waits for
+    pub blocked_by_wallets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QueueStatus {
+    pub queue: String,
+    pub concurrency: usize,
+    pub running: usize,
+    pub queued: usize,
+}
+
+struct Pending {
+    job_id: String,
+    request: JobRequest,
+    order: u64,
+    last_position: Option<usize>,
+    start: oneshot::Sender<()>,
+}
+
+struct QueueState {
+    concurrency: usize,
+    running: usize,
+}
+
+struct SchedulerState {
+    queues: HashMap<String, QueueState>,
+    pending: Vec<Pending>,
+    locked_wallets: HashSet<String>,
+    next_order: u64,
+}
+
+/// Starts queued jobs by priority, within the concurrency of their queue and
+/// never two at once signing with the same wallet
+pub struct JobScheduler {
+    state: Mutex<SchedulerState>,
+}
+
+/// Held while a job runs; frees its queue slot and wallets when dropped
+pub struct JobPermit {
+    queue: String,
+    wallets: Vec<String>,
+}
+
+static JOB_SCHEDULER: LazyLock<JobScheduler> = LazyLock::new(|| JobScheduler {
+    state: Mutex::new(SchedulerState {
+        queues: DEFAULT_CONCURRENCY
+            .iter()
+            .map(|(name, concurrency)| {
+                (
+                    name.to_string(),
+                    QueueState {
+                        concurrency: *concurrency,
+                        running: 0,
+                    },
+                )
+            })
+            .collect(),
+        pending: Vec::new(),
+        locked_wallets: HashSet::new(),
+        next_order: 0,
+    }),
+});
+
+pub fn job_scheduler() -> &'static JobScheduler {
+    &JOB_SCHEDULER
+}
+
+impl SchedulerState {
+    fn queue(&mut self, name: &str) -> &mut QueueState {
+        self.queues
+            .entry(name.to_string())
+            .or_insert_with(|| QueueState {
+                concurrency: UNKNOWN_QUEUE_CONCURRENCY,
+                running: 0,
+            })
+    }
+
+    /// Highest priority first, then first come first served
+    fn sort_pending(&mut self) {
+        self.pending.sort_by(|a, b| {
+            b.request
+                .priority
+                .cmp(&a.request.priority)
+                .then(a.order.cmp(&b.order))
+        });
+    }
+
+    /// Start every job that can run now and report the new queue positions
+    fn dispatch(&mut self) {
+        self.sort_pending();
+        let mut idx = 0;
+        while idx < self.pending.len() {
+            let request = &self.pending[idx].request;
+            let queue_name = request.queue.clone();
+            let queue = self.queue(&queue_name);
+            let has_slot = queue.running < queue.concurrency;
+            let wallets_free = self.pending[idx]
+                .request
+                .wallets
+                .iter()
+                .all(|w| !self.locked_wallets.contains(w));
+            if !(has_slot && wallets_free) {
+                idx += 1;
+                continue;
+            }
+
+            let pending = self.pending.remove(idx);
+            self.queue(&queue_name).running += 1;
+            self.locked_wallets
+                .extend(pending.request.wallets.iter().cloned());
+            if pending.start.send(()).is_ok() {
+                job_events().publish(&pending.job_id, JobEventKind::Started);
+            } else {
+                // The job was dropped while queued, nothing will hold the permit
+                self.release(&queue_name, &pending.request.wallets);
+            }
+        }
+
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for pending in self.pending.iter_mut() {
+            let position = positions.entry(pending.request.queue.clone()).or_insert(0);
+            *position += 1;
+            if pending.last_position != Some(*position) {
+                pending.last_position = Some(*position);
+                job_events().publish(
+                    &pending.job_id,
+                    JobEventKind::Queued {
+                        queue: pending.request.queue.clone(),
+                        position: *position,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Add a job behind those already waiting; it may start once the receiver fires
+    fn enqueue(&mut self, job_id: &str, request: JobRequest) -> oneshot::Receiver<()> {
+        let (start, started) = oneshot::channel();
+        let order = self.next_order;
+        self.next_order += 1;
+        self.pending.push(Pending {
+            job_id: job_id.to_string(),
+            request,
+            order,
+            last_position: None,
+            start,
+        });
+        started
+    }
+
+    fn release(&mut self, queue: &str, wallets: &[String]) {
+        let queue = self.queue(queue);
+        queue.running = queue.running.saturating_sub(1);
+        for wallet in wallets {
+            self.locked_wallets.remove(wallet);
+        }
+    }
+}
+
+impl JobScheduler {
+    /// Wait for the job's turn; the returned permit must be kept until the job ends
+    pub async fn acquire(&self, job_id: &str, request: JobRequest) -> JobPermit {
+        let mut wallets = request.wallets.clone();
+        wallets.sort();
+        wallets.dedup();
+        let request = JobRequest { wallets, ..request };
+        let started = {
+            let mut state = self.state.lock();
+            let started = state.enqueue(job_id, request.clone());
+            state.dispatch();
+            started
+        };
+        // The sender lives in the scheduler state, which is never dropped
+        let _ = started.await;
+        JobPermit {
+            queue: request.queue,
+            wallets: request.wallets,
+        }
+    }
+
+    /// Change how many jobs of a queue may run at once
+    pub fn configure_queue(&self, queue: &str, concurrency: usize) {
+        let mut state = self.state.lock();
+        state.queue(queue).concurrency = concurrency.max(1);
+        state.dispatch();
+    }
+
+    pub fn queue_status(&self) -> Vec<QueueStatus> {
+        let state = self.state.lock();
+        let mut status: Vec<QueueStatus> = state
+            .queues
+            .iter()
+            .map(|(name, queue)| QueueStatus {
+                queue: name.clone(),
+                concurrency: queue.concurrency,
+                running: queue.running,
+                queued: state
+                    .pending
+                    .iter()
+                    .filter(|p| p.request.queue == *name)
+                    .count(),
+            })
+            .collect();
+        status.sort_by(|a, b| a.queue.cmp(&b.queue));
+        status
+    }
+
+    /// Jobs waiting to start, in start order
+    pub fn queued_jobs(&self) -> Vec<QueuedJob> {
+        let state = self.state.lock();
+        state
+            .pending
+            .iter()
+            .map(|pending| QueuedJob {
+                job_id: pending.job_id.clone(),
+                queue: pending.request.queue.clone(),
+                priority: pending.request.priority,
+                position: pending.last_position.unwrap_or(0),
+                wallets: pending.request.wallets.clone(),
+                blocked_by_wallets: pending
+                    .request
+                    .wallets
+                    .iter()
+                    .filter(|w| state.locked_wallets.contains(*w))
+                    .cloned()
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for JobPermit {
+    fn drop(&mut self) {
+        let mut state = job_scheduler().state.lock();
+        state.release(&self.queue, &self.wallets);
+        state.dispatch();
+    }
+}
+
+/// Public key of a base58 private key, used as its wallet lock (None if invalid)
+pub fn signer_pubkey(private_key: &str) -> Option<String> {
+    let bytes = bs58::decode(private_key).into_vec().ok()?;
+    let keypair = Keypair::try_from(&bytes[..]).ok()?;
+    Some(keypair.pubkey().to_string())
+}
+
+/// Wallet locks of the valid private keys among `keys`
+pub fn signer_pubkeys<S: AsRef<str>>(keys: &[S]) -> Vec<String> {
+    keys.iter()
+        .filter_map(|key| signer_pubkey(key.as_ref()))
+        .collect()
+}
+
+/// Spawn a job that waits in its queue, and for its wallets, before running;
+/// like [`spawn_tracked`], `job` receives the id the manager assigned
+pub fn spawn_queued<F, Fut>(
+    manager: &JobManager,
+    name: &str,
+    request: JobRequest,
+    app_handle: AppHandle,
+    job: F,
+) -> String
+where
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    spawn_tracked(manager, name, app_handle, move |job_id| async move {
+        let _permit = job_scheduler().acquire(&job_id, request).await;
+        job(job_id).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(queues: &[(&str, usize)]) -> SchedulerState {
+        SchedulerState {
+            queues: queues
+                .iter()
+                .map(|(name, concurrency)| {
+                    (
+                        name.to_string(),
+                        QueueState {
+                            concurrency: *concurrency,
+                            running: 0,
+                        },
+                    )
+                })
+                .collect(),
+            pending: Vec::new(),
+            locked_wallets: HashSet::new(),
+            next_order: 0,
+        }
+    }
+
+    fn job_id(name: &str) -> String {
+        format!("{}_{}", name, uuid::Uuid::new_v4())
+    }
+
+    fn submit(
+        state: &mut SchedulerState,
+        job_id: &str,
+        queue: &str,
+        priority: JobPriority,
+        wallets: &[&str],
+    ) -> oneshot::Receiver<()> {
+        let request = JobRequest {
+            queue: queue.to_string(),
+            priority,
+            wallets: wallets.iter().map(|w| w.to_string()).collect(),
+        };
+        let started = state.enqueue(job_id, request);
+        state.dispatch();
+        started
+    }
+
+    /// What dropping the permit of a finished job does
+    fn finish(state: &mut SchedulerState, queue: &str, wallets: &[&str]) {
+        let wallets: Vec<String> = wallets.iter().map(|w| w.to_string()).collect();
+        state.release(queue, &wallets);
+        state.dispatch();
+    }
+
+    fn started(receiver: &mut oneshot::Receiver<()>) -> bool {
+        receiver.try_recv().is_ok()
+    }
+
+    fn positions(job_id: &str) -> Vec<usize> {
+        job_events()
+            .replay(Some(job_id), 0)
+            .into_iter()
+            .filter_map(|e| match e.kind {
+                JobEventKind::Queued { position, .. } => Some(position),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn higher_priority_starts_first_then_first_come() {
+        let mut state = state(&[(QUEUE_TRANSACTIONS, 1)]);
+        let mut running = submit(
+            &mut state,
+            &job_id("running"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Normal,
+            &[],
+        );
+        let mut low = submit(
+            &mut state,
+            &job_id("low"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Low,
+            &[],
+        );
+        let mut first = submit(
+            &mut state,
+            &job_id("first"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Normal,
+            &[],
+        );
+        let mut high = submit(
+            &mut state,
+            &job_id("high"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::High,
+            &[],
+        );
+        let mut second = submit(
+            &mut state,
+            &job_id("second"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Normal,
+            &[],
+        );
+        assert!(started(&mut running));
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            finish(&mut state, QUEUE_TRANSACTIONS, &[]);
+            for (name, receiver) in [
+                ("low", &mut low),
+                ("first", &mut first),
+                ("high", &mut high),
+                ("second", &mut second),
+            ] {
+                if started(receiver) {
+                    order.push(name);
+                }
+            }
+        }
+        assert_eq!(order, ["high", "first", "second", "low"]);
+    }
+
+    #[test]
+    fn queues_run_up_to_their_own_concurrency() {
+        let mut state = state(&[(QUEUE_TRANSACTIONS, 2), (QUEUE_READS, 1)]);
+        let mut tx: Vec<_> = (0..3)
+            .map(|i| {
+                submit(
+                    &mut state,
+                    &job_id(&format!("tx{}", i)),
+                    QUEUE_TRANSACTIONS,
+                    JobPriority::Normal,
+                    &[],
+                )
+            })
+            .collect();
+        let mut read = submit(
+            &mut state,
+            &job_id("read"),
+            QUEUE_READS,
+            JobPriority::Normal,
+            &[],
+        );
+        let mut unknown = submit(
+            &mut state,
+            &job_id("unknown"),
+            "unknown",
+            JobPriority::Normal,
+            &[],
+        );
+
+        let running: Vec<bool> = tx.iter_mut().map(started).collect();
+        assert_eq!(running, [true, true, false]);
+        assert!(started(&mut read));
+        assert!(started(&mut unknown));
+        assert_eq!(state.queue(QUEUE_TRANSACTIONS).running, 2);
+        assert_eq!(
+            state.queue("unknown").concurrency,
+            UNKNOWN_QUEUE_CONCURRENCY
+        );
+
+        finish(&mut state, QUEUE_TRANSACTIONS, &[]);
+        assert!(started(&mut tx[2]));
+        assert!(state.pending.is_empty());
+    }
+
+    #[test]
+    fn locked_wallet_blocks_a_second_job() {
+        let mut state = state(&[(QUEUE_TRANSACTIONS, 4)]);
+        let mut first = submit(
+            &mut state,
+            &job_id("first"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Normal,
+            &["wallet_a", "wallet_b"],
+        );
+        let mut same_wallet = submit(
+            &mut state,
+            &job_id("same_wallet"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::High,
+            &["wallet_b"],
+        );
+        let mut other_wallet = submit(
+            &mut state,
+            &job_id("other_wallet"),
+            QUEUE_TRANSACTIONS,
+            JobPriority::Normal,
+            &["wallet_c"],
+        );
+
+        assert!(started(&mut first));
+        assert!(!started(&mut same_wallet));
+        assert!(started(&mut other_wallet));
+        assert!(state.locked_wallets.contains("wallet_b"));
+
+        finish(&mut state, QUEUE_TRANSACTIONS, &["wallet_a", "wallet_b"]);
+        assert!(started(&mut same_wallet));
+        assert!(!state.locked_wallets.contains("wallet_a"));
+    }
+
+    #[test]
+    fn queue_positions_are_published_when_they_change() {
+        let mut state = state(&[(QUEUE_CPU, 1)]);
+        let running = job_id("running");
+        let (a, b) = (job_id("a"), job_id("b"));
+        let _running = submit(&mut state, &running, QUEUE_CPU, JobPriority::Normal, &[]);
+        let _a = submit(&mut state, &a, QUEUE_CPU, JobPriority::Normal, &[]);
+        let _b = submit(&mut state, &b, QUEUE_CPU, JobPriority::Normal, &[]);
+        assert!(positions(&running).is_empty());
+        assert_eq!(positions(&a), [1]);
+        assert_eq!(positions(&b), [2]);
+
+        // A higher priority job goes ahead of both
+        let high = job_id("high");
+        let _high = submit(&mut state, &high, QUEUE_CPU, JobPriority::High, &[]);
+        assert_eq!(positions(&high), [1]);
+        assert_eq!(positions(&a), [1, 2]);
+        assert_eq!(positions(&b), [2, 3]);
+
+        finish(&mut state, QUEUE_CPU, &[]);
+        assert_eq!(positions(&a), [1, 2, 1]);
+        assert_eq!(positions(&b), [2, 3, 2]);
+        let started: Vec<_> = job_events()
+            .replay(Some(&high), 0)
+            .into_iter()
+            .filter(|e| matches!(e.kind, JobEventKind::Started))
+            .collect();
+        assert_eq!(started.len(), 1);
+    }
+}
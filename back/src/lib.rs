@@ -3,7 +3,10 @@ use tracing_subscriber::{fmt::time::ChronoLocal, FmtSubscriber};
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .invoke_handler(commands::invoke_handler())
         .setup(|app| {
+            job_events::job_events().attach(app.handle());
+            api_server::start(app.handle());
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -36,7 +39,19 @@ pub fn set_job_manager_instance(manager: Arc<jobs::JobManager>) {
     let _ = JOB_MANAGER.set(manager);
 }
 
+pub fn job_manager_instance() -> Option<Arc<jobs::JobManager>> {
+    JOB_MANAGER.get().cloned()
+}
+
 pub fn update_job_progress_items(job_id: &str, completed: u32, total: u32, step: String) -> bool {
+    job_events::job_events().publish(
+        job_id,
+        job_events::JobEventKind::Progress {
+            completed,
+            total,
+            step: Some(step.clone()),
+        },
+    );
     if let Some(manager) = JOB_MANAGER.get() {
         manager.update_progress_items(job_id, completed, total, Some(step))
     } else {
@@ -48,21 +63,53 @@ pub fn update_job_progress_items(job_id: &str, completed: u32, total: u32, step:
 pub fn update_job_progress(job_id: &str, completed: u32, total: u32, step: String) -> bool {
     update_job_progress_items(job_id, completed, total, step)
 }
+
+// Helper for services reporting the outcome of one item of a batch job
+pub fn report_job_item(job_id: &str, item: String, success: bool, message: Option<String>) {
+    job_events::job_events().publish(
+        job_id,
+        job_events::JobEventKind::ItemCompleted {
+            item,
+            success,
+            message,
+        },
+    );
+}
+pub mod api_server;
+pub mod commands;
 pub mod config;
+pub mod job_commands;
+pub mod job_events;
+pub mod job_queue;
 pub mod jobs;
+pub mod scheduler;
+pub mod secret;
 pub mod solana;
 pub mod services {
+    pub mod balance_snapshot;
+    pub mod burn_and_close;
     pub mod burn_tokens;
+    pub mod cleanup;
     pub mod close_accounts;
     pub mod close_token_account;
     pub mod common;
     pub mod create_meme_token;
     pub mod distribute_sol;
+    pub mod export;
     pub mod get_sol_balance;
     pub mod get_token_balance;
+    pub mod keystore;
+    pub mod launch_preview;
+    pub mod local_cache;
     pub mod long_polling;
     pub mod quick_buy;
     pub mod quick_sell;
     pub mod refund_wallets;
+    pub mod rent_report;
     pub mod sniper_bot;
+    pub mod token_metadata;
+    pub mod tx_history;
+    pub mod vanity;
+    pub mod vault;
+    pub mod wallet_import;
 }
@@ -0,0 +1,633 @@
+use crate::jobs::JobManager;
+use crate::secret::SecretString;
+use crate::services::local_cache;
+use crate::services::long_polling::{self, BatchJobKind, BatchJobRequest, LongPollingService};
+use crate::services::vault::{self, EncryptedBlob};
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+use tauri::AppHandle;
+
+const SCHEDULE_NAMESPACE: &str = "schedules";
+const SCHEDULE_KEY: &str = "all";
+/// Runs kept per schedule, oldest dropped first
+const MAX_RUNS_KEPT: usize = 50;
+const TICK_INTERVAL: Duration = Duration::from_secs(15);
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Source of the current time, replaced in tests
+pub trait Clock: Send + Sync {
+    /// Unix timestamp in seconds
+    fn now(&self) -> i64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+/// Clock that only moves when told to
+pub struct ManualClock(AtomicI64);
+
+impl ManualClock {
+    pub fn new(now: i64) -> Self {
+        ManualClock(AtomicI64::new(now))
+    }
+
+    pub fn set(&self, now: i64) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleTrigger {
+    /// Run once at a unix timestamp
+    Once { at: i64 },
+    /// Run every `every_secs`, first at `start_at` (one interval from now by default)
+    Interval {
+        every_secs: u64,
+        #[serde(default)]
+        start_at: Option<i64>,
+    },
+    /// Run every day at hour:minute UTC
+    Daily { hour: u8, minute: u8 },
+}
+
+impl ScheduleTrigger {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            ScheduleTrigger::Once { .. } => Ok(()),
+            ScheduleTrigger::Interval { every_secs, .. } if *every_secs < 60 => {
+                Err("Interval must be at least 60 seconds".to_string())
+            }
+            ScheduleTrigger::Interval { .. } => Ok(()),
+            ScheduleTrigger::Daily { hour, minute } if *hour > 23 || *minute > 59 => {
+                Err(format!("Invalid time of day {:02}:{:02}", hour, minute))
+            }
+            ScheduleTrigger::Daily { .. } => Ok(()),
+        }
+    }
+
+    /// First run of a schedule created at `now`
+    fn first_run(&self, now: i64) -> i64 {
+        match self {
+            ScheduleTrigger::Once { at } => *at,
+            ScheduleTrigger::Interval {
+                every_secs,
+                start_at,
+            } => start_at.unwrap_or(now + *every_secs as i64),
+            ScheduleTrigger::Daily { .. } => self.next_after(now).unwrap_or(now),
+        }
+    }
+
+    /// Next run strictly after `now` of a run due at `scheduled`; missed
+    /// occurrences are skipped rather than replayed
+    fn next_after_run(&self, scheduled: i64, now: i64) -> Option<i64> {
+        match self {
+            ScheduleTrigger::Interval { every_secs, .. } => {
+                let every = *every_secs as i64;
+                let missed = (now - scheduled).max(0) / every;
+                Some(scheduled + every * (missed + 1))
+            }
+            _ => self.next_after(now),
+        }
+    }
+
+    fn next_after(&self, now: i64) -> Option<i64> {
+        match self {
+            ScheduleTrigger::Once { .. } => None,
+            ScheduleTrigger::Interval { every_secs, .. } => Some(now + *every_secs as i64),
+            ScheduleTrigger::Daily { hour, minute } => {
+                let day_start = now - now.rem_euclid(SECS_PER_DAY);
+                let today = day_start + *hour as i64 * 3600 + *minute as i64 * 60;
+                Some(if today > now {
+                    today
+                } else {
+                    today + SECS_PER_DAY
+                })
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleRunStatus {
+    /// Jobs started, outcome not known yet
+    Fired,
+    Completed,
+    Failed,
+    /// Not started, see the run error
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleRun {
+    pub scheduled_for: i64,
+    pub fired_at: i64,
+    pub batch_id: Option<String>,
+    pub status: ScheduleRunStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Schedule {
+    pub id: String,
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    pub enabled: bool,
+    /// Kinds of the scheduled jobs, readable without the password
+    pub job_kinds: Vec<String>,
+    pub max_concurrent: Option<usize>,
+    /// Scheduled jobs (they hold private keys), encrypted with the schedules password
+    pub jobs: EncryptedBlob,
+    pub created_at: i64,
+    pub next_run_at: Option<i64>,
+    pub runs: Vec<ScheduleRun>,
+}
+
+/// Schedule as created from the UI
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScheduleSpec {
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    pub jobs: Vec<BatchJobKind>,
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+}
+
+/// Due schedules and their runs, driven by a [`Clock`]
+pub struct Scheduler {
+    clock: Arc<dyn Clock>,
+    schedules: Mutex<Vec<Schedule>>,
+    persist: bool,
+}
+
+impl Scheduler {
+    /// Scheduler keeping schedules in memory only
+    pub fn in_memory(clock: Arc<dyn Clock>) -> Self {
+        Scheduler {
+            clock,
+            schedules: Mutex::new(Vec::new()),
+            persist: false,
+        }
+    }
+
+    /// Scheduler backed by the local cache
+    pub fn load(clock: Arc<dyn Clock>) -> Self {
+        Scheduler {
+            clock,
+            schedules: Mutex::new(
+                local_cache::load(SCHEDULE_NAMESPACE, SCHEDULE_KEY).unwrap_or_default(),
+            ),
+            persist: true,
+        }
+    }
+
+    fn save(&self, schedules: &[Schedule]) {
+        if !self.persist {
+            return;
+        }
+        if let Err(e) = local_cache::store(SCHEDULE_NAMESPACE, SCHEDULE_KEY, &schedules) {
+            warn!("Failed to save schedules: {:#}", e);
+        }
+    }
+
+    pub fn add(
+        &self,
+        name: String,
+        trigger: ScheduleTrigger,
+        job_kinds: Vec<String>,
+        max_concurrent: Option<usize>,
+        jobs: EncryptedBlob,
+    ) -> Result<Schedule, String> {
+        trigger.validate()?;
+        let now = self.clock.now();
+        let next_run_at = trigger.first_run(now);
+        if matches!(trigger, ScheduleTrigger::Once { .. }) && next_run_at <= now {
+            return Err("Scheduled time is in the past".to_string());
+        }
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            trigger,
+            enabled: true,
+            job_kinds,
+            max_concurrent,
+            jobs,
+            created_at: now,
+            next_run_at: Some(next_run_at),
+            runs: Vec::new(),
+        };
+        let mut schedules = self.schedules.lock();
+        schedules.push(schedule.clone());
+        self.save(&schedules);
+        Ok(schedule)
+    }
+
+    pub fn list(&self) -> Vec<Schedule> {
+        self.schedules.lock().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Schedule> {
+        self.schedules.lock().iter().find(|s| s.id == id).cloned()
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        let mut schedules = self.schedules.lock();
+        let before = schedules.len();
+        schedules.retain(|s| s.id != id);
+        let removed = schedules.len() != before;
+        if removed {
+            self.save(&schedules);
+        }
+        removed
+    }
+
+    /// Pause or resume a schedule; a resumed schedule skips the runs it missed
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> Result<Schedule, String> {
+        let now = self.clock.now();
+        let mut schedules = self.schedules.lock();
+        let schedule = schedules
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Unknown schedule {}", id))?;
+        schedule.enabled = enabled;
+        if enabled {
+            if let Some(next) = schedule.next_run_at.filter(|next| *next <= now) {
+                schedule.next_run_at = schedule.trigger.next_after_run(next, now);
+            }
+        }
+        let schedule = schedule.clone();
+        self.save(&schedules);
+        Ok(schedule)
+    }
+
+    /// Fire every enabled schedule that is due; `fire` returns the started batch id.
+    /// Returns the runs recorded by this tick
+    pub fn tick(
+        &self,
+        mut fire: impl FnMut(&Schedule) -> Result<String, String>,
+    ) -> Vec<ScheduleRun> {
+        let now = self.clock.now();
+        let mut schedules = self.schedules.lock();
+        let mut recorded = Vec::new();
+
+        for schedule in schedules.iter_mut() {
+            let Some(scheduled_for) = schedule.next_run_at else {
+                continue;
+            };
+            if !schedule.enabled || scheduled_for > now {
+                continue;
+            }
+
+            let run = match fire(schedule) {
+                Ok(batch_id) => ScheduleRun {
+                    scheduled_for,
+                    fired_at: now,
+                    batch_id: Some(batch_id),
+                    status: ScheduleRunStatus::Fired,
+                    error: None,
+                },
+                Err(e) => ScheduleRun {
+                    scheduled_for,
+                    fired_at: now,
+                    batch_id: None,
+                    status: ScheduleRunStatus::Skipped,
+                    error: Some(e),
+                },
+            };
+            info!(
+                "Schedule {} ({}) run for {}: {:?}",
+                schedule.name, schedule.id, scheduled_for, run.status
+            );
+            schedule.next_run_at = schedule.trigger.next_after_run(scheduled_for, now);
+            if schedule.runs.len() == MAX_RUNS_KEPT {
+                schedule.runs.remove(0);
+            }
+            schedule.runs.push(run.clone());
+            recorded.push(run);
+        }
+
+        if !recorded.is_empty() {
+            self.save(&schedules);
+        }
+        recorded
+    }
+
+    /// Record the outcome of fired runs once `outcome` knows it (Some(Ok) or Some(Err))
+    pub fn record_outcomes(&self, outcome: impl Fn(&str) -> Option<Result<(), String>>) {
+        let mut schedules = self.schedules.lock();
+        let mut changed = false;
+        for run in schedules.iter_mut().flat_map(|s| s.runs.iter_mut()) {
+            if run.status != ScheduleRunStatus::Fired {
+                continue;
+            }
+            let Some(result) = run.batch_id.as_deref().and_then(&outcome) else {
+                continue;
+            };
+            (run.status, run.error) = match result {
+                Ok(()) => (ScheduleRunStatus::Completed, None),
+                Err(e) => (ScheduleRunStatus::Failed, Some(e)),
+            };
+            changed = true;
+        }
+        if changed {
+            self.save(&schedules);
+        }
+    }
+}
+
+static SCHEDULER: LazyLock<Scheduler> = LazyLock::new(|| Scheduler::load(Arc::new(SystemClock)));
+/// Password of the scheduled jobs, held in memory once unlocked
+static SCHEDULES_PASSWORD: Mutex<Option<SecretString>> = Mutex::new(None);
+static RUNNER_STARTED: OnceLock<()> = OnceLock::new();
+
+pub fn scheduler() -> &'static Scheduler {
+    &SCHEDULER
+}
+
+fn session_password() -> Result<SecretString, String> {
+    SCHEDULES_PASSWORD
+        .lock()
+        .clone()
+        .ok_or_else(|| "Schedules are locked, unlock them to run scheduled jobs".to_string())
+}
+
+/// Encrypt the jobs with the session password and add the schedule
+pub fn create_schedule(spec: ScheduleSpec) -> Result<Schedule, String> {
+    if spec.jobs.is_empty() {
+        return Err("No jobs provided".to_string());
+    }
+    let password = session_password()?;
+    let job_kinds = spec.jobs.iter().map(|j| j.label().to_string()).collect();
+    let plaintext = serde_json::to_vec(&spec.jobs).map_err(|e| e.to_string())?;
+    let jobs = vault::encrypt(password.expose(), &plaintext).map_err(|e| format!("{:#}", e))?;
+    scheduler().add(
+        spec.name,
+        spec.trigger,
+        job_kinds,
+        spec.max_concurrent,
+        jobs,
+    )
+}
+
+fn decrypt_jobs(schedule: &Schedule, password: &str) -> Result<Vec<BatchJobKind>, String> {
+    let plaintext = vault::decrypt(password, &schedule.jobs).map_err(|e| format!("{:#}", e))?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("invalid scheduled jobs: {}", e))
+}
+
+/// Batch outcome once every child job ended
+fn batch_outcome(batch_id: &str) -> Option<Result<(), String>> {
+    let batch = long_polling::get_batch(batch_id)?;
+    batch.finished_at?;
+    if batch.completed_jobs == batch.total_jobs {
+        Some(Ok(()))
+    } else {
+        Some(Err(format!(
+            "{} completed, {} failed, {} cancelled",
+            batch.completed_jobs, batch.failed_jobs, batch.cancelled_jobs
+        )))
+    }
+}
+
+/// Hold the password in memory and start firing due schedules through the job manager
+pub fn unlock_schedules(
+    password: SecretString,
+    job_manager: JobManager,
+    app_handle: AppHandle,
+) -> Result<Vec<Schedule>, String> {
+    let schedules = scheduler().list();
+    // Every schedule shares the password, checking one is enough
+    if let Some(schedule) = schedules.first() {
+        decrypt_jobs(schedule, password.expose())?;
+    } else if password.expose().is_empty() {
+        return Err("Password must not be empty".to_string());
+    }
+    *SCHEDULES_PASSWORD.lock() = Some(password);
+
+    if RUNNER_STARTED.set(()).is_ok() {
+        info!("Starting schedule runner");
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let job_manager = job_manager.clone();
+                let app_handle = app_handle.clone();
+                // Decrypting the jobs is slow on purpose, keep it off the async workers
+                let ticked = tauri::async_runtime::spawn_blocking(move || {
+                    scheduler().tick(|schedule| {
+                        let password = session_password()?;
+                        let jobs = decrypt_jobs(schedule, password.expose())?;
+                        LongPollingService::start_controlled_batch(
+                            BatchJobRequest {
+                                name: Some(format!("schedule {}", schedule.name)),
+                                jobs,
+                                max_concurrent: schedule.max_concurrent,
+                                priority: None,
+                            },
+                            &job_manager,
+                            app_handle.clone(),
+                        )
+                    });
+                    scheduler().record_outcomes(batch_outcome);
+                })
+                .await;
+                if let Err(e) = ticked {
+                    warn!("Schedule tick failed: {}", e);
+                }
+                tokio::time::sleep(TICK_INTERVAL).await;
+            }
+        });
+    }
+    Ok(schedules)
+}
+
+/// Forget the password; due schedules are skipped until unlocked again
+pub fn lock_schedules() {
+    *SCHEDULES_PASSWORD.lock() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-01-01T00:00:00Z
+    const T0: i64 = 1_735_689_600;
+
+    fn blob() -> EncryptedBlob {
+        EncryptedBlob {
+            kdf: "test".to_string(),
+            iterations: 0,
+            salt: String::new(),
+            nonce: String::new(),
+            ciphertext: String::new(),
+        }
+    }
+
+    fn setup() -> (Arc<ManualClock>, Scheduler) {
+        let clock = Arc::new(ManualClock::new(T0));
+        let scheduler = Scheduler::in_memory(clock.clone());
+        (clock, scheduler)
+    }
+
+    fn add(scheduler: &Scheduler, trigger: ScheduleTrigger) -> Schedule {
+        scheduler
+            .add("test".to_string(), trigger, vec![], None, blob())
+            .unwrap()
+    }
+
+    #[test]
+    fn once_fires_when_due_and_only_once() {
+        let (clock, scheduler) = setup();
+        add(&scheduler, ScheduleTrigger::Once { at: T0 + 3600 });
+
+        let mut fired = 0;
+        assert!(scheduler.tick(|_| Ok("early".to_string())).is_empty());
+
+        clock.advance(3600);
+        let runs = scheduler.tick(|_| {
+            fired += 1;
+            Ok("batch".to_string())
+        });
+        assert_eq!(fired, 1);
+        assert_eq!(runs[0].scheduled_for, T0 + 3600);
+        assert_eq!(runs[0].status, ScheduleRunStatus::Fired);
+
+        clock.advance(SECS_PER_DAY);
+        assert!(scheduler.tick(|_| Ok("again".to_string())).is_empty());
+        assert_eq!(scheduler.list()[0].next_run_at, None);
+    }
+
+    #[test]
+    fn once_in_the_past_is_rejected() {
+        let (_, scheduler) = setup();
+        let result = scheduler.add(
+            "late".to_string(),
+            ScheduleTrigger::Once { at: T0 - 1 },
+            vec![],
+            None,
+            blob(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn interval_skips_missed_runs() {
+        let (clock, scheduler) = setup();
+        add(
+            &scheduler,
+            ScheduleTrigger::Interval {
+                every_secs: 300,
+                start_at: None,
+            },
+        );
+        assert_eq!(scheduler.list()[0].next_run_at, Some(T0 + 300));
+
+        // Closed for ~an hour: one catch-up run, then back on the grid
+        clock.set(T0 + 3_700);
+        assert_eq!(scheduler.tick(|_| Ok("b".to_string())).len(), 1);
+        assert!(scheduler.tick(|_| Ok("b".to_string())).is_empty());
+        assert_eq!(scheduler.list()[0].next_run_at, Some(T0 + 3_900));
+    }
+
+    #[test]
+    fn daily_runs_at_time_of_day() {
+        let (clock, scheduler) = setup();
+        clock.set(T0 + 3 * 3600);
+        add(
+            &scheduler,
+            ScheduleTrigger::Daily {
+                hour: 2,
+                minute: 30,
+            },
+        );
+        // 02:30 already passed today
+        let first = T0 + SECS_PER_DAY + 2 * 3600 + 30 * 60;
+        assert_eq!(scheduler.list()[0].next_run_at, Some(first));
+
+        clock.set(first);
+        assert_eq!(scheduler.tick(|_| Ok("b".to_string())).len(), 1);
+        assert_eq!(scheduler.list()[0].next_run_at, Some(first + SECS_PER_DAY));
+    }
+
+    #[test]
+    fn invalid_triggers_are_rejected() {
+        let (_, scheduler) = setup();
+        for trigger in [
+            ScheduleTrigger::Daily {
+                hour: 24,
+                minute: 0,
+            },
+            ScheduleTrigger::Interval {
+                every_secs: 10,
+                start_at: None,
+            },
+        ] {
+            assert!(scheduler
+                .add("bad".to_string(), trigger, vec![], None, blob())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn disabled_schedules_do_not_fire_and_resume_on_the_grid() {
+        let (clock, scheduler) = setup();
+        let schedule = add(
+            &scheduler,
+            ScheduleTrigger::Interval {
+                every_secs: 600,
+                start_at: Some(T0 + 600),
+            },
+        );
+        scheduler.set_enabled(&schedule.id, false).unwrap();
+
+        clock.set(T0 + 1_900);
+        assert!(scheduler.tick(|_| Ok("b".to_string())).is_empty());
+
+        let resumed = scheduler.set_enabled(&schedule.id, true).unwrap();
+        assert_eq!(resumed.next_run_at, Some(T0 + 2_400));
+    }
+
+    #[test]
+    fn failed_fires_and_outcomes_are_recorded() {
+        let (clock, scheduler) = setup();
+        add(&scheduler, ScheduleTrigger::Once { at: T0 + 60 });
+        add(&scheduler, ScheduleTrigger::Once { at: T0 + 60 });
+        clock.advance(60);
+
+        let mut calls = 0;
+        let runs = scheduler.tick(|_| {
+            calls += 1;
+            if calls == 1 {
+                Ok("batch-1".to_string())
+            } else {
+                Err("locked".to_string())
+            }
+        });
+        assert_eq!(runs[1].status, ScheduleRunStatus::Skipped);
+        assert_eq!(runs[1].error.as_deref(), Some("locked"));
+
+        scheduler.record_outcomes(|batch_id| {
+            (batch_id == "batch-1").then(|| Err("1 failed".to_string()))
+        });
+        let schedules = scheduler.list();
+        assert_eq!(schedules[0].runs[0].status, ScheduleRunStatus::Failed);
+        assert_eq!(schedules[0].runs[0].error.as_deref(), Some("1 failed"));
+    }
+}
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A private key or password. `Debug` and `Display` print `[REDACTED]` so request
+/// structs can be logged as a whole; serde still reads and writes the value, since
+/// requests carry the key to the backend and scheduled jobs are stored encrypted.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        SecretString(secret)
+    }
+
+    /// The secret itself; keep it out of logs and error messages
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(secret.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::ScheduleSpec;
+    use crate::services::burn_tokens::BurnTokensBatchRequest;
+    use crate::services::close_token_account::CloseTokenAccountBatchRequest;
+    use crate::services::common::{CreateTokenReq, QuickBuyReq, QuickSellReq};
+    use crate::services::long_polling::{BatchJobKind, BatchJobRequest};
+    use crate::services::wallet_import::{ImportSource, ImportedWallet};
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+    use solana_sdk::signature::Keypair;
+
+    const PASSWORD: &str = "correct horse battery staple";
+    const PHRASE: &str =
+        "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    fn key() -> String {
+        Keypair::new().to_base58_string()
+    }
+
+    fn assert_redacted(debug: &str, secrets: &[&str]) {
+        for secret in secrets {
+            assert!(!debug.contains(secret), "secret leaked in {}", debug);
+        }
+        assert!(debug.contains(REDACTED), "nothing redacted in {}", debug);
+    }
+
+    /// Parse a request the way a command receives it, check that no secret shows
+    /// in its `Debug` output and that serde still carries every secret
+    fn check<T: fmt::Debug + Serialize + DeserializeOwned>(payload: Value, secrets: &[&str]) {
+        let req: T = serde_json::from_value(payload.clone()).unwrap();
+        assert_redacted(&format!("{:?}", req), secrets);
+        assert_redacted(&format!("{:#?}", req), secrets);
+        assert_eq!(serde_json::to_value(&req).unwrap(), payload);
+    }
+
+    #[test]
+    fn secret_string_redacts_debug_and_display() {
+        let secret = SecretString::from(PASSWORD);
+        assert_eq!(format!("{:?}", secret), REDACTED);
+        assert_eq!(secret.to_string(), REDACTED);
+        assert_eq!(secret.expose(), PASSWORD);
+        assert_eq!(serde_json::to_value(&secret).unwrap(), json!(PASSWORD));
+    }
+
+    #[test]
+    fn command_requests_do_not_leak_keys() {
+        let (dev, sniper, wallet) = (key(), key(), key());
+
+        check::<QuickBuyReq>(
+            json!({ "pk": wallet, "amount_sol": 0.5, "mint": "mint", "slippage_bps": 500 }),
+            &[&wallet],
+        );
+        check::<QuickSellReq>(
+            json!({
+                "wallet": "wallet",
+                "pk": wallet,
+                "percent": 100,
+                "mint": "mint",
+                "slippage_bps": null,
+            }),
+            &[&wallet],
+        );
+        check::<CreateTokenReq>(
+            json!({
+                "name": "Token",
+                "symbol": "TKN",
+                "decimals": 6.0,
+                "description": "",
+                "website": "",
+                "telegram": "",
+                "twitter": "",
+                "supply_human": 1_000_000_000u64,
+                "supply_base_units": 1.0e15,
+                "dev_wallet": dev,
+                "dev_token_amount": 0,
+                "dev_sol_amount": 1.0,
+                "sniper_wallet_one": sniper,
+                "amount_sol_sniper_one": 0.5,
+                "creation_tip_sol": 0.001,
+                "tip_sol": 0.001,
+                "slippage_bps": 500,
+                "cu_price_microlamports": 100_000,
+                "max_unit_price_microlamports": 200_000,
+                "image_path": null,
+                "image_base64": null,
+                "template": null,
+                "vanity_mint": null,
+                "plan_id": null,
+            }),
+            &[&dev, &sniper],
+        );
+        check::<BurnTokensBatchRequest>(
+            json!({ "wallet_pk": wallet, "token_mints": ["mint"] }),
+            &[&wallet],
+        );
+        check::<CloseTokenAccountBatchRequest>(
+            json!({ "wallet_pk": wallet, "token_mints": ["mint"], "rent_destination": null }),
+            &[&wallet],
+        );
+        check::<ImportSource>(
+            json!({
+                "type": "mnemonic",
+                "phrase": PHRASE,
+                "passphrase": PASSWORD,
+                "start_index": 0,
+                "count": 1,
+            }),
+            &[PHRASE, PASSWORD],
+        );
+        check::<ImportSource>(
+            json!({ "type": "bundle", "path": "backup.json", "password": PASSWORD }),
+            &[PASSWORD],
+        );
+        check::<ImportedWallet>(
+            json!({ "pubkey": "pubkey", "private_key": wallet }),
+            &[&wallet],
+        );
+    }
+
+    #[test]
+    fn batch_and_scheduled_jobs_do_not_leak_keys() {
+        let wallet = key();
+        let jobs = json!([
+            { "kind": "quick_buy", "params": {
+                "pk": wallet, "amount_sol": 0.1, "mint": "mint", "slippage_bps": null,
+            } },
+            { "kind": "quick_sell", "params": {
+                "wallet": "wallet", "pk": wallet, "percent": 50, "mint": "mint",
+                "slippage_bps": null,
+            } },
+            { "kind": "burn_tokens", "params": {
+                "wallet_pk": wallet, "mint_address": "mint", "burn_percentage": 100.0,
+            } },
+            { "kind": "close_accounts", "params": {
+                "wallet_pk": wallet, "rent_destination": null,
+            } },
+            { "kind": "close_token_account", "params": {
+                "wallet_pk": wallet, "token_mint": "mint", "rent_destination": null,
+            } },
+            { "kind": "burn_and_close", "params": {
+                "wallet_pk": wallet, "mints": ["mint"], "rent_destination": null,
+            } },
+        ]);
+
+        for job in jobs.as_array().unwrap() {
+            check::<BatchJobKind>(job.clone(), &[&wallet]);
+        }
+
+        let batch: BatchJobRequest =
+            serde_json::from_value(json!({ "jobs": jobs, "max_concurrent": 2 })).unwrap();
+        assert_redacted(&format!("{:?}", batch), &[&wallet]);
+
+        let schedule: ScheduleSpec = serde_json::from_value(json!({
+            "name": "nightly",
+            "trigger": { "type": "daily", "hour": 3, "minute": 0 },
+            "jobs": jobs,
+        }))
+        .unwrap();
+        assert_redacted(&format!("{:?}", schedule), &[&wallet]);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::config::CONFIG;
+use crate::services::common::lamports_to_sol;
+use crate::services::get_token_balance::TokenBalance;
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{decode_keyed_account, fetch_token_accounts};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
+
+/// getMultipleAccounts accepts at most 100 keys per request
+const ACCOUNTS_PER_REQUEST: usize = 100;
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+const MAX_WALLETS: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletSnapshot {
+    pub wallet: String,
+    pub sol_lamports: u64,
+    pub sol: f64,
+    pub tokens: Vec<TokenBalance>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MintTotal {
+    pub mint: String,
+    pub decimals: u8,
+    /// Exact total in base units, as a string since it can exceed u64
+    pub amount_raw: String,
+    pub amount: f64,
+    pub holders: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupBalanceSnapshot {
+    pub wallets: Vec<WalletSnapshot>,
+    pub total_sol_lamports: u64,
+    pub total_sol: f64,
+    pub mint_totals: Vec<MintTotal>,
+    pub taken_at: i64,
+}
+
+/// Fetch lamports for many wallets with one getMultipleAccounts call per 100 wallets;
+/// a failed call only fails the wallets of its chunk
+fn fetch_sol_balances(client: &dyn SolanaRpc, wallets: &[Pubkey]) -> Vec<Result<u64, String>> {
+    let mut balances = Vec::with_capacity(wallets.len());
+    for chunk in wallets.chunks(ACCOUNTS_PER_REQUEST) {
+        match client.get_multiple_accounts(chunk) {
+            // Wallets that were never funded come back as None
+            Ok(accounts) => balances.extend(
+                accounts
+                    .iter()
+                    .map(|a| Ok(a.as_ref().map_or(0, |a| a.lamports))),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to get SOL balances of {} wallets: {}",
+                    chunk.len(),
+                    e
+                );
+                let error = format!("failed to get SOL balance: {}", e);
+                balances.extend(chunk.iter().map(|_| Err(error.clone())));
+            }
+        }
+    }
+    balances
+}
+
+/// Fetch all token holdings of a wallet, one getTokenAccountsByOwner call per token program
+fn fetch_token_holdings(
+    client: &dyn SolanaRpc,
+    wallet: &Pubkey,
+) -> anyhow::Result<Vec<TokenBalance>> {
+    let token_accounts = fetch_token_accounts(client, wallet)?;
+
+    let mut holdings = Vec::with_capacity(token_accounts.len());
+    for token_account in token_accounts {
+        match decode_keyed_account(&token_account) {
+            Ok((account_pubkey, decoded)) => holdings.push(TokenBalance {
+                balance: decoded.ui_amount().unwrap_or(0.0),
+                balance_raw: decoded.amount,
+                decimals: decoded.decimals.unwrap_or(0),
+                token_account: account_pubkey.to_string(),
+                mint: Some(decoded.mint.to_string()),
+            }),
+            Err(e) => warn!("Could not parse token account data: {:#}", e),
+        }
+    }
+    Ok(holdings)
+}
+
+fn aggregate_mints(wallets: &[WalletSnapshot]) -> Vec<MintTotal> {
+    let mut totals: BTreeMap<String, (u8, u128, usize)> = BTreeMap::new();
+    for wallet in wallets {
+        for token in &wallet.tokens {
+            let Some(mint) = &token.mint else { continue };
+            let entry = totals.entry(mint.clone()).or_insert((token.decimals, 0, 0));
+            entry.1 += token.balance_raw as u128;
+            if token.balance_raw > 0 {
+                entry.2 += 1;
+            }
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(mint, (decimals, amount_raw, holders))| MintTotal {
+            mint,
+            decimals,
+            amount_raw: amount_raw.to_string(),
+            amount: amount_raw as f64 / 10f64.powi(decimals as i32),
+            holders,
+        })
+        .collect()
+}
+
+/// Snapshot of one wallet; whatever could not be fetched counts as zero and is
+/// reported in `error`
+fn wallet_snapshot(
+    wallet: String,
+    lamports: Result<u64, String>,
+    tokens: Option<anyhow::Result<Vec<TokenBalance>>>,
+) -> WalletSnapshot {
+    let mut errors = Vec::new();
+    let sol_lamports = lamports.unwrap_or_else(|e| {
+        errors.push(e);
+        0
+    });
+    let tokens = match tokens {
+        Some(Ok(tokens)) => tokens,
+        Some(Err(e)) => {
+            warn!("Failed to fetch token holdings for {}: {}", wallet, e);
+            errors.push(e.to_string());
+            vec![]
+        }
+        None => {
+            errors.push("token holdings not fetched".to_string());
+            vec![]
+        }
+    };
+    WalletSnapshot {
+        wallet,
+        sol_lamports,
+        sol: lamports_to_sol(sol_lamports),
+        tokens,
+        error: (!errors.is_empty()).then(|| errors.join("; ")),
+    }
+}
+
+fn group_snapshot(wallets: Vec<WalletSnapshot>) -> GroupBalanceSnapshot {
+    let total_sol_lamports = wallets.iter().map(|w| w.sol_lamports).sum::<u64>();
+    GroupBalanceSnapshot {
+        mint_totals: aggregate_mints(&wallets),
+        wallets,
+        total_sol_lamports,
+        total_sol: lamports_to_sol(total_sol_lamports),
+        taken_at: chrono::Utc::now().timestamp(),
+    }
+}
+
+/// Consolidated SOL and token balances for a group of wallets
+pub async fn get_group_balance_snapshot(
+    wallets: Vec<String>,
+    max_concurrency: Option<usize>,
+    job_id: Option<String>,
+) -> Result<GroupBalanceSnapshot, String> {
+    if wallets.is_empty() {
+        return Err("No wallets provided".to_string());
+    }
+    if wallets.len() > MAX_WALLETS {
+        return Err(format!(
+            "Too many wallets provided: {} (max: {})",
+            wallets.len(),
+            MAX_WALLETS
+        ));
+    }
+
+    let pubkeys = wallets
+        .iter()
+        .map(|w| Pubkey::from_str(w).map_err(|e| format!("invalid wallet pubkey {}: {}", w, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Taking balance snapshot for {} wallets", pubkeys.len());
+    let total = pubkeys.len() as u32;
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(job_id, 0, total, "Fetching SOL balances".to_string());
+    }
+
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let sol_pubkeys = pubkeys.clone();
+    let sol_rpc_url = rpc_url.clone();
+    let sol_balances = task::spawn_blocking(move || {
+        fetch_sol_balances(&RpcClient::new(sol_rpc_url), &sol_pubkeys)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))?;
+
+    // Token holdings: one call per wallet, bounded concurrency
+    let semaphore = Arc::new(Semaphore::new(
+        max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY).max(1),
+    ));
+    let mut tasks = JoinSet::new();
+    for (idx, pubkey) in pubkeys.iter().copied().enumerate() {
+        let semaphore = semaphore.clone();
+        let rpc_url = rpc_url.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let holdings = task::spawn_blocking(move || {
+                fetch_token_holdings(&RpcClient::new(rpc_url), &pubkey)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("join error: {}", e))
+            .and_then(|r| r);
+            (idx, holdings)
+        });
+    }
+
+    let mut holdings: Vec<Option<anyhow::Result<Vec<TokenBalance>>>> =
+        (0..pubkeys.len()).map(|_| None).collect();
+    let mut completed = 0u32;
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, result) = joined.map_err(|e| format!("join error: {}", e))?;
+        holdings[idx] = Some(result);
+        completed += 1;
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                job_id,
+                completed,
+                total,
+                format!("Fetched token holdings for {} of {} wallets", completed, total),
+            );
+        }
+    }
+
+    let snapshot = group_snapshot(
+        wallets
+            .into_iter()
+            .zip(sol_balances)
+            .zip(holdings)
+            .map(|((wallet, lamports), tokens)| wallet_snapshot(wallet, lamports, tokens))
+            .collect(),
+    );
+
+    info!(
+        "Balance snapshot done: {} SOL across {} wallets, {} distinct mints",
+        snapshot.total_sol,
+        snapshot.wallets.len(),
+        snapshot.mint_totals.len()
+    );
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, RpcMethod};
+    use solana_sdk::account::Account;
+
+    fn token(mint: Option<&str>, balance_raw: u64) -> TokenBalance {
+        TokenBalance {
+            balance: balance_raw as f64 / 1e6,
+            balance_raw,
+            decimals: 6,
+            token_account: Pubkey::new_unique().to_string(),
+            mint: mint.map(str::to_string),
+        }
+    }
+
+    fn wallet(tokens: Vec<TokenBalance>) -> WalletSnapshot {
+        wallet_snapshot(Pubkey::new_unique().to_string(), Ok(0), Some(Ok(tokens)))
+    }
+
+    fn funded(rpc: &FakeRpc, lamports: u64) -> Pubkey {
+        let pubkey = Pubkey::new_unique();
+        rpc.set_account(
+            &pubkey,
+            Account {
+                lamports,
+                data: vec![],
+                owner: solana_sdk::system_program::id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        pubkey
+    }
+
+    #[test]
+    fn aggregate_mints_sums_exactly_and_counts_holders() {
+        let (a, b) = (
+            Pubkey::new_unique().to_string(),
+            Pubkey::new_unique().to_string(),
+        );
+        let wallets = [
+            wallet(vec![token(Some(&a), u64::MAX), token(Some(&b), 0)]),
+            wallet(vec![token(Some(&a), u64::MAX), token(None, 5)]),
+            wallet(vec![token(Some(&a), 0)]),
+        ];
+
+        let totals = aggregate_mints(&wallets);
+
+        assert_eq!(totals.len(), 2);
+        let total_a = totals.iter().find(|t| t.mint == a).unwrap();
+        assert_eq!(total_a.amount_raw, (u64::MAX as u128 * 2).to_string());
+        assert_eq!(total_a.holders, 2);
+        assert_eq!(total_a.decimals, 6);
+        let total_b = totals.iter().find(|t| t.mint == b).unwrap();
+        assert_eq!(total_b.amount_raw, "0");
+        assert_eq!(total_b.holders, 0);
+    }
+
+    #[test]
+    fn sol_balances_are_fetched_in_chunks_of_100() {
+        let rpc = FakeRpc::new();
+        let wallets: Vec<Pubkey> = (0..250).map(|i| funded(&rpc, i)).collect();
+        let unfunded = Pubkey::new_unique();
+        let mut all = wallets.clone();
+        all.push(unfunded);
+
+        let balances = fetch_sol_balances(&rpc, &all);
+
+        assert_eq!(rpc.calls(RpcMethod::GetMultipleAccounts), 3);
+        assert_eq!(balances.len(), 251);
+        for (i, balance) in balances[..250].iter().enumerate() {
+            assert_eq!(*balance, Ok(i as u64));
+        }
+        assert_eq!(balances[250], Ok(0));
+    }
+
+    #[test]
+    fn failed_chunk_only_fails_its_wallets() {
+        let rpc = FakeRpc::new();
+        let wallets: Vec<Pubkey> = (0..150).map(|_| funded(&rpc, 1_000_000_000)).collect();
+        rpc.fail_next(RpcMethod::GetMultipleAccounts, "rate limited");
+
+        let balances = fetch_sol_balances(&rpc, &wallets);
+
+        assert_eq!(balances.len(), 150);
+        assert!(balances[..100]
+            .iter()
+            .all(|b| b.as_ref().is_err_and(|e| e.contains("rate limited"))));
+        assert!(balances[100..].iter().all(|b| *b == Ok(1_000_000_000)));
+
+        let snapshot = group_snapshot(
+            wallets
+                .iter()
+                .zip(balances)
+                .map(|(wallet, lamports)| {
+                    wallet_snapshot(wallet.to_string(), lamports, Some(Ok(vec![])))
+                })
+                .collect(),
+        );
+        assert_eq!(snapshot.wallets.len(), 150);
+        assert_eq!(snapshot.total_sol_lamports, 50_000_000_000);
+        assert_eq!(snapshot.total_sol, 50.0);
+        assert_eq!(
+            snapshot
+                .wallets
+                .iter()
+                .filter(|w| w.error.is_some())
+                .count(),
+            100
+        );
+    }
+
+    #[test]
+    fn wallet_snapshot_reports_every_failure() {
+        let mint = Pubkey::new_unique().to_string();
+        let ok = wallet_snapshot(
+            "ok".to_string(),
+            Ok(1_500_000_000),
+            Some(Ok(vec![token(Some(&mint), 10)])),
+        );
+        assert_eq!(ok.sol, 1.5);
+        assert_eq!(ok.tokens.len(), 1);
+        assert_eq!(ok.error, None);
+
+        let no_tokens = wallet_snapshot(
+            "no_tokens".to_string(),
+            Ok(1),
+            Some(Err(anyhow::anyhow!("node behind"))),
+        );
+        assert_eq!(no_tokens.sol_lamports, 1);
+        assert!(no_tokens.tokens.is_empty());
+        assert_eq!(no_tokens.error.as_deref(), Some("node behind"));
+
+        let nothing = wallet_snapshot("nothing".to_string(), Err("rate limited".to_string()), None);
+        assert_eq!(nothing.sol_lamports, 0);
+        assert_eq!(
+            nothing.error.as_deref(),
+            Some("rate limited; token holdings not fetched")
+        );
+    }
+}
@@ -0,0 +1,466 @@
+use crate::config::CONFIG;
+use crate::services::common::{lamports_to_sol, parse_keypair};
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{
+    fetch_owned_token_accounts, resolve_rent_destination, OwnedTokenAccount,
+};
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use solana_sdk::{instruction::Instruction, transaction::Transaction};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use tokio::task;
+
+/// Maximum serialized transaction size (IPv6 MTU minus headers)
+const PACKET_DATA_SIZE: usize = 1232;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MintBurnCloseResult {
+    pub mint: String,
+    pub success: bool,
+    pub token_accounts_closed: usize,
+    /// Raw amount burned across the mint's accounts, as a string since it is exact
+    pub burned_raw: String,
+    pub rent_reclaimed_lamports: u64,
+    pub rent_reclaimed_sol: f64,
+    pub transaction_signatures: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BurnAndCloseResult {
+    pub wallet: String,
+    pub rent_destination: String,
+    pub results: Vec<MintBurnCloseResult>,
+    pub transaction_signatures: Vec<String>,
+    pub total_rent_reclaimed_lamports: u64,
+    pub total_rent_reclaimed_sol: f64,
+}
+
+/// Burn the full balance of a token account (if any) and close it
+pub fn burn_and_close_instructions(
+    owned: &OwnedTokenAccount,
+    owner: &Pubkey,
+    destination: &Pubkey,
+) -> anyhow::Result<Vec<Instruction>> {
+    let account = &owned.account;
+    let mut instructions = Vec::with_capacity(2);
+    // Native (wrapped SOL) balances are returned by the close itself
+    if account.amount > 0 && !account.is_native {
+        instructions.push(
+            spl_token_2022::instruction::burn(
+                &account.program_id,
+                &owned.pubkey,
+                &account.mint,
+                owner,
+                &[],
+                account.amount,
+            )
+            .context("failed to build burn instruction")?,
+        );
+    }
+    instructions.push(
+        spl_token_2022::instruction::close_account(
+            &account.program_id,
+            &owned.pubkey,
+            destination,
+            owner,
+            &[],
+        )
+        .context("failed to build close account instruction")?,
+    );
+    Ok(instructions)
+}
+
+/// Wire size of a transaction signed by `payer` only
+fn transaction_size(instructions: &[Instruction], payer: &Pubkey) -> usize {
+    let mut tx = Transaction::new_with_payer(instructions, Some(payer));
+    tx.message.recent_blockhash = Hash::default();
+    // Compact-u16 signature count (1 byte below 128) + one 64-byte signature
+    1 + 64 * tx.message.header.num_required_signatures as usize + tx.message.serialize().len()
+}
+
+/// Group per-account instruction sets into as few transactions as fit the packet size,
+/// never splitting one account's instructions across transactions
+pub fn pack_instruction_groups(
+    groups: Vec<(usize, Vec<Instruction>)>,
+    payer: &Pubkey,
+) -> Vec<(Vec<usize>, Vec<Instruction>)> {
+    let mut packed: Vec<(Vec<usize>, Vec<Instruction>)> = Vec::new();
+    for (idx, group) in groups {
+        if let Some((indices, instructions)) = packed.last_mut() {
+            let mut candidate = instructions.clone();
+            candidate.extend(group.iter().cloned());
+            if transaction_size(&candidate, payer) <= PACKET_DATA_SIZE {
+                indices.push(idx);
+                *instructions = candidate;
+                continue;
+            }
+        }
+        packed.push((vec![idx], group));
+    }
+    packed
+}
+
+/// Record `error` for a mint, keeping the errors of its other token accounts
+fn push_error(result: &mut MintBurnCloseResult, error: String) {
+    result.error = Some(match result.error.take() {
+        Some(earlier) => format!("{}; {}", earlier, error),
+        None => error,
+    });
+}
+
+/// Blocking part of `burn_and_close`, against any RPC
+fn burn_and_close_mints(
+    client: &dyn SolanaRpc,
+    wallet_pk: &str,
+    mints: &[String],
+    rent_destination: Option<&str>,
+    job_id: Option<String>,
+) -> anyhow::Result<BurnAndCloseResult> {
+    let wallet_keypair = parse_keypair(wallet_pk)?;
+    let wallet_pubkey = wallet_keypair.pubkey();
+    let destination = resolve_rent_destination(rent_destination, &wallet_pubkey)?;
+
+    let mut results: BTreeMap<Pubkey, MintBurnCloseResult> = BTreeMap::new();
+    for mint in mints {
+        let mint_pubkey =
+            Pubkey::from_str(mint).with_context(|| format!("invalid mint address {}", mint))?;
+        results.insert(
+            mint_pubkey,
+            MintBurnCloseResult {
+                mint: mint.clone(),
+                success: false,
+                token_accounts_closed: 0,
+                burned_raw: "0".to_string(),
+                rent_reclaimed_lamports: 0,
+                rent_reclaimed_sol: 0.0,
+                transaction_signatures: vec![],
+                error: None,
+            },
+        );
+    }
+
+    let accounts: Vec<OwnedTokenAccount> = fetch_owned_token_accounts(client, &wallet_pubkey)?
+        .into_iter()
+        .filter(|owned| results.contains_key(&owned.account.mint))
+        .collect();
+
+    let found_mints: HashSet<Pubkey> = accounts.iter().map(|o| o.account.mint).collect();
+    for (mint, result) in results.iter_mut() {
+        if !found_mints.contains(mint) {
+            result.error = Some("No token account found".to_string());
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (idx, owned) in accounts.iter().enumerate() {
+        let result = results
+            .get_mut(&owned.account.mint)
+            .expect("filtered on requested mints");
+        let blockers = owned.account.close_blockers(true);
+        if !blockers.is_empty() {
+            let reasons = blockers
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            warn!("Skipping token account {}: {}", owned.pubkey, reasons);
+            push_error(result, format!("{}: {}", owned.pubkey, reasons));
+            continue;
+        }
+        groups.push((
+            idx,
+            burn_and_close_instructions(owned, &wallet_pubkey, &destination)?,
+        ));
+    }
+
+    let transactions = pack_instruction_groups(groups, &wallet_pubkey);
+    info!(
+        "Burning and closing {} token accounts in {} transactions",
+        transactions
+            .iter()
+            .map(|(indices, _)| indices.len())
+            .sum::<usize>(),
+        transactions.len()
+    );
+
+    let mut burned: BTreeMap<Pubkey, u128> = BTreeMap::new();
+    let mut signatures = Vec::new();
+    let total = transactions.len() as u32;
+    for (tx_idx, (indices, instructions)) in transactions.iter().enumerate() {
+        let outcome = send_and_confirm(client, instructions, &wallet_pubkey, &[&wallet_keypair]);
+        for idx in indices {
+            let owned = &accounts[*idx];
+            let result = results
+                .get_mut(&owned.account.mint)
+                .expect("filtered on requested mints");
+            match &outcome {
+                Ok(sig) => {
+                    result.token_accounts_closed += 1;
+                    result.rent_reclaimed_lamports += owned.lamports;
+                    *burned.entry(owned.account.mint).or_default() += owned.account.amount as u128;
+                    if !result.transaction_signatures.contains(&sig.to_string()) {
+                        result.transaction_signatures.push(sig.to_string());
+                    }
+                }
+                Err(e) => {
+                    push_error(result, format!("{}: {}", owned.pubkey, e));
+                }
+            }
+        }
+        match outcome {
+            Ok(sig) => {
+                info!("Transaction {} confirmed: {}", tx_idx + 1, sig);
+                signatures.push(sig.to_string());
+            }
+            Err(e) => warn!("Transaction {} failed: {}", tx_idx + 1, e),
+        }
+
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                job_id,
+                tx_idx as u32 + 1,
+                total,
+                format!(
+                    "Sent {} of {} burn and close transactions",
+                    tx_idx + 1,
+                    total
+                ),
+            );
+        }
+    }
+
+    let mut results: Vec<MintBurnCloseResult> = results
+        .into_iter()
+        .map(|(mint, mut result)| {
+            result.burned_raw = burned.get(&mint).copied().unwrap_or(0).to_string();
+            result.rent_reclaimed_sol = lamports_to_sol(result.rent_reclaimed_lamports);
+            result.success = result.error.is_none() && result.token_accounts_closed > 0;
+            result
+        })
+        .collect();
+    // Keep the caller's mint order
+    results.sort_by_key(|r| mints.iter().position(|m| *m == r.mint));
+
+    let total_rent_reclaimed_lamports = results
+        .iter()
+        .map(|r| r.rent_reclaimed_lamports)
+        .sum::<u64>();
+    info!(
+        "burn_and_close done: {} transactions, {} SOL reclaimed",
+        signatures.len(),
+        lamports_to_sol(total_rent_reclaimed_lamports)
+    );
+    Ok(BurnAndCloseResult {
+        wallet: wallet_pubkey.to_string(),
+        rent_destination: destination.to_string(),
+        results,
+        transaction_signatures: signatures,
+        total_rent_reclaimed_lamports,
+        total_rent_reclaimed_sol: lamports_to_sol(total_rent_reclaimed_lamports),
+    })
+}
+
+/// Burn 100% of each mint's balance and close its token accounts, packing several
+/// accounts per transaction; rent goes to `rent_destination` or the wallet itself
+pub async fn burn_and_close(
+    wallet_pk: String,
+    mints: Vec<String>,
+    rent_destination: Option<String>,
+    job_id: Option<String>,
+) -> Result<BurnAndCloseResult, String> {
+    if mints.is_empty() {
+        return Err("No mint addresses provided".to_string());
+    }
+
+    info!("Starting burn_and_close for {} mints", mints.len());
+
+    let rpc_url = CONFIG.api.helius_https.clone();
+
+    task::spawn_blocking(move || {
+        burn_and_close_mints(
+            &RpcClient::new(rpc_url),
+            &wallet_pk,
+            &mints,
+            rent_destination.as_deref(),
+            job_id,
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount, Landing, RpcMethod};
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::transaction::TransactionError;
+
+    #[test]
+    fn reports_missing_and_blocked_mints_and_burns_the_rest() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let (burnable, frozen, missing) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let account = FakeTokenAccount::new(wallet.pubkey(), burnable, 1_000);
+        let rent = account.lamports;
+        rpc.add_token_account(account);
+        let frozen_account = rpc.add_token_account(FakeTokenAccount {
+            frozen: true,
+            ..FakeTokenAccount::new(wallet.pubkey(), frozen, 1_000)
+        });
+        let mints: Vec<String> = [burnable, frozen, missing]
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+
+        let res =
+            burn_and_close_mints(&rpc, &wallet.to_base58_string(), &mints, None, None).unwrap();
+
+        assert_eq!(rpc.sent().len(), 1);
+        assert_eq!(res.transaction_signatures.len(), 1);
+        assert_eq!(res.total_rent_reclaimed_lamports, rent);
+
+        let [burned, blocked, not_found] = &res.results[..] else {
+            panic!("expected one result per mint");
+        };
+        assert_eq!(burned.mint, mints[0]);
+        assert!(burned.success);
+        assert_eq!(burned.burned_raw, "1000");
+        assert_eq!(burned.token_accounts_closed, 1);
+        assert!(!blocked.success);
+        assert!(blocked
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with(&frozen_account.to_string()));
+        assert!(!not_found.success);
+        assert_eq!(not_found.error.as_deref(), Some("No token account found"));
+    }
+
+    #[test]
+    fn keeps_the_error_of_every_account_of_a_mint() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let frozen = rpc.add_token_account(FakeTokenAccount {
+            frozen: true,
+            ..FakeTokenAccount::new(wallet.pubkey(), mint, 1_000)
+        });
+        let failed = rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), mint, 2_000));
+        rpc.land_next(Landing::Failed(TransactionError::InsufficientFundsForFee));
+
+        let res = burn_and_close_mints(
+            &rpc,
+            &wallet.to_base58_string(),
+            &[mint.to_string()],
+            None,
+            None,
+        )
+        .unwrap();
+
+        let result = &res.results[0];
+        assert!(!result.success);
+        assert_eq!(result.token_accounts_closed, 0);
+        assert_eq!(result.burned_raw, "0");
+        let error = result.error.as_deref().unwrap();
+        assert!(error.contains(&frozen.to_string()), "{}", error);
+        assert!(error.contains(&failed.to_string()), "{}", error);
+        assert!(res.transaction_signatures.is_empty());
+    }
+
+    #[test]
+    fn failed_transaction_only_fails_its_accounts() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let mints: Vec<Pubkey> = (0..30).map(|_| Pubkey::new_unique()).collect();
+        for mint in &mints {
+            rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), *mint, 1_000));
+        }
+        let rent = FakeTokenAccount::new(wallet.pubkey(), mints[0], 1_000).lamports;
+        rpc.land_next(Landing::Failed(TransactionError::InsufficientFundsForFee));
+
+        let mints: Vec<String> = mints.iter().map(|m| m.to_string()).collect();
+        let res =
+            burn_and_close_mints(&rpc, &wallet.to_base58_string(), &mints, None, None).unwrap();
+
+        let sent = rpc.sent();
+        assert!(sent.len() > 1, "expected several transactions");
+        // Every account burns and closes in the same transaction
+        let in_first = sent[0].message.instructions.len() / 2;
+        let (succeeded, failed): (Vec<_>, Vec<_>) = res.results.iter().partition(|r| r.success);
+        assert_eq!(failed.len(), in_first);
+        assert_eq!(succeeded.len(), mints.len() - in_first);
+        for result in &failed {
+            assert_eq!(result.token_accounts_closed, 0);
+            assert!(result.error.is_some());
+        }
+        assert_eq!(res.transaction_signatures.len(), sent.len() - 1);
+        assert_eq!(
+            res.total_rent_reclaimed_lamports,
+            succeeded.len() as u64 * rent
+        );
+    }
+
+    #[test]
+    fn rent_goes_to_the_requested_destination() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let (mint, treasury) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), mint, 0));
+
+        let res = burn_and_close_mints(
+            &rpc,
+            &wallet.to_base58_string(),
+            &[mint.to_string()],
+            Some(&treasury.to_string()),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(res.rent_destination, treasury.to_string());
+        assert!(res.results[0].success);
+        // Empty accounts are only closed
+        assert_eq!(res.results[0].burned_raw, "0");
+        let sent = rpc.sent();
+        assert_eq!(sent[0].message.instructions.len(), 1);
+        assert!(sent[0].message.account_keys.contains(&treasury));
+    }
+
+    #[test]
+    fn invalid_input_and_unreadable_accounts_send_nothing() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new().to_base58_string();
+        let mint = Pubkey::new_unique().to_string();
+
+        assert!(burn_and_close_mints(&rpc, "not-a-key", &[mint.clone()], None, None).is_err());
+        let error = burn_and_close_mints(&rpc, &wallet, &["not-a-mint".to_string()], None, None)
+            .unwrap_err();
+        assert!(error.to_string().contains("not-a-mint"), "{}", error);
+        assert!(
+            burn_and_close_mints(&rpc, &wallet, &[mint.clone()], Some("not-a-pubkey"), None)
+                .is_err()
+        );
+
+        rpc.fail_next(RpcMethod::GetTokenAccountsByOwner, "node unavailable");
+        let error = burn_and_close_mints(&rpc, &wallet, &[mint], None, None).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("node unavailable"),
+            "{:#}",
+            error
+        );
+        assert!(rpc.sent().is_empty());
+    }
+}
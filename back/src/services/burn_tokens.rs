@@ -1,12 +1,15 @@
 use crate::config::CONFIG;
+use crate::job_queue::signer_pubkey;
 use crate::jobs::JobManager;
+use crate::secret::SecretString;
+use crate::services::common::parse_keypair;
 use anyhow::Context;
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use solana_client::rpc_client::RpcClient;
 use solana_client::rpc_request::TokenAccountsFilter;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::{bs58, signature::Keypair, signer::Signer};
+use solana_sdk::signer::Signer;
 use solana_sdk::{instruction::Instruction, transaction::Transaction};
 use std::str::FromStr;
 use tokio::task;
@@ -32,47 +35,17 @@ pub struct BurnTokenResult {
     pub error: Option<String>,
 }
 
-fn parse_keypair(private_key: &str) -> anyhow::Result<Keypair> {
-    let bytes = bs58::decode(private_key)
-        .into_vec()
-        .context("invalid base58 key")?;
-    if bytes.len() == 64 {
-        Keypair::try_from(&bytes[..]).context("invalid keypair bytes")
-    } else {
-        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
-    }
-}
-
-/// Extract token account info from parsed data
-fn extract_token_info(
-    data: &solana_account_decoder_client_types::UiAccountData,
-) -> Option<(String, f64, u8)> {
-    if let solana_account_decoder_client_types::UiAccountData::Json(parsed) = data {
-        if let Some(parsed_account) = parsed.parsed.as_object() {
-            if let Some(info) = parsed_account.get("info").and_then(|v| v.as_object()) {
-                let mint = info.get("mint")?.as_str()?.to_string();
-                let token_amount = info.get("tokenAmount")?.as_object()?;
-                let ui_amount = token_amount.get("uiAmount")?.as_f64()?;
-                let decimals = token_amount.get("decimals")?.as_u64()? as u8;
-                return Some((mint, ui_amount, decimals));
-            }
-        }
-    }
-    None
-}
-
-/// Create burn instruction for SPL token
+/// Create burn instruction for a legacy SPL Token or Token-2022 account; the
+/// program must be the one owning the token account
 fn create_burn_instruction(
+    program_id: &Pubkey,
     token_account: &Pubkey,
     mint: &Pubkey,
     owner: &Pubkey,
     amount: u64,
-) -> Instruction {
-    // SPL Token burn instruction
-    let program_id = spl_token::id();
-
-   // This is private code
-   // Please contact me if you need it.
+) -> anyhow::Result<Instruction> {
+    spl_token_2022::instruction::burn(program_id, token_account, mint, owner, &[], amount)
+        .context("failed to build burn instruction")
 }
 
 pub async fn burn_tokens(
@@ -86,7 +59,9 @@ pub async fn burn_tokens(
 
     info!(
         "Starting burn_tokens for wallet {} - burning {}% of mint {}",
-        wallet_pk, burn_percentage, mint_address
+        signer_pubkey(&wallet_pk).unwrap_or_default(),
+        burn_percentage,
+        mint_address
     );
 
     let rpc_url = CONFIG.api.helius_https.clone();
@@ -119,7 +94,9 @@ pub async fn burn_tokens_with_progress(
 
     info!(
         "Starting burn_tokens_with_progress for wallet {} - burning {}% of mint {}",
-        wallet_pk, burn_percentage, mint_address
+        signer_pubkey(&wallet_pk).unwrap_or_default(),
+        burn_percentage,
+        mint_address
     );
 
     // Update progress - starting
@@ -153,7 +130,7 @@ pub async fn burn_each_tokens(
 
     info!(
         "Starting burn_each_tokens for wallet {} - burning {}% of {} different mints",
-        wallet_pk,
+        signer_pubkey(&wallet_pk).unwrap_or_default(),
         burn_percentage,
         mint_addresses.len()
     );
@@ -173,7 +150,7 @@ pub async fn burn_each_tokens(
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BurnTokensBatchRequest {
-    pub wallet_pk: String,
+    pub wallet_pk: SecretString,
     pub token_mints: Vec<String>,
 }
 
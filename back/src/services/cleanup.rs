@@ -0,0 +1,840 @@
+use crate::config::CONFIG;
+use crate::services::burn_and_close::burn_and_close_instructions;
+use crate::services::common::{lamports_to_sol, parse_keypair, QuickSellReq};
+use crate::services::quick_sell;
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::pump::{bonding_curve_pda, decode_bonding_curve, PUMP_PROGRAM_ID};
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{
+    fetch_owned_token_accounts, parse_rent_destination, OwnedTokenAccount,
+};
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
+
+const DEFAULT_DUST_THRESHOLD_SOL: f64 = 0.001;
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+const MAX_WALLETS: usize = 500;
+const MAX_CLOSES_PER_TX: usize = 5;
+const ACCOUNTS_PER_REQUEST: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupKind {
+    /// Empty account: close and reclaim rent
+    Close,
+    /// Worth less than the dust threshold, or impossible to price: burn the
+    /// balance, then close
+    BurnAndClose,
+    /// Still trading on a live pump.fun curve: sell everything, then close
+    SellAndClose,
+    /// Frozen, carrying withheld fees, or unpriced while `keep_unpriced` is set:
+    /// left alone
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CleanupOptions {
+    /// Balances worth less than this (in SOL) are burned instead of sold
+    pub dust_threshold_sol: Option<f64>,
+    pub max_concurrency: Option<usize>,
+    /// Leave balances without a live curve to price them, instead of burning them
+    pub keep_unpriced: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CleanupAction {
+    pub token_account: String,
+    pub mint: String,
+    pub program_id: String,
+    pub kind: CleanupKind,
+    pub amount_raw: u64,
+    pub decimals: Option<u8>,
+    /// Value of the balance on its bonding curve, when the curve is live
+    pub estimated_value_lamports: Option<u64>,
+    pub rent_lamports: u64,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletCleanupPlan {
+    pub wallet: String,
+    pub actions: Vec<CleanupAction>,
+    pub reclaimable_rent_lamports: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CleanupPlan {
+    pub wallets: Vec<WalletCleanupPlan>,
+    pub dust_threshold_lamports: u64,
+    #[serde(default)]
+    pub keep_unpriced: bool,
+    pub closes: usize,
+    pub burns: usize,
+    pub sells: usize,
+    pub skipped: usize,
+    /// Rent returned if every non-skipped action succeeds
+    pub reclaimable_rent_lamports: u64,
+    pub reclaimable_rent_sol: f64,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CleanupActionResult {
+    pub wallet: String,
+    pub token_account: String,
+    pub mint: String,
+    pub kind: CleanupKind,
+    pub success: bool,
+    pub signatures: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CleanupReport {
+    pub results: Vec<CleanupActionResult>,
+    /// Rent recipient, None when each wallet kept its own rent
+    pub rent_destination: Option<String>,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub reclaimed_rent_lamports: u64,
+    pub reclaimed_rent_sol: f64,
+}
+
+/// Token accounts of every wallet, one task per wallet with bounded concurrency
+pub async fn scan_group_token_accounts(
+    wallets: &[Pubkey],
+    max_concurrency: usize,
+) -> Result<Vec<anyhow::Result<Vec<OwnedTokenAccount>>>, String> {
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for (idx, wallet) in wallets.iter().copied().enumerate() {
+        let semaphore = semaphore.clone();
+        let rpc_url = rpc_url.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let accounts = task::spawn_blocking(move || {
+                fetch_owned_token_accounts(&RpcClient::new(rpc_url), &wallet)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("join error: {}", e))
+            .and_then(|r| r);
+            (idx, accounts)
+        });
+    }
+
+    let mut results: Vec<anyhow::Result<Vec<OwnedTokenAccount>>> = (0..wallets.len())
+        .map(|_| Err(anyhow::anyhow!("token accounts not fetched")))
+        .collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, accounts) = joined.map_err(|e| format!("join error: {}", e))?;
+        results[idx] = accounts;
+    }
+    Ok(results)
+}
+
+/// Price of each mint on its live bonding curve, as (virtual SOL, virtual token) reserves
+fn fetch_live_curves(
+    client: &dyn SolanaRpc,
+    mints: &[Pubkey],
+) -> anyhow::Result<HashMap<Pubkey, (u64, u64)>> {
+    let mut curves = HashMap::new();
+    for chunk in mints.chunks(ACCOUNTS_PER_REQUEST) {
+        let keys: Vec<Pubkey> = chunk.iter().map(bonding_curve_pda).collect();
+        let accounts = client
+            .get_multiple_accounts(&keys)
+            .context("failed to get bonding curves")?;
+        for (mint, account) in chunk.iter().zip(accounts) {
+            let curve = account
+                .filter(|a| a.owner == PUMP_PROGRAM_ID)
+                .and_then(|a| decode_bonding_curve(&a.data))
+                .filter(|c| !c.complete && c.virtual_token_reserves > 0);
+            if let Some(curve) = curve {
+                curves.insert(
+                    *mint,
+                    (curve.virtual_sol_reserves, curve.virtual_token_reserves),
+                );
+            }
+        }
+    }
+    Ok(curves)
+}
+
+fn classify(
+    owned: &OwnedTokenAccount,
+    curves: &HashMap<Pubkey, (u64, u64)>,
+    dust_threshold_lamports: u64,
+    keep_unpriced: bool,
+) -> CleanupAction {
+    let account = &owned.account;
+    let mut action = CleanupAction {
+        token_account: owned.pubkey.to_string(),
+        mint: account.mint.to_string(),
+        program_id: account.program_id.to_string(),
+        kind: CleanupKind::Skip,
+        amount_raw: account.amount,
+        decimals: account.decimals,
+        estimated_value_lamports: None,
+        rent_lamports: owned.lamports,
+        reason: None,
+    };
+
+    // Burning in the same transaction takes care of the balance, nothing else does
+    let blockers = account.close_blockers(true);
+    if !blockers.is_empty() {
+        action.reason = Some(
+            blockers
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+        );
+        return action;
+    }
+
+    if account.is_empty() || account.is_native {
+        action.kind = CleanupKind::Close;
+        return action;
+    }
+
+    match curves.get(&account.mint) {
+        Some(&(virtual_sol, virtual_token)) => {
+            let value =
+                (account.amount as u128 * virtual_sol as u128 / virtual_token as u128) as u64;
+            action.estimated_value_lamports = Some(value);
+            action.kind = if value < dust_threshold_lamports {
+                CleanupKind::BurnAndClose
+            } else {
+                CleanupKind::SellAndClose
+            };
+        }
+        None => {
+            action.reason = Some("no live bonding curve to price or sell this token".to_string());
+            if !keep_unpriced {
+                action.kind = CleanupKind::BurnAndClose;
+            }
+        }
+    }
+    action
+}
+
+/// Classify the token accounts of `owner` as they are now, keyed by token account
+fn reclassify_wallet(
+    client: &dyn SolanaRpc,
+    owner: &Pubkey,
+    dust_threshold_lamports: u64,
+    keep_unpriced: bool,
+) -> anyhow::Result<HashMap<String, (OwnedTokenAccount, CleanupAction)>> {
+    let accounts = fetch_owned_token_accounts(client, owner)?;
+    let held_mints: Vec<Pubkey> = accounts
+        .iter()
+        .filter(|owned| !owned.account.is_empty())
+        .map(|owned| owned.account.mint)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let curves = fetch_live_curves(client, &held_mints)?;
+    Ok(accounts
+        .into_iter()
+        .map(|owned| {
+            let action = classify(&owned, &curves, dust_threshold_lamports, keep_unpriced);
+            (action.token_account.clone(), (owned, action))
+        })
+        .collect())
+}
+
+/// Why `planned` must not run against the account as it is now, if anything changed
+fn plan_drift(planned: &CleanupAction, current: &CleanupAction) -> Option<String> {
+    if current.kind != planned.kind {
+        return Some(format!(
+            "account is now classified {:?} instead of {:?}",
+            current.kind, planned.kind
+        ));
+    }
+    if current.amount_raw != planned.amount_raw {
+        return Some(format!(
+            "balance changed from {} to {} since planning",
+            planned.amount_raw, current.amount_raw
+        ));
+    }
+    None
+}
+
+/// Classify every token account of the group and estimate the rent it would free
+pub async fn plan_cleanup(
+    wallets: Vec<String>,
+    options: CleanupOptions,
+) -> Result<CleanupPlan, String> {
+    if wallets.is_empty() {
+        return Err("No wallets provided".to_string());
+    }
+    if wallets.len() > MAX_WALLETS {
+        return Err(format!(
+            "Too many wallets provided: {} (max: {})",
+            wallets.len(),
+            MAX_WALLETS
+        ));
+    }
+    let dust_threshold_sol = options
+        .dust_threshold_sol
+        .unwrap_or(DEFAULT_DUST_THRESHOLD_SOL);
+    if dust_threshold_sol < 0.0 {
+        return Err("Dust threshold must not be negative".to_string());
+    }
+    let dust_threshold_lamports = (dust_threshold_sol * 1_000_000_000.0) as u64;
+    let keep_unpriced = options.keep_unpriced.unwrap_or(false);
+
+    let pubkeys = wallets
+        .iter()
+        .map(|w| Pubkey::from_str(w).map_err(|e| format!("invalid wallet pubkey {}: {}", w, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Planning cleanup for {} wallets", pubkeys.len());
+    let scanned = scan_group_token_accounts(
+        &pubkeys,
+        options.max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY),
+    )
+    .await?;
+
+    let held_mints: Vec<Pubkey> = scanned
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|owned| !owned.account.is_empty())
+        .map(|owned| owned.account.mint)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let curves =
+        task::spawn_blocking(move || fetch_live_curves(&RpcClient::new(rpc_url), &held_mints))
+            .await
+            .map_err(|e| format!("join error: {}", e))
+            .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+
+    let mut plan = CleanupPlan {
+        wallets: Vec::with_capacity(wallets.len()),
+        dust_threshold_lamports,
+        keep_unpriced,
+        closes: 0,
+        burns: 0,
+        sells: 0,
+        skipped: 0,
+        reclaimable_rent_lamports: 0,
+        reclaimable_rent_sol: 0.0,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    for (wallet, accounts) in wallets.into_iter().zip(scanned) {
+        let (actions, error) = match accounts {
+            Ok(accounts) => (
+                accounts
+                    .iter()
+                    .map(|owned| classify(owned, &curves, dust_threshold_lamports, keep_unpriced))
+                    .collect::<Vec<_>>(),
+                None,
+            ),
+            Err(e) => {
+                warn!("Failed to fetch token accounts for {}: {:#}", wallet, e);
+                (vec![], Some(format!("{:#}", e)))
+            }
+        };
+
+        let mut reclaimable = 0;
+        for action in &actions {
+            match action.kind {
+                CleanupKind::Close => plan.closes += 1,
+                CleanupKind::BurnAndClose => plan.burns += 1,
+                CleanupKind::SellAndClose => plan.sells += 1,
+                CleanupKind::Skip => plan.skipped += 1,
+            }
+            if action.kind != CleanupKind::Skip {
+                reclaimable += action.rent_lamports;
+            }
+        }
+        plan.reclaimable_rent_lamports += reclaimable;
+        plan.wallets.push(WalletCleanupPlan {
+            wallet,
+            actions,
+            reclaimable_rent_lamports: reclaimable,
+            error,
+        });
+    }
+    plan.reclaimable_rent_sol = lamports_to_sol(plan.reclaimable_rent_lamports);
+
+    info!(
+        "Cleanup plan: {} closes, {} burns, {} sells, {} skipped, {} SOL reclaimable",
+        plan.closes, plan.burns, plan.sells, plan.skipped, plan.reclaimable_rent_sol
+    );
+    Ok(plan)
+}
+
+fn send_instructions(
+    client: &dyn SolanaRpc,
+    wallet: &Keypair,
+    instructions: &[Instruction],
+) -> anyhow::Result<String> {
+    let sig = send_and_confirm(client, instructions, &wallet.pubkey(), &[wallet])
+        .context("transaction failed")?;
+    Ok(sig.to_string())
+}
+
+/// Burn the balance of `owned` and close it in one transaction
+fn burn_and_close(
+    client: &dyn SolanaRpc,
+    wallet: &Keypair,
+    owned: &OwnedTokenAccount,
+    destination: &Pubkey,
+) -> anyhow::Result<String> {
+    let instructions = burn_and_close_instructions(owned, &wallet.pubkey(), destination)?;
+    send_instructions(client, wallet, &instructions)
+}
+
+fn action_result(
+    wallet: &str,
+    action: &CleanupAction,
+    outcome: anyhow::Result<Vec<String>>,
+) -> CleanupActionResult {
+    let (success, signatures, error) = match outcome {
+        Ok(signatures) => (true, signatures, None),
+        Err(e) => (false, vec![], Some(format!("{:#}", e))),
+    };
+    CleanupActionResult {
+        wallet: wallet.to_string(),
+        token_account: action.token_account.clone(),
+        mint: action.mint.clone(),
+        kind: action.kind,
+        success,
+        signatures,
+        error,
+    }
+}
+
+/// Sell the whole balance on the curve, then burn whatever rounding left behind and close
+async fn sell_and_close(
+    wallet_pk: &str,
+    keypair: Arc<Keypair>,
+    action: &CleanupAction,
+    mut owned: OwnedTokenAccount,
+    destination: Pubkey,
+) -> anyhow::Result<Vec<String>> {
+    let sold = quick_sell::quick_sell(QuickSellReq {
+        wallet: keypair.pubkey().to_string(),
+        pk: wallet_pk.into(),
+        percent: 100,
+        mint: action.mint.clone(),
+        slippage_bps: None,
+    })
+    .await;
+    if !sold.ok {
+        anyhow::bail!(
+            "sell failed: {}",
+            sold.error.unwrap_or_else(|| "unknown error".to_string())
+        );
+    }
+
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let close_sig = task::spawn_blocking(move || -> anyhow::Result<String> {
+        let client = RpcClient::new(rpc_url);
+        owned.account.amount = client
+            .get_token_account_balance(&owned.pubkey)
+            .context("failed to read balance after sell")?
+            .amount
+            .parse::<u64>()
+            .context("invalid token amount")?;
+        burn_and_close(&client, &keypair, &owned, &destination)
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("join error: {}", e))??;
+
+    Ok(sold.tx_sig.into_iter().chain([close_sig]).collect())
+}
+
+/// Run a cleanup plan; `pks` are the private keys of the planned wallets. Accounts that
+/// changed since planning are refused. Rent goes to `rent_destination` when given,
+/// otherwise back to each wallet
+pub async fn execute_cleanup_plan(
+    plan: CleanupPlan,
+    pks: Vec<String>,
+    rent_destination: Option<String>,
+    job_id: Option<String>,
+) -> Result<CleanupReport, String> {
+    // Validated up front so a bad treasury address fails before anything is sent
+    let treasury = rent_destination
+        .as_deref()
+        .map(parse_rent_destination)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let mut keypairs = HashMap::new();
+    for pk in pks {
+        let keypair = parse_keypair(&pk).map_err(|e| e.to_string())?;
+        keypairs.insert(keypair.pubkey().to_string(), (pk, Arc::new(keypair)));
+    }
+
+    let total = plan
+        .wallets
+        .iter()
+        .flat_map(|w| &w.actions)
+        .filter(|a| a.kind != CleanupKind::Skip)
+        .count() as u32;
+    let mut completed = 0u32;
+    let mut report = CleanupReport {
+        results: vec![],
+        rent_destination: treasury.map(|t| t.to_string()),
+        succeeded: 0,
+        failed: 0,
+        reclaimed_rent_lamports: 0,
+        reclaimed_rent_sol: 0.0,
+    };
+
+    for wallet_plan in &plan.wallets {
+        let actions: Vec<&CleanupAction> = wallet_plan
+            .actions
+            .iter()
+            .filter(|a| a.kind != CleanupKind::Skip)
+            .collect();
+        if actions.is_empty() {
+            continue;
+        }
+        let Some((wallet_pk, keypair)) = keypairs.get(&wallet_plan.wallet) else {
+            warn!("No private key provided for {}", wallet_plan.wallet);
+            for action in actions {
+                report.results.push(action_result(
+                    &wallet_plan.wallet,
+                    action,
+                    Err(anyhow::anyhow!("no private key provided for wallet")),
+                ));
+            }
+            continue;
+        };
+
+        // The plan may be stale: re-read every account and refuse whatever changed since
+        let rpc_url = CONFIG.api.helius_https.clone();
+        let owner = keypair.pubkey();
+        let (dust_threshold_lamports, keep_unpriced) =
+            (plan.dust_threshold_lamports, plan.keep_unpriced);
+        let current = task::spawn_blocking(move || {
+            reclassify_wallet(
+                &RpcClient::new(rpc_url),
+                &owner,
+                dust_threshold_lamports,
+                keep_unpriced,
+            )
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("join error: {}", e))
+        .and_then(|r| r);
+        let current = match current {
+            Ok(current) => current,
+            Err(e) => {
+                warn!(
+                    "Failed to re-check token accounts of {}: {:#}",
+                    wallet_plan.wallet, e
+                );
+                for action in actions {
+                    report.results.push(action_result(
+                        &wallet_plan.wallet,
+                        action,
+                        Err(anyhow::anyhow!("failed to re-check token account: {:#}", e)),
+                    ));
+                }
+                continue;
+            }
+        };
+        let mut runnable = Vec::with_capacity(actions.len());
+        for action in actions {
+            let reason = match current.get(&action.token_account) {
+                Some((owned, fresh)) => match plan_drift(action, fresh) {
+                    Some(reason) => reason,
+                    None => {
+                        runnable.push((action, owned.clone()));
+                        continue;
+                    }
+                },
+                None => "token account no longer exists".to_string(),
+            };
+            warn!("Refusing to clean up {}: {}", action.token_account, reason);
+            report.results.push(action_result(
+                &wallet_plan.wallet,
+                action,
+                Err(anyhow::anyhow!("{}", reason)),
+            ));
+            completed += 1;
+        }
+        let actions = runnable;
+        if actions.is_empty() {
+            continue;
+        }
+
+        let destination = treasury.unwrap_or_else(|| keypair.pubkey());
+        info!(
+            "Cleaning up {} token accounts of {}",
+            actions.len(),
+            wallet_plan.wallet
+        );
+
+        // Empty accounts are closed together, a few per transaction
+        let closes: Vec<(CleanupAction, OwnedTokenAccount)> = actions
+            .iter()
+            .filter(|(a, _)| a.kind == CleanupKind::Close)
+            .map(|(a, owned)| ((*a).clone(), owned.clone()))
+            .collect();
+        for batch in closes.chunks(MAX_CLOSES_PER_TX) {
+            let rpc_url = CONFIG.api.helius_https.clone();
+            let batch_clone = batch.to_vec();
+            let keypair = keypair.clone();
+            let outcome = task::spawn_blocking(move || -> anyhow::Result<String> {
+                let owner = keypair.pubkey();
+                let mut instructions = Vec::with_capacity(batch_clone.len());
+                for (_, owned) in &batch_clone {
+                    instructions.extend(burn_and_close_instructions(owned, &owner, &destination)?);
+                }
+                send_instructions(&RpcClient::new(rpc_url), &keypair, &instructions)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("join error: {}", e))
+            .and_then(|r| r);
+
+            for (action, _) in batch {
+                let outcome = match &outcome {
+                    Ok(sig) => Ok(vec![sig.clone()]),
+                    Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+                };
+                report
+                    .results
+                    .push(action_result(&wallet_plan.wallet, action, outcome));
+            }
+            completed += batch.len() as u32;
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    job_id,
+                    completed,
+                    total,
+                    format!("Closed empty accounts of {}", wallet_plan.wallet),
+                );
+            }
+        }
+
+        for (action, owned) in actions
+            .into_iter()
+            .filter(|(a, _)| a.kind != CleanupKind::Close)
+        {
+            let outcome = match action.kind {
+                CleanupKind::BurnAndClose => {
+                    let rpc_url = CONFIG.api.helius_https.clone();
+                    let keypair = keypair.clone();
+                    task::spawn_blocking(move || {
+                        burn_and_close(&RpcClient::new(rpc_url), &keypair, &owned, &destination)
+                    })
+                    .await
+                    .map_err(|e| anyhow::anyhow!("join error: {}", e))
+                    .and_then(|r| r)
+                    .map(|sig| vec![sig])
+                }
+                _ => sell_and_close(wallet_pk, keypair.clone(), action, owned, destination).await,
+            };
+            report
+                .results
+                .push(action_result(&wallet_plan.wallet, action, outcome));
+            completed += 1;
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    job_id,
+                    completed,
+                    total,
+                    format!("Cleaned up token account {}", action.token_account),
+                );
+            }
+        }
+    }
+
+    let rent_by_account: HashMap<&str, u64> = plan
+        .wallets
+        .iter()
+        .flat_map(|w| &w.actions)
+        .map(|a| (a.token_account.as_str(), a.rent_lamports))
+        .collect();
+    for result in &report.results {
+        if result.success {
+            report.succeeded += 1;
+            report.reclaimed_rent_lamports += rent_by_account
+                .get(result.token_account.as_str())
+                .copied()
+                .unwrap_or(0);
+        } else {
+            report.failed += 1;
+        }
+    }
+    report.reclaimed_rent_sol = lamports_to_sol(report.reclaimed_rent_lamports);
+
+    info!(
+        "Cleanup done: {} succeeded, {} failed, {} SOL reclaimed",
+        report.succeeded, report.failed, report.reclaimed_rent_sol
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::pump::BONDING_CURVE_DISCRIMINATOR;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount};
+    use crate::solana::token_account::{DecodedTokenAccount, TokenAccountState};
+    use solana_sdk::account::Account;
+
+    const DUST: u64 = 1_000_000;
+
+    fn owned(mint: Pubkey, amount: u64) -> OwnedTokenAccount {
+        OwnedTokenAccount {
+            pubkey: Pubkey::new_unique(),
+            lamports: 2_039_280,
+            account: DecodedTokenAccount {
+                program_id: spl_token::id(),
+                mint,
+                owner: Pubkey::new_unique(),
+                amount,
+                decimals: Some(6),
+                state: TokenAccountState::Initialized,
+                delegate: None,
+                delegated_amount: 0,
+                close_authority: None,
+                is_native: false,
+                withheld_transfer_fees: 0,
+            },
+        }
+    }
+
+    /// Curve pricing one raw token at 1/1000 lamport
+    fn curves(mint: Pubkey) -> HashMap<Pubkey, (u64, u64)> {
+        HashMap::from([(mint, (1_000, 1_000_000))])
+    }
+
+    #[test]
+    fn classify_closes_empty_and_native_accounts() {
+        let mint = Pubkey::new_unique();
+        let action = classify(&owned(mint, 0), &HashMap::new(), DUST, false);
+        assert_eq!(action.kind, CleanupKind::Close);
+        assert_eq!(action.rent_lamports, 2_039_280);
+
+        let mut wrapped = owned(mint, 5_000);
+        wrapped.account.is_native = true;
+        assert_eq!(
+            classify(&wrapped, &HashMap::new(), DUST, false).kind,
+            CleanupKind::Close
+        );
+    }
+
+    #[test]
+    fn classify_skips_frozen_accounts_and_withheld_fees() {
+        let mint = Pubkey::new_unique();
+        let mut frozen = owned(mint, 0);
+        frozen.account.state = TokenAccountState::Frozen;
+        let action = classify(&frozen, &curves(mint), DUST, false);
+        assert_eq!(action.kind, CleanupKind::Skip);
+        assert!(action.reason.is_some());
+
+        let mut withheld = owned(mint, 10);
+        withheld.account.withheld_transfer_fees = 3;
+        assert_eq!(
+            classify(&withheld, &curves(mint), DUST, false).kind,
+            CleanupKind::Skip
+        );
+    }
+
+    #[test]
+    fn classify_burns_dust_and_sells_the_rest_on_live_curves() {
+        let mint = Pubkey::new_unique();
+        let dust = classify(&owned(mint, 999_999_999), &curves(mint), DUST, false);
+        assert_eq!(dust.kind, CleanupKind::BurnAndClose);
+        assert_eq!(dust.estimated_value_lamports, Some(999_999));
+
+        let worth_selling = classify(&owned(mint, 1_000_000_000), &curves(mint), DUST, false);
+        assert_eq!(worth_selling.kind, CleanupKind::SellAndClose);
+        assert_eq!(worth_selling.estimated_value_lamports, Some(DUST));
+    }
+
+    #[test]
+    fn classify_burns_unpriced_balances_unless_kept() {
+        let mint = Pubkey::new_unique();
+        let unpriced = classify(&owned(mint, 1_000), &HashMap::new(), DUST, false);
+        assert_eq!(unpriced.kind, CleanupKind::BurnAndClose);
+        assert_eq!(unpriced.estimated_value_lamports, None);
+        assert!(unpriced.reason.is_some());
+
+        let kept = classify(&owned(mint, 1_000), &HashMap::new(), DUST, true);
+        assert_eq!(kept.kind, CleanupKind::Skip);
+
+        // Only balances without a price are kept
+        let dust = classify(&owned(mint, 1_000), &curves(mint), DUST, true);
+        assert_eq!(dust.kind, CleanupKind::BurnAndClose);
+    }
+
+    #[test]
+    fn plan_drift_refuses_reclassified_or_changed_accounts() {
+        let mint = Pubkey::new_unique();
+        let planned = classify(&owned(mint, 1_000), &curves(mint), DUST, false);
+        assert_eq!(plan_drift(&planned, &planned.clone()), None);
+
+        let sold = CleanupAction {
+            kind: CleanupKind::Close,
+            amount_raw: 0,
+            ..planned.clone()
+        };
+        assert!(plan_drift(&planned, &sold).unwrap().contains("classified"));
+
+        let topped_up = CleanupAction {
+            amount_raw: 2_000,
+            ..planned.clone()
+        };
+        assert!(plan_drift(&planned, &topped_up)
+            .unwrap()
+            .contains("balance changed"));
+    }
+
+    #[test]
+    fn reclassify_wallet_prices_current_balances_on_live_curves() {
+        let rpc = FakeRpc::new();
+        let owner = Pubkey::new_unique();
+        let (traded, unlisted) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let traded_account = rpc.add_token_account(FakeTokenAccount::new(owner, traded, 1_000));
+        let unlisted_account = rpc.add_token_account(FakeTokenAccount::new(owner, unlisted, 0));
+
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for value in [1_000_000u64, 1_000, 0, 0, 1_000_000] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(0);
+        rpc.set_account(
+            &bonding_curve_pda(&traded),
+            Account {
+                lamports: 1,
+                data,
+                owner: PUMP_PROGRAM_ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+
+        let current = reclassify_wallet(&rpc, &owner, DUST, false).unwrap();
+        assert_eq!(current.len(), 2);
+        let (_, traded_action) = &current[&traded_account.to_string()];
+        assert_eq!(traded_action.kind, CleanupKind::BurnAndClose);
+        assert_eq!(traded_action.estimated_value_lamports, Some(1));
+        assert_eq!(
+            current[&unlisted_account.to_string()].1.kind,
+            CleanupKind::Close
+        );
+    }
+}
@@ -1,209 +1,296 @@
-use std::str::FromStr;
-
 use crate::config::CONFIG;
+use crate::services::common::parse_keypair;
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{
+    decode_keyed_account, fetch_token_accounts, resolve_rent_destination,
+};
 use anyhow::Context;
 use log::info;
 use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::instruction::Instruction;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::{bs58, signature::Keypair, signer::Signer};
-use solana_sdk::{instruction::Instruction, transaction::Transaction};
+use solana_sdk::signer::Signer;
 use tokio::task;
 
-fn parse_keypair(private_key: &str) -> anyhow::Result<Keypair> {
-    let bytes = bs58::decode(private_key)
-        .into_vec()
-        .context("invalid base58 key")?;
-    if bytes.len() == 64 {
-        Keypair::try_from(&bytes[..]).context("invalid keypair bytes")
-    } else {
-        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
-    }
-}
+const MAX_CLOSES_PER_TX: usize = 5; // Limit closes per transaction
 
-/// Extract token account info from parsed data
-fn extract_token_info(
-    data: &solana_account_decoder_client_types::UiAccountData,
-) -> Option<(String, f64)> {
-    if let solana_account_decoder_client_types::UiAccountData::Json(parsed) = data {
-        if let Some(parsed_account) = parsed.parsed.as_object() {
-            if let Some(info) = parsed_account.get("info").and_then(|v| v.as_object()) {
-                let mint = info.get("mint")?.as_str()?.to_string();
-                let token_amount = info.get("tokenAmount")?.as_object()?;
-                let ui_amount = token_amount.get("uiAmount")?.as_f64()?;
-                return Some((mint, ui_amount));
-            }
-        }
-    }
-    None
-}
-
-/// Create close account instruction for SPL token account
+/// Create close account instruction for a legacy SPL Token or Token-2022 account
 fn create_close_instruction(
+    program_id: &Pubkey,
     token_account: &Pubkey,
     owner: &Pubkey,
     destination: &Pubkey,
-) -> Instruction {
-    // SPL Token close account instruction
-    let program_id = spl_token::id();
-
-    Instruction {
-        program_id,
-        accounts: vec![
-            solana_sdk::instruction::AccountMeta::new(*token_account, false),
-            solana_sdk::instruction::AccountMeta::new(*destination, false),
-            solana_sdk::instruction::AccountMeta::new_readonly(*owner, true),
-        ],
-        data: vec![9], // Close account instruction discriminator
-    }
+) -> anyhow::Result<Instruction> {
+    spl_token_2022::instruction::close_account(program_id, token_account, destination, owner, &[])
+        .context("failed to build close account instruction")
 }
 
-pub async fn close_accounts(wallet_pk: String) -> Result<String, String> {
-    const MAX_CLOSES_PER_TX: usize = 5; // Limit closes per transaction
+/// Transaction signatures, accounts skipped with the reason they cannot be closed,
+/// and the account that received the rent
+type CloseOutcome = (Vec<String>, Vec<String>, Pubkey);
 
-    info!(
-        "Starting close_accounts for wallet - closing empty token accounts and refunding rent to same wallet"
-    );
-
-    let rpc_url = CONFIG.api.helius_https.clone();
-    let wallet_pk_clone = wallet_pk.clone();
-
-    let res = task::spawn_blocking(move || -> anyhow::Result<Vec<String>> {
-        let client = RpcClient::new(rpc_url);
-
-        // Parse the wallet keypair
-        let wallet_keypair = parse_keypair(&wallet_pk_clone)?;
-        let wallet_pubkey = wallet_keypair.pubkey();
-
-        info!("Processing wallet: {}", wallet_pubkey);
-        info!(
-            "Rent will be refunded to the same wallet: {}",
-            wallet_pubkey
-        );
+/// Close the wallet's empty token accounts in batches of `MAX_CLOSES_PER_TX`;
+/// a failed batch is logged and the next one still goes out
+fn close_empty_accounts(
+    client: &dyn SolanaRpc,
+    wallet_pk: &str,
+    rent_destination: Option<&str>,
+) -> anyhow::Result<CloseOutcome> {
+    // Parse the wallet keypair
+    let wallet_keypair = parse_keypair(wallet_pk)?;
+    let wallet_pubkey = wallet_keypair.pubkey();
+    let destination = resolve_rent_destination(rent_destination, &wallet_pubkey)?;
 
-        let mut transaction_signatures = Vec::new();
-        let mut total_closed_accounts = 0;
+    info!("Processing wallet: {}", wallet_pubkey);
+    info!("Rent will be refunded to: {}", destination);
 
-        // Get all token accounts for this wallet
-        let token_accounts = client
-            .get_token_accounts_by_owner(
-                &wallet_pubkey,
-                TokenAccountsFilter::ProgramId(spl_token::id()),
-            )
-            .context("failed to get token accounts")?;
+    let mut transaction_signatures = Vec::new();
+    let mut total_closed_accounts = 0;
 
-        info!(
-            "Found {} token accounts for wallet {}",
-            token_accounts.len(),
-            wallet_pubkey
-        );
+    // Get all token accounts for this wallet (SPL Token and Token-2022)
+    let token_accounts = fetch_token_accounts(client, &wallet_pubkey)?;
 
-        // Find token accounts with 0 balance
-        let mut accounts_to_close = Vec::new();
+    info!(
+        "Found {} token accounts for wallet {}",
+        token_accounts.len(),
+        wallet_pubkey
+    );
 
-        for token_account in token_accounts {
-            let account_pubkey =
-                Pubkey::from_str(&token_account.pubkey).context("invalid token account pubkey")?;
+    // Find token accounts with 0 balance that nothing else prevents from closing
+    let mut accounts_to_close = Vec::new();
+    let mut skipped = Vec::new();
 
-            if let Some((mint, ui_amount)) = extract_token_info(&token_account.account.data) {
+    for token_account in token_accounts {
+        match decode_keyed_account(&token_account) {
+            Ok((account_pubkey, decoded)) => {
                 info!(
-                    "Token account {} - Mint: {}, Balance: {}",
-                    account_pubkey, mint, ui_amount
+                    "Token account {} - Mint: {}, Balance: {} raw",
+                    account_pubkey, decoded.mint, decoded.amount
                 );
 
-                if ui_amount == 0.0 {
+                if decoded.is_empty() {
+                    let blockers = decoded.close_blockers(false);
+                    if !blockers.is_empty() {
+                        let reasons = blockers
+                            .iter()
+                            .map(|b| b.to_string())
+                            .collect::<Vec<_>>()
+                            .join("; ");
+                        info!("Skipping token account {}: {}", account_pubkey, reasons);
+                        skipped.push(format!("{} ({})", account_pubkey, reasons));
+                        continue;
+                    }
                     info!(
                         "Adding token account {} to close list (balance: 0)",
                         account_pubkey
                     );
-                    accounts_to_close.push(account_pubkey);
+                    accounts_to_close.push((account_pubkey, decoded.program_id));
                 }
-            } else {
-                info!("Could not parse token account data for {}", account_pubkey);
             }
+            Err(e) => info!("Could not parse token account data: {:#}", e),
         }
+    }
 
-        if accounts_to_close.is_empty() {
-            info!("No empty token accounts found for wallet {}", wallet_pubkey);
-            return Ok(vec!["No empty token accounts to close".to_string()]);
-        }
+    if accounts_to_close.is_empty() {
+        info!("No empty token accounts found for wallet {}", wallet_pubkey);
+        return Ok((vec![], skipped, destination));
+    }
+
+    info!(
+        "Found {} empty token accounts to close for wallet {}",
+        accounts_to_close.len(),
+        wallet_pubkey
+    );
 
+    // Process accounts in batches
+    for (batch_idx, batch_accounts) in accounts_to_close.chunks(MAX_CLOSES_PER_TX).enumerate() {
         info!(
-            "Found {} empty token accounts to close for wallet {}",
-            accounts_to_close.len(),
-            wallet_pubkey
+            "Processing batch {} with {} accounts to close",
+            batch_idx + 1,
+            batch_accounts.len()
         );
 
-        // Process accounts in batches
-        for (batch_idx, batch_accounts) in accounts_to_close.chunks(MAX_CLOSES_PER_TX).enumerate() {
-            info!(
-                "Processing batch {} with {} accounts to close",
-                batch_idx + 1,
-                batch_accounts.len()
-            );
-
-            let mut instructions = Vec::new();
-
-            // Create close instructions for this batch (refund rent to same wallet)
-            for account_pubkey in batch_accounts {
-                let close_instruction = create_close_instruction(
-                    account_pubkey,
-                    &wallet_pubkey,
-                    &wallet_pubkey, // Refund rent to the same wallet
+        let mut instructions = Vec::new();
+
+        // Create close instructions for this batch
+        for (account_pubkey, program_id) in batch_accounts {
+            let close_instruction =
+                create_close_instruction(program_id, account_pubkey, &wallet_pubkey, &destination)?;
+            instructions.push(close_instruction);
+        }
+
+        // Send transaction
+        match send_and_confirm(client, &instructions, &wallet_pubkey, &[&wallet_keypair]) {
+            Ok(sig) => {
+                info!(
+                    "Batch {} completed with signature: {} (closed {} accounts)",
+                    batch_idx + 1,
+                    sig,
+                    batch_accounts.len()
                 );
-                instructions.push(close_instruction);
+                transaction_signatures.push(sig.to_string());
+                total_closed_accounts += batch_accounts.len();
             }
+            Err(e) => {
+                info!("Failed to close batch {}: {}", batch_idx + 1, e);
+                continue;
+            }
+        }
 
-            let recent_blockhash = client
-                .get_latest_blockhash()
-                .context("failed to fetch blockhash")?;
-
-            let signer_refs: Vec<&dyn Signer> = vec![&wallet_keypair as &dyn Signer];
+        // Small delay between batches
+        std::thread::sleep(std::time::Duration::from_millis(300));
+    }
 
-            let tx = Transaction::new_signed_with_payer(
-                &instructions,
-                Some(&wallet_pubkey),
-                &signer_refs,
-                recent_blockhash,
-            );
+    info!(
+        "All account closures completed. Total closed accounts: {}, Total transactions: {}",
+        total_closed_accounts,
+        transaction_signatures.len()
+    );
+    Ok((transaction_signatures, skipped, destination))
+}
 
-            // Send transaction
-            match client.send_and_confirm_transaction(&tx) {
-                Ok(sig) => {
-                    info!(
-                        "Batch {} completed with signature: {} (closed {} accounts)",
-                        batch_idx + 1,
-                        sig,
-                        batch_accounts.len()
-                    );
-                    transaction_signatures.push(sig.to_string());
-                    total_closed_accounts += batch_accounts.len();
-                }
-                Err(e) => {
-                    info!("Failed to close batch {}: {}", batch_idx + 1, e);
-                    continue;
-                }
-            }
+/// Close every empty token account of the wallet; rent goes to `rent_destination`
+/// when given, to the wallet itself otherwise
+pub async fn close_accounts(
+    wallet_pk: String,
+    rent_destination: Option<String>,
+) -> Result<String, String> {
+    info!("Starting close_accounts for wallet - closing empty token accounts and refunding rent");
 
-            // Small delay between batches
-            std::thread::sleep(std::time::Duration::from_millis(300));
-        }
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let wallet_pk_clone = wallet_pk.clone();
 
-        info!(
-            "All account closures completed. Total closed accounts: {}, Total transactions: {}",
-            total_closed_accounts,
-            transaction_signatures.len()
-        );
-        Ok(transaction_signatures)
+    let res = task::spawn_blocking(move || {
+        close_empty_accounts(
+            &RpcClient::new(rpc_url),
+            &wallet_pk_clone,
+            rent_destination.as_deref(),
+        )
     })
     .await
     .map_err(|e| format!("join error: {}", e))
     .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
 
+    let (signatures, skipped, destination) = res;
+    if signatures.is_empty() && skipped.is_empty() {
+        return Ok("No empty token accounts to close".to_string());
+    }
+
     // Return summary of all transaction signatures
-    Ok(format!(
-        "Closed {} empty token accounts. Transactions: {}",
-        res.len(),
-        res.join(", ")
-    ))
+    let mut summary = format!(
+        "Closed {} empty token accounts. Rent sent to {}. Transactions: {}",
+        signatures.len(),
+        destination,
+        signatures.join(", ")
+    );
+    if !skipped.is_empty() {
+        summary.push_str(&format!(
+            ". Skipped {} accounts: {}",
+            skipped.len(),
+            skipped.join(", ")
+        ));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount, RpcMethod};
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::transaction::Transaction;
+
+    /// Token account closed by each instruction of the transaction
+    fn closed_accounts(tx: &Transaction) -> Vec<Pubkey> {
+        tx.message
+            .instructions
+            .iter()
+            .map(|ix| tx.message.account_keys[ix.accounts[0] as usize])
+            .collect()
+    }
+
+    fn wallet_with_accounts(rpc: &FakeRpc, empty: usize) -> (Keypair, Vec<Pubkey>) {
+        let wallet = Keypair::new();
+        rpc.set_balance(&wallet.pubkey(), 10_000_000);
+        let accounts = (0..empty)
+            .map(|_| {
+                rpc.add_token_account(FakeTokenAccount::new(
+                    wallet.pubkey(),
+                    Pubkey::new_unique(),
+                    0,
+                ))
+            })
+            .collect();
+        (wallet, accounts)
+    }
+
+    #[test]
+    fn closes_only_empty_unfrozen_accounts_in_batches() {
+        let rpc = FakeRpc::new();
+        let (wallet, empty) = wallet_with_accounts(&rpc, 7);
+        let funded = rpc.add_token_account(FakeTokenAccount::new(
+            wallet.pubkey(),
+            Pubkey::new_unique(),
+            1_000,
+        ));
+        let frozen = rpc.add_token_account(FakeTokenAccount {
+            frozen: true,
+            ..FakeTokenAccount::new(wallet.pubkey(), Pubkey::new_unique(), 0)
+        });
+
+        let (signatures, skipped, destination) =
+            close_empty_accounts(&rpc, &wallet.to_base58_string(), None).unwrap();
+
+        let sent = rpc.sent();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(closed_accounts(&sent[0]).len(), MAX_CLOSES_PER_TX);
+        let closed: Vec<Pubkey> = sent.iter().flat_map(closed_accounts).collect();
+        assert_eq!(closed, empty);
+        assert!(!closed.contains(&funded));
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].starts_with(&frozen.to_string()));
+        assert_eq!(destination, wallet.pubkey());
+    }
+
+    #[test]
+    fn failed_batch_does_not_stop_the_next_one() {
+        let rpc = FakeRpc::new();
+        let (wallet, empty) = wallet_with_accounts(&rpc, 7);
+        let collector = Pubkey::new_unique();
+        rpc.fail_next(RpcMethod::SendTransaction, "blockhash not found");
+
+        let (signatures, _, destination) = close_empty_accounts(
+            &rpc,
+            &wallet.to_base58_string(),
+            Some(&collector.to_string()),
+        )
+        .unwrap();
+
+        let sent = rpc.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(signatures, vec![sent[1].signatures[0].to_string()]);
+        assert_eq!(closed_accounts(&sent[1]), empty[MAX_CLOSES_PER_TX..]);
+        assert_eq!(destination, collector);
+    }
+
+    #[test]
+    fn nothing_to_close_sends_nothing() {
+        let rpc = FakeRpc::new();
+        let (wallet, _) = wallet_with_accounts(&rpc, 0);
+        rpc.add_token_account(FakeTokenAccount::new(
+            wallet.pubkey(),
+            Pubkey::new_unique(),
+            42,
+        ));
+
+        let (signatures, skipped, _) =
+            close_empty_accounts(&rpc, &wallet.to_base58_string(), None).unwrap();
+
+        assert!(signatures.is_empty());
+        assert!(skipped.is_empty());
+        assert!(rpc.sent().is_empty());
+        assert_eq!(rpc.calls(RpcMethod::GetLatestBlockhash), 0);
+    }
 }
@@ -1,14 +1,20 @@
 use std::str::FromStr;
 
 use crate::config::CONFIG;
+use crate::secret::SecretString;
+use crate::services::common::parse_keypair;
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{
+    decode_keyed_account, fetch_token_accounts, resolve_rent_destination,
+};
 use anyhow::Context;
 use log::info;
 use serde::{Deserialize, Serialize};
 use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::instruction::Instruction;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::{bs58, signature::Keypair, signer::Signer};
-use solana_sdk::{instruction::Instruction, transaction::Transaction};
+use solana_sdk::{signature::Keypair, signer::Signer};
 use tokio::task;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -17,303 +23,309 @@ pub struct CloseTokenAccountResult {
     pub token_mint: String,
     pub token_account: Option<String>,
     pub transaction_signature: Option<String>,
+    /// Account that received (or would have received) the rent
+    #[serde(default)]
+    pub rent_destination: Option<String>,
     pub message: String,
     pub error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CloseTokenAccountBatchRequest {
-    pub wallet_pk: String,
+    pub wallet_pk: SecretString,
     pub token_mints: Vec<String>,
+    /// Rent recipient for every closed account, the wallet itself when unset
+    #[serde(default)]
+    pub rent_destination: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CloseTokenAccountBatchResult {
     pub total_requested: usize,
+    #[serde(default)]
+    pub rent_destination: Option<String>,
     pub successful_closures: usize,
     pub failed_closures: usize,
     pub results: Vec<CloseTokenAccountResult>,
 }
 
-fn parse_keypair(private_key: &str) -> anyhow::Result<Keypair> {
-    let bytes = bs58::decode(private_key)
-        .into_vec()
-        .context("invalid base58 key")?;
-    if bytes.len() == 64 {
-        Keypair::try_from(&bytes[..]).context("invalid keypair bytes")
-    } else {
-        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
-    }
-}
-
-/// Extract token account info from parsed data
-fn extract_token_info(
-    data: &solana_account_decoder_client_types::UiAccountData,
-) -> Option<(String, f64)> {
-    if let solana_account_decoder_client_types::UiAccountData::Json(parsed) = data {
-        if let Some(parsed_account) = parsed.parsed.as_object() {
-            if let Some(info) = parsed_account.get("info").and_then(|v| v.as_object()) {
-                let mint = info.get("mint")?.as_str()?.to_string();
-                let token_amount = info.get("tokenAmount")?.as_object()?;
-                let ui_amount = token_amount.get("uiAmount")?.as_f64()?;
-                return Some((mint, ui_amount));
-            }
-        }
-    }
-    None
-}
-
-/// Create close account instruction for SPL token account
+/// Create close account instruction for a legacy SPL Token or Token-2022 account
 fn create_close_instruction(
+    program_id: &Pubkey,
     token_account: &Pubkey,
     owner: &Pubkey,
     destination: &Pubkey,
-) -> Instruction {
-    // SPL Token close account instruction
-    let program_id = spl_token::id();
-
-    Instruction {
-        program_id,
-        accounts: vec![
-            solana_sdk::instruction::AccountMeta::new(*token_account, false),
-            solana_sdk::instruction::AccountMeta::new(*destination, false),
-            solana_sdk::instruction::AccountMeta::new_readonly(*owner, true),
-        ],
-        data: vec![9], // Close account instruction discriminator
-    }
+) -> anyhow::Result<Instruction> {
+    spl_token_2022::instruction::close_account(program_id, token_account, destination, owner, &[])
+        .context("failed to build close account instruction")
 }
 
-/// Close specific token account for a given token mint
-pub async fn close_token_account(
-    wallet_pk: String,
-    token_mint: String,
+/// Close the wallet's token account for `token_mint`; a missing or blocked
+/// account and a failed send are reported in the result, not as errors
+fn close_account_for_mint(
+    client: &dyn SolanaRpc,
+    wallet_pk: &str,
+    token_mint: &str,
+    rent_destination: Option<&str>,
     job_id: Option<String>,
-) -> Result<String, String> {
-    info!(
-        "Starting close_token_account for wallet - closing token account for mint: {}",
-        token_mint
-    );
+) -> anyhow::Result<CloseTokenAccountResult> {
+    // Parse the wallet keypair
+    let wallet_keypair = parse_keypair(wallet_pk)?;
+    let wallet_pubkey = wallet_keypair.pubkey();
+    let destination = resolve_rent_destination(rent_destination, &wallet_pubkey)?;
 
-    let rpc_url = CONFIG.api.helius_https.clone();
-    let wallet_pk_clone = wallet_pk.clone();
-    let token_mint_clone = token_mint.clone();
+    // Parse token mint
+    let mint_pubkey = Pubkey::from_str(token_mint).context("invalid token mint pubkey")?;
 
-    let res = task::spawn_blocking(move || -> anyhow::Result<CloseTokenAccountResult> {
-        let client = RpcClient::new(rpc_url);
+    // Update job progress if job_id provided - Starting (Step 1/5)
+    if let Some(ref job_id) = job_id {
+        let _ =
+            crate::update_job_progress(&job_id, 1, 5, "Starting token account closure".to_string());
+    }
 
-        // Parse the wallet keypair
-        let wallet_keypair = parse_keypair(&wallet_pk_clone)?;
-        let wallet_pubkey = wallet_keypair.pubkey();
+    info!(
+        "Processing wallet: {} for mint: {}",
+        wallet_pubkey, mint_pubkey
+    );
+    info!("Rent will be refunded to: {}", destination);
 
-        // Parse token mint
-        let mint_pubkey =
-            Pubkey::from_str(&token_mint_clone).context("invalid token mint pubkey")?;
+    // Get all token accounts for this wallet (SPL Token and Token-2022)
+    let token_accounts = fetch_token_accounts(client, &wallet_pubkey)?;
 
-        // Update job progress if job_id provided - Starting (Step 1/5)
-        if let Some(ref job_id) = job_id {
-            let _ = crate::update_job_progress(
-                &job_id,
-                1,
-                5,
-                "Starting token account closure".to_string(),
-            );
-        }
-
-        info!(
-            "Processing wallet: {} for mint: {}",
-            wallet_pubkey, mint_pubkey
-        );
-        info!(
-            "Rent will be refunded to the same wallet: {}",
-            wallet_pubkey
+    // Update job progress if job_id provided - Searching for token account (Step 2/5)
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(
+            &job_id,
+            2,
+            5,
+            format!(
+                "Found {} token accounts, searching for target mint",
+                token_accounts.len()
+            ),
         );
+    }
 
-        // Get all token accounts for this wallet
-        let token_accounts = client
-            .get_token_accounts_by_owner(
-                &wallet_pubkey,
-                TokenAccountsFilter::ProgramId(spl_token::id()),
-            )
-            .context("failed to get token accounts")?;
+    info!(
+        "Found {} token accounts for wallet {}",
+        token_accounts.len(),
+        wallet_pubkey
+    );
 
-        // Update job progress if job_id provided - Searching for token account (Step 2/5)
-        if let Some(ref job_id) = job_id {
-            let _ = crate::update_job_progress(
-                &job_id,
-                2,
-                5,
-                format!(
-                    "Found {} token accounts, searching for target mint",
-                    token_accounts.len()
-                ),
-            );
-        }
+    // Find the specific token account for the given mint
+    let mut target_account = None;
+    let mut account_balance = 0.0;
 
+    for token_account in token_accounts {
+        let (account_pubkey, decoded) = match decode_keyed_account(&token_account) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                info!("Could not parse token account data: {:#}", e);
+                continue;
+            }
+        };
+        let ui_amount = decoded.ui_amount().unwrap_or(decoded.amount as f64);
         info!(
-            "Found {} token accounts for wallet {}",
-            token_accounts.len(),
-            wallet_pubkey
+            "Token account {} - Mint: {}, Balance: {}",
+            account_pubkey, decoded.mint, ui_amount
         );
 
-        // Find the specific token account for the given mint
-        let mut target_account_pubkey = None;
-        let mut account_balance = 0.0;
-
-        for token_account in token_accounts {
-            let account_pubkey =
-                Pubkey::from_str(&token_account.pubkey).context("invalid token account pubkey")?;
-
-            if let Some((mint, ui_amount)) = extract_token_info(&token_account.account.data) {
-                info!(
-                    "Token account {} - Mint: {}, Balance: {}",
-                    account_pubkey, mint, ui_amount
+        // Check if this is the token account for our target mint
+        if decoded.mint == mint_pubkey {
+            info!(
+                "Found target token account {} for mint {} with balance {}",
+                account_pubkey, decoded.mint, ui_amount
+            );
+            account_balance = ui_amount;
+            target_account = Some((account_pubkey, decoded));
+
+            // Update job progress if job_id provided - Found target account (Step 3/5)
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    &job_id,
+                    3,
+                    5,
+                    format!("Found target token account with balance: {}", ui_amount),
                 );
-
-                // Check if this is the token account for our target mint
-                if mint == token_mint_clone {
-                    info!(
-                        "Found target token account {} for mint {} with balance {}",
-                        account_pubkey, mint, ui_amount
-                    );
-                    target_account_pubkey = Some(account_pubkey);
-                    account_balance = ui_amount;
-
-                    // Update job progress if job_id provided - Found target account (Step 3/5)
-                    if let Some(ref job_id) = job_id {
-                        let _ = crate::update_job_progress(
-                            &job_id,
-                            3,
-                            5,
-                            format!("Found target token account with balance: {}", ui_amount),
-                        );
-                    }
-                    break;
-                }
-            } else {
-                info!("Could not parse token account data for {}", account_pubkey);
             }
+            break;
         }
+    }
 
-        let account_to_close = match target_account_pubkey {
-            Some(account) => account,
-            None => {
-                let message = format!(
-                    "No token account found for mint {} in wallet {}",
-                    token_mint_clone, wallet_pubkey
+    let (account_to_close, decoded) = match target_account {
+        Some(account) => account,
+        None => {
+            let message = format!(
+                "No token account found for mint {} in wallet {}",
+                token_mint, wallet_pubkey
+            );
+            info!("{}", message);
+
+            // Update job progress if job_id provided - Account not found (Step 5/5 - Error)
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    &job_id,
+                    5,
+                    5,
+                    "Token account not found".to_string(),
                 );
-                info!("{}", message);
-
-                // Update job progress if job_id provided - Account not found (Step 5/5 - Error)
-                if let Some(ref job_id) = job_id {
-                    let _ = crate::update_job_progress(
-                        &job_id,
-                        5,
-                        5,
-                        "Token account not found".to_string(),
-                    );
-                }
-
-                return Ok(CloseTokenAccountResult {
-                    success: false,
-                    token_mint: token_mint_clone,
-                    token_account: None,
-                    transaction_signature: None,
-                    message,
-                    error: Some("Token account not found".to_string()),
-                });
             }
-        };
 
-        info!(
-            "Closing token account {} for mint {} from wallet {} (balance: {})",
-            account_to_close, token_mint_clone, wallet_pubkey, account_balance
+            return Ok(CloseTokenAccountResult {
+                success: false,
+                token_mint: token_mint.to_string(),
+                token_account: None,
+                transaction_signature: None,
+                rent_destination: Some(destination.to_string()),
+                message,
+                error: Some("Token account not found".to_string()),
+            });
+        }
+    };
+
+    let blockers = decoded.close_blockers(false);
+    if !blockers.is_empty() {
+        let reasons = blockers
+            .iter()
+            .map(|b| b.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let message = format!(
+            "Token account {} for mint {} cannot be closed: {}",
+            account_to_close, token_mint, reasons
         );
+        info!("{}", message);
 
-        // Update job progress if job_id provided - Creating transaction (Step 4/5)
         if let Some(ref job_id) = job_id {
-            let _ =
-                crate::update_job_progress(&job_id, 4, 5, "Creating close transaction".to_string());
+            let _ = crate::update_job_progress(
+                &job_id,
+                5,
+                5,
+                "Token account cannot be closed".to_string(),
+            );
         }
 
-        // Create close instruction (refund rent to same wallet)
-        let close_instruction = create_close_instruction(
-            &account_to_close,
-            &wallet_pubkey,
-            &wallet_pubkey, // Refund rent to the same wallet
-        );
+        return Ok(CloseTokenAccountResult {
+            success: false,
+            token_mint: token_mint.to_string(),
+            token_account: Some(account_to_close.to_string()),
+            transaction_signature: None,
+            rent_destination: Some(destination.to_string()),
+            message,
+            error: Some(reasons),
+        });
+    }
 
-        let recent_blockhash = client
-            .get_latest_blockhash()
-            .context("failed to fetch blockhash")?;
+    info!(
+        "Closing token account {} for mint {} from wallet {} (balance: {})",
+        account_to_close, token_mint, wallet_pubkey, account_balance
+    );
 
-        let signer_refs: Vec<&dyn Signer> = vec![&wallet_keypair as &dyn Signer];
+    // Update job progress if job_id provided - Creating transaction (Step 4/5)
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(&job_id, 4, 5, "Creating close transaction".to_string());
+    }
 
-        let tx = Transaction::new_signed_with_payer(
-            &[close_instruction],
-            Some(&wallet_pubkey),
-            &signer_refs,
-            recent_blockhash,
-        );
+    // Create close instruction (refund rent to same wallet)
+    let close_instruction = create_close_instruction(
+        &decoded.program_id,
+        &account_to_close,
+        &wallet_pubkey,
+        &destination,
+    )?;
+
+    // Send transaction
+    match send_and_confirm(
+        client,
+        &[close_instruction],
+        &wallet_pubkey,
+        &[&wallet_keypair],
+    ) {
+        Ok(sig) => {
+            let signature = sig.to_string();
+            let message = format!(
+                "Token account {} for mint {} closed successfully (balance: {})",
+                account_to_close, token_mint, account_balance
+            );
+            info!(
+                "Token account closure completed with signature: {} - {}",
+                signature, message
+            );
 
-        // Send transaction
-        match client.send_and_confirm_transaction(&tx) {
-            Ok(sig) => {
-                let signature = sig.to_string();
-                let message = format!(
-                    "Token account {} for mint {} closed successfully (balance: {})",
-                    account_to_close, token_mint_clone, account_balance
-                );
-                info!(
-                    "Token account closure completed with signature: {} - {}",
-                    signature, message
+            // Update job progress if job_id provided - Success (Step 5/5 - Complete)
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    &job_id,
+                    5,
+                    5,
+                    format!("Successfully closed token account: {}", signature),
                 );
-
-                // Update job progress if job_id provided - Success (Step 5/5 - Complete)
-                if let Some(ref job_id) = job_id {
-                    let _ = crate::update_job_progress(
-                        &job_id,
-                        5,
-                        5,
-                        format!("Successfully closed token account: {}", signature),
-                    );
-                }
-
-                Ok(CloseTokenAccountResult {
-                    success: true,
-                    token_mint: token_mint_clone,
-                    token_account: Some(account_to_close.to_string()),
-                    transaction_signature: Some(signature),
-                    message,
-                    error: None,
-                })
             }
-            Err(e) => {
-                let error_msg = format!(
-                    "Failed to close token account {} for mint {}: {}",
-                    account_to_close, token_mint_clone, e
-                );
-                info!("{}", error_msg);
-
-                // Update job progress if job_id provided - Error (Step 5/5 - Failed)
-                if let Some(ref job_id) = job_id {
-                    let _ = crate::update_job_progress(
-                        &job_id,
-                        5,
-                        5,
-                        format!("Failed to close account: {}", error_msg),
-                    );
-                }
 
-                Ok(CloseTokenAccountResult {
-                    success: false,
-                    token_mint: token_mint_clone,
-                    token_account: Some(account_to_close.to_string()),
-                    transaction_signature: None,
-                    message: error_msg.clone(),
-                    error: Some(error_msg),
-                })
+            Ok(CloseTokenAccountResult {
+                success: true,
+                token_mint: token_mint.to_string(),
+                token_account: Some(account_to_close.to_string()),
+                transaction_signature: Some(signature),
+                rent_destination: Some(destination.to_string()),
+                message,
+                error: None,
+            })
+        }
+        Err(e) => {
+            let error_msg = format!(
+                "Failed to close token account {} for mint {}: {}",
+                account_to_close, token_mint, e
+            );
+            info!("{}", error_msg);
+
+            // Update job progress if job_id provided - Error (Step 5/5 - Failed)
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    &job_id,
+                    5,
+                    5,
+                    format!("Failed to close account: {}", error_msg),
+                );
             }
+
+            Ok(CloseTokenAccountResult {
+                success: false,
+                token_mint: token_mint.to_string(),
+                token_account: Some(account_to_close.to_string()),
+                transaction_signature: None,
+                rent_destination: Some(destination.to_string()),
+                message: error_msg.clone(),
+                error: Some(error_msg),
+            })
         }
+    }
+}
+
+/// Close specific token account for a given token mint; rent goes to
+/// `rent_destination` when given, to the wallet itself otherwise
+pub async fn close_token_account(
+    wallet_pk: String,
+    token_mint: String,
+    rent_destination: Option<String>,
+    job_id: Option<String>,
+) -> Result<String, String> {
+    info!(
+        "Starting close_token_account for wallet - closing token account for mint: {}",
+        token_mint
+    );
+
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let wallet_pk_clone = wallet_pk.clone();
+    let token_mint_clone = token_mint.clone();
+
+    let res = task::spawn_blocking(move || {
+        close_account_for_mint(
+            &RpcClient::new(rpc_url),
+            &wallet_pk_clone,
+            &token_mint_clone,
+            rent_destination.as_deref(),
+            job_id,
+        )
     })
     .await
     .map_err(|e| format!("join error: {}", e))
@@ -331,8 +343,9 @@ pub async fn close_token_accounts_batch(
     request: CloseTokenAccountBatchRequest,
     job_id: Option<String>,
 ) -> Result<String, String> {
-    let wallet_pk = request.wallet_pk;
+    let wallet_pk = request.wallet_pk.into_inner();
     let token_mints = request.token_mints;
+    let rent_destination = request.rent_destination;
     let total_accounts = token_mints.len();
 
     if total_accounts == 0 {
@@ -381,7 +394,14 @@ pub async fn close_token_accounts_batch(
         }
 
         // Call the individual close function without job_id to avoid nested progress tracking
-        match close_token_account(wallet_pk.clone(), token_mint.clone(), None).await {
+        match close_token_account(
+            wallet_pk.clone(),
+            token_mint.clone(),
+            rent_destination.clone(),
+            None,
+        )
+        .await
+        {
             Ok(result_json) => {
                 match serde_json::from_str::<CloseTokenAccountResult>(&result_json) {
                     Ok(result) => {
@@ -404,6 +424,7 @@ pub async fn close_token_accounts_batch(
                             token_mint: token_mint.clone(),
                             token_account: None,
                             transaction_signature: None,
+                            rent_destination: rent_destination.clone(),
                             message: format!("Failed to parse result: {}", e),
                             error: Some(format!("Parse error: {}", e)),
                         };
@@ -418,6 +439,7 @@ pub async fn close_token_accounts_batch(
                     token_mint: token_mint.clone(),
                     token_account: None,
                     transaction_signature: None,
+                    rent_destination: rent_destination.clone(),
                     message: format!("Failed to close token account: {}", e),
                     error: Some(e),
                 };
@@ -441,6 +463,7 @@ pub async fn close_token_accounts_batch(
 
     let batch_result = CloseTokenAccountBatchResult {
         total_requested: total_accounts,
+        rent_destination: rent_destination.clone(),
         successful_closures,
         failed_closures,
         results,
@@ -452,3 +475,91 @@ pub async fn close_token_accounts_batch(
         Err(e) => Err(format!("Failed to serialize batch result: {}", e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount, RpcMethod};
+
+    fn close(rpc: &FakeRpc, wallet: &Keypair, mint: &Pubkey) -> CloseTokenAccountResult {
+        close_account_for_mint(
+            rpc,
+            &wallet.to_base58_string(),
+            &mint.to_string(),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn closes_empty_account_with_rent_to_the_wallet() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let account = rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), mint, 0));
+
+        let result = close(&rpc, &wallet, &mint);
+
+        let sent = rpc.sent();
+        assert!(result.success, "{}", result.message);
+        assert_eq!(result.token_account, Some(account.to_string()));
+        assert_eq!(
+            result.transaction_signature,
+            Some(sent[0].signatures[0].to_string())
+        );
+        assert_eq!(result.rent_destination, Some(wallet.pubkey().to_string()));
+        let keys = &sent[0].message.account_keys;
+        let accounts = &sent[0].message.instructions[0].accounts;
+        assert_eq!(keys[accounts[0] as usize], account);
+        assert_eq!(keys[accounts[1] as usize], wallet.pubkey());
+    }
+
+    #[test]
+    fn missing_account_is_reported_without_sending() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        rpc.add_token_account(FakeTokenAccount::new(
+            wallet.pubkey(),
+            Pubkey::new_unique(),
+            0,
+        ));
+
+        let result = close(&rpc, &wallet, &Pubkey::new_unique());
+
+        assert!(!result.success);
+        assert_eq!(result.token_account, None);
+        assert_eq!(result.error.as_deref(), Some("Token account not found"));
+        assert!(rpc.sent().is_empty());
+    }
+
+    #[test]
+    fn non_empty_account_is_not_closed() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), mint, 1_000));
+
+        let result = close(&rpc, &wallet, &mint);
+
+        assert!(!result.success);
+        assert!(result.message.contains("cannot be closed"));
+        assert!(rpc.sent().is_empty());
+    }
+
+    #[test]
+    fn failed_send_is_reported_in_the_result() {
+        let rpc = FakeRpc::new();
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        rpc.add_token_account(FakeTokenAccount::new(wallet.pubkey(), mint, 0));
+        rpc.fail_next(RpcMethod::SendTransaction, "account in use");
+
+        let result = close(&rpc, &wallet, &mint);
+
+        assert!(!result.success);
+        assert_eq!(result.transaction_signature, None);
+        assert!(result.error.unwrap().contains("account in use"));
+        assert_eq!(rpc.sent().len(), 1);
+    }
+}
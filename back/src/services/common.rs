@@ -1,4 +1,7 @@
+use crate::secret::SecretString;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use solana_sdk::{bs58, signature::Keypair};
 
 #[derive(Debug, Serialize, Clone)]
 pub struct TransactionRes {
@@ -15,7 +18,7 @@ pub struct CreateTokenTransactionRes {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateTokenReq {
     pub name: String,
     pub symbol: String,
@@ -26,32 +29,45 @@ pub struct CreateTokenReq {
     pub twitter: String,
     pub supply_human: u64,
     pub supply_base_units: f64,
-    pub dev_wallet: String,
+    pub dev_wallet: SecretString,
     pub dev_token_amount: u64,
     pub dev_sol_amount: f64,
-    pub sniper_wallet_one: String,
+    pub sniper_wallet_one: SecretString,
     pub amount_sol_sniper_one: f64,
     pub creation_tip_sol: f64,
     pub tip_sol: f64,
     pub slippage_bps: u64,
     pub cu_price_microlamports: u64,
     pub max_unit_price_microlamports: u64,
-    // pub description: String,
-    // pub file: String,
+    /// Path of the token image, used when `image_base64` is not set
+    #[serde(default)]
+    pub image_path: Option<String>,
+    /// Token image bytes, base64 encoded
+    #[serde(default)]
+    pub image_base64: Option<String>,
+    /// Built-in template symbol (or "random") filling any field left empty
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Keystore mint (e.g. a vanity address) to launch with instead of a fresh one
+    #[serde(default)]
+    pub vanity_mint: Option<String>,
+    /// Confirmed plan from `preview_launch`, required to launch
+    #[serde(default)]
+    pub plan_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct QuickBuyReq {
-    pub pk: String,
+    pub pk: SecretString,
     pub amount_sol: f64,
     pub mint: String,
     pub slippage_bps: Option<u16>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct QuickSellReq {
     pub wallet: String,
-    pub pk: String,
+    pub pk: SecretString,
     pub percent: u8,
     pub mint: String,
     pub slippage_bps: Option<u64>,
@@ -68,3 +84,41 @@ pub struct WalletBalance {
     pub balance: f64,
     pub tokens: u64,
 }
+
+/// Keypair from a base58-encoded 64-byte secret key
+pub fn parse_keypair(private_key: &str) -> anyhow::Result<Keypair> {
+    let bytes = bs58::decode(private_key)
+        .into_vec()
+        .context("invalid base58 key")?;
+    if bytes.len() == 64 {
+        Keypair::try_from(&bytes[..]).context("invalid keypair bytes")
+    } else {
+        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
+    }
+}
+
+pub fn lamports_to_sol(lamports: u64) -> f64 {
+    lamports as f64 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signer::Signer;
+
+    #[test]
+    fn parse_keypair_accepts_only_full_base58_secret_keys() {
+        let keypair = Keypair::new();
+        let parsed = parse_keypair(&keypair.to_base58_string()).unwrap();
+        assert_eq!(parsed.pubkey(), keypair.pubkey());
+
+        assert!(parse_keypair("not-base58!").is_err());
+        assert!(parse_keypair(&keypair.pubkey().to_string()).is_err());
+    }
+
+    #[test]
+    fn lamports_to_sol_divides_by_a_billion() {
+        assert_eq!(lamports_to_sol(1_500_000_000), 1.5);
+        assert_eq!(lamports_to_sol(0), 0.0);
+    }
+}
@@ -1,16 +1,28 @@
 use crate::{
     config::CONFIG,
-    services::common::CreateTokenReq,
-    solana::{self, create::create_and_buy_task},
+    secret::SecretString,
+    services::{
+        common::{CreateTokenReq, CreateTokenTransactionRes},
+        keystore, launch_preview, local_cache,
+    },
+    solana::{create::create_and_buy_task, ipfs, tokens::TOKEN_COLLECTION},
 };
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
 use log::{error, info};
 use pumpfun::common::types::{Cluster, PriorityFee, RpcEndpoint};
-use rand::Rng;
+use pumpfun::utils::CreateTokenMetadata;
 use solana_sdk::{
     commitment_config::CommitmentConfig, native_token::sol_str_to_lamports, signature::Keypair,
     signer::Signer,
 };
-use std::{ops::Deref, sync::Arc};
+use std::{fs, sync::Arc};
+
+/// Metaplex limits, enforced on-chain by the create instruction
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_SYMBOL_LEN: usize = 10;
+/// Images received as bytes, stored by content hash
+const IMAGE_NAMESPACE: &str = "token_images";
 
 pub async fn create_meme_token(
     req: CreateTokenReq,
@@ -31,14 +43,32 @@ pub async fn create_meme_token(
     };
 
     info!("Using cluster: {:?}", cluster);
-    let main_wallet: Arc<Keypair> = Arc::new(Keypair::from_base58_string(&req.dev_wallet));
-
-    info!("Using main wallet: {}", req.dev_wallet);
+    let main_wallet: Arc<Keypair> = Arc::new(Keypair::from_base58_string(req.dev_wallet.expose()));
     let pumpfun_sdk = Arc::new(pumpfun::PumpFun::new(main_wallet.clone(), cluster));
 
     info!("Main Wallet Public Key: {}", main_wallet.pubkey());
 
-    let metadata = generate_metadata(&req, mint_keypair.insecure_clone());
+    if let Some(vanity_mint) = &req.vanity_mint {
+        if mint_keypair.pubkey().to_string() != *vanity_mint {
+            anyhow::bail!("mint keypair does not match vanity mint {}", vanity_mint);
+        }
+    }
+    let vanity_mint = req.vanity_mint.clone();
+
+    // Metadata is permanent once the mint exists: only launch what was previewed
+    let plan = launch_preview::take_confirmed_plan(&req)?;
+    let metadata = plan.token_metadata();
+    // Pins are idempotent: pinning again only yields the previewed URI while the image
+    // and metadata are still the ones the plan was confirmed with
+    let uploader = ipfs::uploader_from_config()?;
+    let pinned = ipfs::create_token_metadata(metadata.clone().into(), uploader.as_ref()).await?;
+    if pinned.metadata_uri != plan.metadata_uri {
+        anyhow::bail!(
+            "token image or metadata changed since launch plan {} was previewed",
+            plan.plan_id
+        );
+    }
+    info!("Launching with metadata {}", pinned.metadata_uri);
     let fee_create_and_buy = PriorityFee::new(
         Some(req.cu_price_microlamports as u32),
         Some(req.max_unit_price_microlamports),
@@ -46,17 +76,170 @@ pub async fn create_meme_token(
 
     // Private code.
 
+    // The mint exists now, its keypair cannot be used again
+    if let Some(vanity_mint) = vanity_mint {
+        if let Err(e) = keystore::remove_keypair(&vanity_mint) {
+            error!(
+                "Failed to remove used mint {} from keystore: {:#}",
+                vanity_mint, e
+            );
+        }
+    }
     Ok(result.0)
 }
 
-pub fn generate_metadata(
+/// Launch with the request's mint (a fresh or keystore keypair), reporting failures in the result
+pub async fn create_token(
+    req: CreateTokenReq,
+    keystore_password: Option<SecretString>,
+) -> CreateTokenTransactionRes {
+    let keystore_password = keystore_password.as_ref().map(SecretString::expose);
+    let mint_keypair = match mint_keypair_for(&req, keystore_password) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            return CreateTokenTransactionRes {
+                ok: false,
+                tx_sig: None,
+                mint: None,
+                error: Some(format!("{:#}", e)),
+            }
+        }
+    };
+    let mint = mint_keypair.pubkey();
+
+    let copy_req = req.clone();
+    // tokio::spawn(async move {
+    //     sniper_bot::sniper_buy(copy_req.clone(), mint)
+    //         .await
+    //         .map(|_tx_sig| ())
+    //         .map_err(|e| e.to_string())
+    // });
+    match create_meme_token(req, mint_keypair.insecure_clone()).await {
+        Ok(tx_sig) => CreateTokenTransactionRes {
+            ok: true,
+            tx_sig: Some(tx_sig),
+            mint: Some(mint_keypair.pubkey().to_string()),
+            error: None,
+        },
+        Err(e) => CreateTokenTransactionRes {
+            ok: false,
+            tx_sig: None,
+            mint: None,
+            error: Some(format!("{}", e)),
+        },
+    }
+}
+
+/// Keystore keypair of the requested vanity mint, or a fresh keypair
+pub fn mint_keypair_for(
     req: &CreateTokenReq,
-    _mint_keypair: solana_sdk::signature::Keypair,
-) -> pumpfun::utils::CreateTokenMetadata {
-    let random_index = rand::thread_rng().gen_range(0..=10);
-    let metadata: pumpfun::utils::CreateTokenMetadata =
-        solana::tokens::TOKEN_COLLECTION.deref().tokens[random_index].clone();
-    info!("Creating token metadata for {:?}", metadata);
+    keystore_password: Option<&str>,
+) -> anyhow::Result<Keypair> {
+    match &req.vanity_mint {
+        Some(mint) => keystore::load_keypair(
+            mint,
+            keystore_password.context("keystore password required for a vanity mint")?,
+        ),
+        None => Ok(Keypair::new()),
+    }
+}
+
+/// Empty means unset; anything else must be an http(s) URL
+fn validate_social(field: &str, value: &str) -> anyhow::Result<Option<String>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let url = reqwest::Url::parse(value)
+        .with_context(|| format!("{} is not a valid URL: {}", field, value))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        anyhow::bail!("{} must be an http(s) URL: {}", field, value);
+    }
+    Ok(Some(value.to_string()))
+}
 
-    // Private code
+/// Local path of the validated token image; image bytes are written under the data
+/// dir because the SDK uploads from a file
+fn resolve_image(
+    req: &CreateTokenReq,
+    template: Option<&CreateTokenMetadata>,
+) -> anyhow::Result<String> {
+    if let Some(encoded) = req.image_base64.as_deref().filter(|b| !b.trim().is_empty()) {
+        // Accept data URLs as produced by FileReader in the UI
+        let encoded = match encoded.split_once(',') {
+            Some((prefix, data)) if prefix.starts_with("data:") => data,
+            _ => encoded,
+        };
+        let bytes = general_purpose::STANDARD
+            .decode(encoded.trim())
+            .context("invalid base64 image")?;
+        let (_, extension) = ipfs::validate_image(&bytes)?;
+        let dir = local_cache::namespace_dir(IMAGE_NAMESPACE);
+        fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let path = dir.join(format!("{}.{}", ipfs::content_hash(&bytes), extension));
+        fs::write(&path, &bytes).with_context(|| format!("failed to write {}", path.display()))?;
+        return Ok(path.to_string_lossy().into_owned());
+    }
+
+    let path = match req.image_path.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(path) => path.trim().to_string(),
+        None => template
+            .map(|t| t.file.clone())
+            .context("no token image provided")?,
+    };
+    let bytes = fs::read(&path).with_context(|| format!("failed to read image {}", path))?;
+    ipfs::validate_image(&bytes).with_context(|| format!("invalid image {}", path))?;
+    Ok(path)
+}
+
+/// Build the token metadata from the request; fields left empty are taken from the
+/// requested template, if any
+pub fn generate_metadata(req: &CreateTokenReq) -> anyhow::Result<CreateTokenMetadata> {
+    let template = match req.template.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(key) => Some(
+            TOKEN_COLLECTION
+                .find(key.trim())
+                .with_context(|| format!("unknown token template: {}", key))?
+                .clone(),
+        ),
+        None => None,
+    };
+    let pick = |value: &str, fallback: Option<&String>| -> String {
+        match value.trim() {
+            "" => fallback.cloned().unwrap_or_default(),
+            value => value.to_string(),
+        }
+    };
+
+    let name = pick(&req.name, template.as_ref().map(|t| &t.name));
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        anyhow::bail!("token name must be 1 to {} bytes long", MAX_NAME_LEN);
+    }
+    let symbol = pick(&req.symbol, template.as_ref().map(|t| &t.symbol));
+    if symbol.is_empty() || symbol.len() > MAX_SYMBOL_LEN {
+        anyhow::bail!("token symbol must be 1 to {} bytes long", MAX_SYMBOL_LEN);
+    }
+    if symbol.chars().any(char::is_whitespace) {
+        anyhow::bail!("token symbol cannot contain whitespace");
+    }
+    let description = pick(&req.description, template.as_ref().map(|t| &t.description));
+
+    let twitter = validate_social("twitter", &req.twitter)?
+        .or_else(|| template.as_ref().and_then(|t| t.twitter.clone()));
+    let telegram = validate_social("telegram", &req.telegram)?
+        .or_else(|| template.as_ref().and_then(|t| t.telegram.clone()));
+    let website = validate_social("website", &req.website)?
+        .or_else(|| template.as_ref().and_then(|t| t.website.clone()));
+
+    let metadata = CreateTokenMetadata {
+        file: resolve_image(req, template.as_ref())?,
+        name,
+        symbol,
+        description,
+        twitter,
+        telegram,
+        website,
+    };
+    info!("Creating token metadata for {:?}", metadata);
+    Ok(metadata)
 }
@@ -1,14 +1,138 @@
 use crate::config::CONFIG;
+use crate::services::common::parse_keypair;
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::rpc::SolanaRpc;
 use anyhow::Context;
 use log::info;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
 use solana_sdk::system_instruction;
-use solana_sdk::transaction::Transaction;
-use solana_sdk::{bs58, signature::Keypair, signer::Signer};
 use std::str::FromStr;
 use tokio::task;
 
+const MAX_TRANSFERS_PER_TX: usize = 10;
+
+/// Split `total_amount_sol` evenly over the destinations and send it in batches
+/// of transfers paid for by the source wallet
+fn distribute(
+    client: &dyn SolanaRpc,
+    source_private_key: &str,
+    destination_wallets: &[String],
+    total_amount_sol: f64,
+    job_id: Option<String>,
+) -> anyhow::Result<Vec<String>> {
+    let source_keypair = parse_keypair(source_private_key).context("invalid source key")?;
+
+    let source_pubkey = source_keypair.pubkey();
+    info!("Distributing from source wallet: {}", source_pubkey);
+
+    // Check source wallet balance
+    let source_balance = client
+        .get_balance(&source_pubkey)
+        .context("failed to get source wallet balance")?;
+
+    let total_amount_lamports = (total_amount_sol * 1_000_000_000.0) as u64;
+
+    if source_balance < total_amount_lamports {
+        anyhow::bail!(
+            "Insufficient balance in source wallet. Required: {} lamports, Available: {} lamports",
+            total_amount_lamports,
+            source_balance
+        );
+    }
+
+    // Parse destination pubkeys
+    let mut dest_pubkeys = Vec::with_capacity(destination_wallets.len());
+    for dest_str in destination_wallets.iter() {
+        let dest_pubkey = Pubkey::from_str(dest_str).context("invalid destination pubkey")?;
+        dest_pubkeys.push(dest_pubkey);
+    }
+
+    // Calculate amount per destination
+    let amount_per_dest = total_amount_lamports / dest_pubkeys.len() as u64;
+    info!(
+        "Distributing {} lamports ({} SOL) to each of {} destinations",
+        amount_per_dest,
+        amount_per_dest as f64 / 1_000_000_000.0,
+        dest_pubkeys.len()
+    );
+
+    // Update job progress if job_id provided
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(
+            &job_id,
+            0,
+            dest_pubkeys.len() as u32,
+            "Starting SOL distribution".to_string(),
+        );
+    }
+
+    // Process destinations in batches of 10
+    let mut transaction_signatures = Vec::new();
+    let mut completed_transfers = 0;
+
+    for (batch_idx, batch_destinations) in dest_pubkeys.chunks(MAX_TRANSFERS_PER_TX).enumerate() {
+        info!(
+            "Processing batch {} with {} destination wallets",
+            batch_idx + 1,
+            batch_destinations.len()
+        );
+
+        let mut batch_instructions = Vec::new();
+
+        // Create transfer instructions for this batch (from source to each destination)
+        for dest_pubkey in batch_destinations.iter() {
+            batch_instructions.push(system_instruction::transfer(
+                &source_pubkey,
+                dest_pubkey,
+                amount_per_dest,
+            ));
+
+            info!(
+                "Will transfer {} lamports to {}",
+                amount_per_dest, dest_pubkey
+            );
+        }
+
+        // Only the source keypair needs to sign (it's paying for all transfers)
+        let sig = send_and_confirm(
+            client,
+            &batch_instructions,
+            &source_pubkey,
+            &[&source_keypair],
+        )
+        .context("transfer batch failed")?;
+
+        info!("Batch {} completed with signature: {}", batch_idx + 1, sig);
+        transaction_signatures.push(sig.to_string());
+
+        // Update progress after each batch
+        completed_transfers += batch_destinations.len();
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                &job_id,
+                completed_transfers as u32,
+                dest_pubkeys.len() as u32,
+                format!(
+                    "Completed batch {} of {} (transfers: {}/{})",
+                    batch_idx + 1,
+                    (dest_pubkeys.len() + MAX_TRANSFERS_PER_TX - 1) / MAX_TRANSFERS_PER_TX,
+                    completed_transfers,
+                    dest_pubkeys.len()
+                ),
+            );
+        } // Small delay between batches to avoid overwhelming the RPC
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+
+    info!(
+        "All batches completed. Total transactions: {}",
+        transaction_signatures.len()
+    );
+    Ok(transaction_signatures)
+}
+
 pub async fn distribute_sol(
     source_private_key: String,
     destination_wallets: Vec<String>,
@@ -16,7 +140,6 @@ pub async fn distribute_sol(
     job_id: Option<String>,
 ) -> Result<String, String> {
     const MAX_DESTINATION_WALLETS: usize = 200;
-    const MAX_TRANSFERS_PER_TX: usize = 10;
 
     if destination_wallets.len() > MAX_DESTINATION_WALLETS {
         return Err(format!(
@@ -40,133 +163,114 @@ pub async fn distribute_sol(
     let source_pk_clone = source_private_key.clone();
     let destinations_clone = destination_wallets.clone();
 
-    let res = task::spawn_blocking(move || -> anyhow::Result<Vec<String>> {
-        let client = RpcClient::new(rpc_url);
-
-        // Parse source keypair
-        let source_bytes = bs58::decode(&source_pk_clone).into_vec().context("invalid base58 source key")?;
-        let source_keypair = if source_bytes.len() == 64 {
-            Keypair::try_from(&source_bytes[..]).context("invalid source keypair bytes")?
-        } else {
-            anyhow::bail!("unexpected source key length: {} bytes", source_bytes.len());
-        };
+    let res = task::spawn_blocking(move || {
+        distribute(
+            &RpcClient::new(rpc_url),
+            &source_pk_clone,
+            &destinations_clone,
+            total_amount_sol,
+            job_id,
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
 
-        let source_pubkey = source_keypair.pubkey();
-        info!("Distributing from source wallet: {}", source_pubkey);
+    // Return summary of all transaction signatures
+    Ok(format!(
+        "Distributed {} SOL to {} wallets in {} transactions: {}",
+        total_amount_sol,
+        destination_wallets.len(),
+        res.len(),
+        res.join(", ")
+    ))
+}
 
-        // Check source wallet balance
-        let source_balance = client
-            .get_balance(&source_pubkey)
-            .context("failed to get source wallet balance")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{system_transfers, FakeRpc, RpcMethod};
+    use solana_sdk::signature::Keypair;
 
-        let total_amount_lamports = (total_amount_sol * 1_000_000_000.0) as u64;
+    fn source() -> (Keypair, String) {
+        let keypair = Keypair::new();
+        let private_key = keypair.to_base58_string();
+        (keypair, private_key)
+    }
 
-        if source_balance < total_amount_lamports {
-            anyhow::bail!(
-                "Insufficient balance in source wallet. Required: {} lamports, Available: {} lamports",
-                total_amount_lamports,
-                source_balance
-            );
-        }
+    fn destinations(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| Pubkey::new_unique().to_string())
+            .collect()
+    }
 
-        // Parse destination pubkeys
-        let mut dest_pubkeys = Vec::with_capacity(destinations_clone.len());
-        for dest_str in destinations_clone.iter() {
-            let dest_pubkey = Pubkey::from_str(dest_str).context("invalid destination pubkey")?;
-            dest_pubkeys.push(dest_pubkey);
-        }
+    #[test]
+    fn splits_evenly_in_batches_of_ten() {
+        let rpc = FakeRpc::new();
+        let (keypair, private_key) = source();
+        rpc.set_balance(&keypair.pubkey(), 2_000_000_000);
+        let wallets = destinations(12);
 
-        // Calculate amount per destination
-        let amount_per_dest = total_amount_lamports / dest_pubkeys.len() as u64;
-        info!(
-            "Distributing {} lamports ({} SOL) to each of {} destinations",
-            amount_per_dest,
-            amount_per_dest as f64 / 1_000_000_000.0,
-            dest_pubkeys.len()
-        );
+        let signatures = distribute(&rpc, &private_key, &wallets, 1.2, None).unwrap();
 
-        // Update job progress if job_id provided
-        if let Some(ref job_id) = job_id {
-            let _ = crate::update_job_progress(&job_id, 0, dest_pubkeys.len() as u32, "Starting SOL distribution".to_string());
+        let sent = rpc.sent();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(system_transfers(&sent[0]).len(), 10);
+        assert_eq!(system_transfers(&sent[1]).len(), 2);
+        let transfers: Vec<_> = sent.iter().flat_map(system_transfers).collect();
+        for ((from, to, lamports), wallet) in transfers.iter().zip(&wallets) {
+            assert_eq!(*from, keypair.pubkey());
+            assert_eq!(to.to_string(), *wallet);
+            assert_eq!(*lamports, 100_000_000);
         }
+    }
 
-        // Process destinations in batches of 10
-        let mut transaction_signatures = Vec::new();
-        let mut completed_transfers = 0;
-
-        for (batch_idx, batch_destinations) in dest_pubkeys.chunks(MAX_TRANSFERS_PER_TX).enumerate() {
-            info!(
-                "Processing batch {} with {} destination wallets",
-                batch_idx + 1,
-                batch_destinations.len()
-            );
+    #[test]
+    fn insufficient_balance_sends_nothing() {
+        let rpc = FakeRpc::new();
+        let (keypair, private_key) = source();
+        rpc.set_balance(&keypair.pubkey(), 999_999_999);
 
-            let mut batch_instructions = Vec::new();
+        let err = distribute(&rpc, &private_key, &destinations(3), 1.0, None).unwrap_err();
 
-            // Create transfer instructions for this batch (from source to each destination)
-            for dest_pubkey in batch_destinations.iter() {
-                batch_instructions.push(system_instruction::transfer(
-                    &source_pubkey,
-                    dest_pubkey,
-                    amount_per_dest
-                ));
+        assert!(err.to_string().contains("Insufficient balance"));
+        assert!(rpc.sent().is_empty());
+    }
 
-                info!("Will transfer {} lamports to {}", amount_per_dest, dest_pubkey);
-            }
+    #[test]
+    fn invalid_destination_sends_nothing() {
+        let rpc = FakeRpc::new();
+        let (keypair, private_key) = source();
+        rpc.set_balance(&keypair.pubkey(), 1_000_000_000);
+        let mut wallets = destinations(2);
+        wallets.push("not-a-pubkey".to_string());
 
-            let recent_blockhash = client
-                .get_latest_blockhash()
-                .context("failed to fetch blockhash")?;
+        assert!(distribute(&rpc, &private_key, &wallets, 0.3, None).is_err());
+        assert!(rpc.sent().is_empty());
+    }
 
-            // Only the source keypair needs to sign (it's paying for all transfers)
-            let signer_refs: Vec<&dyn Signer> = vec![&source_keypair as &dyn Signer];
+    #[test]
+    fn failed_batch_stops_the_distribution() {
+        let rpc = FakeRpc::new();
+        let (keypair, private_key) = source();
+        rpc.set_balance(&keypair.pubkey(), 2_000_000_000);
+        rpc.fail_next(RpcMethod::SendTransaction, "blockhash not found");
 
-            let tx = Transaction::new_signed_with_payer(
-                &batch_instructions,
-                Some(&source_pubkey),
-                &signer_refs,
-                recent_blockhash,
-            );
+        let err = distribute(&rpc, &private_key, &destinations(12), 1.2, None).unwrap_err();
 
-            // Send transaction
-            let sig = client
-                .send_and_confirm_transaction(&tx)
-                .context("send_and_confirm_transaction failed")?;
-
-            info!("Batch {} completed with signature: {}", batch_idx + 1, sig);
-            transaction_signatures.push(sig.to_string());
-
-            // Update progress after each batch
-            completed_transfers += batch_destinations.len();
-            if let Some(ref job_id) = job_id {
-                let _ = crate::update_job_progress(
-                    &job_id,
-                    completed_transfers as u32,
-                    dest_pubkeys.len() as u32,
-                    format!("Completed batch {} of {} (transfers: {}/{})", batch_idx + 1,
-                           (dest_pubkeys.len() + MAX_TRANSFERS_PER_TX - 1) / MAX_TRANSFERS_PER_TX,
-                           completed_transfers, dest_pubkeys.len())
-                );
-            }            // Small delay between batches to avoid overwhelming the RPC
-            std::thread::sleep(std::time::Duration::from_millis(500));
-        }
+        assert!(format!("{:#}", err).contains("blockhash not found"));
+        // Later batches are not attempted, so nothing is sent twice on retry
+        assert_eq!(rpc.sent().len(), 1);
+    }
 
-        info!(
-            "All batches completed. Total transactions: {}",
-            transaction_signatures.len()
-        );
-        Ok(transaction_signatures)
-    })
-    .await
-    .map_err(|e| format!("join error: {}", e))
-    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+    #[test]
+    fn balance_lookup_failure_is_an_error() {
+        let rpc = FakeRpc::new();
+        let (_, private_key) = source();
+        rpc.fail_next(RpcMethod::GetBalance, "rpc unavailable");
 
-    // Return summary of all transaction signatures
-    Ok(format!(
-        "Distributed {} SOL to {} wallets in {} transactions: {}",
-        total_amount_sol,
-        destination_wallets.len(),
-        res.len(),
-        res.join(", ")
-    ))
+        assert!(distribute(&rpc, &private_key, &destinations(1), 0.1, None).is_err());
+        assert_eq!(rpc.calls(RpcMethod::SendTransaction), 0);
+    }
 }
@@ -0,0 +1,495 @@
+//! CSV / JSON exports for accounting.
+//!
+//! Every export writes one row type with a fixed column order; JSON exports are
+//! an array of objects using the same keys. Timestamps are RFC 3339 in UTC and
+//! amounts are exact decimal strings (never floats). `explorer_url` is always
+//! present and left empty unless explorer links were requested. Balances carry
+//! the time their snapshot was read; trades and transfers export the cached
+//! transaction history of each wallet, fetched when nothing is cached and
+//! refreshed first only when `refresh_history` is set. Transfers get one row
+//! per transfer leg, and a transaction's fee goes on its first row only.
+//!
+//! | Export      | Columns |
+//! |-------------|---------|
+//! | jobs        | job_id, name, status, progress, current_step, completed_items, total_items, error, result |
+//! | balances    | snapshot_time_utc, wallet, token_account, mint, amount, amount_raw, decimals |
+//! | trades      | time_utc, wallet, signature, kind, mint, token_amount, token_amount_raw, sol_amount, fee_sol, success, explorer_url |
+//! | transfers   | time_utc, wallet, signature, direction, counterparty, amount_sol, fee_sol, success, explorer_url |
+
+use crate::jobs::JobInfo;
+use crate::services::get_token_balance::GetTokenBalanceResult;
+use crate::services::tx_history::{self, HistoryEvent, HistoryEventKind, HistoryFilter};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+const EXPLORER_TX_URL: &str = "https://solscan.io/tx/";
+const SOL_DECIMALS: u8 = 9;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportOptions {
+    pub path: String,
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub include_explorer_links: bool,
+    /// Fetch new transactions before exporting trades or transfers
+    #[serde(default)]
+    pub refresh_history: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JobRow {
+    pub job_id: String,
+    pub name: String,
+    pub status: String,
+    pub progress: String,
+    pub current_step: String,
+    pub completed_items: String,
+    pub total_items: String,
+    pub error: String,
+    pub result: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BalanceRow {
+    pub snapshot_time_utc: String,
+    pub wallet: String,
+    pub token_account: String,
+    pub mint: String,
+    pub amount: String,
+    pub amount_raw: String,
+    pub decimals: u8,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TradeRow {
+    pub time_utc: String,
+    pub wallet: String,
+    pub signature: String,
+    pub kind: String,
+    pub mint: String,
+    pub token_amount: String,
+    pub token_amount_raw: String,
+    pub sol_amount: String,
+    pub fee_sol: String,
+    pub success: bool,
+    pub explorer_url: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TransferRow {
+    pub time_utc: String,
+    pub wallet: String,
+    pub signature: String,
+    pub direction: String,
+    pub counterparty: String,
+    pub amount_sol: String,
+    pub fee_sol: String,
+    pub success: bool,
+    pub explorer_url: String,
+}
+
+/// Format a raw integer amount as an exact decimal string (e.g. 1500000, 6 -> "1.500000")
+pub fn format_amount(raw: i128, decimals: u8) -> String {
+    let sign = if raw < 0 { "-" } else { "" };
+    let abs = raw.unsigned_abs();
+    if decimals == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    let scale = 10u128.pow(decimals as u32);
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        abs / scale,
+        abs % scale,
+        width = decimals as usize
+    )
+}
+
+/// Format a unix timestamp as RFC 3339 UTC, empty when unknown
+pub fn format_utc(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|t| DateTime::<Utc>::from_timestamp(t, 0))
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn explorer_url(signature: &str, options: &ExportOptions) -> String {
+    if options.include_explorer_links {
+        format!("{}{}", EXPLORER_TX_URL, signature)
+    } else {
+        String::new()
+    }
+}
+
+fn write_rows<T: Serialize>(rows: &[T], options: &ExportOptions) -> anyhow::Result<ExportSummary> {
+    let path = Path::new(&options.path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    match options.format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            for row in rows {
+                writer.serialize(row).context("failed to write csv row")?;
+            }
+            writer.flush().context("failed to flush csv file")?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(file, rows).context("failed to write json file")?;
+        }
+    }
+
+    info!("Exported {} rows to {}", rows.len(), path.display());
+    Ok(ExportSummary {
+        path: options.path.clone(),
+        rows: rows.len(),
+    })
+}
+
+fn value_to_cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Export job results as reported by the job manager
+pub fn export_jobs(jobs: &[JobInfo], options: &ExportOptions) -> Result<ExportSummary, String> {
+    let mut rows = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let value = serde_json::to_value(job).map_err(|e| e.to_string())?;
+        let cell = |key: &str| value_to_cell(value.get(key));
+        rows.push(JobRow {
+            job_id: cell("id"),
+            name: cell("name"),
+            status: cell("status"),
+            progress: cell("progress"),
+            current_step: cell("current_step"),
+            completed_items: cell("completed_items"),
+            total_items: cell("total_items"),
+            error: cell("error"),
+            result: cell("result"),
+        });
+    }
+    write_rows(&rows, options).map_err(|e| e.to_string())
+}
+
+/// Export token balance snapshots, each stamped with the time it was read
+pub fn export_token_balances(
+    snapshots: &[GetTokenBalanceResult],
+    options: &ExportOptions,
+) -> Result<ExportSummary, String> {
+    let rows: Vec<BalanceRow> = snapshots
+        .iter()
+        .flat_map(|snapshot| {
+            let snapshot_time = format_utc(snapshot.fetched_at);
+            snapshot.balances.iter().map(move |balance| BalanceRow {
+                snapshot_time_utc: snapshot_time.clone(),
+                wallet: snapshot.wallet.clone(),
+                token_account: balance.token_account.clone(),
+                mint: balance.mint.clone().unwrap_or_default(),
+                amount: format_amount(balance.balance_raw as i128, balance.decimals),
+                amount_raw: balance.balance_raw.to_string(),
+                decimals: balance.decimals,
+            })
+        })
+        .collect();
+    write_rows(&rows, options).map_err(|e| e.to_string())
+}
+
+async fn cached_events(
+    wallets: &[String],
+    kinds: Vec<HistoryEventKind>,
+    refresh: bool,
+) -> Result<Vec<(String, HistoryEvent)>, String> {
+    let mut events = Vec::new();
+    for wallet in wallets {
+        let filter = HistoryFilter {
+            kinds: Some(kinds.clone()),
+            ..Default::default()
+        };
+        for event in
+            tx_history::get_wallet_history(wallet.clone(), refresh, Some(filter), None).await?
+        {
+            events.push((wallet.clone(), event));
+        }
+    }
+    // Oldest first reads naturally in a ledger
+    events.sort_by(|a, b| a.1.slot.cmp(&b.1.slot));
+    Ok(events)
+}
+
+/// Export pump.fun trades from the wallets' transaction history
+pub async fn export_trades(
+    wallets: Vec<String>,
+    options: ExportOptions,
+) -> Result<ExportSummary, String> {
+    let events = cached_events(
+        &wallets,
+        vec![
+            HistoryEventKind::PumpBuy,
+            HistoryEventKind::PumpSell,
+            HistoryEventKind::PumpCreate,
+        ],
+        options.refresh_history,
+    )
+    .await?;
+
+    let rows: Vec<TradeRow> = events
+        .into_iter()
+        .map(|(wallet, event)| {
+            let kind = serde_json::to_value(event.kind)
+                .ok()
+                .and_then(|v| v.as_str().map(|s| s.to_string()))
+                .unwrap_or_default();
+            let token_raw = event.token_change_raw.unwrap_or(0) as i128;
+            TradeRow {
+                time_utc: format_utc(event.block_time),
+                wallet,
+                explorer_url: explorer_url(&event.signature, &options),
+                signature: event.signature,
+                kind,
+                mint: event.mint.unwrap_or_default(),
+                token_amount: format_amount(token_raw, event.token_decimals.unwrap_or(0)),
+                token_amount_raw: token_raw.to_string(),
+                sol_amount: format_amount(event.sol_change_lamports as i128, SOL_DECIMALS),
+                fee_sol: format_amount(event.fee_lamports as i128, SOL_DECIMALS),
+                success: event.success,
+            }
+        })
+        .collect();
+    write_rows(&rows, &options).map_err(|e| e.to_string())
+}
+
+/// One row per transfer leg of `event`, signed from the wallet's side; events
+/// cached before legs were recorded give one row with the net change
+fn transfer_rows(wallet: &str, event: &HistoryEvent, options: &ExportOptions) -> Vec<TransferRow> {
+    let row = |direction: &str, counterparty: &str, lamports: i64, fee: u64| TransferRow {
+        time_utc: format_utc(event.block_time),
+        wallet: wallet.to_string(),
+        signature: event.signature.clone(),
+        direction: direction.to_string(),
+        counterparty: counterparty.to_string(),
+        amount_sol: format_amount(lamports as i128, SOL_DECIMALS),
+        fee_sol: format_amount(fee as i128, SOL_DECIMALS),
+        success: event.success,
+        explorer_url: explorer_url(&event.signature, options),
+    };
+
+    if event.sol_transfers.is_empty() {
+        let direction = if event.sol_change_lamports < 0 {
+            "out"
+        } else {
+            "in"
+        };
+        return vec![row(
+            direction,
+            event.counterparty.as_deref().unwrap_or_default(),
+            event.sol_change_lamports,
+            event.fee_lamports,
+        )];
+    }
+    event
+        .sol_transfers
+        .iter()
+        .enumerate()
+        .map(|(idx, leg)| {
+            let fee = if idx == 0 { event.fee_lamports } else { 0 };
+            if leg.source == wallet {
+                row("out", &leg.destination, -(leg.lamports as i64), fee)
+            } else {
+                row("in", &leg.source, leg.lamports as i64, fee)
+            }
+        })
+        .collect()
+}
+
+/// Export SOL transfers (distributions and refunds) from the wallets' history
+pub async fn export_transfers(
+    wallets: Vec<String>,
+    options: ExportOptions,
+) -> Result<ExportSummary, String> {
+    let events = cached_events(
+        &wallets,
+        vec![HistoryEventKind::SolTransfer],
+        options.refresh_history,
+    )
+    .await?;
+
+    let rows: Vec<TransferRow> = events
+        .iter()
+        .flat_map(|(wallet, event)| transfer_rows(wallet, event, &options))
+        .collect();
+    write_rows(&rows, &options).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::get_token_balance::TokenBalance;
+    use crate::services::tx_history::SolTransfer;
+    use std::collections::HashMap;
+
+    #[test]
+    fn format_amount_is_exact() {
+        assert_eq!(format_amount(1_500_000, 6), "1.500000");
+        assert_eq!(format_amount(1, 9), "0.000000001");
+        assert_eq!(format_amount(0, 9), "0.000000000");
+        assert_eq!(format_amount(-1_005_000, 9), "-0.001005000");
+        assert_eq!(format_amount(-2_000_000_000, 9), "-2.000000000");
+        assert_eq!(format_amount(42, 0), "42");
+        assert_eq!(format_amount(-42, 0), "-42");
+        assert_eq!(format_amount(u64::MAX as i128, 6), "18446744073709.551615");
+        assert_eq!(
+            format_amount(i128::MIN, 0),
+            "-170141183460469231731687303715884105728"
+        );
+    }
+
+    #[test]
+    fn format_utc_is_rfc3339_in_utc() {
+        assert_eq!(format_utc(Some(0)), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(Some(1_700_000_000)), "2023-11-14T22:13:20Z");
+        assert_eq!(format_utc(None), "");
+        assert_eq!(format_utc(Some(i64::MAX)), "");
+    }
+
+    #[test]
+    fn balances_are_stamped_with_their_snapshot_time() {
+        let balance = |mint: &str| TokenBalance {
+            balance: 1.5,
+            balance_raw: 1_500_000,
+            decimals: 6,
+            token_account: "account".to_string(),
+            mint: Some(mint.to_string()),
+        };
+        let snapshots = [
+            GetTokenBalanceResult {
+                wallet: "first".to_string(),
+                balances: vec![balance("a"), balance("b")],
+                total_tokens: 2,
+                metadata: HashMap::new(),
+                fetched_at: Some(1_700_000_000),
+            },
+            GetTokenBalanceResult {
+                wallet: "failed".to_string(),
+                balances: vec![balance("c")],
+                total_tokens: 1,
+                metadata: HashMap::new(),
+                fetched_at: None,
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("balances_{}.json", uuid::Uuid::new_v4()));
+        let options = ExportOptions {
+            path: path.to_string_lossy().into_owned(),
+            format: ExportFormat::Json,
+            include_explorer_links: false,
+            refresh_history: false,
+        };
+
+        let summary = export_token_balances(&snapshots, &options).unwrap();
+        assert_eq!(summary.rows, 3);
+        let rows: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let times: Vec<&str> = rows
+            .iter()
+            .map(|row| row["snapshot_time_utc"].as_str().unwrap())
+            .collect();
+        assert_eq!(times, ["2023-11-14T22:13:20Z", "2023-11-14T22:13:20Z", ""]);
+        assert_eq!(rows[0]["amount"], "1.500000");
+        assert_eq!(rows[0]["amount_raw"], "1500000");
+    }
+
+    #[test]
+    fn transfers_export_one_row_per_leg() {
+        let (wallet, first, second) = ("wallet", "first", "second");
+        let leg = |source: &str, destination: &str, lamports: u64| SolTransfer {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            lamports,
+        };
+        let event = HistoryEvent {
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: Some(0),
+            kind: HistoryEventKind::SolTransfer,
+            success: true,
+            error: None,
+            fee_lamports: 5_000,
+            sol_change_lamports: -2_499_995_000,
+            mint: None,
+            token_change_raw: None,
+            token_decimals: None,
+            counterparty: Some(first.to_string()),
+            sol_transfers: vec![
+                leg(wallet, first, 1_000_000_000),
+                leg(wallet, second, 1_500_000_000),
+                leg(second, wallet, 10_000),
+            ],
+        };
+        let options = ExportOptions {
+            path: String::new(),
+            format: ExportFormat::Csv,
+            include_explorer_links: true,
+            refresh_history: false,
+        };
+
+        let rows = transfer_rows(wallet, &event, &options);
+        let cells: Vec<(&str, &str, &str, &str)> = rows
+            .iter()
+            .map(|r| {
+                (
+                    r.direction.as_str(),
+                    r.counterparty.as_str(),
+                    r.amount_sol.as_str(),
+                    r.fee_sol.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                ("out", first, "-1.000000000", "0.000005000"),
+                ("out", second, "-1.500000000", "0.000000000"),
+                ("in", second, "0.000010000", "0.000000000"),
+            ]
+        );
+        assert!(rows
+            .iter()
+            .all(|r| r.signature == "sig" && r.explorer_url == "https://solscan.io/tx/sig"));
+
+        // Cached before legs were recorded: the net change
+        let legacy = HistoryEvent {
+            sol_transfers: vec![],
+            ..event
+        };
+        let rows = transfer_rows(wallet, &legacy, &options);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].direction, "out");
+        assert_eq!(rows[0].counterparty, first);
+        assert_eq!(rows[0].amount_sol, "-2.499995000");
+        assert_eq!(rows[0].fee_sol, "0.000005000");
+    }
+}
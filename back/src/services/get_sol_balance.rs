@@ -1,15 +1,54 @@
+use crate::config::CONFIG;
+use crate::services::common::lamports_to_sol;
+use crate::solana::rpc::SolanaRpc;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
+use tokio::task;
 
-pub async fn get_sol_balance(wallet: String) -> Result<f64, String> {
-    let rpc_url = "https://api.mainnet-beta.solana.com";
-    let client = RpcClient::new(rpc_url.to_string());
-
-    let pubkey = Pubkey::from_str(&wallet).map_err(|e| e.to_string())?;
+fn read_sol_balance(client: &dyn SolanaRpc, wallet: &str) -> Result<f64, String> {
+    let pubkey = Pubkey::from_str(wallet).map_err(|e| e.to_string())?;
     let balance = client
         .get_balance(&pubkey)
         .map_err(|e| format!("RPC error: {}", e))?;
 
-    Ok(balance as f64 / 1_000_000_000.0)
+    Ok(lamports_to_sol(balance))
+}
+
+pub async fn get_sol_balance(wallet: String) -> Result<f64, String> {
+    let rpc_url = CONFIG.api.helius_https.clone();
+    task::spawn_blocking(move || read_sol_balance(&RpcClient::new(rpc_url), &wallet))
+        .await
+        .map_err(|e| format!("join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, RpcMethod};
+
+    #[test]
+    fn balance_is_read_in_sol() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        rpc.set_balance(&wallet, 1_250_000_000);
+
+        assert_eq!(read_sol_balance(&rpc, &wallet.to_string()), Ok(1.25));
+        // Unknown wallets hold nothing
+        let empty = Pubkey::new_unique().to_string();
+        assert_eq!(read_sol_balance(&rpc, &empty), Ok(0.0));
+    }
+
+    #[test]
+    fn rpc_and_pubkey_errors_are_reported() {
+        let rpc = FakeRpc::new();
+        rpc.fail_next(RpcMethod::GetBalance, "node unavailable");
+
+        let error = read_sol_balance(&rpc, &Pubkey::new_unique().to_string()).unwrap_err();
+        assert!(error.starts_with("RPC error"), "{}", error);
+        assert!(error.contains("node unavailable"), "{}", error);
+
+        assert!(read_sol_balance(&rpc, "not-a-pubkey").is_err());
+        assert_eq!(rpc.calls(RpcMethod::GetBalance), 1);
+    }
 }
@@ -1,9 +1,17 @@
+use crate::config::CONFIG;
+use crate::services::token_metadata::{self, TokenMetadata};
+use crate::solana::rpc::SolanaRpc;
+use crate::solana::token_account::{decode_keyed_account, fetch_token_accounts};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use solana_client::{rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
+use solana_client::{
+    rpc_client::RpcClient, rpc_request::TokenAccountsFilter, rpc_response::RpcKeyedAccount,
+};
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 use std::str::FromStr;
+use tokio::task;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenBalance {
@@ -19,147 +27,93 @@ pub struct GetTokenBalanceResult {
     pub wallet: String,
     pub balances: Vec<TokenBalance>,
     pub total_tokens: usize,
+    /// Name, symbol, image and curve status keyed by mint, when resolved
+    #[serde(default)]
+    pub metadata: HashMap<String, TokenMetadata>,
+    /// Unix time the balances were read, None if they could not be
+    #[serde(default)]
+    pub fetched_at: Option<i64>,
 }
 
-/// Extract mint from token account debug string (fallback method)
-fn extract_mint_from_debug_string(debug_str: &str) -> Option<String> {
-    // Look for mint pattern in debug string: "mint": String("...")
-    if let Some(start) = debug_str.find(r#""mint": String(""#) {
-        let after_start = &debug_str[start + r#""mint": String(""#.len()..];
-        if let Some(end) = after_start.find('"') {
-            return Some(after_start[..end].to_string());
+/// Build a TokenBalance from a keyed token account, only asking the RPC for the
+/// balance when the account data did not carry decimals (binary encoding)
+fn token_balance_from_account(
+    client: &dyn SolanaRpc,
+    token_account: &RpcKeyedAccount,
+) -> Result<TokenBalance, anyhow::Error> {
+    let (token_account_pubkey, decoded) = decode_keyed_account(token_account)?;
+
+    let (balance_ui, decimals) = match (decoded.ui_amount(), decoded.decimals) {
+        (Some(ui_amount), Some(decimals)) => (ui_amount, decimals),
+        _ => {
+            let token_account_balance = client.get_token_account_balance(&token_account_pubkey)?;
+            (
+                token_account_balance.ui_amount.unwrap_or(0.0),
+                token_account_balance.decimals,
+            )
         }
-    }
-    None
+    };
+
+    info!(
+        "Token account {} (mint {}): {} tokens ({} raw units, {} decimals)",
+        token_account_pubkey, decoded.mint, balance_ui, decoded.amount, decimals
+    );
+
+    Ok(TokenBalance {
+        balance: balance_ui,
+        balance_raw: decoded.amount,
+        decimals,
+        token_account: token_account_pubkey.to_string(),
+        mint: Some(decoded.mint.to_string()),
+    })
 }
 
-/// Extract mint from parsed JSON token account data
-fn extract_mint_from_token_account(
-    token_account: &solana_client::rpc_response::RpcKeyedAccount,
-) -> Option<String> {
-    // Extract mint from debug string representation
-    let debug_str = format!("{:?}", token_account);
-    extract_mint_from_debug_string(&debug_str)
+fn empty_result(wallet_pubkey: &Pubkey) -> GetTokenBalanceResult {
+    GetTokenBalanceResult {
+        wallet: wallet_pubkey.to_string(),
+        balances: vec![],
+        total_tokens: 0,
+        metadata: HashMap::new(),
+        fetched_at: Some(chrono::Utc::now().timestamp()),
+    }
 }
 
-async fn get_token_balance(
-    wallet: String,
-    mint: Option<String>,
+/// Balances of the wallet's token accounts, of one mint or of every mint
+fn read_token_balances(
+    client: &dyn SolanaRpc,
+    wallet_pubkey: &Pubkey,
+    mint: Option<Pubkey>,
 ) -> Result<GetTokenBalanceResult, anyhow::Error> {
-    let wallet_pubkey = Pubkey::from_str(&wallet).unwrap();
-
     info!("Getting token balance for wallet: {}", wallet_pubkey);
 
-    let mut token_balances = Vec::new();
-
-    match mint {
-        Some(specific_mint) => {
-            let mint = Pubkey::from_str(&specific_mint).ok();
-            let mint_clone = mint.unwrap().clone();
-            info!("Getting balance for specific token: {}", mint_clone);
-            let rpc_url = "https://api.mainnet-beta.solana.com";
-            let client = RpcClient::new(rpc_url.to_string());
-            let token_accounts = client.get_token_accounts_by_owner(
-                &wallet_pubkey,
-                TokenAccountsFilter::Mint(mint_clone),
-            )?;
-
-            if token_accounts.is_empty() {
-                info!("No token account found for mint: {}", mint_clone);
-                return Ok(GetTokenBalanceResult {
-                    wallet: wallet_pubkey.to_string(),
-                    balances: vec![],
-                    total_tokens: 0,
-                });
-            }
-
-            for token_account in token_accounts {
-                let token_account_pubkey = Pubkey::from_str(&token_account.pubkey)?;
-
-                let token_account_balance =
-                    client.get_token_account_balance(&token_account_pubkey)?;
-
-                let balance_ui = token_account_balance.ui_amount.unwrap_or(0.0);
-                let balance_raw = token_account_balance.amount.parse::<u64>().unwrap_or(0);
-                let decimals = token_account_balance.decimals;
-
-                // Extract mint from parsed JSON data
-                let extracted_mint = extract_mint_from_token_account(&token_account);
-
-                info!("token account : {:?}", token_account);
-                if let Some(mint) = &extracted_mint {
-                    info!("Extracted mint from JSON: {}", mint);
-                }
-
-                info!(
-                    "Token account {}: {} tokens ({} raw units, {} decimals)",
-                    token_account_pubkey, balance_ui, balance_raw, decimals
-                );
-
-                token_balances.push(TokenBalance {
-                    balance: balance_ui,
-                    balance_raw,
-                    decimals,
-                    token_account: token_account_pubkey.to_string(),
-                    mint: extracted_mint,
-                });
-            }
+    let token_accounts = match mint {
+        Some(mint) => {
+            info!("Getting balance for specific token: {}", mint);
+            client.get_token_accounts_by_owner(wallet_pubkey, TokenAccountsFilter::Mint(mint))?
         }
-
         None => {
             info!("Getting all token balances for wallet");
-            let rpc_url = "https://api.mainnet-beta.solana.com";
-            let client = RpcClient::new(rpc_url.to_string());
-            let token_accounts = client.get_token_accounts_by_owner(
-                &wallet_pubkey,
-                TokenAccountsFilter::ProgramId(spl_token::id()),
-            )?;
-
-            if token_accounts.is_empty() {
-                info!("No token account found");
-                return Ok(GetTokenBalanceResult {
-                    wallet: wallet_pubkey.to_string(),
-                    balances: vec![],
-                    total_tokens: 0,
-                });
-            }
-            for token_account in token_accounts {
-                let token_account_pubkey = Pubkey::from_str(&token_account.pubkey)?;
-
-                let token_account_balance =
-                    client.get_token_account_balance(&token_account_pubkey)?;
-                let balance_ui = token_account_balance.ui_amount.unwrap_or(0.0);
-                let balance_raw = token_account_balance.amount.parse::<u64>().unwrap_or(0);
-                let decimals = token_account_balance.decimals;
-
-                // Extract mint from parsed JSON data
-                let extracted_mint = extract_mint_from_token_account(&token_account);
-
-                info!("token account : {:?}", token_account);
-                if let Some(mint) = &extracted_mint {
-                    info!("Extracted mint from JSON: {}", mint);
-                }
-
-                info!(
-                    "Token account {}: {} tokens ({} raw units, {} decimals)",
-                    token_account_pubkey, balance_ui, balance_raw, decimals
-                );
+            fetch_token_accounts(client, wallet_pubkey)?
+        }
+    };
 
-                token_balances.push(TokenBalance {
-                    balance: balance_ui,
-                    balance_raw,
-                    decimals,
-                    token_account: token_account_pubkey.to_string(),
-                    mint: extracted_mint,
-                });
-            }
+    if token_accounts.is_empty() {
+        match mint {
+            Some(mint) => info!("No token account found for mint: {}", mint),
+            None => info!("No token account found"),
         }
+        return Ok(empty_result(wallet_pubkey));
+    }
+
+    let mut token_balances = Vec::new();
+    for token_account in token_accounts {
+        token_balances.push(token_balance_from_account(client, &token_account)?);
     }
 
     let result = GetTokenBalanceResult {
-        wallet: wallet_pubkey.to_string(),
-        balances: token_balances.clone(),
         total_tokens: token_balances.len(),
+        balances: token_balances,
+        ..empty_result(wallet_pubkey)
     };
 
     info!(
@@ -169,37 +123,31 @@ async fn get_token_balance(
 
     Ok(result)
 }
-async fn get_tokens_to(wallet: String) -> Result<GetTokenBalanceResult, anyhow::Error> {
-    let wallet_pubkey = Pubkey::from_str(&wallet).unwrap();
 
+/// Mints the wallet holds token accounts of, without reading the balances
+fn read_token_mints(
+    client: &dyn SolanaRpc,
+    wallet_pubkey: &Pubkey,
+) -> Result<GetTokenBalanceResult, anyhow::Error> {
     info!("Getting token balance for wallet: {}", wallet_pubkey);
 
     let mut token_balances = Vec::new();
 
     info!("Getting all token balances for wallet");
-    let rpc_url = "https://api.mainnet-beta.solana.com";
-    let client = RpcClient::new(rpc_url.to_string());
-    let token_accounts = client.get_token_accounts_by_owner(
-        &wallet_pubkey,
-        TokenAccountsFilter::ProgramId(spl_token::id()),
-    )?;
+    let token_accounts = fetch_token_accounts(client, wallet_pubkey)?;
 
     if token_accounts.is_empty() {
         info!("No token account found");
-        return Ok(GetTokenBalanceResult {
-            wallet: wallet_pubkey.to_string(),
-            balances: vec![],
-            total_tokens: 0,
-        });
+        return Ok(empty_result(wallet_pubkey));
     }
     for token_account in token_accounts {
-        // Extract mint from parsed JSON data
-        let extracted_mint = extract_mint_from_token_account(&token_account);
-
-        info!("token account : {:?}", token_account);
-        if let Some(mint) = &extracted_mint {
-            info!("Extracted mint from JSON: {}", mint);
-        }
+        let extracted_mint = match decode_keyed_account(&token_account) {
+            Ok((_, decoded)) => Some(decoded.mint.to_string()),
+            Err(e) => {
+                warn!("{:#}", e);
+                None
+            }
+        };
 
         token_balances.push(TokenBalance {
             balance: 0.0,
@@ -211,9 +159,9 @@ async fn get_tokens_to(wallet: String) -> Result<GetTokenBalanceResult, anyhow::
     }
 
     let result = GetTokenBalanceResult {
-        wallet: wallet_pubkey.to_string(),
-        balances: token_balances.clone(),
         total_tokens: token_balances.len(),
+        balances: token_balances,
+        ..empty_result(wallet_pubkey)
     };
 
     info!(
@@ -224,6 +172,25 @@ async fn get_tokens_to(wallet: String) -> Result<GetTokenBalanceResult, anyhow::
     Ok(result)
 }
 
+async fn get_token_balance(
+    wallet: String,
+    mint: Option<String>,
+) -> Result<GetTokenBalanceResult, anyhow::Error> {
+    let wallet_pubkey = Pubkey::from_str(&wallet)?;
+    let mint = mint.map(|mint| Pubkey::from_str(&mint)).transpose()?;
+    let rpc_url = CONFIG.api.helius_https.clone();
+    task::spawn_blocking(move || {
+        read_token_balances(&RpcClient::new(rpc_url), &wallet_pubkey, mint)
+    })
+    .await?
+}
+
+async fn get_tokens_to(wallet: String) -> Result<GetTokenBalanceResult, anyhow::Error> {
+    let wallet_pubkey = Pubkey::from_str(&wallet)?;
+    let rpc_url = CONFIG.api.helius_https.clone();
+    task::spawn_blocking(move || read_token_mints(&RpcClient::new(rpc_url), &wallet_pubkey)).await?
+}
+
 pub async fn get_single_token_balance(wallet: String, mint: Option<String>) -> Result<f64, String> {
     let result = get_token_balance(wallet, mint)
         .await
@@ -236,27 +203,28 @@ pub async fn get_single_token_balance(wallet: String, mint: Option<String>) -> R
     }
 }
 pub async fn get_tokens_balances(wallet: String) -> Result<GetTokenBalanceResult, String> {
-    get_token_balance(wallet, None)
+    let mut result = get_token_balance(wallet, None)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    // Balances are still useful without names and images
+    if let Err(e) = token_metadata::enrich_balances(&mut result).await {
+        warn!(
+            "Failed to resolve token metadata for {}: {}",
+            result.wallet, e
+        );
+    }
+    Ok(result)
 }
 pub async fn get_tokens(wallet: String) -> Result<GetTokenBalanceResult, String> {
     get_tokens_to(wallet).await.map_err(|e| e.to_string())
 }
 
-/// Public function to extract mint from a token account
-pub fn extract_mint_from_account_debug(account_debug: &str) -> Option<String> {
-    extract_mint_from_debug_string(account_debug)
-}
-
 /// Get tokens balances with progress tracking for multiple wallets
 pub async fn get_tokens_balances_batch_with_progress(
     wallets: Vec<String>,
     job_id: String,
     job_manager: crate::jobs::JobManager,
-) -> Result<std::collections::HashMap<String, GetTokenBalanceResult>, String> {
-    use std::collections::HashMap;
-
+) -> Result<HashMap<String, GetTokenBalanceResult>, String> {
     if wallets.is_empty() {
         return Err("No wallets provided".to_string());
     }
@@ -305,6 +273,8 @@ pub async fn get_tokens_balances_batch_with_progress(
                         wallet: wallet.clone(),
                         balances: vec![],
                         total_tokens: 0,
+                        metadata: HashMap::new(),
+                        fetched_at: None,
                     },
                 );
             }
@@ -332,3 +302,91 @@ pub async fn get_tokens_balances_batch_with_progress(
     );
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount, RpcMethod};
+
+    fn mints(result: &GetTokenBalanceResult) -> Vec<Option<String>> {
+        result.balances.iter().map(|b| b.mint.clone()).collect()
+    }
+
+    #[test]
+    fn reads_the_balances_of_one_mint_or_every_mint() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        let (legacy, token_2022) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let legacy_account =
+            rpc.add_token_account(FakeTokenAccount::new(wallet, legacy, 1_500_000));
+        rpc.add_token_account(FakeTokenAccount {
+            program_id: spl_token_2022::id(),
+            ..FakeTokenAccount::new(wallet, token_2022, 0)
+        });
+        // Another wallet's account of the same mint
+        rpc.add_token_account(FakeTokenAccount::new(Pubkey::new_unique(), legacy, 7));
+
+        let result = read_token_balances(&rpc, &wallet, Some(legacy)).unwrap();
+        assert_eq!(result.wallet, wallet.to_string());
+        assert_eq!(result.total_tokens, 1);
+        let balance = &result.balances[0];
+        assert_eq!(balance.balance, 1.5);
+        assert_eq!(balance.balance_raw, 1_500_000);
+        assert_eq!(balance.decimals, 6);
+        assert_eq!(balance.token_account, legacy_account.to_string());
+        assert!(result.fetched_at.is_some());
+
+        let result = read_token_balances(&rpc, &wallet, None).unwrap();
+        assert_eq!(
+            mints(&result),
+            [Some(legacy.to_string()), Some(token_2022.to_string())]
+        );
+        assert_eq!(result.total_tokens, 2);
+        // Parsed accounts carry their decimals, no balance lookup needed
+        assert_eq!(rpc.calls(RpcMethod::GetTokenAccountBalance), 0);
+    }
+
+    #[test]
+    fn missing_accounts_give_an_empty_result() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+
+        let result = read_token_balances(&rpc, &wallet, Some(Pubkey::new_unique())).unwrap();
+        assert_eq!(result.total_tokens, 0);
+        assert!(result.balances.is_empty());
+        assert_eq!(read_token_mints(&rpc, &wallet).unwrap().total_tokens, 0);
+    }
+
+    #[test]
+    fn rpc_failure_is_returned() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        rpc.add_token_account(FakeTokenAccount::new(wallet, Pubkey::new_unique(), 1));
+        rpc.fail_next(RpcMethod::GetTokenAccountsByOwner, "rate limited");
+
+        let error = read_token_balances(&rpc, &wallet, None).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("rate limited"),
+            "{:#}",
+            error
+        );
+        assert_eq!(
+            read_token_balances(&rpc, &wallet, None)
+                .unwrap()
+                .total_tokens,
+            1
+        );
+    }
+
+    #[test]
+    fn token_mints_are_listed_without_balances() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        rpc.add_token_account(FakeTokenAccount::new(wallet, mint, 42));
+
+        let result = read_token_mints(&rpc, &wallet).unwrap();
+        assert_eq!(mints(&result), [Some(mint.to_string())]);
+        assert_eq!(result.balances[0].balance_raw, 0);
+    }
+}
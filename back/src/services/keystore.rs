@@ -0,0 +1,93 @@
+use crate::services::local_cache;
+use crate::services::vault::{self, EncryptedBlob};
+use anyhow::Context;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{signature::Keypair, signer::Signer};
+use std::fs;
+
+const KEYSTORE_NAMESPACE: &str = "keystore";
+
+/// Keypair encrypted with a user password, stored until used
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeystoreEntry {
+    pub pubkey: String,
+    pub label: String,
+    pub created_at: i64,
+    pub encrypted: EncryptedBlob,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeystoreSummary {
+    pub pubkey: String,
+    pub label: String,
+    pub created_at: i64,
+}
+
+impl From<&KeystoreEntry> for KeystoreSummary {
+    fn from(entry: &KeystoreEntry) -> Self {
+        KeystoreSummary {
+            pubkey: entry.pubkey.clone(),
+            label: entry.label.clone(),
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// Encrypt and store a keypair under its public key
+pub fn store_keypair(
+    keypair: &Keypair,
+    label: &str,
+    password: &str,
+) -> anyhow::Result<KeystoreSummary> {
+    let entry = KeystoreEntry {
+        pubkey: keypair.pubkey().to_string(),
+        label: label.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        encrypted: vault::encrypt(password, &keypair.to_bytes())?,
+    };
+    local_cache::store(KEYSTORE_NAMESPACE, &entry.pubkey, &entry)?;
+    Ok(KeystoreSummary::from(&entry))
+}
+
+/// Decrypt a stored keypair, checking it matches the public key it is stored under
+pub fn load_keypair(pubkey: &str, password: &str) -> anyhow::Result<Keypair> {
+    let entry = local_cache::load::<KeystoreEntry>(KEYSTORE_NAMESPACE, pubkey)
+        .with_context(|| format!("no keypair {} in the keystore", pubkey))?;
+    let bytes = vault::decrypt(password, &entry.encrypted)?;
+    let keypair = Keypair::try_from(&bytes[..]).context("invalid keypair bytes")?;
+    if keypair.pubkey().to_string() != pubkey {
+        anyhow::bail!("keystore entry {} holds another keypair", pubkey);
+    }
+    Ok(keypair)
+}
+
+/// Delete a keypair once it has been used
+pub fn remove_keypair(pubkey: &str) -> anyhow::Result<()> {
+    local_cache::remove(KEYSTORE_NAMESPACE, pubkey)
+}
+
+/// Stored keypairs, newest first
+pub fn list_keypairs() -> Vec<KeystoreSummary> {
+    let dir = local_cache::namespace_dir(KEYSTORE_NAMESPACE);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return vec![];
+    };
+    let mut summaries: Vec<KeystoreSummary> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let key = path.file_stem()?.to_str()?.to_string();
+            let loaded = local_cache::load::<KeystoreEntry>(KEYSTORE_NAMESPACE, &key);
+            if loaded.is_none() {
+                warn!("Skipping unreadable keystore entry {}", path.display());
+            }
+            loaded.map(|entry| KeystoreSummary::from(&entry))
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    summaries
+}
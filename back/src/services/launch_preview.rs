@@ -0,0 +1,524 @@
+use crate::config::CONFIG;
+use crate::services::common::{lamports_to_sol, parse_keypair, CreateTokenReq};
+use crate::services::create_meme_token::generate_metadata;
+use crate::services::local_cache;
+use crate::solana::ipfs::{self, TokenMetadata};
+use crate::solana::rpc::SolanaRpc;
+use anyhow::Context;
+use log::info;
+use pumpfun::utils::CreateTokenMetadata;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use tokio::task;
+
+const PLAN_NAMESPACE: &str = "launch_plans";
+/// A plan must be confirmed and launched within this delay, as costs drift
+const PLAN_TTL_SECS: i64 = 15 * 60;
+
+const MIN_IMAGE_SIDE: u32 = 100;
+const MAX_IMAGE_SIDE: u32 = 4096;
+
+/// Account sizes of a pump.fun launch, used for rent estimates
+const MINT_ACCOUNT_SIZE: usize = 82;
+/// Discriminator, five u64 reserves, complete flag and creator
+const BONDING_CURVE_ACCOUNT_SIZE: usize = 8 + 5 * 8 + 1 + 32;
+/// Metaplex MAX_METADATA_LEN
+const METADATA_ACCOUNT_SIZE: usize = 679;
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+
+/// Compute unit estimates of the launch transactions
+const CREATE_AND_BUY_COMPUTE_UNITS: u64 = 300_000;
+const BUY_COMPUTE_UNITS: u64 = 100_000;
+const SIGNATURE_FEE_LAMPORTS: u64 = 5_000;
+/// pump.fun fee on bonding curve buys
+const PUMP_FEE_BPS: u64 = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageCheck {
+    pub path: String,
+    pub mime: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: usize,
+    /// Hex sha256 of the image, checked again before launching
+    pub content_hash: String,
+    pub uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LaunchCostEstimate {
+    pub mint_rent_lamports: u64,
+    pub bonding_curve_rent_lamports: u64,
+    pub metadata_rent_lamports: u64,
+    /// Bonding curve and dev token accounts
+    pub token_accounts_rent_lamports: u64,
+    pub dev_buy_lamports: u64,
+    /// Dev buy with slippage and the pump.fun fee, the most the buy can cost
+    pub dev_buy_max_lamports: u64,
+    pub sniper_buy_max_lamports: u64,
+    pub tips_lamports: u64,
+    pub priority_fee_lamports: u64,
+    pub network_fee_lamports: u64,
+    pub dev_wallet_total_lamports: u64,
+    pub sniper_wallet_total_lamports: u64,
+    pub total_lamports: u64,
+    pub total_sol: f64,
+}
+
+/// Metadata fields passed to the SDK at launch
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LaunchMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+    pub image_path: String,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+    pub website: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LaunchPlan {
+    pub plan_id: String,
+    /// Hash of everything shown to the user, echoed back to confirm the plan
+    pub plan_hash: String,
+    /// Hash of the create request the plan was built from
+    pub request_hash: String,
+    pub launch_metadata: LaunchMetadata,
+    /// Final pump.fun metadata JSON
+    pub metadata: TokenMetadata,
+    /// URI of the pinned metadata JSON, the one the token launches with
+    pub metadata_uri: String,
+    pub image: ImageCheck,
+    pub cost: LaunchCostEstimate,
+    pub dev_wallet: String,
+    pub dev_wallet_balance_lamports: u64,
+    pub warnings: Vec<String>,
+    pub confirmed: bool,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl LaunchPlan {
+    pub fn token_metadata(&self) -> CreateTokenMetadata {
+        let m = &self.launch_metadata;
+        CreateTokenMetadata {
+            name: m.name.clone(),
+            symbol: m.symbol.clone(),
+            description: m.description.clone(),
+            file: m.image_path.clone(),
+            twitter: m.twitter.clone(),
+            telegram: m.telegram.clone(),
+            website: m.website.clone(),
+        }
+    }
+
+    fn compute_hash(&self) -> anyhow::Result<String> {
+        let shown = serde_json::to_vec(&(
+            &self.plan_id,
+            &self.request_hash,
+            &self.metadata,
+            &self.metadata_uri,
+            &self.image,
+            &self.cost,
+            self.expires_at,
+        ))?;
+        Ok(ipfs::content_hash(&shown))
+    }
+}
+
+fn sol_to_lamports(sol: f64) -> u64 {
+    (sol.max(0.0) * 1_000_000_000.0).round() as u64
+}
+
+/// Hash of the request, ignoring the plan it refers to
+fn request_hash(req: &CreateTokenReq) -> anyhow::Result<String> {
+    let mut req = req.clone();
+    req.plan_id = None;
+    Ok(ipfs::content_hash(&serde_json::to_vec(&req)?))
+}
+
+/// Format, size and dimensions of the token image
+fn check_image(path: &str, warnings: &mut Vec<String>) -> anyhow::Result<ImageCheck> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read image {}", path))?;
+    let (mime, _) = ipfs::validate_image(&bytes)?;
+    let (width, height) =
+        ipfs::image_dimensions(&bytes).context("could not read image dimensions")?;
+    if width < MIN_IMAGE_SIDE || height < MIN_IMAGE_SIDE {
+        anyhow::bail!(
+            "image is {}x{}, min is {}x{}",
+            width,
+            height,
+            MIN_IMAGE_SIDE,
+            MIN_IMAGE_SIDE
+        );
+    }
+    if width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE {
+        warnings.push(format!(
+            "image is {}x{}, it will be downscaled by most clients",
+            width, height
+        ));
+    }
+    if width != height {
+        warnings.push(format!(
+            "image is {}x{}, pump.fun displays images as squares",
+            width, height
+        ));
+    }
+    Ok(ImageCheck {
+        path: path.to_string(),
+        mime: mime.to_string(),
+        width,
+        height,
+        size_bytes: bytes.len(),
+        content_hash: ipfs::content_hash(&bytes),
+        uri: String::new(),
+    })
+}
+
+/// Rent and fee estimate of the launch, with the dev wallet balance
+fn estimate_cost(
+    client: &dyn SolanaRpc,
+    req: &CreateTokenReq,
+    dev_wallet: &Keypair,
+) -> anyhow::Result<(LaunchCostEstimate, u64)> {
+    let rent = |size: usize| -> anyhow::Result<u64> {
+        client
+            .get_minimum_balance_for_rent_exemption(size)
+            .context("failed to get rent exemption")
+    };
+    let with_slippage_and_fee = |lamports: u64| -> u64 {
+        let max = lamports as u128 * (10_000 + req.slippage_bps as u128) / 10_000;
+        (max * (10_000 + PUMP_FEE_BPS as u128) / 10_000) as u64
+    };
+    let priority_fee = |units: u64| -> u64 {
+        (req.cu_price_microlamports as u128 * units as u128 / 1_000_000) as u64
+    };
+
+    let mut cost = LaunchCostEstimate {
+        mint_rent_lamports: rent(MINT_ACCOUNT_SIZE)?,
+        bonding_curve_rent_lamports: rent(BONDING_CURVE_ACCOUNT_SIZE)?,
+        metadata_rent_lamports: rent(METADATA_ACCOUNT_SIZE)?,
+        token_accounts_rent_lamports: 2 * rent(TOKEN_ACCOUNT_SIZE)?,
+        dev_buy_lamports: sol_to_lamports(req.dev_sol_amount),
+        ..Default::default()
+    };
+    cost.dev_buy_max_lamports = with_slippage_and_fee(cost.dev_buy_lamports);
+    let creation_tip = sol_to_lamports(req.creation_tip_sol);
+    let create_priority_fee = priority_fee(CREATE_AND_BUY_COMPUTE_UNITS);
+    // Dev wallet and mint sign the create transaction
+    let create_network_fee = 2 * SIGNATURE_FEE_LAMPORTS;
+
+    let sniper_buy = sol_to_lamports(req.amount_sol_sniper_one);
+    let (sniper_tip, sniper_priority_fee, sniper_network_fee) = if sniper_buy > 0 {
+        cost.sniper_buy_max_lamports = with_slippage_and_fee(sniper_buy);
+        (
+            sol_to_lamports(req.tip_sol),
+            priority_fee(BUY_COMPUTE_UNITS),
+            SIGNATURE_FEE_LAMPORTS,
+        )
+    } else {
+        (0, 0, 0)
+    };
+
+    cost.tips_lamports = creation_tip + sniper_tip;
+    cost.priority_fee_lamports = create_priority_fee + sniper_priority_fee;
+    cost.network_fee_lamports = create_network_fee + sniper_network_fee;
+    cost.dev_wallet_total_lamports = cost.mint_rent_lamports
+        + cost.bonding_curve_rent_lamports
+        + cost.metadata_rent_lamports
+        + cost.token_accounts_rent_lamports
+        + cost.dev_buy_max_lamports
+        + creation_tip
+        + create_priority_fee
+        + create_network_fee;
+    if sniper_buy > 0 {
+        // The sniper pays for its own token account
+        cost.sniper_wallet_total_lamports = cost.sniper_buy_max_lamports
+            + rent(TOKEN_ACCOUNT_SIZE)?
+            + sniper_tip
+            + sniper_priority_fee
+            + sniper_network_fee;
+    }
+    cost.total_lamports = cost.dev_wallet_total_lamports + cost.sniper_wallet_total_lamports;
+    cost.total_sol = lamports_to_sol(cost.total_lamports);
+
+    let balance = client
+        .get_balance(&dev_wallet.pubkey())
+        .context("failed to get dev wallet balance")?;
+    Ok((cost, balance))
+}
+
+/// Build the final metadata, validate the image and socials, pin the image and
+/// estimate the launch cost; the returned plan must be confirmed before launching
+pub async fn preview_launch(req: CreateTokenReq) -> Result<LaunchPlan, String> {
+    let metadata = generate_metadata(&req).map_err(|e| format!("{:#}", e))?;
+    let dev_wallet =
+        parse_keypair(req.dev_wallet.expose()).map_err(|e| format!("dev wallet: {:#}", e))?;
+
+    let mut warnings = Vec::new();
+    let mut image = check_image(&metadata.file, &mut warnings).map_err(|e| format!("{:#}", e))?;
+    if metadata.twitter.is_none() && metadata.telegram.is_none() && metadata.website.is_none() {
+        warnings.push("no social links, they cannot be added after launch".to_string());
+    }
+
+    // Pinning is idempotent, so previewing the same launch again does not re-pin it
+    let uploader = ipfs::uploader_from_config().map_err(|e| format!("{:#}", e))?;
+    let pinned = ipfs::create_token_metadata(metadata.clone().into(), uploader.as_ref())
+        .await
+        .map_err(|e| format!("metadata upload failed: {:#}", e))?;
+    image.uri = pinned.metadata.image.clone();
+
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let cost_req = req.clone();
+    let dev_keypair = dev_wallet.insecure_clone();
+    let (cost, balance) = task::spawn_blocking(move || {
+        estimate_cost(&RpcClient::new(rpc_url), &cost_req, &dev_keypair)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+
+    if balance < cost.dev_wallet_total_lamports {
+        warnings.push(format!(
+            "dev wallet holds {} SOL, the launch needs up to {} SOL",
+            lamports_to_sol(balance),
+            lamports_to_sol(cost.dev_wallet_total_lamports)
+        ));
+    }
+
+    let launch_metadata = LaunchMetadata {
+        name: metadata.name,
+        symbol: metadata.symbol,
+        description: metadata.description,
+        image_path: metadata.file,
+        twitter: metadata.twitter,
+        telegram: metadata.telegram,
+        website: metadata.website,
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let mut plan = LaunchPlan {
+        plan_id: uuid::Uuid::new_v4().to_string(),
+        plan_hash: String::new(),
+        request_hash: request_hash(&req).map_err(|e| e.to_string())?,
+        launch_metadata,
+        metadata: pinned.metadata,
+        metadata_uri: pinned.metadata_uri,
+        image,
+        cost,
+        dev_wallet: dev_wallet.pubkey().to_string(),
+        dev_wallet_balance_lamports: balance,
+        warnings,
+        confirmed: false,
+        created_at: now,
+        expires_at: now + PLAN_TTL_SECS,
+    };
+    plan.plan_hash = plan.compute_hash().map_err(|e| e.to_string())?;
+    local_cache::store(PLAN_NAMESPACE, &plan.plan_id, &plan).map_err(|e| format!("{:#}", e))?;
+
+    info!(
+        "Launch plan {} for {}: up to {} SOL",
+        plan.plan_id, plan.metadata.symbol, plan.cost.total_sol
+    );
+    Ok(plan)
+}
+
+fn load_plan(plan_id: &str) -> anyhow::Result<LaunchPlan> {
+    let plan = local_cache::load::<LaunchPlan>(PLAN_NAMESPACE, plan_id)
+        .with_context(|| format!("unknown launch plan {}", plan_id))?;
+    if plan.expires_at <= chrono::Utc::now().timestamp() {
+        let _ = local_cache::remove(PLAN_NAMESPACE, plan_id);
+        anyhow::bail!("launch plan {} expired, preview the launch again", plan_id);
+    }
+    Ok(plan)
+}
+
+/// Sign off a plan; `plan_hash` must be the one returned by the preview
+pub fn confirm_launch(plan_id: String, plan_hash: String) -> Result<LaunchPlan, String> {
+    let mut plan = load_plan(&plan_id).map_err(|e| format!("{:#}", e))?;
+    let expected = plan.compute_hash().map_err(|e| e.to_string())?;
+    if plan_hash != expected || plan.plan_hash != expected {
+        return Err(format!(
+            "launch plan {} does not match the preview",
+            plan_id
+        ));
+    }
+    plan.confirmed = true;
+    local_cache::store(PLAN_NAMESPACE, &plan.plan_id, &plan).map_err(|e| format!("{:#}", e))?;
+    info!("Launch plan {} confirmed", plan_id);
+    Ok(plan)
+}
+
+/// Confirmed plan matching the request, removed so it launches at most once
+pub fn take_confirmed_plan(req: &CreateTokenReq) -> anyhow::Result<LaunchPlan> {
+    let plan_id = req
+        .plan_id
+        .as_deref()
+        .context("no launch plan: preview and confirm the launch first")?;
+    let plan = load_plan(plan_id)?;
+    if !plan.confirmed {
+        anyhow::bail!("launch plan {} is not confirmed", plan_id);
+    }
+    if plan.request_hash != request_hash(req)? {
+        anyhow::bail!(
+            "request changed since launch plan {} was previewed",
+            plan_id
+        );
+    }
+    // The request only names the image file, its content may have changed since
+    let image = std::fs::read(&plan.image.path)
+        .with_context(|| format!("failed to read image {}", plan.image.path))?;
+    if ipfs::content_hash(&image) != plan.image.content_hash {
+        anyhow::bail!(
+            "token image changed since launch plan {} was previewed",
+            plan_id
+        );
+    }
+    local_cache::remove(PLAN_NAMESPACE, plan_id)
+        .with_context(|| format!("failed to consume launch plan {}", plan_id))?;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, RpcMethod};
+
+    fn request(dev_sol_amount: f64, amount_sol_sniper_one: f64) -> CreateTokenReq {
+        CreateTokenReq {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            decimals: 6.0,
+            description: String::new(),
+            website: String::new(),
+            telegram: String::new(),
+            twitter: String::new(),
+            supply_human: 1_000_000_000,
+            supply_base_units: 1e15,
+            dev_wallet: Keypair::new().to_base58_string().into(),
+            dev_token_amount: 0,
+            dev_sol_amount,
+            sniper_wallet_one: Keypair::new().to_base58_string().into(),
+            amount_sol_sniper_one,
+            creation_tip_sol: 0.001,
+            tip_sol: 0.002,
+            slippage_bps: 500,
+            // One lamport per compute unit
+            cu_price_microlamports: 1_000_000,
+            max_unit_price_microlamports: 1_000_000,
+            image_path: None,
+            image_base64: None,
+            template: None,
+            vanity_mint: None,
+            plan_id: None,
+        }
+    }
+
+    fn rent(rpc: &FakeRpc, size: usize) -> u64 {
+        rpc.get_minimum_balance_for_rent_exemption(size).unwrap()
+    }
+
+    #[test]
+    fn dev_wallet_pays_rent_buy_tip_and_fees() {
+        let rpc = FakeRpc::new();
+        let dev_wallet = Keypair::new();
+        rpc.set_balance(&dev_wallet.pubkey(), 3_000_000_000);
+
+        let (cost, balance) = estimate_cost(&rpc, &request(1.0, 0.0), &dev_wallet).unwrap();
+
+        assert_eq!(balance, 3_000_000_000);
+        assert_eq!(
+            cost.token_accounts_rent_lamports,
+            2 * rent(&rpc, TOKEN_ACCOUNT_SIZE)
+        );
+        assert_eq!(cost.dev_buy_lamports, 1_000_000_000);
+        // 5% slippage, then the 1% pump.fun fee
+        assert_eq!(cost.dev_buy_max_lamports, 1_060_500_000);
+        assert_eq!(cost.tips_lamports, 1_000_000);
+        assert_eq!(cost.priority_fee_lamports, CREATE_AND_BUY_COMPUTE_UNITS);
+        assert_eq!(cost.network_fee_lamports, 2 * SIGNATURE_FEE_LAMPORTS);
+        let rents = rent(&rpc, MINT_ACCOUNT_SIZE)
+            + rent(&rpc, BONDING_CURVE_ACCOUNT_SIZE)
+            + rent(&rpc, METADATA_ACCOUNT_SIZE)
+            + 2 * rent(&rpc, TOKEN_ACCOUNT_SIZE);
+        assert_eq!(
+            cost.dev_wallet_total_lamports,
+            rents + 1_060_500_000 + 1_000_000 + CREATE_AND_BUY_COMPUTE_UNITS + 10_000
+        );
+        assert_eq!(cost.sniper_buy_max_lamports, 0);
+        assert_eq!(cost.sniper_wallet_total_lamports, 0);
+        assert_eq!(cost.total_lamports, cost.dev_wallet_total_lamports);
+        assert_eq!(cost.total_sol, lamports_to_sol(cost.total_lamports));
+    }
+
+    #[test]
+    fn sniper_buy_is_costed_on_its_own_wallet() {
+        let rpc = FakeRpc::new();
+
+        let (cost, _) = estimate_cost(&rpc, &request(1.0, 0.5), &Keypair::new()).unwrap();
+
+        assert_eq!(cost.sniper_buy_max_lamports, 530_250_000);
+        assert_eq!(
+            cost.sniper_wallet_total_lamports,
+            530_250_000
+                + rent(&rpc, TOKEN_ACCOUNT_SIZE)
+                + 2_000_000
+                + BUY_COMPUTE_UNITS
+                + SIGNATURE_FEE_LAMPORTS
+        );
+        assert_eq!(cost.tips_lamports, 3_000_000);
+        assert_eq!(
+            cost.priority_fee_lamports,
+            CREATE_AND_BUY_COMPUTE_UNITS + BUY_COMPUTE_UNITS
+        );
+        assert_eq!(cost.network_fee_lamports, 3 * SIGNATURE_FEE_LAMPORTS);
+        assert_eq!(
+            cost.total_lamports,
+            cost.dev_wallet_total_lamports + cost.sniper_wallet_total_lamports
+        );
+    }
+
+    #[test]
+    fn rpc_failures_fail_the_estimate() {
+        let rpc = FakeRpc::new();
+        rpc.fail_next(RpcMethod::GetBalance, "node unavailable");
+        let error = estimate_cost(&rpc, &request(1.0, 0.0), &Keypair::new()).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("failed to get dev wallet balance"),
+            "{:#}",
+            error
+        );
+
+        rpc.fail_next(
+            RpcMethod::GetMinimumBalanceForRentExemption,
+            "node unavailable",
+        );
+        let error = estimate_cost(&rpc, &request(1.0, 0.0), &Keypair::new()).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("failed to get rent exemption"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn request_hash_ignores_the_plan_id() {
+        let req = request(1.0, 0.0);
+        let with_plan = CreateTokenReq {
+            plan_id: Some("plan".to_string()),
+            ..req.clone()
+        };
+        assert_eq!(
+            request_hash(&req).unwrap(),
+            request_hash(&with_plan).unwrap()
+        );
+
+        let changed = CreateTokenReq {
+            dev_sol_amount: 2.0,
+            ..req.clone()
+        };
+        assert_ne!(request_hash(&req).unwrap(), request_hash(&changed).unwrap());
+    }
+}
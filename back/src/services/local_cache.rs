@@ -0,0 +1,65 @@
+use crate::config::CONFIG;
+use anyhow::Context;
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Directory holding all files of a cache namespace (e.g. "tx_history")
+pub fn namespace_dir(namespace: &str) -> PathBuf {
+    PathBuf::from(&CONFIG.storage.data_dir).join(namespace)
+}
+
+/// Keep cache file names portable: only alphanumerics, '-' and '_'
+fn sanitize_key(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn entry_path(namespace: &str, key: &str) -> PathBuf {
+    namespace_dir(namespace).join(format!("{}.json", sanitize_key(key)))
+}
+
+/// Load a cached JSON value, returning None if missing or unreadable
+pub fn load<T: DeserializeOwned>(namespace: &str, key: &str) -> Option<T> {
+    let path = entry_path(namespace, key);
+    let raw = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&raw) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring corrupted cache entry {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Store a JSON value, writing to a temp file first so readers never see partial data
+pub fn store<T: Serialize>(namespace: &str, key: &str, value: &T) -> anyhow::Result<()> {
+    let dir = namespace_dir(namespace);
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let path = entry_path(namespace, key);
+    let tmp_path = path.with_extension("json.tmp");
+    let json = serde_json::to_string_pretty(value).context("failed to serialize cache entry")?;
+    fs::write(&tmp_path, json)
+        .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Remove a cached entry (missing entries are not an error)
+pub fn remove(namespace: &str, key: &str) -> anyhow::Result<()> {
+    let path = entry_path(namespace, key);
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("failed to remove {}", path.display())),
+    }
+}
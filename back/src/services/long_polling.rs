@@ -1,15 +1,107 @@
+use crate::job_events::{job_events, spawn_tracked};
+use crate::job_queue::{
+    signer_pubkeys, spawn_queued, JobPriority, JobRequest, QUEUE_READS, QUEUE_TRANSACTIONS,
+};
 use crate::jobs::{JobInfo, JobManager};
+use crate::secret::SecretString;
+use crate::services::common::{QuickBuyReq, QuickSellReq};
+use crate::services::{
+    burn_and_close, burn_tokens, close_accounts, close_token_account, get_token_balance, quick_buy,
+    quick_sell,
+};
 use log::{info, warn};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
 use std::time::{Duration, Instant};
 use tauri::State;
+use tokio::sync::{mpsc, Semaphore};
 
-#[derive(Debug, Serialize, Deserialize)]
+const MAX_BATCH_JOBS: usize = 500;
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// Batches started since launch, by batch id
+static BATCHES: LazyLock<Mutex<HashMap<String, BatchInfo>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Job kinds a batch can run, with their parameters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "params", rename_all = "snake_case")]
+pub enum BatchJobKind {
+    QuickBuy(QuickBuyReq),
+    QuickSell(QuickSellReq),
+    BurnTokens {
+        wallet_pk: SecretString,
+        mint_address: String,
+        burn_percentage: f64,
+    },
+    CloseAccounts {
+        wallet_pk: SecretString,
+        #[serde(default)]
+        rent_destination: Option<String>,
+    },
+    CloseTokenAccount {
+        wallet_pk: SecretString,
+        token_mint: String,
+        #[serde(default)]
+        rent_destination: Option<String>,
+    },
+    BurnAndClose {
+        wallet_pk: SecretString,
+        mints: Vec<String>,
+        #[serde(default)]
+        rent_destination: Option<String>,
+    },
+    GetTokenBalances {
+        wallet: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
 pub struct BatchJobRequest {
-    pub job_type: String,
-    pub requests: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub jobs: Vec<BatchJobKind>,
     pub max_concurrent: Option<usize>,
-    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub priority: Option<JobPriority>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchChildStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchChild {
+    pub index: usize,
+    pub kind: String,
+    pub job_id: Option<String>,
+    pub status: BatchChildStatus,
+    pub error: Option<String>,
+}
+
+/// Parent batch job with the state of its children
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchInfo {
+    pub batch_id: String,
+    pub name: String,
+    pub total_jobs: usize,
+    pub running_jobs: usize,
+    pub completed_jobs: usize,
+    pub failed_jobs: usize,
+    pub cancelled_jobs: usize,
+    /// Share of children done, 0 to 100
+    pub progress: f32,
+    pub children: Vec<BatchChild>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -72,37 +164,503 @@ impl LongPollingService {
         // Private code.
     }
     /// Démarre un batch de jobs avec limite de concurrence
-    pub async fn start_controlled_batch(
-        job_type: &str,
-        requests: Vec<serde_json::Value>,
-        max_concurrent: usize,
+    pub fn start_controlled_batch(
+        request: BatchJobRequest,
         job_manager: &JobManager,
         app_handle: tauri::AppHandle,
-    ) -> Result<Vec<String>, String> {
+    ) -> Result<String, String> {
+        if request.jobs.is_empty() {
+            return Err("No jobs provided".to_string());
+        }
+        if request.jobs.len() > MAX_BATCH_JOBS {
+            return Err(format!(
+                "Too many jobs in batch: {} (max: {})",
+                request.jobs.len(),
+                MAX_BATCH_JOBS
+            ));
+        }
+        let max_concurrent = request
+            .max_concurrent
+            .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+            .max(1);
+        let total_jobs = request.jobs.len();
+
+        let info = BatchInfo {
+            batch_id: String::new(),
+            name: request.name.clone().unwrap_or_else(|| "batch".to_string()),
+            total_jobs: request.jobs.len(),
+            running_jobs: 0,
+            completed_jobs: 0,
+            failed_jobs: 0,
+            cancelled_jobs: 0,
+            progress: 0.0,
+            children: request
+                .jobs
+                .iter()
+                .enumerate()
+                .map(|(index, job)| BatchChild {
+                    index,
+                    kind: job.label().to_string(),
+                    job_id: None,
+                    status: BatchChildStatus::Pending,
+                    error: None,
+                })
+                .collect(),
+            started_at: chrono::Utc::now().timestamp(),
+            finished_at: None,
+        };
+
+        let manager = job_manager.clone();
+        let name = info.name.clone();
+        let pending = info.clone();
+        // The batch is its parent job; whichever of the job and this function sees
+        // the manager id first registers the batch under it
+        let batch_id = spawn_tracked(
+            job_manager,
+            &name,
+            app_handle.clone(),
+            move |batch_id| async move {
+                register_batch(&batch_id, pending);
+                Self::run_batch(batch_id, request, max_concurrent, manager, app_handle).await
+            },
+        );
+        register_batch(&batch_id, info);
         info!(
-            "Starting controlled batch: {} jobs, max {} concurrent",
-            requests.len(),
-            max_concurrent
+            "Started controlled batch {}: {} jobs, max {} concurrent",
+            batch_id, total_jobs, max_concurrent
         );
+        Ok(batch_id)
+    }
 
-        let mut job_ids = Vec::new();
-        let chunks: Vec<_> = requests.chunks(max_concurrent).collect();
+    /// Spawn the children at most `max_concurrent` at a time and aggregate their outcomes
+    async fn run_batch(
+        batch_id: String,
+        request: BatchJobRequest,
+        max_concurrent: usize,
+        manager: JobManager,
+        app_handle: tauri::AppHandle,
+    ) -> Result<(), String> {
+        let total = request.jobs.len();
+        let semaphore = Arc::new(Semaphore::new(max_concurrent));
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel::<(usize, ChildOutcome)>();
 
-        // Private code.
+        for (index, job) in request.jobs.into_iter().enumerate() {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| e.to_string())?;
+            let job_request = job.job_request(request.priority);
+            let report = ChildReport::new(index, done_tx.clone());
+            let child_id = spawn_queued(
+                &manager,
+                &format!("{}_{}", batch_id, job.label()),
+                job_request,
+                app_handle.clone(),
+                move |_| async move {
+                    // Dropped with the job when it is cancelled or aborted
+                    let (_permit, report) = (permit, report);
+                    let result = job.run().await;
+                    report.finished(result.clone());
+                    result
+                },
+            );
+            update_batch(&batch_id, |batch| {
+                let child = &mut batch.children[index];
+                child.job_id = Some(child_id);
+                child.status = BatchChildStatus::Running;
+            });
+        }
+        drop(done_tx);
+
+        let mut finished = 0;
+        while let Some((index, outcome)) = done_rx.recv().await {
+            finished += 1;
+            if let Some(child) = record_outcome(&batch_id, index, outcome) {
+                if let Some(child_id) = child.job_id {
+                    crate::report_job_item(
+                        &batch_id,
+                        child_id,
+                        child.status == BatchChildStatus::Completed,
+                        child.error,
+                    );
+                }
+            }
+            let _ = crate::update_job_progress(
+                &batch_id,
+                finished as u32,
+                total as u32,
+                format!("{} of {} batch jobs done", finished, total),
+            );
+        }
 
-        Ok(job_ids)
+        let batch = update_batch(&batch_id, |batch| {
+            batch.finished_at = Some(chrono::Utc::now().timestamp());
+        })
+        .ok_or_else(|| format!("unknown batch {}", batch_id))?;
+        info!(
+            "Batch {} done: {} completed, {} failed, {} cancelled",
+            batch_id, batch.completed_jobs, batch.failed_jobs, batch.cancelled_jobs
+        );
+        if batch.completed_jobs == batch.total_jobs {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} of {} batch jobs did not complete",
+                batch.total_jobs - batch.completed_jobs,
+                batch.total_jobs
+            ))
+        }
     }
+}
 
-    fn start_job_chunk(
-        job_type: &str,
-        requests: &[serde_json::Value],
-        job_manager: &JobManager,
-        app_handle: &tauri::AppHandle,
-    ) -> Result<Vec<String>, String> {
-        let mut job_ids = Vec::new();
+impl BatchJobKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BatchJobKind::QuickBuy(_) => "quick_buy",
+            BatchJobKind::QuickSell(_) => "quick_sell",
+            BatchJobKind::BurnTokens { .. } => "burn_tokens",
+            BatchJobKind::CloseAccounts { .. } => "close_accounts",
+            BatchJobKind::CloseTokenAccount { .. } => "close_token_account",
+            BatchJobKind::BurnAndClose { .. } => "burn_and_close",
+            BatchJobKind::GetTokenBalances { .. } => "get_token_balances",
+        }
+    }
 
-        // Private code
+    /// Scheduler queue and signing wallet of the job
+    fn job_request(&self, priority: Option<JobPriority>) -> JobRequest {
+        let (queue, signer) = match self {
+            BatchJobKind::QuickBuy(req) => (QUEUE_TRANSACTIONS, Some(&req.pk)),
+            BatchJobKind::QuickSell(req) => (QUEUE_TRANSACTIONS, Some(&req.pk)),
+            BatchJobKind::BurnTokens { wallet_pk, .. }
+            | BatchJobKind::CloseAccounts { wallet_pk, .. }
+            | BatchJobKind::CloseTokenAccount { wallet_pk, .. }
+            | BatchJobKind::BurnAndClose { wallet_pk, .. } => (QUEUE_TRANSACTIONS, Some(wallet_pk)),
+            BatchJobKind::GetTokenBalances { .. } => (QUEUE_READS, None),
+        };
+        JobRequest {
+            queue: queue.to_string(),
+            priority: priority.unwrap_or(JobPriority::Normal),
+            wallets: signer
+                .map(|pk| signer_pubkeys(&[pk.expose()]))
+                .unwrap_or_default(),
+        }
+    }
+
+    async fn run(self) -> Result<(), String> {
+        match self {
+            BatchJobKind::QuickBuy(req) => {
+                let res = quick_buy::quick_buy(req).await?;
+                if res.ok {
+                    Ok(())
+                } else {
+                    Err(res.error.unwrap_or_else(|| "buy failed".to_string()))
+                }
+            }
+            BatchJobKind::QuickSell(req) => {
+                let res = quick_sell::quick_sell(req).await;
+                if res.ok {
+                    Ok(())
+                } else {
+                    Err(res.error.unwrap_or_else(|| "sell failed".to_string()))
+                }
+            }
+            BatchJobKind::BurnTokens {
+                wallet_pk,
+                mint_address,
+                burn_percentage,
+            } => burn_tokens::burn_tokens(wallet_pk.into_inner(), mint_address, burn_percentage)
+                .await
+                .map(|_| ()),
+            BatchJobKind::CloseAccounts {
+                wallet_pk,
+                rent_destination,
+            } => close_accounts::close_accounts(wallet_pk.into_inner(), rent_destination)
+                .await
+                .map(|_| ()),
+            BatchJobKind::CloseTokenAccount {
+                wallet_pk,
+                token_mint,
+                rent_destination,
+            } => close_token_account::close_token_account(
+                wallet_pk.into_inner(),
+                token_mint,
+                rent_destination,
+                None,
+            )
+            .await
+            .map(|_| ()),
+            BatchJobKind::BurnAndClose {
+                wallet_pk,
+                mints,
+                rent_destination,
+            } => burn_and_close::burn_and_close(
+                wallet_pk.into_inner(),
+                mints,
+                rent_destination,
+                None,
+            )
+            .await
+            .map(|_| ()),
+            BatchJobKind::GetTokenBalances { wallet } => {
+                get_token_balance::get_tokens_balances(wallet)
+                    .await
+                    .map(|_| ())
+            }
+        }
+    }
+}
+
+/// How a child job of a batch ended
+#[derive(Debug)]
+enum ChildOutcome {
+    Finished(Result<(), String>),
+    /// Dropped before finishing: cancelled, aborted or never started
+    Dropped,
+}
+
+/// Sends the outcome of a child job to its batch exactly once, and reports the
+/// child as dropped if the job goes away without finishing
+struct ChildReport {
+    index: usize,
+    done: Option<mpsc::UnboundedSender<(usize, ChildOutcome)>>,
+}
+
+impl ChildReport {
+    fn new(index: usize, done: mpsc::UnboundedSender<(usize, ChildOutcome)>) -> Self {
+        ChildReport {
+            index,
+            done: Some(done),
+        }
+    }
+
+    fn finished(mut self, result: Result<(), String>) {
+        if let Some(done) = self.done.take() {
+            let _ = done.send((self.index, ChildOutcome::Finished(result)));
+        }
+    }
+}
+
+impl Drop for ChildReport {
+    fn drop(&mut self) {
+        if let Some(done) = self.done.take() {
+            let _ = done.send((self.index, ChildOutcome::Dropped));
+        }
+    }
+}
+
+/// Set the final status of a child; a failure after the child published a
+/// cancelled event counts as a cancellation
+fn record_outcome(batch_id: &str, index: usize, outcome: ChildOutcome) -> Option<BatchChild> {
+    let batch = update_batch(batch_id, |batch| {
+        let child = &mut batch.children[index];
+        let cancelled = child
+            .job_id
+            .as_deref()
+            .is_some_and(|id| job_events().is_cancelled(id));
+        let (status, error) = match outcome {
+            ChildOutcome::Finished(Ok(())) => (BatchChildStatus::Completed, None),
+            ChildOutcome::Finished(Err(e)) if cancelled => (BatchChildStatus::Cancelled, Some(e)),
+            ChildOutcome::Finished(Err(e)) => (BatchChildStatus::Failed, Some(e)),
+            ChildOutcome::Dropped => (
+                BatchChildStatus::Cancelled,
+                Some("cancelled before finishing".to_string()),
+            ),
+        };
+        child.status = status;
+        child.error = error;
+    })?;
+    batch.children.into_iter().nth(index)
+}
+
+/// Track a new batch under its parent job id, unless already tracked
+fn register_batch(batch_id: &str, info: BatchInfo) {
+    BATCHES
+        .lock()
+        .entry(batch_id.to_string())
+        .or_insert_with(|| BatchInfo {
+            batch_id: batch_id.to_string(),
+            ..info
+        });
+}
+
+/// Apply `update` to a batch and recompute its counts
+fn update_batch(batch_id: &str, update: impl FnOnce(&mut BatchInfo)) -> Option<BatchInfo> {
+    let mut batches = BATCHES.lock();
+    let batch = batches.get_mut(batch_id)?;
+    update(batch);
+    let count = |status: BatchChildStatus| {
+        batch
+            .children
+            .iter()
+            .filter(|child| child.status == status)
+            .count()
+    };
+    batch.running_jobs = count(BatchChildStatus::Running);
+    batch.completed_jobs = count(BatchChildStatus::Completed);
+    batch.failed_jobs = count(BatchChildStatus::Failed);
+    batch.cancelled_jobs = count(BatchChildStatus::Cancelled);
+    let done = batch.completed_jobs + batch.failed_jobs + batch.cancelled_jobs;
+    batch.progress = done as f32 * 100.0 / batch.total_jobs.max(1) as f32;
+    Some(batch.clone())
+}
+
+pub fn get_batch(batch_id: &str) -> Option<BatchInfo> {
+    BATCHES.lock().get(batch_id).cloned()
+}
+
+/// All batches, newest first
+pub fn list_batches() -> Vec<BatchInfo> {
+    let mut batches: Vec<BatchInfo> = BATCHES.lock().values().cloned().collect();
+    batches.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_events::JobEventKind;
+
+    /// A registered batch of `kinds.len()` pending children
+    fn batch(kinds: &[&str]) -> String {
+        let batch_id = format!("job_{}", uuid::Uuid::new_v4());
+        register_batch(
+            &batch_id,
+            BatchInfo {
+                batch_id: String::new(),
+                name: "test".to_string(),
+                total_jobs: kinds.len(),
+                running_jobs: 0,
+                completed_jobs: 0,
+                failed_jobs: 0,
+                cancelled_jobs: 0,
+                progress: 0.0,
+                children: kinds
+                    .iter()
+                    .enumerate()
+                    .map(|(index, kind)| BatchChild {
+                        index,
+                        kind: kind.to_string(),
+                        job_id: None,
+                        status: BatchChildStatus::Pending,
+                        error: None,
+                    })
+                    .collect(),
+                started_at: 0,
+                finished_at: None,
+            },
+        );
+        batch_id
+    }
+
+    fn start(batch_id: &str, index: usize) -> String {
+        let child_id = format!("job_{}", uuid::Uuid::new_v4());
+        update_batch(batch_id, |batch| {
+            batch.children[index].job_id = Some(child_id.clone());
+            batch.children[index].status = BatchChildStatus::Running;
+        });
+        child_id
+    }
+
+    fn counts(batch: &BatchInfo) -> [usize; 4] {
+        [
+            batch.running_jobs,
+            batch.completed_jobs,
+            batch.failed_jobs,
+            batch.cancelled_jobs,
+        ]
+    }
+
+    #[test]
+    fn update_batch_recomputes_counts_and_progress() {
+        let batch_id = batch(&["quick_buy", "quick_buy", "quick_sell", "burn_tokens"]);
+        let info = get_batch(&batch_id).unwrap();
+        assert_eq!(info.batch_id, batch_id);
+        assert_eq!(counts(&info), [0, 0, 0, 0]);
+        assert_eq!(info.progress, 0.0);
+
+        let info = update_batch(&batch_id, |batch| {
+            for child in batch.children.iter_mut() {
+                child.status = BatchChildStatus::Running;
+            }
+        })
+        .unwrap();
+        assert_eq!(counts(&info), [4, 0, 0, 0]);
+        assert_eq!(info.progress, 0.0);
+
+        let info = update_batch(&batch_id, |batch| {
+            batch.children[0].status = BatchChildStatus::Completed;
+            batch.children[1].status = BatchChildStatus::Failed;
+            batch.children[2].status = BatchChildStatus::Cancelled;
+        })
+        .unwrap();
+        assert_eq!(counts(&info), [1, 1, 1, 1]);
+        assert_eq!(info.progress, 75.0);
+        assert_eq!(counts(&get_batch(&batch_id).unwrap()), [1, 1, 1, 1]);
+
+        assert!(update_batch("job_unknown", |_| {}).is_none());
+    }
+
+    #[test]
+    fn register_batch_keeps_the_first_registration() {
+        let batch_id = batch(&["quick_buy"]);
+        update_batch(&batch_id, |batch| {
+            batch.children[0].status = BatchChildStatus::Completed
+        });
+        register_batch(
+            &batch_id,
+            BatchInfo {
+                name: "again".to_string(),
+                ..get_batch(&batch_id).unwrap()
+            },
+        );
+        let info = get_batch(&batch_id).unwrap();
+        assert_eq!(info.name, "test");
+        assert_eq!(info.completed_jobs, 1);
+    }
+
+    #[test]
+    fn outcomes_set_child_status() {
+        let batch_id = batch(&["a", "b", "c", "d"]);
+        let ids: Vec<String> = (0..4).map(|i| start(&batch_id, i)).collect();
+        job_events().publish(&ids[2], JobEventKind::Cancelled);
+
+        let child = record_outcome(&batch_id, 0, ChildOutcome::Finished(Ok(()))).unwrap();
+        assert_eq!(child.status, BatchChildStatus::Completed);
+        assert_eq!(child.error, None);
+        let child =
+            record_outcome(&batch_id, 1, ChildOutcome::Finished(Err("boom".into()))).unwrap();
+        assert_eq!(child.status, BatchChildStatus::Failed);
+        assert_eq!(child.error.as_deref(), Some("boom"));
+        let child =
+            record_outcome(&batch_id, 2, ChildOutcome::Finished(Err("stop".into()))).unwrap();
+        assert_eq!(child.status, BatchChildStatus::Cancelled);
+        let child = record_outcome(&batch_id, 3, ChildOutcome::Dropped).unwrap();
+        assert_eq!(child.status, BatchChildStatus::Cancelled);
+
+        let info = get_batch(&batch_id).unwrap();
+        assert_eq!(counts(&info), [0, 1, 1, 2]);
+        assert_eq!(info.progress, 100.0);
+    }
+
+    #[test]
+    fn dropped_child_reports_itself_cancelled_once() {
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+
+        ChildReport::new(0, done_tx.clone()).finished(Ok(()));
+        drop(ChildReport::new(1, done_tx.clone()));
+        // A cancelled or aborted job drops its future, and the report with it
+        let report = ChildReport::new(2, done_tx);
+        let aborted = async move {
+            let _report = report;
+            std::future::pending::<()>().await;
+        };
+        drop(aborted);
 
-        Ok(job_ids)
+        let outcomes: Vec<_> = std::iter::from_fn(|| done_rx.try_recv().ok()).collect();
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(outcomes[0], (0, ChildOutcome::Finished(Ok(())))));
+        assert!(matches!(outcomes[1], (1, ChildOutcome::Dropped)));
+        assert!(matches!(outcomes[2], (2, ChildOutcome::Dropped)));
     }
 }
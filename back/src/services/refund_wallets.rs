@@ -1,27 +1,20 @@
 use crate::config::CONFIG;
+use crate::services::common::parse_keypair;
+use crate::solana::confirm::send_and_confirm;
+use crate::solana::rpc::SolanaRpc;
 use anyhow::Context;
 use log::info;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::system_instruction;
-use solana_sdk::transaction::Transaction;
-use solana_sdk::{bs58, signature::Keypair, signer::Signer};
+use solana_sdk::{signature::Keypair, signer::Signer};
 use std::str::FromStr;
 use tokio::task;
 
-// ============= HELPER FUNCTIONS =============
+/// Lamports left in each wallet to pay the transfer fee
+const MIN_RESERVE: u64 = 5_000;
 
-/// Parse a base58 private key string into a Keypair
-fn parse_keypair(private_key: &str) -> anyhow::Result<Keypair> {
-    let bytes = bs58::decode(private_key)
-        .into_vec()
-        .context("invalid base58 key")?;
-    if bytes.len() == 64 {
-        Keypair::try_from(&bytes[..]).context("invalid keypair bytes")
-    } else {
-        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
-    }
-}
+// ============= HELPER FUNCTIONS =============
 
 /// Parse multiple private keys into keypairs
 fn parse_keypairs(private_keys: &[String]) -> anyhow::Result<Vec<Keypair>> {
@@ -37,7 +30,7 @@ fn parse_keypairs(private_keys: &[String]) -> anyhow::Result<Vec<Keypair>> {
 
 /// Create and send a single SOL transfer transaction
 fn send_transfer(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     source_kp: &Keypair,
     dest_pubkey: &Pubkey,
     amount_lamports: u64,
@@ -47,32 +40,16 @@ fn send_transfer(
     // Create transfer instruction
     let instruction = system_instruction::transfer(&source_pubkey, dest_pubkey, amount_lamports);
 
-    // Get recent blockhash
-    let recent_blockhash = client
-        .get_latest_blockhash()
-        .context("failed to fetch blockhash")?;
-
-    // Create signer references
-    let signer_refs: Vec<&dyn Signer> = vec![source_kp as &dyn Signer];
-
-    // Create and send transaction
-    let tx = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&source_pubkey),
-        &signer_refs,
-        recent_blockhash,
-    );
-
-    let sig = client
-        .send_and_confirm_transaction(&tx)
-        .context("send_and_confirm_transaction failed")?;
+    // Send and wait for the transfer to land
+    let sig = send_and_confirm(client, &[instruction], &source_pubkey, &[source_kp])
+        .context("transfer failed")?;
 
     Ok(sig.to_string())
 }
 
 /// Check if wallet has sufficient balance for a transfer
 fn check_balance_sufficient(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     wallet_pubkey: &Pubkey,
     required_amount: u64,
     min_reserve: u64,
@@ -87,7 +64,7 @@ fn check_balance_sufficient(
 
 /// Get transferable balance (total - reserve)
 fn get_transferable_balance(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     wallet_pubkey: &Pubkey,
     min_reserve: u64,
 ) -> anyhow::Result<Option<u64>> {
@@ -107,6 +84,140 @@ fn get_transferable_balance(
     }
 }
 
+/// Send everything but `MIN_RESERVE` of each wallet to `refund_to`, one transfer
+/// per wallet; wallets at or below the reserve and failed transfers are skipped
+fn refund_all(
+    client: &dyn SolanaRpc,
+    pks: &[String],
+    refund_to: &str,
+    job_id: Option<String>,
+) -> anyhow::Result<Vec<String>> {
+    let dest_pubkey = Pubkey::from_str(refund_to).context("invalid refund destination pubkey")?;
+    info!("Refunding to: {}", dest_pubkey);
+
+    // Parse all keypairs using helper function
+    let keypairs = parse_keypairs(pks)?;
+
+    info!("Total transfers to process: {}", keypairs.len());
+
+    // Process transfers ONE BY ONE (no batching)
+    let mut transaction_signatures = Vec::new();
+    let total_wallets = keypairs.len() as u32;
+
+    // Update job progress if job_id provided
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(
+            &job_id,
+            0,
+            total_wallets,
+            "Starting refund process".to_string(),
+        );
+    }
+
+    for (transfer_idx, kp) in keypairs.iter().enumerate() {
+        let pubkey = kp.pubkey();
+        let current_idx = transfer_idx as u32 + 1;
+
+        // Update progress if job_id provided
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                &job_id,
+                current_idx - 1,
+                total_wallets,
+                format!(
+                    "Processing wallet {} of {} ({})...",
+                    current_idx, total_wallets, pubkey
+                ),
+            );
+        }
+
+        // Get transferable balance using helper function
+        match get_transferable_balance(client, &pubkey, MIN_RESERVE)? {
+            None => {
+                info!(
+                    "Wallet {} has insufficient transferable balance, skipping",
+                    pubkey
+                );
+
+                // Update progress for skipped wallets if job_id provided
+                if let Some(ref job_id) = job_id {
+                    let _ = crate::update_job_progress(
+                        &job_id,
+                        current_idx,
+                        total_wallets,
+                        format!(
+                            "Skipped wallet {} of {} (insufficient balance)",
+                            current_idx, total_wallets
+                        ),
+                    );
+                }
+                continue;
+            }
+            Some(amount) => {
+                info!(
+                    "Processing transfer {} of {}: {} will transfer {} lamports",
+                    transfer_idx + 1,
+                    keypairs.len(),
+                    pubkey,
+                    amount
+                );
+
+                // Send transfer using helper function
+                match send_transfer(client, kp, &dest_pubkey, amount) {
+                    Ok(sig) => {
+                        info!("Transfer {} completed with signature: {}", current_idx, sig);
+                        let sig_clone = sig.clone();
+                        transaction_signatures.push(sig);
+
+                        // Update progress after successful transfer if job_id provided
+                        if let Some(ref job_id) = job_id {
+                            let _ = crate::update_job_progress(
+                                &job_id,
+                                current_idx,
+                                total_wallets,
+                                format!(
+                                    "Completed transfer {} of {} ({})",
+                                    current_idx, total_wallets, sig_clone
+                                ),
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        info!(
+                            "Transfer {} failed for wallet {}: {}",
+                            current_idx, pubkey, e
+                        );
+
+                        // Update progress even for failed transfers if job_id provided
+                        if let Some(ref job_id) = job_id {
+                            let _ = crate::update_job_progress(
+                                &job_id,
+                                current_idx,
+                                total_wallets,
+                                format!(
+                                    "Failed transfer {} of {} ({}): {}",
+                                    current_idx, total_wallets, pubkey, e
+                                ),
+                            );
+                        }
+                        // Continue with next transfer instead of failing completely
+                        continue;
+                    }
+                }
+
+                // Small delay between individual transfers
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+        }
+    }
+
+    info!(
+        "All transfers completed. Total successful transactions: {}",
+        transaction_signatures.len()
+    );
+    Ok(transaction_signatures)
+}
+
 // ============= PUBLIC FUNCTIONS =============
 
 pub async fn refunds_to(
@@ -116,7 +227,6 @@ pub async fn refunds_to(
     job_id: Option<String>,
 ) -> Result<String, String> {
     const MAX_PRIVATE_KEYS: usize = 200;
-    const MIN_RESERVE: u64 = 5_000;
 
     if pks.len() > MAX_PRIVATE_KEYS {
         return Err(format!(
@@ -136,134 +246,13 @@ pub async fn refunds_to(
     let pks_clone = pks.clone();
     let refund_to_clone = refund_to.clone();
 
-    let res = task::spawn_blocking(move || -> anyhow::Result<Vec<String>> {
-        let client = RpcClient::new(rpc_url);
-
-        let dest_pubkey =
-            Pubkey::from_str(&refund_to_clone).context("invalid refund destination pubkey")?;
-        info!("Refunding to: {}", dest_pubkey);
-
-        // Parse all keypairs using helper function
-        let keypairs = parse_keypairs(&pks_clone)?;
-
-        info!("Total transfers to process: {}", keypairs.len());
-
-        // Process transfers ONE BY ONE (no batching)
-        let mut transaction_signatures = Vec::new();
-        let total_wallets = keypairs.len() as u32;
-
-        // Update job progress if job_id provided
-        if let Some(ref job_id) = job_id {
-            let _ = crate::update_job_progress(
-                &job_id,
-                0,
-                total_wallets,
-                "Starting refund process".to_string(),
-            );
-        }
-
-        for (transfer_idx, kp) in keypairs.iter().enumerate() {
-            let pubkey = kp.pubkey();
-            let current_idx = transfer_idx as u32 + 1;
-
-            // Update progress if job_id provided
-            if let Some(ref job_id) = job_id {
-                let _ = crate::update_job_progress(
-                    &job_id,
-                    current_idx - 1,
-                    total_wallets,
-                    format!(
-                        "Processing wallet {} of {} ({})...",
-                        current_idx, total_wallets, pubkey
-                    ),
-                );
-            }
-
-            // Get transferable balance using helper function
-            match get_transferable_balance(&client, &pubkey, MIN_RESERVE)? {
-                None => {
-                    info!(
-                        "Wallet {} has insufficient transferable balance, skipping",
-                        pubkey
-                    );
-
-                    // Update progress for skipped wallets if job_id provided
-                    if let Some(ref job_id) = job_id {
-                        let _ = crate::update_job_progress(
-                            &job_id,
-                            current_idx,
-                            total_wallets,
-                            format!(
-                                "Skipped wallet {} of {} (insufficient balance)",
-                                current_idx, total_wallets
-                            ),
-                        );
-                    }
-                    continue;
-                }
-                Some(amount) => {
-                    info!(
-                        "Processing transfer {} of {}: {} will transfer {} lamports",
-                        transfer_idx + 1,
-                        keypairs.len(),
-                        pubkey,
-                        amount
-                    );
-
-                    // Send transfer using helper function
-                    match send_transfer(&client, kp, &dest_pubkey, amount) {
-                        Ok(sig) => {
-                            info!("Transfer {} completed with signature: {}", current_idx, sig);
-                            let sig_clone = sig.clone();
-                            transaction_signatures.push(sig);
-
-                            // Update progress after successful transfer if job_id provided
-                            if let Some(ref job_id) = job_id {
-                                let _ = crate::update_job_progress(
-                                    &job_id,
-                                    current_idx,
-                                    total_wallets,
-                                    format!(
-                                        "Completed transfer {} of {} ({})",
-                                        current_idx, total_wallets, sig_clone
-                                    ),
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            info!(
-                                "Transfer {} failed for wallet {}: {}",
-                                current_idx, pubkey, e
-                            );
-
-                            // Update progress even for failed transfers if job_id provided
-                            if let Some(ref job_id) = job_id {
-                                let _ = crate::update_job_progress(
-                                    &job_id,
-                                    current_idx,
-                                    total_wallets,
-                                    format!(
-                                        "Failed transfer {} of {} ({}): {}",
-                                        current_idx, total_wallets, pubkey, e
-                                    ),
-                                );
-                            }
-                            // Continue with next transfer instead of failing completely
-                            continue;
-                        }
-                    }
-
-                    // Small delay between individual transfers
-                    std::thread::sleep(std::time::Duration::from_millis(200));
-                }
-            }
-        }
-
-        info!(
-            "All transfers completed. Total successful transactions: {}",
-            transaction_signatures.len()
-        );
-        Ok(transaction_signatures)
+    let res = task::spawn_blocking(move || {
+        refund_all(
+            &RpcClient::new(rpc_url),
+            &pks_clone,
+            &refund_to_clone,
+            job_id,
+        )
     })
     .await
     .map_err(|e| format!("join error: {}", e))
@@ -277,6 +266,52 @@ pub async fn refunds_to(
     ))
 }
 
+/// Send `amount_lamports` from the source wallet to `refund_to`, keeping the
+/// reserve and fee in the source
+fn refund_amount(
+    client: &dyn SolanaRpc,
+    source_pk: &str,
+    refund_to: &str,
+    amount_lamports: u64,
+) -> anyhow::Result<String> {
+    // Parse destination pubkey
+    let dest_pubkey = Pubkey::from_str(refund_to).context("invalid refund destination pubkey")?;
+    info!("Refunding to: {}", dest_pubkey);
+
+    // Parse source keypair using helper function
+    let source_kp = parse_keypair(source_pk)?;
+    let source_pubkey = source_kp.pubkey();
+    info!("Source wallet: {}", source_pubkey);
+
+    // Check balance using helper function
+    if !check_balance_sufficient(client, &source_pubkey, amount_lamports, MIN_RESERVE)? {
+        let balance = client.get_balance(&source_pubkey)?;
+        let required_balance = amount_lamports + MIN_RESERVE + 5_000;
+        anyhow::bail!(
+            "Insufficient balance in source wallet. Required: {} lamports ({} SOL), Available: {} lamports ({} SOL)",
+            required_balance,
+            required_balance as f64 / 1_000_000_000.0,
+            balance,
+            balance as f64 / 1_000_000_000.0
+        );
+    }
+
+    info!(
+        "Transferring {} lamports ({} SOL) from {} to {}",
+        amount_lamports,
+        amount_lamports as f64 / 1_000_000_000.0,
+        source_pubkey,
+        dest_pubkey
+    );
+
+    // Send transfer using helper function
+    let sig = send_transfer(client, &source_kp, &dest_pubkey, amount_lamports)?;
+
+    info!("Transfer completed successfully with signature: {}", sig);
+
+    Ok(sig)
+}
+
 pub async fn refund_amount_to(
     source_pk: String,
     refund_to: String,
@@ -289,7 +324,6 @@ pub async fn refund_amount_to(
     }
 
     let amount_lamports = (amount_sol * 1_000_000_000.0) as u64;
-    const MIN_RESERVE: u64 = 5_000;
 
     info!(
         "Starting refund_specific_amount: {} SOL ({} lamports) from source to {}",
@@ -300,56 +334,182 @@ pub async fn refund_amount_to(
     let source_pk_clone = source_pk.clone();
     let refund_to_clone = refund_to.clone();
 
-    let res = task::spawn_blocking(move || -> anyhow::Result<String> {
-        let client = RpcClient::new(rpc_url);
+    let res = task::spawn_blocking(move || {
+        refund_amount(
+            &RpcClient::new(rpc_url),
+            &source_pk_clone,
+            &refund_to_clone,
+            amount_lamports,
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+
+    // Return transaction signature
+    Ok(format!(
+        "Successfully transferred {} SOL. Transaction signature: {}",
+        amount_sol, res
+    ))
+}
+
+/// Send `amount_lamports` from each wallet to `refund_to`, one transfer per
+/// wallet; wallets that cannot cover it and failed transfers are skipped
+fn refund_amount_all(
+    client: &dyn SolanaRpc,
+    pks: &[String],
+    refund_to: &str,
+    amount_lamports: u64,
+    job_id: Option<String>,
+) -> anyhow::Result<Vec<String>> {
+    let dest_pubkey = Pubkey::from_str(refund_to).context("invalid refund destination pubkey")?;
+    info!("Refunding to: {}", dest_pubkey);
+
+    // Parse all keypairs using helper function
+    let keypairs = parse_keypairs(pks)?;
+
+    info!("Total wallets to process: {}", keypairs.len());
+
+    // Update job progress if job_id provided
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(
+            &job_id,
+            0,
+            keypairs.len() as u32,
+            "Starting specific amount refund process".to_string(),
+        );
+    }
+
+    // Process transfers ONE BY ONE (no batching)
+    let mut transaction_signatures = Vec::new();
+    let mut successful_transfers = 0;
+    let mut failed_transfers = 0;
 
-        // Parse destination pubkey
-        let dest_pubkey =
-            Pubkey::from_str(&refund_to_clone).context("invalid refund destination pubkey")?;
-        info!("Refunding to: {}", dest_pubkey);
+    for (transfer_idx, kp) in keypairs.iter().enumerate() {
+        let pubkey = kp.pubkey();
+        let current_idx = transfer_idx as u32 + 1;
 
-        // Parse source keypair using helper function
-        let source_kp = parse_keypair(&source_pk_clone)?;
-        let source_pubkey = source_kp.pubkey();
-        info!("Source wallet: {}", source_pubkey);
+        // Update progress if job_id provided
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                &job_id,
+                current_idx - 1,
+                keypairs.len() as u32,
+                format!(
+                    "Processing wallet {} of {} ({})...",
+                    current_idx,
+                    keypairs.len(),
+                    pubkey
+                ),
+            );
+        }
 
         // Check balance using helper function
-        if !check_balance_sufficient(&client, &source_pubkey, amount_lamports, MIN_RESERVE)? {
-            let balance = client.get_balance(&source_pubkey)?;
+        if !check_balance_sufficient(client, &pubkey, amount_lamports, MIN_RESERVE)? {
+            let balance = client.get_balance(&pubkey)?;
             let required_balance = amount_lamports + MIN_RESERVE + 5_000;
-            anyhow::bail!(
-                "Insufficient balance in source wallet. Required: {} lamports ({} SOL), Available: {} lamports ({} SOL)",
+            info!(
+                "Wallet {} has insufficient balance. Required: {} lamports ({} SOL), Available: {} lamports ({} SOL), skipping",
+                pubkey,
                 required_balance,
                 required_balance as f64 / 1_000_000_000.0,
                 balance,
                 balance as f64 / 1_000_000_000.0
             );
+            failed_transfers += 1;
+
+            // Update progress for skipped wallets if job_id provided
+            if let Some(ref job_id) = job_id {
+                let _ = crate::update_job_progress(
+                    &job_id,
+                    current_idx,
+                    keypairs.len() as u32,
+                    format!(
+                        "Skipped wallet {} of {} (insufficient balance)",
+                        current_idx,
+                        keypairs.len()
+                    ),
+                );
+            }
+            continue;
         }
 
         info!(
-            "Transferring {} lamports ({} SOL) from {} to {}",
-            amount_lamports, amount_sol, source_pubkey, dest_pubkey
+            "Processing transfer {} of {}: {} will transfer {} lamports ({} SOL)",
+            transfer_idx + 1,
+            keypairs.len(),
+            pubkey,
+            amount_lamports,
+            amount_lamports as f64 / 1_000_000_000.0
         );
 
         // Send transfer using helper function
-        let sig = send_transfer(&client, &source_kp, &dest_pubkey, amount_lamports)?;
+        match send_transfer(client, kp, &dest_pubkey, amount_lamports) {
+            Ok(sig) => {
+                info!(
+                    "Transfer {} completed with signature: {}",
+                    transfer_idx + 1,
+                    sig
+                );
+                let sig_clone = sig.clone();
+                transaction_signatures.push(sig);
+                successful_transfers += 1;
 
-        info!(
-            "Transfer completed successfully with signature: {}",
-            sig
-        );
+                // Update progress after successful transfer if job_id provided
+                if let Some(ref job_id) = job_id {
+                    let _ = crate::update_job_progress(
+                        &job_id,
+                        current_idx,
+                        keypairs.len() as u32,
+                        format!(
+                            "Completed transfer {} of {} ({})",
+                            current_idx,
+                            keypairs.len(),
+                            sig_clone
+                        ),
+                    );
+                }
+            }
+            Err(e) => {
+                info!(
+                    "Transfer {} failed for wallet {}: {}",
+                    transfer_idx + 1,
+                    pubkey,
+                    e
+                );
+                failed_transfers += 1;
 
-        Ok(sig)
-    })
-    .await
-    .map_err(|e| format!("join error: {}", e))
-    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+                // Update progress even for failed transfers if job_id provided
+                if let Some(ref job_id) = job_id {
+                    let _ = crate::update_job_progress(
+                        &job_id,
+                        current_idx,
+                        keypairs.len() as u32,
+                        format!(
+                            "Failed transfer {} of {} ({}): {}",
+                            current_idx,
+                            keypairs.len(),
+                            pubkey,
+                            e
+                        ),
+                    );
+                }
+                // Continue with next transfer instead of failing completely
+                continue;
+            }
+        }
 
-    // Return transaction signature
-    Ok(format!(
-        "Successfully transferred {} SOL. Transaction signature: {}",
-        amount_sol, res
-    ))
+        // Small delay between individual transfers
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+
+    info!(
+        "All specific amount transfers completed. Successful: {}, Failed: {}, Total signatures: {}",
+        successful_transfers,
+        failed_transfers,
+        transaction_signatures.len()
+    );
+    Ok(transaction_signatures)
 }
 
 pub async fn refunds_amount_to(
@@ -360,7 +520,6 @@ pub async fn refunds_amount_to(
 ) -> Result<String, String> {
     // Security check: limit maximum number of private keys
     const MAX_PRIVATE_KEYS: usize = 200;
-    const MIN_RESERVE: u64 = 5_000;
 
     if pks.len() > MAX_PRIVATE_KEYS {
         return Err(format!(
@@ -388,141 +547,123 @@ pub async fn refunds_amount_to(
     let pks_clone = pks.clone();
     let refund_to_clone = refund_to.clone();
 
-    let res = task::spawn_blocking(move || -> anyhow::Result<Vec<String>> {
-        let client = RpcClient::new(rpc_url);
-
-        let dest_pubkey =
-            Pubkey::from_str(&refund_to_clone).context("invalid refund destination pubkey")?;
-        info!("Refunding to: {}", dest_pubkey);
-
-        // Parse all keypairs using helper function
-        let keypairs = parse_keypairs(&pks_clone)?;
+    let res = task::spawn_blocking(move || {
+        refund_amount_all(
+            &RpcClient::new(rpc_url),
+            &pks_clone,
+            &refund_to_clone,
+            amount_lamports,
+            job_id,
+        )
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
 
-        info!("Total wallets to process: {}", keypairs.len());
+    // Return summary of all transaction signatures
+    Ok(format!(
+        "Completed {} transfers of {} SOL each. Transactions: {}",
+        res.len(),
+        amount_sol,
+        res.join(", ")
+    ))
+}
 
-        // Update job progress if job_id provided
-        if let Some(ref job_id) = job_id {
-            let _ = crate::update_job_progress(&job_id, 0, keypairs.len() as u32, "Starting specific amount refund process".to_string());
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{system_transfers, FakeRpc, RpcMethod};
 
-        // Process transfers ONE BY ONE (no batching)
-        let mut transaction_signatures = Vec::new();
-        let mut successful_transfers = 0;
-        let mut failed_transfers = 0;
+    fn wallet(rpc: &FakeRpc, lamports: u64) -> (Pubkey, String) {
+        let keypair = Keypair::new();
+        rpc.set_balance(&keypair.pubkey(), lamports);
+        (keypair.pubkey(), keypair.to_base58_string())
+    }
 
-        for (transfer_idx, kp) in keypairs.iter().enumerate() {
-            let pubkey = kp.pubkey();
-            let current_idx = transfer_idx as u32 + 1;
+    #[test]
+    fn refund_all_skips_reserve_only_wallets_and_failed_sends() {
+        let rpc = FakeRpc::new();
+        let destination = Pubkey::new_unique();
+        let (_, failing) = wallet(&rpc, 1_000_000_000);
+        let (_, reserve_only) = wallet(&rpc, MIN_RESERVE);
+        let (funded, funded_pk) = wallet(&rpc, 1_000_000_000);
+        rpc.fail_next(RpcMethod::SendTransaction, "insufficient funds for fee");
+
+        let signatures = refund_all(
+            &rpc,
+            &[failing, reserve_only, funded_pk],
+            &destination.to_string(),
+            None,
+        )
+        .unwrap();
+
+        let sent = rpc.sent();
+        // The failed send was attempted, the reserve-only wallet never was
+        assert_eq!(sent.len(), 2);
+        assert_eq!(signatures, vec![sent[1].signatures[0].to_string()]);
+        assert_eq!(
+            system_transfers(&sent[1]),
+            vec![(funded, destination, 1_000_000_000 - MIN_RESERVE)]
+        );
+    }
 
-            // Update progress if job_id provided
-            if let Some(ref job_id) = job_id {
-                let _ = crate::update_job_progress(
-                    &job_id,
-                    current_idx - 1,
-                    keypairs.len() as u32,
-                    format!("Processing wallet {} of {} ({})...", current_idx, keypairs.len(), pubkey)
-                );
-            }
+    #[test]
+    fn refund_all_rejects_bad_destination_before_any_rpc_call() {
+        let rpc = FakeRpc::new();
+        let (_, pk) = wallet(&rpc, 1_000_000_000);
 
-            // Check balance using helper function
-            if !check_balance_sufficient(&client, &pubkey, amount_lamports, MIN_RESERVE)? {
-                let balance = client.get_balance(&pubkey)?;
-                let required_balance = amount_lamports + MIN_RESERVE + 5_000;
-                info!(
-                    "Wallet {} has insufficient balance. Required: {} lamports ({} SOL), Available: {} lamports ({} SOL), skipping",
-                    pubkey,
-                    required_balance,
-                    required_balance as f64 / 1_000_000_000.0,
-                    balance,
-                    balance as f64 / 1_000_000_000.0
-                );
-                failed_transfers += 1;
+        assert!(refund_all(&rpc, &[pk], "not-a-pubkey", None).is_err());
+        assert_eq!(rpc.calls(RpcMethod::GetBalance), 0);
+        assert!(rpc.sent().is_empty());
+    }
 
-                // Update progress for skipped wallets if job_id provided
-                if let Some(ref job_id) = job_id {
-                    let _ = crate::update_job_progress(
-                        &job_id,
-                        current_idx,
-                        keypairs.len() as u32,
-                        format!("Skipped wallet {} of {} (insufficient balance)", current_idx, keypairs.len())
-                    );
-                }
-                continue;
-            }
+    #[test]
+    fn refund_amount_all_skips_wallets_that_cannot_cover_amount_and_fees() {
+        let rpc = FakeRpc::new();
+        let destination = Pubkey::new_unique();
+        let amount = 250_000_000;
+        let (funded, funded_pk) = wallet(&rpc, 1_000_000_000);
+        // One lamport short of amount + reserve + fee
+        let (_, short_pk) = wallet(&rpc, amount + MIN_RESERVE + 5_000 - 1);
+
+        let signatures = refund_amount_all(
+            &rpc,
+            &[funded_pk, short_pk],
+            &destination.to_string(),
+            amount,
+            None,
+        )
+        .unwrap();
+
+        let sent = rpc.sent();
+        assert_eq!(signatures.len(), 1);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            system_transfers(&sent[0]),
+            vec![(funded, destination, amount)]
+        );
+    }
 
-            info!(
-                "Processing transfer {} of {}: {} will transfer {} lamports ({} SOL)",
-                transfer_idx + 1,
-                keypairs.len(),
-                pubkey,
-                amount_lamports,
-                amount_sol
-            );
+    #[test]
+    fn refund_amount_fails_on_insufficient_balance() {
+        let rpc = FakeRpc::new();
+        let (_, pk) = wallet(&rpc, 100_000);
 
-            // Send transfer using helper function
-            match send_transfer(&client, kp, &dest_pubkey, amount_lamports) {
-                Ok(sig) => {
-                    info!(
-                        "Transfer {} completed with signature: {}",
-                        transfer_idx + 1,
-                        sig
-                    );
-                    let sig_clone = sig.clone();
-                    transaction_signatures.push(sig);
-                    successful_transfers += 1;
-
-                    // Update progress after successful transfer if job_id provided
-                    if let Some(ref job_id) = job_id {
-                        let _ = crate::update_job_progress(
-                            &job_id,
-                            current_idx,
-                            keypairs.len() as u32,
-                            format!("Completed transfer {} of {} ({})", current_idx, keypairs.len(), sig_clone)
-                        );
-                    }
-                }
-                Err(e) => {
-                    info!(
-                        "Transfer {} failed for wallet {}: {}",
-                        transfer_idx + 1,
-                        pubkey,
-                        e
-                    );
-                    failed_transfers += 1;
+        let err = refund_amount(&rpc, &pk, &Pubkey::new_unique().to_string(), 100_000).unwrap_err();
 
-                    // Update progress even for failed transfers if job_id provided
-                    if let Some(ref job_id) = job_id {
-                        let _ = crate::update_job_progress(
-                            &job_id,
-                            current_idx,
-                            keypairs.len() as u32,
-                            format!("Failed transfer {} of {} ({}): {}", current_idx, keypairs.len(), pubkey, e)
-                        );
-                    }
-                    // Continue with next transfer instead of failing completely
-                    continue;
-                }
-            }
+        assert!(err.to_string().contains("Insufficient balance"));
+        assert!(rpc.sent().is_empty());
+    }
 
-            // Small delay between individual transfers
-            std::thread::sleep(std::time::Duration::from_millis(200));
-        }
+    #[test]
+    fn refund_amount_surfaces_send_failure() {
+        let rpc = FakeRpc::new();
+        let (_, pk) = wallet(&rpc, 1_000_000_000);
+        rpc.fail_next(RpcMethod::GetLatestBlockhash, "node is behind");
 
-        info!(
-            "All specific amount transfers completed. Successful: {}, Failed: {}, Total signatures: {}",
-            successful_transfers, failed_transfers, transaction_signatures.len()
-        );
-        Ok(transaction_signatures)
-    })
-    .await
-    .map_err(|e| format!("join error: {}", e))
-    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+        let err = refund_amount(&rpc, &pk, &Pubkey::new_unique().to_string(), 1_000).unwrap_err();
 
-    // Return summary of all transaction signatures
-    Ok(format!(
-        "Completed {} transfers of {} SOL each. Transactions: {}",
-        res.len(),
-        amount_sol,
-        res.join(", ")
-    ))
+        assert!(format!("{:#}", err).contains("node is behind"));
+        assert!(rpc.sent().is_empty());
+    }
 }
@@ -0,0 +1,240 @@
+use crate::services::cleanup::scan_group_token_accounts;
+use crate::services::common::lamports_to_sol;
+use crate::solana::token_account::OwnedTokenAccount;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+const MAX_WALLETS: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RentBreakdown {
+    pub accounts: usize,
+    pub lamports: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletRentReport {
+    pub wallet: String,
+    pub token_accounts: usize,
+    pub total_rent_lamports: u64,
+    pub empty: RentBreakdown,
+    pub non_empty: RentBreakdown,
+    /// Empty accounts that can be closed right away (not frozen, no withheld fees)
+    pub reclaimable_now: RentBreakdown,
+    pub reclaimable_now_sol: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupRentReport {
+    pub wallets: Vec<WalletRentReport>,
+    pub token_accounts: usize,
+    pub total_rent_lamports: u64,
+    pub total_rent_sol: f64,
+    pub empty: RentBreakdown,
+    pub non_empty: RentBreakdown,
+    pub reclaimable_now: RentBreakdown,
+    pub reclaimable_now_sol: f64,
+    pub taken_at: i64,
+}
+
+impl RentBreakdown {
+    fn add(&mut self, lamports: u64) {
+        self.accounts += 1;
+        self.lamports += lamports;
+    }
+
+    fn merge(&mut self, other: &RentBreakdown) {
+        self.accounts += other.accounts;
+        self.lamports += other.lamports;
+    }
+}
+
+fn build_wallet_report(wallet: String, accounts: &[OwnedTokenAccount]) -> WalletRentReport {
+    let mut report = WalletRentReport {
+        wallet,
+        token_accounts: accounts.len(),
+        total_rent_lamports: 0,
+        empty: RentBreakdown::default(),
+        non_empty: RentBreakdown::default(),
+        reclaimable_now: RentBreakdown::default(),
+        reclaimable_now_sol: 0.0,
+        error: None,
+    };
+
+    for owned in accounts {
+        report.total_rent_lamports += owned.lamports;
+        if owned.account.is_empty() {
+            report.empty.add(owned.lamports);
+        } else {
+            report.non_empty.add(owned.lamports);
+        }
+        if owned.account.close_blockers(false).is_empty() {
+            report.reclaimable_now.add(owned.lamports);
+        }
+    }
+    report.reclaimable_now_sol = lamports_to_sol(report.reclaimable_now.lamports);
+    report
+}
+
+/// Sum the wallets' rent; a wallet whose accounts could not be read counts for
+/// nothing and carries its error
+fn group_report(
+    wallets: Vec<String>,
+    scanned: Vec<anyhow::Result<Vec<OwnedTokenAccount>>>,
+) -> GroupRentReport {
+    let mut report = GroupRentReport {
+        wallets: Vec::with_capacity(wallets.len()),
+        token_accounts: 0,
+        total_rent_lamports: 0,
+        total_rent_sol: 0.0,
+        empty: RentBreakdown::default(),
+        non_empty: RentBreakdown::default(),
+        reclaimable_now: RentBreakdown::default(),
+        reclaimable_now_sol: 0.0,
+        taken_at: chrono::Utc::now().timestamp(),
+    };
+
+    for (wallet, accounts) in wallets.into_iter().zip(scanned) {
+        let wallet_report = match accounts {
+            Ok(accounts) => build_wallet_report(wallet, &accounts),
+            Err(e) => {
+                warn!("Failed to fetch token accounts for {}: {:#}", wallet, e);
+                WalletRentReport {
+                    error: Some(format!("{:#}", e)),
+                    ..build_wallet_report(wallet, &[])
+                }
+            }
+        };
+        report.token_accounts += wallet_report.token_accounts;
+        report.total_rent_lamports += wallet_report.total_rent_lamports;
+        report.empty.merge(&wallet_report.empty);
+        report.non_empty.merge(&wallet_report.non_empty);
+        report.reclaimable_now.merge(&wallet_report.reclaimable_now);
+        report.wallets.push(wallet_report);
+    }
+    report.total_rent_sol = lamports_to_sol(report.total_rent_lamports);
+    report.reclaimable_now_sol = lamports_to_sol(report.reclaimable_now.lamports);
+    report
+}
+
+/// Read-only report of the SOL locked in token account rent across a group
+pub async fn get_rent_report(
+    wallets: Vec<String>,
+    max_concurrency: Option<usize>,
+) -> Result<GroupRentReport, String> {
+    if wallets.is_empty() {
+        return Err("No wallets provided".to_string());
+    }
+    if wallets.len() > MAX_WALLETS {
+        return Err(format!(
+            "Too many wallets provided: {} (max: {})",
+            wallets.len(),
+            MAX_WALLETS
+        ));
+    }
+
+    let pubkeys = wallets
+        .iter()
+        .map(|w| Pubkey::from_str(w).map_err(|e| format!("invalid wallet pubkey {}: {}", w, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Building rent report for {} wallets", pubkeys.len());
+    let scanned =
+        scan_group_token_accounts(&pubkeys, max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY))
+            .await?;
+
+    let report = group_report(wallets, scanned);
+
+    info!(
+        "Rent report: {} token accounts hold {} SOL, {} SOL reclaimable now",
+        report.token_accounts, report.total_rent_sol, report.reclaimable_now_sol
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, FakeTokenAccount, RpcMethod};
+    use crate::solana::token_account::fetch_owned_token_accounts;
+
+    const RENT: u64 = 2_039_280;
+
+    fn scan(rpc: &FakeRpc, wallets: &[Pubkey]) -> Vec<anyhow::Result<Vec<OwnedTokenAccount>>> {
+        wallets
+            .iter()
+            .map(|wallet| fetch_owned_token_accounts(rpc, wallet))
+            .collect()
+    }
+
+    #[test]
+    fn rent_is_split_by_emptiness_and_closability() {
+        let rpc = FakeRpc::new();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.add_token_account(FakeTokenAccount::new(first, Pubkey::new_unique(), 0));
+        rpc.add_token_account(FakeTokenAccount::new(first, Pubkey::new_unique(), 500));
+        rpc.add_token_account(FakeTokenAccount {
+            frozen: true,
+            ..FakeTokenAccount::new(second, Pubkey::new_unique(), 0)
+        });
+        rpc.add_token_account(FakeTokenAccount {
+            lamports: 3_000_000,
+            ..FakeTokenAccount::new(second, Pubkey::new_unique(), 0)
+        });
+
+        let wallets = [first, second];
+        let report = group_report(
+            wallets.iter().map(Pubkey::to_string).collect(),
+            scan(&rpc, &wallets),
+        );
+
+        let first_report = &report.wallets[0];
+        assert_eq!(first_report.token_accounts, 2);
+        assert_eq!(first_report.empty.lamports, RENT);
+        assert_eq!(first_report.non_empty.lamports, RENT);
+        assert_eq!(first_report.reclaimable_now.accounts, 1);
+
+        // Frozen accounts stay locked until thawed
+        let second_report = &report.wallets[1];
+        assert_eq!(second_report.empty.accounts, 2);
+        assert_eq!(second_report.reclaimable_now.lamports, 3_000_000);
+        assert_eq!(second_report.error, None);
+
+        assert_eq!(report.token_accounts, 4);
+        assert_eq!(report.total_rent_lamports, 3 * RENT + 3_000_000);
+        assert_eq!(report.empty.accounts, 3);
+        assert_eq!(report.non_empty.accounts, 1);
+        assert_eq!(report.reclaimable_now.lamports, RENT + 3_000_000);
+        assert_eq!(report.total_rent_sol, lamports_to_sol(3 * RENT + 3_000_000));
+        assert_eq!(
+            report.reclaimable_now_sol,
+            lamports_to_sol(RENT + 3_000_000)
+        );
+    }
+
+    #[test]
+    fn unreadable_wallet_carries_its_error() {
+        let rpc = FakeRpc::new();
+        let (failed, read) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.add_token_account(FakeTokenAccount::new(failed, Pubkey::new_unique(), 0));
+        rpc.add_token_account(FakeTokenAccount::new(read, Pubkey::new_unique(), 0));
+        rpc.fail_next(RpcMethod::GetTokenAccountsByOwner, "node unavailable");
+
+        let wallets = [failed, read];
+        let report = group_report(
+            wallets.iter().map(Pubkey::to_string).collect(),
+            scan(&rpc, &wallets),
+        );
+
+        let error = report.wallets[0].error.as_deref().unwrap();
+        assert!(error.contains("node unavailable"), "{}", error);
+        assert_eq!(report.wallets[0].token_accounts, 0);
+        assert_eq!(report.wallets[1].token_accounts, 1);
+        assert_eq!(report.token_accounts, 1);
+        assert_eq!(report.total_rent_lamports, RENT);
+    }
+}
@@ -8,7 +8,9 @@ use crate::{
 use chrono::{self, TimeDelta};
 use log::info;
 use pumpfun::common::types::{Cluster, PriorityFee, RpcEndpoint};
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
 use std::{sync::Arc, time::Duration};
 
 pub async fn sniper_buy(req: CreateTokenReq, mint_keypair: Pubkey) -> Result<(), String> {
@@ -27,9 +29,9 @@ pub async fn sniper_buy(req: CreateTokenReq, mint_keypair: Pubkey) -> Result<(),
     };
 
     info!("Using cluster: {:?}", cluster);
-    let main_wallet: Arc<Keypair> = Arc::new(Keypair::from_base58_string(&req.dev_wallet));
+    let main_wallet: Arc<Keypair> = Arc::new(Keypair::from_base58_string(req.dev_wallet.expose()));
 
-    info!("Using main wallet: {}", req.dev_wallet);
+    info!("Using main wallet: {}", main_wallet.pubkey());
     let pumpfun_sdk = Arc::new(pumpfun::PumpFun::new(main_wallet.clone(), cluster));
 
     // info!("Main Wallet Public Key: {}", main_wallet.pubkey());
@@ -0,0 +1,421 @@
+use crate::config::CONFIG;
+use crate::services::get_token_balance::GetTokenBalanceResult;
+use crate::services::local_cache;
+use crate::solana::metadata::{decode_metadata, metadata_pda, METADATA_PROGRAM_ID};
+use crate::solana::pump::{bonding_curve_pda, decode_bonding_curve, PUMP_PROGRAM_ID};
+use crate::solana::rpc::SolanaRpc;
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinSet};
+
+const CACHE_NAMESPACE: &str = "token_metadata";
+/// Cached entries older than this are refreshed (curve status changes while trading)
+const CACHE_TTL_SECS: i64 = 30 * 60;
+/// Entries that failed to resolve are retried after this instead
+const ERROR_CACHE_TTL_SECS: i64 = 60;
+const OFFCHAIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// Metadata JSON is a few hundred bytes; anything much larger is not metadata
+const MAX_OFFCHAIN_BYTES: usize = 256 * 1024;
+const MAX_OFFCHAIN_FETCHES: usize = 8;
+/// Two accounts (metadata + bonding curve) per mint, 100 keys per getMultipleAccounts
+const MINTS_PER_REQUEST: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PumpCurveStatus {
+    pub bonding_curve: String,
+    /// Curve filled and liquidity migrated; pump.fun trades no longer possible
+    pub complete: bool,
+    pub real_sol_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenMetadata {
+    pub mint: String,
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+    pub image: Option<String>,
+    pub description: Option<String>,
+    /// None when the mint has no pump.fun bonding curve
+    pub pump_curve: Option<PumpCurveStatus>,
+    /// Why part of the metadata could not be resolved, if anything failed
+    pub error: Option<String>,
+    pub fetched_at: i64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct OffchainMetadata {
+    image: Option<String>,
+    description: Option<String>,
+}
+
+fn account_owned_by<'a>(account: &'a Option<Account>, program: &Pubkey) -> Option<&'a Account> {
+    account.as_ref().filter(|a| a.owner == *program)
+}
+
+/// Read metadata and bonding curve accounts of every mint
+fn fetch_onchain(client: &dyn SolanaRpc, mints: &[Pubkey]) -> anyhow::Result<Vec<TokenMetadata>> {
+    let now = chrono::Utc::now().timestamp();
+    let mut results = Vec::with_capacity(mints.len());
+
+    for chunk in mints.chunks(MINTS_PER_REQUEST) {
+        let keys: Vec<Pubkey> = chunk
+            .iter()
+            .flat_map(|mint| [metadata_pda(mint), bonding_curve_pda(mint)])
+            .collect();
+        let accounts = client
+            .get_multiple_accounts(&keys)
+            .context("failed to get metadata accounts")?;
+
+        for (idx, mint) in chunk.iter().enumerate() {
+            let metadata = account_owned_by(&accounts[idx * 2], &METADATA_PROGRAM_ID)
+                .and_then(|a| decode_metadata(&a.data));
+            let curve = account_owned_by(&accounts[idx * 2 + 1], &PUMP_PROGRAM_ID)
+                .and_then(|a| decode_bonding_curve(&a.data));
+
+            results.push(TokenMetadata {
+                mint: mint.to_string(),
+                name: metadata.as_ref().map(|m| m.name.clone()),
+                symbol: metadata.as_ref().map(|m| m.symbol.clone()),
+                uri: metadata
+                    .as_ref()
+                    .map(|m| m.uri.clone())
+                    .filter(|uri| !uri.is_empty()),
+                image: None,
+                description: None,
+                pump_curve: curve.map(|curve| PumpCurveStatus {
+                    bonding_curve: keys[idx * 2 + 1].to_string(),
+                    complete: curve.complete,
+                    real_sol_reserves: curve.real_sol_reserves,
+                    virtual_sol_reserves: curve.virtual_sol_reserves,
+                    virtual_token_reserves: curve.virtual_token_reserves,
+                }),
+                error: metadata
+                    .is_none()
+                    .then(|| "no Metaplex metadata account".to_string()),
+                fetched_at: now,
+            });
+        }
+    }
+    Ok(results)
+}
+
+/// Append a chunk of a response body, failing once it grows past `MAX_OFFCHAIN_BYTES`
+fn push_capped(body: &mut Vec<u8>, chunk: &[u8]) -> anyhow::Result<()> {
+    if body.len() + chunk.len() > MAX_OFFCHAIN_BYTES {
+        anyhow::bail!(
+            "off-chain metadata larger than {} bytes",
+            MAX_OFFCHAIN_BYTES
+        );
+    }
+    body.extend_from_slice(chunk);
+    Ok(())
+}
+
+async fn fetch_offchain(client: &reqwest::Client, uri: &str) -> anyhow::Result<OffchainMetadata> {
+    let mut response = client
+        .get(uri)
+        .send()
+        .await
+        .context("failed to fetch off-chain metadata")?
+        .error_for_status()
+        .context("off-chain metadata request failed")?;
+    if let Some(len) = response.content_length() {
+        if len > MAX_OFFCHAIN_BYTES as u64 {
+            anyhow::bail!(
+                "off-chain metadata larger than {} bytes",
+                MAX_OFFCHAIN_BYTES
+            );
+        }
+    }
+
+    // The length header is optional, so the body is capped while it streams in
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("failed to read off-chain metadata")?
+    {
+        push_capped(&mut body, &chunk)?;
+    }
+    serde_json::from_slice(&body).context("invalid off-chain metadata json")
+}
+
+/// How long a cached entry stays fresh; failures are only kept briefly
+fn cache_ttl(entry: &TokenMetadata) -> i64 {
+    if entry.error.is_some() {
+        ERROR_CACHE_TTL_SECS
+    } else {
+        CACHE_TTL_SECS
+    }
+}
+
+/// Resolve on-chain metadata, then the off-chain JSON of each mint with a uri
+async fn resolve(mints: Vec<Pubkey>) -> Result<Vec<TokenMetadata>, String> {
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let mut resolved =
+        task::spawn_blocking(move || fetch_onchain(&RpcClient::new(rpc_url), &mints))
+            .await
+            .map_err(|e| format!("join error: {}", e))
+            .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+
+    let http = reqwest::Client::builder()
+        .timeout(OFFCHAIN_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let semaphore = Arc::new(Semaphore::new(MAX_OFFCHAIN_FETCHES));
+    let mut tasks = JoinSet::new();
+    for (idx, entry) in resolved.iter().enumerate() {
+        let Some(uri) = entry.uri.clone() else {
+            continue;
+        };
+        let http = http.clone();
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            (idx, fetch_offchain(&http, &uri).await)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, offchain) = joined.map_err(|e| format!("join error: {}", e))?;
+        let entry = &mut resolved[idx];
+        match offchain {
+            Ok(offchain) => {
+                entry.image = offchain.image;
+                entry.description = offchain.description;
+            }
+            Err(e) => {
+                warn!("Off-chain metadata for {} unavailable: {:#}", entry.mint, e);
+                entry.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Name, symbol, image and pump.fun curve status for each mint, served from the
+/// local cache unless the entry is older than the TTL or `refresh` is set
+pub async fn get_tokens_metadata(
+    mints: Vec<String>,
+    refresh: bool,
+) -> Result<Vec<TokenMetadata>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let mut seen = HashSet::new();
+    let mints: Vec<String> = mints
+        .into_iter()
+        .filter(|m| seen.insert(m.clone()))
+        .collect();
+
+    let mut results: Vec<Option<TokenMetadata>> = Vec::with_capacity(mints.len());
+    let mut missing = Vec::new();
+    for mint in &mints {
+        let pubkey = Pubkey::from_str(mint).map_err(|e| format!("invalid mint {}: {}", mint, e))?;
+        let cached = if refresh {
+            None
+        } else {
+            local_cache::load::<TokenMetadata>(CACHE_NAMESPACE, mint)
+                .filter(|entry| now - entry.fetched_at < cache_ttl(entry))
+        };
+        if cached.is_none() {
+            missing.push((results.len(), pubkey));
+        }
+        results.push(cached);
+    }
+
+    if !missing.is_empty() {
+        info!(
+            "Resolving metadata for {} mints ({} cached)",
+            missing.len(),
+            mints.len() - missing.len()
+        );
+        let resolved = resolve(missing.iter().map(|(_, mint)| *mint).collect()).await?;
+        for ((idx, _), entry) in missing.into_iter().zip(resolved) {
+            if let Err(e) = local_cache::store(CACHE_NAMESPACE, &entry.mint, &entry) {
+                warn!("Failed to cache metadata for {}: {:#}", entry.mint, e);
+            }
+            results[idx] = Some(entry);
+        }
+    }
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Attach metadata of every held mint to a balance result
+pub async fn enrich_balances(result: &mut GetTokenBalanceResult) -> Result<(), String> {
+    let mints: Vec<String> = result
+        .balances
+        .iter()
+        .filter_map(|balance| balance.mint.clone())
+        .collect();
+    if mints.is_empty() {
+        return Ok(());
+    }
+
+    for entry in get_tokens_metadata(mints, false).await? {
+        result.metadata.insert(entry.mint.clone(), entry);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::pump::BONDING_CURVE_DISCRIMINATOR;
+    use crate::solana::rpc::fake::{FakeRpc, RpcMethod};
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    /// MetadataV1 account with unpadded name, symbol and uri
+    fn metadata_account(mint: &Pubkey, name: &str, symbol: &str, uri: &str) -> Account {
+        let mut data = vec![4];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for field in [name, symbol, uri] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        account(METADATA_PROGRAM_ID, data)
+    }
+
+    fn curve_account(real_sol_reserves: u64, complete: bool) -> Account {
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for value in [1_000_000u64, 30_000, 0, real_sol_reserves, 1_000_000] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(complete as u8);
+        account(PUMP_PROGRAM_ID, data)
+    }
+
+    fn entry(error: Option<&str>) -> TokenMetadata {
+        TokenMetadata {
+            mint: Pubkey::new_unique().to_string(),
+            name: Some("Token".to_string()),
+            symbol: Some("TKN".to_string()),
+            uri: Some("https://example.com/token.json".to_string()),
+            image: None,
+            description: None,
+            pump_curve: None,
+            error: error.map(|e| e.to_string()),
+            fetched_at: 0,
+        }
+    }
+
+    #[test]
+    fn failed_entries_expire_sooner() {
+        assert_eq!(cache_ttl(&entry(None)), CACHE_TTL_SECS);
+        assert_eq!(cache_ttl(&entry(Some("timed out"))), ERROR_CACHE_TTL_SECS);
+        assert!(ERROR_CACHE_TTL_SECS < CACHE_TTL_SECS);
+    }
+
+    #[test]
+    fn offchain_body_is_capped() {
+        let mut body = Vec::new();
+        push_capped(&mut body, &[b'{'; 1024]).unwrap();
+        push_capped(&mut body, &vec![b' '; MAX_OFFCHAIN_BYTES - 1024]).unwrap();
+        assert_eq!(body.len(), MAX_OFFCHAIN_BYTES);
+        assert!(push_capped(&mut body, b"}").is_err());
+        assert_eq!(body.len(), MAX_OFFCHAIN_BYTES);
+    }
+
+    #[test]
+    fn onchain_metadata_and_curve_are_read_per_mint() {
+        let rpc = FakeRpc::new();
+        let (pump, plain, spoofed) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        rpc.set_account(
+            &metadata_pda(&pump),
+            metadata_account(&pump, "Pump", "PMP", "https://example.com/pump.json"),
+        );
+        rpc.set_account(&bonding_curve_pda(&pump), curve_account(2_500, true));
+        rpc.set_account(
+            &metadata_pda(&plain),
+            metadata_account(&plain, "Plain", "PLN", ""),
+        );
+        // Accounts at the right address but owned by another program are ignored
+        rpc.set_account(
+            &metadata_pda(&spoofed),
+            account(
+                Pubkey::new_unique(),
+                metadata_account(&spoofed, "Fake", "FK", "").data,
+            ),
+        );
+
+        let resolved = fetch_onchain(&rpc, &[pump, plain, spoofed]).unwrap();
+
+        let pump_entry = &resolved[0];
+        assert_eq!(pump_entry.mint, pump.to_string());
+        assert_eq!(pump_entry.name.as_deref(), Some("Pump"));
+        assert_eq!(pump_entry.symbol.as_deref(), Some("PMP"));
+        assert_eq!(
+            pump_entry.uri.as_deref(),
+            Some("https://example.com/pump.json")
+        );
+        let curve = pump_entry.pump_curve.as_ref().unwrap();
+        assert_eq!(curve.bonding_curve, bonding_curve_pda(&pump).to_string());
+        assert!(curve.complete);
+        assert_eq!(curve.real_sol_reserves, 2_500);
+        assert_eq!(pump_entry.error, None);
+
+        // An empty uri leaves nothing to fetch off-chain
+        assert_eq!(resolved[1].name.as_deref(), Some("Plain"));
+        assert_eq!(resolved[1].uri, None);
+        assert!(resolved[1].pump_curve.is_none());
+
+        assert_eq!(resolved[2].name, None);
+        assert_eq!(
+            resolved[2].error.as_deref(),
+            Some("no Metaplex metadata account")
+        );
+    }
+
+    #[test]
+    fn onchain_accounts_are_read_in_chunks() {
+        let rpc = FakeRpc::new();
+        let mints: Vec<Pubkey> = (0..MINTS_PER_REQUEST + 1)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+
+        let resolved = fetch_onchain(&rpc, &mints).unwrap();
+
+        assert_eq!(rpc.calls(RpcMethod::GetMultipleAccounts), 2);
+        let order: Vec<String> = resolved.iter().map(|e| e.mint.clone()).collect();
+        let expected: Vec<String> = mints.iter().map(Pubkey::to_string).collect();
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn onchain_rpc_failure_is_returned() {
+        let rpc = FakeRpc::new();
+        rpc.fail_next(RpcMethod::GetMultipleAccounts, "node unavailable");
+
+        let error = fetch_onchain(&rpc, &[Pubkey::new_unique()]).unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("node unavailable"),
+            "{:#}",
+            error
+        );
+    }
+}
@@ -0,0 +1,1050 @@
+use crate::config::CONFIG;
+use crate::services::local_cache;
+use crate::solana::pump::{self, PumpInstruction};
+use crate::solana::rpc::SolanaRpc;
+use anyhow::Context;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::bs58;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+    UiParsedInstruction, UiTransactionEncoding,
+};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use tokio::task;
+
+const CACHE_NAMESPACE: &str = "tx_history";
+const SIGNATURES_PAGE_SIZE: usize = 1000;
+const DEFAULT_MAX_SIGNATURES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEventKind {
+    SolTransfer,
+    PumpBuy,
+    PumpSell,
+    PumpCreate,
+    Burn,
+    CloseAccount,
+    Other,
+}
+
+/// One normalized transaction, seen from the point of view of `wallet`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: HistoryEventKind,
+    pub success: bool,
+    pub error: Option<String>,
+    pub fee_lamports: u64,
+    /// Net SOL change of the wallet, fee included
+    pub sol_change_lamports: i64,
+    pub mint: Option<String>,
+    /// Net raw token change of the wallet for `mint`
+    pub token_change_raw: Option<i64>,
+    pub token_decimals: Option<u8>,
+    pub counterparty: Option<String>,
+    /// System transfers from or to the wallet, in instruction order
+    #[serde(default)]
+    pub sol_transfers: Vec<SolTransfer>,
+}
+
+/// One system transfer leg of a transaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SolTransfer {
+    pub source: String,
+    pub destination: String,
+    pub lamports: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletHistory {
+    pub wallet: String,
+    /// Newest first
+    pub events: Vec<HistoryEvent>,
+    pub updated_at: i64,
+    /// Signatures whose transaction could not be fetched, retried on the next refresh
+    #[serde(default)]
+    pub failed_signatures: Vec<String>,
+    /// Oldest signature of a fetch cut short by `max_signatures`; the next refresh
+    /// resumes the backfill below it
+    #[serde(default)]
+    pub backfill_before: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub kinds: Option<Vec<HistoryEventKind>>,
+    pub mint: Option<String>,
+    pub counterparty: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub include_failed: Option<bool>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, event: &HistoryEvent) -> bool {
+        if !self.include_failed.unwrap_or(true) && !event.success {
+            return false;
+        }
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&event.kind) {
+                return false;
+            }
+        }
+        if let Some(mint) = &self.mint {
+            if event.mint.as_deref() != Some(mint.as_str()) {
+                return false;
+            }
+        }
+        if let Some(counterparty) = &self.counterparty {
+            if event.counterparty.as_deref() != Some(counterparty.as_str()) {
+                return false;
+            }
+        }
+        match (event.block_time, self.since, self.until) {
+            (Some(t), Some(since), _) if t < since => false,
+            (Some(t), _, Some(until)) if t > until => false,
+            (None, Some(_), _) | (None, _, Some(_)) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Flattened view of an instruction, top-level or inner
+enum FlatInstruction {
+    Parsed {
+        program: String,
+        parsed: serde_json::Value,
+    },
+    Raw {
+        program_id: String,
+        accounts: Vec<String>,
+        data: String,
+    },
+}
+
+fn flatten_instruction(ix: &UiInstruction, out: &mut Vec<FlatInstruction>) {
+    match ix {
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => {
+            out.push(FlatInstruction::Parsed {
+                program: parsed.program.clone(),
+                parsed: parsed.parsed.clone(),
+            });
+        }
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(raw)) => {
+            out.push(FlatInstruction::Raw {
+                program_id: raw.program_id.clone(),
+                accounts: raw.accounts.clone(),
+                data: raw.data.clone(),
+            });
+        }
+        UiInstruction::Compiled(_) => {}
+    }
+}
+
+fn info_str(parsed: &serde_json::Value, field: &str) -> Option<String> {
+    parsed
+        .get("info")?
+        .get(field)?
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+fn info_u64(parsed: &serde_json::Value, field: &str) -> Option<u64> {
+    let value = parsed.get("info")?.get(field)?;
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Classify a JsonParsed transaction and compute the wallet's balance changes
+pub fn parse_transaction(
+    wallet: &str,
+    signature: &str,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> HistoryEvent {
+    let mut event = HistoryEvent {
+        signature: signature.to_string(),
+        slot: tx.slot,
+        block_time: tx.block_time,
+        kind: HistoryEventKind::Other,
+        success: true,
+        error: None,
+        fee_lamports: 0,
+        sol_change_lamports: 0,
+        mint: None,
+        token_change_raw: None,
+        token_decimals: None,
+        counterparty: None,
+        sol_transfers: vec![],
+    };
+
+    let mut instructions = Vec::new();
+    let mut account_keys = Vec::new();
+    if let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction {
+        if let UiMessage::Parsed(message) = &ui_tx.message {
+            account_keys = message
+                .account_keys
+                .iter()
+                .map(|k| k.pubkey.clone())
+                .collect();
+            for ix in &message.instructions {
+                flatten_instruction(ix, &mut instructions);
+            }
+        }
+    }
+
+    if let Some(meta) = &tx.transaction.meta {
+        event.fee_lamports = meta.fee;
+        if let Some(err) = &meta.err {
+            event.success = false;
+            event.error = Some(format!("{:?}", err));
+        }
+
+        if let Some(idx) = account_keys.iter().position(|k| k == wallet) {
+            let pre = meta.pre_balances.get(idx).copied().unwrap_or(0) as i64;
+            let post = meta.post_balances.get(idx).copied().unwrap_or(0) as i64;
+            event.sol_change_lamports = post - pre;
+        }
+
+        let inner: Option<Vec<_>> = meta.inner_instructions.clone().into();
+        for group in inner.unwrap_or_default() {
+            for ix in &group.instructions {
+                flatten_instruction(ix, &mut instructions);
+            }
+        }
+
+        // Net token change per mint for accounts owned by the wallet
+        let mut token_changes: HashMap<String, (i64, u8)> = HashMap::new();
+        let pre_tokens: Option<Vec<_>> = meta.pre_token_balances.clone().into();
+        let post_tokens: Option<Vec<_>> = meta.post_token_balances.clone().into();
+        for (sign, balances) in [(-1i64, pre_tokens), (1i64, post_tokens)] {
+            for balance in balances.unwrap_or_default() {
+                let owner: Option<String> = balance.owner.clone().into();
+                if owner.as_deref() != Some(wallet) {
+                    continue;
+                }
+                let amount = balance.ui_token_amount.amount.parse::<i64>().unwrap_or(0);
+                let entry = token_changes
+                    .entry(balance.mint.clone())
+                    .or_insert((0, balance.ui_token_amount.decimals));
+                entry.0 += sign * amount;
+            }
+        }
+        if let Some((mint, (change, decimals))) = token_changes
+            .into_iter()
+            .max_by_key(|(_, (change, _))| change.unsigned_abs())
+        {
+            event.mint = Some(mint);
+            event.token_change_raw = Some(change);
+            event.token_decimals = Some(decimals);
+        }
+    }
+
+    event.sol_transfers = sol_transfers(wallet, &instructions);
+    classify(wallet, &instructions, &mut event);
+    event
+}
+
+fn sol_transfers(wallet: &str, instructions: &[FlatInstruction]) -> Vec<SolTransfer> {
+    instructions
+        .iter()
+        .filter_map(|ix| match ix {
+            FlatInstruction::Parsed { program, parsed }
+                if program == "system"
+                    && matches!(
+                        parsed.get("type").and_then(|t| t.as_str()),
+                        Some("transfer" | "transferWithSeed")
+                    ) =>
+            {
+                Some(SolTransfer {
+                    source: info_str(parsed, "source")?,
+                    destination: info_str(parsed, "destination")?,
+                    lamports: info_u64(parsed, "lamports")?,
+                })
+            }
+            _ => None,
+        })
+        .filter(|t| t.source == wallet || t.destination == wallet)
+        .collect()
+}
+
+fn classify(wallet: &str, instructions: &[FlatInstruction], event: &mut HistoryEvent) {
+    let pump_program = pump::PUMP_PROGRAM_ID.to_string();
+
+    // pump.fun first: a create usually carries a dev buy, and buys/sells carry transfers
+    let mut pump_kind = None;
+    for ix in instructions {
+        if let FlatInstruction::Raw {
+            program_id,
+            accounts,
+            data,
+        } = ix
+        {
+            if *program_id != pump_program {
+                continue;
+            }
+            let Ok(bytes) = bs58::decode(data).into_vec() else {
+                continue;
+            };
+            let mint = accounts.get(pump::TRADE_MINT_INDEX).cloned();
+            match pump::decode_instruction(&bytes) {
+                Some(PumpInstruction::Create) => {
+                    // The new mint is the first account of a create instruction
+                    event.mint = accounts.first().cloned().or(event.mint.take());
+                    pump_kind = Some((HistoryEventKind::PumpCreate, None));
+                    break;
+                }
+                Some(PumpInstruction::Buy { .. }) if pump_kind.is_none() => {
+                    event.mint = mint.or(event.mint.take());
+                    pump_kind = Some((
+                        HistoryEventKind::PumpBuy,
+                        accounts.get(pump::TRADE_BONDING_CURVE_INDEX).cloned(),
+                    ));
+                }
+                Some(PumpInstruction::Sell { .. }) if pump_kind.is_none() => {
+                    event.mint = mint.or(event.mint.take());
+                    pump_kind = Some((
+                        HistoryEventKind::PumpSell,
+                        accounts.get(pump::TRADE_BONDING_CURVE_INDEX).cloned(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some((kind, counterparty)) = pump_kind {
+        event.kind = kind;
+        event.counterparty = counterparty;
+        return;
+    }
+
+    let parsed_of = |program: &str, types: &[&str]| {
+        instructions.iter().find_map(|ix| match ix {
+            FlatInstruction::Parsed {
+                program: p,
+                parsed,
+            } if p.starts_with(program)
+                && parsed
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .is_some_and(|t| types.contains(&t)) =>
+            {
+                Some(parsed)
+            }
+            _ => None,
+        })
+    };
+
+    if let Some(parsed) = parsed_of("spl-token", &["burn", "burnChecked"]) {
+        event.kind = HistoryEventKind::Burn;
+        if event.mint.is_none() {
+            event.mint = info_str(parsed, "mint");
+        }
+        return;
+    }
+
+    if let Some(parsed) = parsed_of("spl-token", &["closeAccount"]) {
+        event.kind = HistoryEventKind::CloseAccount;
+        event.counterparty = info_str(parsed, "destination");
+        return;
+    }
+
+    if let Some(parsed) = parsed_of("system", &["transfer", "transferWithSeed"]) {
+        event.kind = HistoryEventKind::SolTransfer;
+        let source = info_str(parsed, "source");
+        let destination = info_str(parsed, "destination");
+        event.counterparty = if source.as_deref() == Some(wallet) {
+            destination
+        } else {
+            source
+        };
+        if event.sol_change_lamports == 0 {
+            if let Some(lamports) = info_u64(parsed, "lamports") {
+                event.sol_change_lamports = lamports as i64;
+            }
+        }
+    }
+}
+
+/// Page through signatures older than `before` and newer than `until`, newest
+/// first, stopping at `max_signatures`. Also returns whether the pages ran out
+/// before the cap, i.e. nothing is left between the last signature and `until`.
+fn fetch_signatures(
+    client: &dyn SolanaRpc,
+    wallet: &Pubkey,
+    mut before: Option<Signature>,
+    until: Option<Signature>,
+    max_signatures: usize,
+) -> anyhow::Result<(Vec<(String, u64)>, bool)> {
+    let mut signatures = Vec::new();
+
+    while signatures.len() < max_signatures {
+        let limit = SIGNATURES_PAGE_SIZE.min(max_signatures - signatures.len());
+        let page = client
+            .get_signatures_for_address_with_config(
+                wallet,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(limit),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .context("failed to get signatures for address")?;
+
+        let page_len = page.len();
+        for status in page {
+            before = Some(Signature::from_str(&status.signature).context("invalid signature")?);
+            signatures.push((status.signature, status.slot));
+        }
+        if page_len < limit {
+            return Ok((signatures, true));
+        }
+    }
+
+    Ok((signatures, false))
+}
+
+fn refresh_history(
+    client: &dyn SolanaRpc,
+    wallet: &str,
+    cached: Option<WalletHistory>,
+    max_signatures: usize,
+) -> anyhow::Result<WalletHistory> {
+    let wallet_pubkey = Pubkey::from_str(wallet).context("invalid wallet pubkey")?;
+    let mut history = cached.unwrap_or_else(|| WalletHistory {
+        wallet: wallet.to_string(),
+        events: vec![],
+        updated_at: 0,
+        failed_signatures: vec![],
+        backfill_before: None,
+    });
+
+    let until = history
+        .events
+        .first()
+        .and_then(|e| Signature::from_str(&e.signature).ok());
+    // A cold fetch starts over from the newest signature
+    let resume = history
+        .backfill_before
+        .take()
+        .filter(|_| until.is_some())
+        .map(|sig| Signature::from_str(&sig))
+        .transpose()
+        .context("invalid backfill signature")?;
+    // Newer than the cache: page all the way down to it, a capped fetch would
+    // leave a hole no later refresh looks into
+    let mut signatures = match until {
+        Some(until) => fetch_signatures(client, &wallet_pubkey, None, Some(until), usize::MAX)?.0,
+        None => Vec::new(),
+    };
+    // Older than the cache, up to the cap: the cold fetch or the rest of a backfill
+    if until.is_none() || resume.is_some() {
+        let (older, complete) =
+            fetch_signatures(client, &wallet_pubkey, resume, None, max_signatures)?;
+        if !complete {
+            history.backfill_before = older.last().map(|(sig, _slot)| sig.clone());
+        }
+        signatures.extend(older);
+    }
+    info!(
+        "Found {} new signatures for wallet {}",
+        signatures.len(),
+        wallet
+    );
+
+    // Earlier failures first, then new signatures, each fetched once
+    let known: HashSet<String> = history.events.iter().map(|e| e.signature.clone()).collect();
+    let mut queued = HashSet::new();
+    let to_fetch: Vec<String> = std::mem::take(&mut history.failed_signatures)
+        .into_iter()
+        .chain(signatures.into_iter().map(|(sig, _slot)| sig))
+        .filter(|sig| !known.contains(sig) && queued.insert(sig.clone()))
+        .collect();
+
+    let mut new_events = Vec::new();
+    for sig in to_fetch {
+        let signature = Signature::from_str(&sig).context("invalid signature")?;
+        match client.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::JsonParsed),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        ) {
+            Ok(tx) => new_events.push(parse_transaction(wallet, &sig, &tx)),
+            Err(e) => {
+                // Older than the newest cached event, so the next signature page
+                // would not return it again
+                warn!(
+                    "Failed to fetch transaction {}, retrying on next refresh: {}",
+                    sig, e
+                );
+                history.failed_signatures.push(sig);
+            }
+        }
+    }
+    if !history.failed_signatures.is_empty() {
+        warn!(
+            "{} transactions of wallet {} left to retry",
+            history.failed_signatures.len(),
+            wallet
+        );
+    }
+
+    history.events.extend(new_events);
+    history
+        .events
+        .sort_by(|a, b| b.slot.cmp(&a.slot).then(a.signature.cmp(&b.signature)));
+    history.updated_at = chrono::Utc::now().timestamp();
+    Ok(history)
+}
+
+/// Load the wallet history from the local cache, refreshing from RPC when
+/// `refresh` is set or nothing is cached yet, then apply `filter`
+pub async fn get_wallet_history(
+    wallet: String,
+    refresh: bool,
+    filter: Option<HistoryFilter>,
+    max_signatures: Option<usize>,
+) -> Result<Vec<HistoryEvent>, String> {
+    let rpc_url = CONFIG.api.helius_https.clone();
+    let max_signatures = max_signatures.unwrap_or(DEFAULT_MAX_SIGNATURES);
+
+    let history = task::spawn_blocking(move || -> anyhow::Result<WalletHistory> {
+        let cached: Option<WalletHistory> = local_cache::load(CACHE_NAMESPACE, &wallet);
+        if !refresh {
+            if let Some(history) = cached {
+                return Ok(history);
+            }
+        }
+
+        let client = RpcClient::new(rpc_url);
+        let history = refresh_history(&client, &wallet, cached, max_signatures)?;
+        local_cache::store(CACHE_NAMESPACE, &wallet, &history)?;
+        Ok(history)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+    .and_then(|r| r.map_err(|e| format!("rpc error: {}", e)))?;
+
+    let filter = filter.unwrap_or_default();
+    Ok(history
+        .events
+        .into_iter()
+        .filter(|e| filter.matches(e))
+        .collect())
+}
+
+/// Refresh the history of several wallets with progress tracking
+pub async fn import_group_history(
+    wallets: Vec<String>,
+    job_id: Option<String>,
+) -> Result<String, String> {
+    if wallets.is_empty() {
+        return Err("No wallets provided".to_string());
+    }
+
+    let total = wallets.len() as u32;
+    let mut imported = HashMap::new();
+    for (idx, wallet) in wallets.iter().enumerate() {
+        if let Some(ref job_id) = job_id {
+            let _ = crate::update_job_progress(
+                job_id,
+                idx as u32,
+                total,
+                format!("Importing history for wallet {} of {}", idx + 1, total),
+            );
+        }
+
+        match get_wallet_history(wallet.clone(), true, None, None).await {
+            Ok(events) => {
+                imported.insert(wallet.clone(), events.len());
+            }
+            Err(e) => {
+                warn!("Failed to import history for wallet {}: {}", wallet, e);
+            }
+        }
+    }
+
+    if let Some(ref job_id) = job_id {
+        let _ = crate::update_job_progress(
+            job_id,
+            total,
+            total,
+            format!("Imported history for {} of {} wallets", imported.len(), total),
+        );
+    }
+
+    serde_json::to_string(&imported).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Drop the cached history of a wallet so the next view re-imports it
+pub fn clear_wallet_history(wallet: &str) -> Result<(), String> {
+    local_cache::remove(CACHE_NAMESPACE, wallet).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{FakeRpc, RpcMethod};
+    use serde_json::{json, Value};
+
+    const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+
+    fn key() -> String {
+        Pubkey::new_unique().to_string()
+    }
+
+    fn blank_event() -> HistoryEvent {
+        HistoryEvent {
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: None,
+            kind: HistoryEventKind::Other,
+            success: true,
+            error: None,
+            fee_lamports: 0,
+            sol_change_lamports: 0,
+            mint: None,
+            token_change_raw: None,
+            token_decimals: None,
+            counterparty: None,
+            sol_transfers: vec![],
+        }
+    }
+
+    fn parsed(program: &str, kind: &str, info: Value) -> FlatInstruction {
+        FlatInstruction::Parsed {
+            program: program.to_string(),
+            parsed: json!({ "type": kind, "info": info }),
+        }
+    }
+
+    fn pump_data(discriminator: [u8; 8]) -> String {
+        let mut data = discriminator.to_vec();
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&50_000_000u64.to_le_bytes());
+        bs58::encode(data).into_string()
+    }
+
+    fn pump(discriminator: [u8; 8], accounts: &[String]) -> FlatInstruction {
+        FlatInstruction::Raw {
+            program_id: pump::PUMP_PROGRAM_ID.to_string(),
+            accounts: accounts.to_vec(),
+            data: pump_data(discriminator),
+        }
+    }
+
+    /// Accounts of a buy or sell, with the mint and bonding curve in place
+    fn trade_accounts(mint: &str, curve: &str) -> Vec<String> {
+        vec![key(), key(), mint.to_string(), curve.to_string(), key()]
+    }
+
+    fn classified(wallet: &str, instructions: &[FlatInstruction]) -> HistoryEvent {
+        let mut event = blank_event();
+        classify(wallet, instructions, &mut event);
+        event
+    }
+
+    #[test]
+    fn classify_pump_trades_and_prefers_create() {
+        let (wallet, mint, curve) = (key(), key(), key());
+        let transfer = parsed(
+            "system",
+            "transfer",
+            json!({ "source": wallet, "destination": curve, "lamports": 10 }),
+        );
+
+        let event = classified(
+            &wallet,
+            &[
+                transfer,
+                pump(pump::BUY_DISCRIMINATOR, &trade_accounts(&mint, &curve)),
+            ],
+        );
+        assert_eq!(event.kind, HistoryEventKind::PumpBuy);
+        assert_eq!(event.mint.as_deref(), Some(mint.as_str()));
+        assert_eq!(event.counterparty.as_deref(), Some(curve.as_str()));
+
+        let event = classified(
+            &wallet,
+            &[pump(
+                pump::SELL_DISCRIMINATOR,
+                &trade_accounts(&mint, &curve),
+            )],
+        );
+        assert_eq!(event.kind, HistoryEventKind::PumpSell);
+        assert_eq!(event.mint.as_deref(), Some(mint.as_str()));
+
+        // A launch with a dev buy is a create, of the mint it creates
+        let created = key();
+        let event = classified(
+            &wallet,
+            &[
+                pump(pump::CREATE_DISCRIMINATOR, &[created.clone(), key()]),
+                pump(pump::BUY_DISCRIMINATOR, &trade_accounts(&created, &curve)),
+            ],
+        );
+        assert_eq!(event.kind, HistoryEventKind::PumpCreate);
+        assert_eq!(event.mint.as_deref(), Some(created.as_str()));
+        assert_eq!(event.counterparty, None);
+
+        // Other programs, or unknown pump.fun instructions, are not trades
+        let event = classified(
+            &wallet,
+            &[FlatInstruction::Raw {
+                program_id: key(),
+                accounts: trade_accounts(&mint, &curve),
+                data: pump_data(pump::BUY_DISCRIMINATOR),
+            }],
+        );
+        assert_eq!(event.kind, HistoryEventKind::Other);
+        let event = classified(&wallet, &[pump([0; 8], &trade_accounts(&mint, &curve))]);
+        assert_eq!(event.kind, HistoryEventKind::Other);
+    }
+
+    #[test]
+    fn classify_token_instructions() {
+        let (wallet, mint, destination) = (key(), key(), key());
+
+        let event = classified(
+            &wallet,
+            &[parsed("spl-token", "burnChecked", json!({ "mint": mint }))],
+        );
+        assert_eq!(event.kind, HistoryEventKind::Burn);
+        assert_eq!(event.mint.as_deref(), Some(mint.as_str()));
+
+        // Token-2022 programs parse as spl-token-2022
+        let event = classified(
+            &wallet,
+            &[
+                parsed(
+                    "spl-token-2022",
+                    "closeAccount",
+                    json!({ "destination": destination }),
+                ),
+                parsed(
+                    "system",
+                    "transfer",
+                    json!({ "source": wallet, "destination": key(), "lamports": 10 }),
+                ),
+            ],
+        );
+        assert_eq!(event.kind, HistoryEventKind::CloseAccount);
+        assert_eq!(event.counterparty.as_deref(), Some(destination.as_str()));
+    }
+
+    #[test]
+    fn classify_sol_transfers_from_the_wallet_side() {
+        let (wallet, other) = (key(), key());
+        let transfer = |source: &str, destination: &str| {
+            parsed(
+                "system",
+                "transfer",
+                json!({ "source": source, "destination": destination, "lamports": "2500" }),
+            )
+        };
+
+        let event = classified(&wallet, &[transfer(&wallet, &other)]);
+        assert_eq!(event.kind, HistoryEventKind::SolTransfer);
+        assert_eq!(event.counterparty.as_deref(), Some(other.as_str()));
+
+        let event = classified(&wallet, &[transfer(&other, &wallet)]);
+        assert_eq!(event.counterparty.as_deref(), Some(other.as_str()));
+        // Without balances the amount comes from the instruction
+        assert_eq!(event.sol_change_lamports, 2500);
+
+        let event = classified(&wallet, &[parsed("system", "createAccount", json!({}))]);
+        assert_eq!(event.kind, HistoryEventKind::Other);
+    }
+
+    fn meta(fee: u64, pre_balances: &[u64], post_balances: &[u64]) -> Value {
+        json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": fee,
+            "preBalances": pre_balances,
+            "postBalances": post_balances,
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": [],
+        })
+    }
+
+    fn transaction(
+        account_keys: &[&str],
+        instructions: Value,
+        meta: Value,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let account_keys: Vec<Value> = account_keys
+            .iter()
+            .enumerate()
+            .map(|(i, pubkey)| {
+                json!({ "pubkey": pubkey, "writable": true, "signer": i == 0, "source": "transaction" })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "slot": 42,
+            "blockTime": 1_700_000_000,
+            "transaction": {
+                "signatures": ["sig"],
+                "message": {
+                    "accountKeys": account_keys,
+                    "recentBlockhash": SYSTEM_PROGRAM,
+                    "instructions": instructions,
+                },
+            },
+            "meta": meta,
+        }))
+        .unwrap()
+    }
+
+    fn token_balance(index: u8, mint: &str, owner: &str, amount: u64) -> Value {
+        json!({
+            "accountIndex": index,
+            "mint": mint,
+            "owner": owner,
+            "programId": spl_token::id().to_string(),
+            "uiTokenAmount": {
+                "uiAmount": amount as f64 / 1e6,
+                "decimals": 6,
+                "amount": amount.to_string(),
+                "uiAmountString": (amount as f64 / 1e6).to_string(),
+            },
+        })
+    }
+
+    #[test]
+    fn parse_transaction_reads_sol_transfer_balances() {
+        let (wallet, other) = (key(), key());
+        let tx = transaction(
+            &[&wallet, &other, SYSTEM_PROGRAM],
+            json!([{
+                "program": "system",
+                "programId": SYSTEM_PROGRAM,
+                "parsed": {
+                    "type": "transfer",
+                    "info": { "source": wallet, "destination": other, "lamports": 1_000_000 },
+                },
+                "stackHeight": null,
+            }]),
+            meta(5_000, &[10_000_000, 0, 1], &[8_995_000, 1_000_000, 1]),
+        );
+
+        let event = parse_transaction(&wallet, "sig", &tx);
+        assert_eq!(event.signature, "sig");
+        assert_eq!(event.slot, 42);
+        assert_eq!(event.block_time, Some(1_700_000_000));
+        assert_eq!(event.kind, HistoryEventKind::SolTransfer);
+        assert!(event.success);
+        assert_eq!(event.fee_lamports, 5_000);
+        assert_eq!(event.sol_change_lamports, -1_005_000);
+        assert_eq!(event.counterparty.as_deref(), Some(other.as_str()));
+        assert_eq!(event.mint, None);
+
+        // Seen from the receiving side
+        let event = parse_transaction(&other, "sig", &tx);
+        assert_eq!(event.sol_change_lamports, 1_000_000);
+        assert_eq!(event.counterparty.as_deref(), Some(wallet.as_str()));
+    }
+
+    #[test]
+    fn parse_transaction_keeps_every_transfer_leg_of_the_wallet() {
+        let (wallet, first, second) = (key(), key(), key());
+        let transfer = |source: &str, destination: &str, lamports: u64| {
+            json!({
+                "program": "system",
+                "programId": SYSTEM_PROGRAM,
+                "parsed": {
+                    "type": "transfer",
+                    "info": { "source": source, "destination": destination, "lamports": lamports },
+                },
+                "stackHeight": null,
+            })
+        };
+        let tx = transaction(
+            &[&wallet, &first, &second, SYSTEM_PROGRAM],
+            json!([
+                transfer(&wallet, &first, 1_000),
+                transfer(&first, &second, 10),
+                transfer(&wallet, &second, 2_000),
+            ]),
+            meta(5_000, &[10_000, 0, 0, 1], &[2_000, 990, 2_010, 1]),
+        );
+
+        let event = parse_transaction(&wallet, "sig", &tx);
+        assert_eq!(event.sol_change_lamports, -8_000);
+        let legs: Vec<(&str, &str, u64)> = event
+            .sol_transfers
+            .iter()
+            .map(|t| (t.source.as_str(), t.destination.as_str(), t.lamports))
+            .collect();
+        assert_eq!(
+            legs,
+            [
+                (wallet.as_str(), first.as_str(), 1_000),
+                (wallet.as_str(), second.as_str(), 2_000),
+            ]
+        );
+
+        // The legs of the other side only
+        let event = parse_transaction(&second, "sig", &tx);
+        assert_eq!(event.sol_transfers.len(), 2);
+        assert!(event.sol_transfers.iter().all(|t| t.destination == second));
+    }
+
+    #[test]
+    fn parse_transaction_reads_pump_buy_and_token_change() {
+        let (wallet, mint, curve, other_owner) = (key(), key(), key(), key());
+        let pump_program = pump::PUMP_PROGRAM_ID.to_string();
+        let mut meta = meta(5_000, &[100_000_000, 0], &[49_995_000, 0]);
+        meta["innerInstructions"] = json!([{
+            "index": 0,
+            "instructions": [{
+                "program": "system",
+                "programId": SYSTEM_PROGRAM,
+                "parsed": {
+                    "type": "transfer",
+                    "info": { "source": wallet, "destination": curve, "lamports": 50_000_000 },
+                },
+                "stackHeight": 2,
+            }],
+        }]);
+        meta["preTokenBalances"] = json!([token_balance(3, &mint, &other_owner, 9_000_000)]);
+        meta["postTokenBalances"] = json!([
+            token_balance(2, &mint, &wallet, 1_000_000),
+            token_balance(3, &mint, &other_owner, 8_000_000),
+        ]);
+        let tx = transaction(
+            &[&wallet, &pump_program],
+            json!([{
+                "programId": pump_program,
+                "accounts": trade_accounts(&mint, &curve),
+                "data": pump_data(pump::BUY_DISCRIMINATOR),
+                "stackHeight": null,
+            }]),
+            meta,
+        );
+
+        let event = parse_transaction(&wallet, "sig", &tx);
+        assert_eq!(event.kind, HistoryEventKind::PumpBuy);
+        assert_eq!(event.mint.as_deref(), Some(mint.as_str()));
+        assert_eq!(event.counterparty.as_deref(), Some(curve.as_str()));
+        assert_eq!(event.token_change_raw, Some(1_000_000));
+        assert_eq!(event.token_decimals, Some(6));
+        assert_eq!(event.sol_change_lamports, -50_005_000);
+    }
+
+    #[test]
+    fn parse_transaction_keeps_failed_transactions() {
+        let wallet = key();
+        let error = json!({ "InstructionError": [0, { "Custom": 6001 }] });
+        let mut meta = meta(5_000, &[1_000_000], &[995_000]);
+        meta["err"] = error.clone();
+        meta["status"] = json!({ "Err": error });
+        let tx = transaction(&[&wallet], json!([]), meta);
+
+        let event = parse_transaction(&wallet, "sig", &tx);
+        assert!(!event.success);
+        assert!(event.error.unwrap().contains("6001"));
+        assert_eq!(event.kind, HistoryEventKind::Other);
+        assert_eq!(event.sol_change_lamports, -5_000);
+    }
+
+    /// Push a transaction at `slot` that moved 1000 lamports and a 5000 fee out
+    /// of `wallet`
+    fn push_transfer(rpc: &FakeRpc, wallet: &Pubkey, slot: u64) -> String {
+        let (owner, other) = (wallet.to_string(), key());
+        let mut tx = transaction(
+            &[&owner, &other, SYSTEM_PROGRAM],
+            json!([]),
+            meta(5_000, &[1_000_000, 0, 1], &[994_000, 1_000, 1]),
+        );
+        tx.slot = slot;
+        rpc.push_transaction(wallet, tx).to_string()
+    }
+
+    fn slots(history: &WalletHistory) -> Vec<u64> {
+        history.events.iter().map(|e| e.slot).collect()
+    }
+
+    #[test]
+    fn refresh_backfills_past_the_signature_cap() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        for slot in 1..=5 {
+            push_transfer(&rpc, &wallet, slot);
+        }
+
+        // Cold fetch: the newest two, the rest left to backfill
+        let history = refresh_history(&rpc, &wallet.to_string(), None, 2).unwrap();
+        assert_eq!(slots(&history), [5, 4]);
+        assert_eq!(history.events[0].sol_change_lamports, -6_000);
+        assert!(history.backfill_before.is_some());
+
+        // More new signatures than the cap are all fetched, then the backfill
+        // resumes below the cache
+        for slot in 6..=8 {
+            push_transfer(&rpc, &wallet, slot);
+        }
+        let history = refresh_history(&rpc, &wallet.to_string(), Some(history), 2).unwrap();
+        assert_eq!(slots(&history), [8, 7, 6, 5, 4, 3, 2]);
+        assert!(history.backfill_before.is_some());
+
+        let history = refresh_history(&rpc, &wallet.to_string(), Some(history), 2).unwrap();
+        assert_eq!(slots(&history), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(history.backfill_before, None);
+
+        // Nothing left to fetch
+        let fetched = rpc.calls(RpcMethod::GetTransaction);
+        let history = refresh_history(&rpc, &wallet.to_string(), Some(history), 2).unwrap();
+        assert_eq!(history.events.len(), 8);
+        assert_eq!(rpc.calls(RpcMethod::GetTransaction), fetched);
+    }
+
+    #[test]
+    fn refresh_retries_transactions_that_failed_to_fetch() {
+        let rpc = FakeRpc::new();
+        let wallet = Pubkey::new_unique();
+        push_transfer(&rpc, &wallet, 1);
+        let newer = push_transfer(&rpc, &wallet, 2);
+
+        // Newest first, so the failure hits the newer transaction
+        rpc.fail_next(RpcMethod::GetTransaction, "node behind");
+        let history = refresh_history(&rpc, &wallet.to_string(), None, 10).unwrap();
+        assert_eq!(slots(&history), [1]);
+        assert_eq!(history.failed_signatures, [newer]);
+
+        // Also newer than the cache now, but fetched once
+        let fetched = rpc.calls(RpcMethod::GetTransaction);
+        let history = refresh_history(&rpc, &wallet.to_string(), Some(history), 10).unwrap();
+        assert_eq!(slots(&history), [2, 1]);
+        assert!(history.failed_signatures.is_empty());
+        assert_eq!(rpc.calls(RpcMethod::GetTransaction), fetched + 1);
+    }
+
+    #[test]
+    fn histories_cached_before_retries_still_load() {
+        let history: WalletHistory = serde_json::from_value(json!({
+            "wallet": "wallet",
+            "events": [],
+            "updated_at": 1,
+        }))
+        .unwrap();
+        assert!(history.failed_signatures.is_empty());
+    }
+}
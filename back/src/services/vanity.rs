@@ -0,0 +1,366 @@
+use crate::job_events::{job_events, JobEventKind};
+use crate::secret::SecretString;
+use crate::services::keystore::{self, KeystoreSummary};
+use log::info;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{signature::Keypair, signer::Signer};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::task;
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// Longer patterns would take years on a desktop
+const MAX_PATTERN_LEN: usize = 8;
+/// Keys generated by a worker between two looks at the shared state
+const BATCH_SIZE: u64 = 1_000;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const BENCHMARK_DURATION: Duration = Duration::from_millis(500);
+
+/// Stop flags of running searches, by job id
+static SEARCHES: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VanityPattern {
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub suffix: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VanityEstimate {
+    /// Keys to try on average before a match
+    pub expected_attempts: f64,
+    pub threads: usize,
+    pub keys_per_sec: f64,
+    pub expected_secs: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VanityResult {
+    pub keypair: KeystoreSummary,
+    pub attempts: u64,
+    pub elapsed_secs: f64,
+    pub keys_per_sec: f64,
+}
+
+impl VanityPattern {
+    fn validate(&self) -> Result<(), String> {
+        if self.prefix.is_empty() && self.suffix.is_empty() {
+            return Err("Empty vanity pattern".to_string());
+        }
+        if self.prefix.len() + self.suffix.len() > MAX_PATTERN_LEN {
+            return Err(format!(
+                "Vanity pattern too long: {} characters (max: {})",
+                self.prefix.len() + self.suffix.len(),
+                MAX_PATTERN_LEN
+            ));
+        }
+        for c in self.prefix.chars().chain(self.suffix.chars()) {
+            if self.alphabet_matches(c) == 0 {
+                return Err(format!("'{}' never appears in a base58 address", c));
+            }
+        }
+        Ok(())
+    }
+
+    /// Base58 characters an address character may be to match `c`
+    fn alphabet_matches(&self, c: char) -> usize {
+        BASE58_ALPHABET
+            .chars()
+            .filter(|a| {
+                if self.case_sensitive {
+                    *a == c
+                } else {
+                    a.eq_ignore_ascii_case(&c)
+                }
+            })
+            .count()
+    }
+
+    fn expected_attempts(&self) -> f64 {
+        self.prefix
+            .chars()
+            .chain(self.suffix.chars())
+            .map(|c| 58.0 / self.alphabet_matches(c) as f64)
+            .product()
+    }
+
+    fn matcher(&self) -> impl Fn(&str) -> bool + Send + Sync + 'static {
+        let case_sensitive = self.case_sensitive;
+        let fold = move |s: &str| {
+            if case_sensitive {
+                s.to_string()
+            } else {
+                s.to_ascii_lowercase()
+            }
+        };
+        let prefix = fold(&self.prefix);
+        let suffix = fold(&self.suffix);
+        move |address: &str| {
+            let address = fold(address);
+            address.starts_with(&prefix) && address.ends_with(&suffix)
+        }
+    }
+}
+
+fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Run `threads` workers until one finds a match or `stop` is set
+fn grind(
+    pattern: &VanityPattern,
+    threads: usize,
+    stop: Arc<AtomicBool>,
+    attempts: Arc<AtomicU64>,
+) -> Option<Keypair> {
+    let found: Arc<Mutex<Option<Keypair>>> = Arc::new(Mutex::new(None));
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let matches = pattern.matcher();
+            let stop = stop.clone();
+            let attempts = attempts.clone();
+            let found = found.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    for _ in 0..BATCH_SIZE {
+                        let keypair = Keypair::new();
+                        if matches(&keypair.pubkey().to_string()) {
+                            found.lock().get_or_insert(keypair);
+                            stop.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                    attempts.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    let keypair = found.lock().take();
+    keypair
+}
+
+/// Expected search time for a pattern, from a short benchmark of this machine
+pub async fn estimate_vanity(
+    pattern: VanityPattern,
+    threads: Option<usize>,
+) -> Result<VanityEstimate, String> {
+    pattern.validate()?;
+    let threads = threads.unwrap_or_else(default_threads).max(1);
+    task::spawn_blocking(move || {
+        // A pattern nothing can match keeps the workers busy until the benchmark ends
+        let benchmark = VanityPattern {
+            prefix: "0".to_string(),
+            suffix: String::new(),
+            case_sensitive: true,
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let attempts = Arc::new(AtomicU64::new(0));
+        let timer = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                thread::sleep(BENCHMARK_DURATION);
+                stop.store(true, Ordering::Relaxed);
+            })
+        };
+        let started = Instant::now();
+        grind(&benchmark, threads, stop, attempts.clone());
+        let _ = timer.join();
+
+        let keys_per_sec = attempts.load(Ordering::Relaxed) as f64
+            / started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let expected_attempts = pattern.expected_attempts();
+        VanityEstimate {
+            expected_attempts,
+            threads,
+            keys_per_sec,
+            expected_secs: expected_attempts / keys_per_sec.max(1.0),
+        }
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))
+}
+
+/// Search a mint keypair matching the pattern and store it encrypted in the keystore;
+/// progress and ETA are reported on the job, `cancel_vanity_search` stops it
+pub async fn grind_vanity_mint(
+    pattern: VanityPattern,
+    threads: Option<usize>,
+    password: SecretString,
+    job_id: String,
+) -> Result<VanityResult, String> {
+    pattern.validate()?;
+    if password.expose().is_empty() {
+        return Err("Keystore password must not be empty".to_string());
+    }
+    let threads = threads.unwrap_or_else(default_threads).max(1);
+    let expected_attempts = pattern.expected_attempts();
+    info!(
+        "Grinding vanity mint (prefix '{}', suffix '{}') on {} threads, ~{} attempts expected",
+        pattern.prefix, pattern.suffix, threads, expected_attempts as u64
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    SEARCHES.lock().insert(job_id.clone(), stop.clone());
+
+    let attempts = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+    let search = {
+        let pattern = pattern.clone();
+        let stop = stop.clone();
+        let attempts = attempts.clone();
+        task::spawn_blocking(move || grind(&pattern, threads, stop, attempts))
+    };
+
+    while !search.is_finished() {
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+        let tried = attempts.load(Ordering::Relaxed);
+        let keys_per_sec = tried as f64 / started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let remaining_secs = (expected_attempts - tried as f64).max(0.0) / keys_per_sec.max(1.0);
+        // Luck decides when the search ends, so progress stays below 100% until it does
+        let percent = ((tried as f64 / expected_attempts) * 100.0).min(99.0) as u32;
+        let _ = crate::update_job_progress(
+            &job_id,
+            percent,
+            100,
+            format!(
+                "{} keys tried, {:.0} keys/s, ~{:.0}s left",
+                tried, keys_per_sec, remaining_secs
+            ),
+        );
+    }
+
+    let outcome = search.await;
+    SEARCHES.lock().remove(&job_id);
+    let keypair = outcome.map_err(|e| format!("join error: {}", e))?;
+    let attempts = attempts.load(Ordering::Relaxed);
+    let elapsed_secs = started.elapsed().as_secs_f64();
+
+    // Workers only stop without a match when cancelled
+    let Some(keypair) = keypair else {
+        return Err(format!(
+            "Vanity search cancelled after {} attempts",
+            attempts
+        ));
+    };
+
+    let label = format!("vanity mint {}…{}", pattern.prefix, pattern.suffix);
+    let stored = keystore::store_keypair(&keypair, &label, password.expose())
+        .map_err(|e| format!("{:#}", e))?;
+    info!(
+        "Found vanity mint {} after {} attempts in {:.1}s",
+        stored.pubkey, attempts, elapsed_secs
+    );
+    Ok(VanityResult {
+        keypair: stored,
+        attempts,
+        elapsed_secs,
+        keys_per_sec: attempts as f64 / elapsed_secs.max(f64::EPSILON),
+    })
+}
+
+/// Stop a running search; false if no search runs under this job id
+pub fn cancel_vanity_search(job_id: &str) -> bool {
+    match SEARCHES.lock().get(job_id) {
+        Some(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            job_events().publish(job_id, JobEventKind::Cancelled);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(prefix: &str, suffix: &str, case_sensitive: bool) -> VanityPattern {
+        VanityPattern {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            case_sensitive,
+        }
+    }
+
+    #[test]
+    fn validate_rejects_empty_long_and_non_base58_patterns() {
+        assert!(pattern("abc", "", true).validate().is_ok());
+        assert!(pattern("", "pump", false).validate().is_ok());
+        assert!(pattern("abcd", "wxyz", true).validate().is_ok());
+
+        assert!(pattern("", "", false).validate().is_err());
+        assert!(pattern("abcde", "wxyz", true).validate().is_err());
+        for invalid in ["0", "I", "l"] {
+            assert!(
+                pattern(invalid, "", true).validate().is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert!(pattern("0", "", false).validate().is_err());
+        // Without case sensitivity a character matches if either case is base58
+        assert!(pattern("I", "", false).validate().is_ok());
+        assert!(pattern("", "l", false).validate().is_ok());
+    }
+
+    #[test]
+    fn matcher_checks_prefix_and_suffix() {
+        let matches = pattern("Ab", "Yz", true).matcher();
+        assert!(matches("Ab1111Yz"));
+        assert!(!matches("ab1111Yz"));
+        assert!(!matches("Ab1111yz"));
+        assert!(!matches("1Ab111Yz"));
+
+        let matches = pattern("Ab", "Yz", false).matcher();
+        assert!(matches("Ab1111Yz"));
+        assert!(matches("aB1111yZ"));
+        assert!(matches("AB1111YZ"));
+        assert!(!matches("Ac1111Yz"));
+        assert!(!matches("Ab1111Yx"));
+
+        let matches = pattern("", "pump", true).matcher();
+        assert!(matches("9xQeWvG816bUx9EPjHmaT23yvVMpump"));
+        assert!(!matches("9xQeWvG816bUx9EPjHmaT23yvVMPUMP"));
+    }
+
+    #[test]
+    fn expected_attempts_count_each_character() {
+        assert_eq!(pattern("a", "", true).expected_attempts(), 58.0);
+        assert_eq!(pattern("ab", "", true).expected_attempts(), 58.0 * 58.0);
+        assert_eq!(pattern("a", "z", true).expected_attempts(), 58.0 * 58.0);
+        // Both cases match, so each letter is twice as likely
+        assert_eq!(pattern("a", "", false).expected_attempts(), 29.0);
+        assert_eq!(pattern("ab", "", false).expected_attempts(), 29.0 * 29.0);
+        // Digits, and letters base58 only has in one case, do not get easier
+        assert_eq!(pattern("1", "", false).expected_attempts(), 58.0);
+        assert_eq!(pattern("l", "", false).expected_attempts(), 58.0);
+    }
+
+    #[test]
+    fn cancel_stops_the_search_of_that_job_id() {
+        let job_id = format!("job_{}", uuid::Uuid::new_v4());
+        let stop = Arc::new(AtomicBool::new(false));
+        SEARCHES.lock().insert(job_id.clone(), stop.clone());
+
+        assert!(!cancel_vanity_search("vanity_mint"));
+        assert!(!stop.load(Ordering::Relaxed));
+        assert!(cancel_vanity_search(&job_id));
+        assert!(stop.load(Ordering::Relaxed));
+        assert!(job_events().is_cancelled(&job_id));
+        SEARCHES.lock().remove(&job_id);
+    }
+}
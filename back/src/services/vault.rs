@@ -0,0 +1,172 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::Context;
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+const KDF_NAME: &str = "pbkdf2-sha256";
+const KDF_ITERATIONS: u32 = 600_000;
+/// A blob asking for more is refused rather than tying up a core for minutes
+const MAX_KDF_ITERATIONS: u32 = 10 * KDF_ITERATIONS;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Password-encrypted payload (PBKDF2-SHA256 key derivation, AES-256-GCM)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncryptedBlob {
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+/// Encrypt `plaintext` with a key derived from `password`
+pub fn encrypt(password: &str, plaintext: &[u8]) -> anyhow::Result<EncryptedBlob> {
+    if password.is_empty() {
+        anyhow::bail!("password must not be empty");
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(password, &salt, KDF_ITERATIONS);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+
+    Ok(EncryptedBlob {
+        kdf: KDF_NAME.to_string(),
+        iterations: KDF_ITERATIONS,
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+/// Decrypt a blob produced by [`encrypt`]; fails on a wrong password or tampered data
+pub fn decrypt(password: &str, blob: &EncryptedBlob) -> anyhow::Result<Vec<u8>> {
+    if blob.kdf != KDF_NAME {
+        anyhow::bail!("unsupported key derivation: {}", blob.kdf);
+    }
+    // Fewer iterations than we write would mean a weakened or forged blob
+    if !(KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&blob.iterations) {
+        anyhow::bail!(
+            "unsupported key derivation iterations: {} (expected {} to {})",
+            blob.iterations,
+            KDF_ITERATIONS,
+            MAX_KDF_ITERATIONS
+        );
+    }
+
+    let salt = general_purpose::STANDARD
+        .decode(&blob.salt)
+        .context("invalid salt encoding")?;
+    let nonce = general_purpose::STANDARD
+        .decode(&blob.nonce)
+        .context("invalid nonce encoding")?;
+    let ciphertext = general_purpose::STANDARD
+        .decode(&blob.ciphertext)
+        .context("invalid ciphertext encoding")?;
+    if nonce.len() != NONCE_LEN {
+        anyhow::bail!("invalid nonce length: {} bytes", nonce.len());
+    }
+
+    let key = derive_key(password, &salt, blob.iterations);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| anyhow::anyhow!("wrong password or corrupted data"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse battery staple";
+    const SECRET: &[u8] = b"wallet keys";
+
+    #[test]
+    fn round_trip_and_wrong_password() {
+        let blob = encrypt(PASSWORD, SECRET).unwrap();
+        assert_eq!(blob.kdf, KDF_NAME);
+        assert_eq!(blob.iterations, KDF_ITERATIONS);
+        assert!(!blob.ciphertext.contains("wallet"));
+
+        // Through JSON, the way blobs are stored
+        let stored: EncryptedBlob =
+            serde_json::from_str(&serde_json::to_string(&blob).unwrap()).unwrap();
+        assert_eq!(decrypt(PASSWORD, &stored).unwrap(), SECRET);
+
+        let error = decrypt("wrong password", &blob).unwrap_err();
+        assert!(error.to_string().contains("wrong password"), "{}", error);
+    }
+
+    #[test]
+    fn encryptions_use_fresh_salt_and_nonce() {
+        let (a, b) = (
+            encrypt(PASSWORD, SECRET).unwrap(),
+            encrypt(PASSWORD, SECRET).unwrap(),
+        );
+        assert_ne!(a.salt, b.salt);
+        assert_ne!(a.nonce, b.nonce);
+        assert_ne!(a.ciphertext, b.ciphertext);
+        assert!(encrypt("", SECRET).is_err());
+    }
+
+    #[test]
+    fn malformed_blobs_are_refused_before_key_derivation() {
+        let blob = EncryptedBlob {
+            kdf: KDF_NAME.to_string(),
+            iterations: KDF_ITERATIONS,
+            salt: general_purpose::STANDARD.encode([0u8; SALT_LEN]),
+            nonce: general_purpose::STANDARD.encode([0u8; NONCE_LEN]),
+            ciphertext: general_purpose::STANDARD.encode([0u8; 32]),
+        };
+
+        for iterations in [0, 1, KDF_ITERATIONS - 1, MAX_KDF_ITERATIONS + 1, u32::MAX] {
+            let forged = EncryptedBlob {
+                iterations,
+                ..blob.clone()
+            };
+            let error = decrypt(PASSWORD, &forged).unwrap_err();
+            assert!(error.to_string().contains("iterations"), "{}", error);
+        }
+
+        let other_kdf = EncryptedBlob {
+            kdf: "scrypt".to_string(),
+            ..blob.clone()
+        };
+        assert!(decrypt(PASSWORD, &other_kdf).is_err());
+        let short_nonce = EncryptedBlob {
+            nonce: general_purpose::STANDARD.encode([0u8; 8]),
+            ..blob.clone()
+        };
+        assert!(decrypt(PASSWORD, &short_nonce).is_err());
+        let not_base64 = EncryptedBlob {
+            salt: "not base64!".to_string(),
+            ..blob
+        };
+        assert!(decrypt(PASSWORD, &not_base64).is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_refused() {
+        let mut blob = encrypt(PASSWORD, SECRET).unwrap();
+        let mut ciphertext = general_purpose::STANDARD.decode(&blob.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        blob.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        assert!(decrypt(PASSWORD, &blob).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use crate::secret::SecretString;
+use crate::services::vault::{self, EncryptedBlob};
+use anyhow::Context;
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_sdk::derivation_path::DerivationPath;
+use solana_sdk::signer::keypair::keypair_from_seed_and_derivation_path;
+use solana_sdk::{bs58, signature::Keypair, signer::Signer};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+const MAX_IMPORTED_WALLETS: usize = 200;
+const BUNDLE_FORMAT: &str = "memecore-group-backup";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportSource {
+    /// Solana CLI keypair files (JSON array of 64 bytes)
+    KeypairFiles { paths: Vec<String> },
+    /// CSV with a `privateKey` column (optional `publicKey` is checked), or keys in the first column
+    Csv { path: String },
+    /// BIP39 mnemonic derived along m/44'/501'/index'/0'
+    Mnemonic {
+        phrase: SecretString,
+        passphrase: Option<SecretString>,
+        start_index: u32,
+        count: u32,
+    },
+    /// Encrypted bundle written by `export_group_bundle`
+    Bundle {
+        path: String,
+        password: SecretString,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportedWallet {
+    pub pubkey: String,
+    pub private_key: SecretString,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportFailure {
+    /// File path, CSV line number or derivation index the failure refers to
+    pub source: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportResult {
+    pub wallets: Vec<ImportedWallet>,
+    pub failures: Vec<ImportFailure>,
+    pub duplicates: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct GroupBackupPayload {
+    group_name: String,
+    wallets: Vec<ImportedWallet>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupBackupBundle {
+    pub format: String,
+    pub version: u32,
+    pub group_name: String,
+    pub wallet_count: usize,
+    pub created_at: String,
+    pub encrypted: EncryptedBlob,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupBackupSummary {
+    pub path: String,
+    pub group_name: String,
+    pub wallet_count: usize,
+}
+
+/// Validate 64 keypair bytes, including that the embedded pubkey matches the secret
+fn keypair_from_bytes(bytes: &[u8]) -> anyhow::Result<Keypair> {
+    if bytes.len() != 64 {
+        anyhow::bail!("unexpected key length: {} bytes", bytes.len());
+    }
+    let keypair = Keypair::try_from(bytes).context("invalid keypair bytes")?;
+    if keypair.pubkey().to_bytes()[..] != bytes[32..] {
+        anyhow::bail!("public key does not match secret key");
+    }
+    Ok(keypair)
+}
+
+fn parse_base58_keypair(private_key: &str) -> anyhow::Result<Keypair> {
+    let bytes = bs58::decode(private_key.trim())
+        .into_vec()
+        .context("invalid base58 key")?;
+    keypair_from_bytes(&bytes)
+}
+
+fn parse_keypair_file(path: &str) -> anyhow::Result<Keypair> {
+    let raw = fs::read_to_string(path).context("failed to read keypair file")?;
+    let bytes: Vec<u8> =
+        serde_json::from_str(&raw).context("keypair file is not a JSON byte array")?;
+    keypair_from_bytes(&bytes)
+}
+
+fn parse_csv(path: &str, accepted: &mut Vec<(String, anyhow::Result<Keypair>)>) -> anyhow::Result<()> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .context("failed to open csv file")?;
+
+    let mut private_col = 0;
+    let mut public_col = None;
+    for (idx, record) in reader.records().enumerate() {
+        let line = idx + 1;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                accepted.push((format!("line {}", line), Err(anyhow::anyhow!(e))));
+                continue;
+            }
+        };
+
+        if idx == 0 {
+            let headers: Vec<String> = record
+                .iter()
+                .map(|h| h.to_lowercase().replace(['_', ' '], ""))
+                .collect();
+            if let Some(col) = headers.iter().position(|h| h == "privatekey") {
+                private_col = col;
+                public_col = headers.iter().position(|h| h == "publickey");
+                continue;
+            }
+        }
+
+        let Some(private_key) = record.get(private_col).filter(|k| !k.is_empty()) else {
+            continue;
+        };
+        let parsed = parse_base58_keypair(private_key).and_then(|kp| {
+            match public_col.and_then(|col| record.get(col)).filter(|p| !p.is_empty()) {
+                Some(expected) if expected != kp.pubkey().to_string() => {
+                    anyhow::bail!("publicKey column does not match private key")
+                }
+                _ => Ok(kp),
+            }
+        });
+        accepted.push((format!("line {}", line), parsed));
+    }
+    Ok(())
+}
+
+fn read_bundle(path: &str, password: &str) -> anyhow::Result<GroupBackupPayload> {
+    let raw = fs::read_to_string(path).context("failed to read bundle")?;
+    let bundle: GroupBackupBundle = serde_json::from_str(&raw).context("invalid bundle file")?;
+    if bundle.format != BUNDLE_FORMAT || bundle.version != BUNDLE_VERSION {
+        anyhow::bail!(
+            "unsupported bundle format {} v{}",
+            bundle.format,
+            bundle.version
+        );
+    }
+    let plaintext = vault::decrypt(password, &bundle.encrypted)?;
+    serde_json::from_slice(&plaintext).context("invalid bundle payload")
+}
+
+/// Import wallets, validating every key and skipping pubkeys already in `existing`
+pub fn import_wallets(
+    source: ImportSource,
+    existing: Vec<String>,
+) -> Result<ImportResult, String> {
+    let mut parsed: Vec<(String, anyhow::Result<Keypair>)> = Vec::new();
+
+    match source {
+        ImportSource::KeypairFiles { paths } => {
+            for path in paths {
+                let keypair = parse_keypair_file(&path);
+                parsed.push((path, keypair));
+            }
+        }
+        ImportSource::Csv { path } => {
+            parse_csv(&path, &mut parsed).map_err(|e| e.to_string())?;
+        }
+        ImportSource::Mnemonic {
+            phrase,
+            passphrase,
+            start_index,
+            count,
+        } => {
+            if count as usize > MAX_IMPORTED_WALLETS {
+                return Err(format!(
+                    "Too many wallets requested: {} (max: {})",
+                    count, MAX_IMPORTED_WALLETS
+                ));
+            }
+            let mnemonic = bip39::Mnemonic::parse_normalized(phrase.expose().trim())
+                .map_err(|e| format!("Invalid mnemonic: {}", e))?;
+            let seed = mnemonic.to_seed(passphrase.as_ref().map_or("", |p| p.expose()));
+            for index in start_index..start_index.saturating_add(count) {
+                let path = DerivationPath::new_bip44(Some(index), Some(0));
+                let keypair = keypair_from_seed_and_derivation_path(&seed, Some(path))
+                    .map_err(|e| anyhow::anyhow!("derivation failed: {}", e));
+                parsed.push((format!("index {}", index), keypair));
+            }
+        }
+        ImportSource::Bundle { path, password } => {
+            let payload = read_bundle(&path, password.expose()).map_err(|e| e.to_string())?;
+            for (idx, wallet) in payload.wallets.iter().enumerate() {
+                let keypair = parse_base58_keypair(wallet.private_key.expose());
+                parsed.push((format!("entry {}", idx + 1), keypair));
+            }
+        }
+    }
+
+    let mut seen: HashSet<String> = existing.into_iter().collect();
+    let mut result = ImportResult {
+        wallets: vec![],
+        failures: vec![],
+        duplicates: 0,
+    };
+
+    for (source, keypair) in parsed {
+        match keypair {
+            Ok(keypair) => {
+                let pubkey = keypair.pubkey().to_string();
+                if !seen.insert(pubkey.clone()) {
+                    result.duplicates += 1;
+                    continue;
+                }
+                if result.wallets.len() >= MAX_IMPORTED_WALLETS {
+                    result.failures.push(ImportFailure {
+                        source,
+                        reason: format!("import limit of {} wallets reached", MAX_IMPORTED_WALLETS),
+                    });
+                    continue;
+                }
+                result.wallets.push(ImportedWallet {
+                    pubkey,
+                    private_key: keypair.to_base58_string().into(),
+                });
+            }
+            Err(e) => result.failures.push(ImportFailure {
+                source,
+                reason: format!("{:#}", e),
+            }),
+        }
+    }
+
+    info!(
+        "Imported {} wallets ({} failed, {} duplicates)",
+        result.wallets.len(),
+        result.failures.len(),
+        result.duplicates
+    );
+    Ok(result)
+}
+
+/// Write a group's keys to a password-encrypted backup bundle
+pub fn export_group_bundle(
+    group_name: String,
+    private_keys: Vec<SecretString>,
+    password: SecretString,
+    path: String,
+) -> Result<GroupBackupSummary, String> {
+    let mut wallets = Vec::with_capacity(private_keys.len());
+    for (idx, private_key) in private_keys.iter().enumerate() {
+        let keypair = parse_base58_keypair(private_key.expose())
+            .map_err(|e| format!("Invalid key at position {}: {:#}", idx + 1, e))?;
+        wallets.push(ImportedWallet {
+            pubkey: keypair.pubkey().to_string(),
+            private_key: keypair.to_base58_string().into(),
+        });
+    }
+
+    let payload = GroupBackupPayload {
+        group_name: group_name.clone(),
+        wallets,
+    };
+    let plaintext = serde_json::to_vec(&payload).map_err(|e| e.to_string())?;
+    let encrypted = vault::encrypt(password.expose(), &plaintext).map_err(|e| e.to_string())?;
+
+    let bundle = GroupBackupBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        group_name: group_name.clone(),
+        wallet_count: payload.wallets.len(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        encrypted,
+    };
+
+    if let Some(parent) = Path::new(&path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write bundle: {}", e))?;
+
+    info!(
+        "Exported group {} ({} wallets) to {}",
+        group_name, bundle.wallet_count, path
+    );
+    Ok(GroupBackupSummary {
+        path,
+        group_name,
+        wallet_count: bundle.wallet_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The all-"abandon" BIP39 test mnemonic
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    /// Its first two wallets along m/44'/501'/index'/0', as Phantom derives them
+    const MNEMONIC_WALLETS: [&str; 2] = [
+        "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk",
+        "Hh8QwFUA6MtVu1qAoq12ucvFHNwCcVTV7hpWjeY1Hztb",
+    ];
+
+    fn temp_file(contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("memecore-import-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn import(source: ImportSource, existing: &[String]) -> ImportResult {
+        import_wallets(source, existing.to_vec()).unwrap()
+    }
+
+    fn mnemonic(start_index: u32, count: u32, passphrase: Option<&str>) -> ImportSource {
+        ImportSource::Mnemonic {
+            phrase: MNEMONIC.to_string().into(),
+            passphrase: passphrase.map(|p| p.to_string().into()),
+            start_index,
+            count,
+        }
+    }
+
+    fn pubkeys(result: &ImportResult) -> Vec<&str> {
+        result.wallets.iter().map(|w| w.pubkey.as_str()).collect()
+    }
+
+    #[test]
+    fn mnemonic_derives_along_the_solana_bip44_path() {
+        let result = import(mnemonic(0, 2, None), &[]);
+        assert_eq!(pubkeys(&result), MNEMONIC_WALLETS);
+        let keypair = parse_base58_keypair(result.wallets[0].private_key.expose()).unwrap();
+        assert_eq!(keypair.pubkey().to_string(), MNEMONIC_WALLETS[0]);
+
+        let result = import(mnemonic(1, 1, None), &[]);
+        assert_eq!(pubkeys(&result), [MNEMONIC_WALLETS[1]]);
+
+        let result = import(mnemonic(0, 1, Some("passphrase")), &[]);
+        assert_ne!(pubkeys(&result), [MNEMONIC_WALLETS[0]]);
+
+        let too_many = import_wallets(mnemonic(0, MAX_IMPORTED_WALLETS as u32 + 1, None), vec![]);
+        assert!(too_many.unwrap_err().contains("Too many wallets"));
+    }
+
+    #[test]
+    fn csv_reads_headered_and_headerless_files() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+
+        let headered = temp_file(&format!(
+            "name,public_key,Private Key\nfirst,{},{}\nsecond,,{}\nwrong,{},{}\n",
+            a.pubkey(),
+            a.to_base58_string(),
+            b.to_base58_string(),
+            a.pubkey(),
+            Keypair::new().to_base58_string(),
+        ));
+        let result = import(
+            ImportSource::Csv {
+                path: headered.clone(),
+            },
+            &[],
+        );
+        let _ = fs::remove_file(&headered);
+        assert_eq!(
+            pubkeys(&result),
+            [a.pubkey().to_string(), b.pubkey().to_string()]
+        );
+        assert_eq!(result.failures.len(), 1);
+        assert_eq!(result.failures[0].source, "line 4");
+        assert!(result.failures[0].reason.contains("does not match"));
+
+        let headerless = temp_file(&format!(
+            "{}\n{},label\n",
+            a.to_base58_string(),
+            b.to_base58_string()
+        ));
+        let result = import(
+            ImportSource::Csv {
+                path: headerless.clone(),
+            },
+            &[],
+        );
+        let _ = fs::remove_file(&headerless);
+        assert_eq!(
+            pubkeys(&result),
+            [a.pubkey().to_string(), b.pubkey().to_string()]
+        );
+        assert!(result.failures.is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_skipped_within_and_across_sources() {
+        let first = import(mnemonic(0, 2, None), &[]);
+        let existing: Vec<String> = first.wallets.iter().map(|w| w.pubkey.clone()).collect();
+
+        // The same key twice in one file, and once more already imported
+        let derived = first.wallets[1].private_key.expose();
+        let other = Keypair::new();
+        let path = temp_file(&format!(
+            "privateKey\n{}\n{}\n{}\n",
+            other.to_base58_string(),
+            derived,
+            other.to_base58_string()
+        ));
+        let result = import(ImportSource::Csv { path: path.clone() }, &existing);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(pubkeys(&result), [other.pubkey().to_string()]);
+        assert_eq!(result.duplicates, 2);
+        assert!(result.failures.is_empty());
+    }
+
+    #[test]
+    fn failures_are_reported_per_entry() {
+        let valid = Keypair::new();
+        let mut mismatched = Keypair::new().to_bytes();
+        mismatched[32..].copy_from_slice(&valid.pubkey().to_bytes());
+        let files = [
+            temp_file(&serde_json::to_string(&valid.to_bytes().to_vec()).unwrap()),
+            temp_file("[1, 2, 3]"),
+            temp_file("not json"),
+            temp_file(&serde_json::to_string(&mismatched.to_vec()).unwrap()),
+        ];
+        let missing = std::env::temp_dir()
+            .join(format!("memecore-import-{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let mut paths = files.to_vec();
+        paths.push(missing.clone());
+
+        let result = import(ImportSource::KeypairFiles { paths }, &[]);
+        for file in &files {
+            let _ = fs::remove_file(file);
+        }
+
+        assert_eq!(pubkeys(&result), [valid.pubkey().to_string()]);
+        let failures: Vec<(&str, &str)> = result
+            .failures
+            .iter()
+            .map(|f| (f.source.as_str(), f.reason.as_str()))
+            .collect();
+        assert_eq!(failures.len(), 4);
+        assert_eq!(
+            failures[0],
+            (files[1].as_str(), "unexpected key length: 3 bytes")
+        );
+        assert_eq!(failures[1].0, files[2]);
+        assert!(failures[1]
+            .1
+            .starts_with("keypair file is not a JSON byte array"));
+        // Refused by the keypair parser or by the public key check
+        assert_eq!(failures[2].0, files[3]);
+        assert_eq!(failures[3].0, missing);
+        assert!(failures[3].1.starts_with("failed to read keypair file"));
+    }
+}
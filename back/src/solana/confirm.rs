@@ -0,0 +1,504 @@
+use crate::solana::rpc::SolanaRpc;
+use log::{info, warn};
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signers::Signers;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_transaction_status_client_types::TransactionStatus;
+use std::fmt;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Longest wait between retries of a failed status read
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+/// Consecutive failed block height reads after which nobody can tell whether a
+/// blockhash expired, so `wait` gives up
+const MAX_FAILED_HEIGHT_READS: u32 = 10;
+/// Most signatures `getSignatureStatuses` accepts per call
+const MAX_STATUSES_PER_CALL: usize = 256;
+/// Submissions of one transaction, each with a fresh blockhash
+pub const MAX_SUBMISSIONS: usize = 3;
+
+/// Final state of a tracked signature
+#[derive(Debug, Clone, PartialEq)]
+pub enum Confirmation {
+    Confirmed {
+        slot: u64,
+    },
+    /// Landed and failed; the fee is paid and the transaction must not be resent
+    Failed(TransactionError),
+    /// Its blockhash expired before it landed, so it never will; resending a
+    /// re-signed copy is safe
+    Expired,
+}
+
+struct Pending {
+    signature: Signature,
+    last_valid_block_height: u64,
+}
+
+/// Signatures waiting for an outcome, each with the last block height its
+/// blockhash is valid for
+pub struct ConfirmationTracker<'a> {
+    client: &'a dyn SolanaRpc,
+    commitment: CommitmentConfig,
+    poll_interval: Duration,
+    pending: Vec<Pending>,
+    /// Highest block height read so far
+    block_height: Option<u64>,
+    failed_height_reads: u32,
+}
+
+impl<'a> ConfirmationTracker<'a> {
+    pub fn new(client: &'a dyn SolanaRpc) -> Self {
+        ConfirmationTracker {
+            client,
+            commitment: CommitmentConfig::confirmed(),
+            poll_interval: POLL_INTERVAL,
+            pending: Vec::new(),
+            block_height: None,
+            failed_height_reads: 0,
+        }
+    }
+
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn track(&mut self, signature: Signature, last_valid_block_height: u64) {
+        self.pending.push(Pending {
+            signature,
+            last_valid_block_height,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Check every pending signature once and return the ones that settled;
+    /// they are no longer tracked
+    pub fn poll(&mut self) -> Result<Vec<(Signature, Confirmation)>, ClientError> {
+        if self.pending.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Read the height before the statuses: a signature still unknown after
+        // the chain passed its last valid height can no longer land
+        let block_height = self.client.get_block_height().inspect_err(|_| {
+            self.failed_height_reads += 1;
+        })?;
+        self.failed_height_reads = 0;
+        self.block_height = Some(block_height);
+        let mut statuses = Vec::with_capacity(self.pending.len());
+        for chunk in self.pending.chunks(MAX_STATUSES_PER_CALL) {
+            let signatures: Vec<Signature> = chunk.iter().map(|p| p.signature).collect();
+            statuses.extend(self.client.get_signature_statuses(&signatures)?);
+        }
+
+        let mut settled = Vec::new();
+        let mut still_pending = Vec::new();
+        for (pending, status) in self.pending.drain(..).zip(statuses) {
+            match classify(status.as_ref(), self.commitment) {
+                Some(confirmation) => settled.push((pending.signature, confirmation)),
+                None if status.is_none() && block_height > pending.last_valid_block_height => {
+                    settled.push((pending.signature, Confirmation::Expired))
+                }
+                None => still_pending.push(pending),
+            }
+        }
+        self.pending = still_pending;
+        Ok(settled)
+    }
+
+    /// Poll until every tracked signature settled. Failed reads are retried
+    /// with backoff while a pending transaction can still land; the error is
+    /// only returned once the chain passed every pending blockhash, or when the
+    /// block height itself cannot be read
+    pub fn wait(&mut self) -> Result<Vec<(Signature, Confirmation)>, ClientError> {
+        let mut settled = Vec::new();
+        let mut failures = 0;
+        loop {
+            let delay = match self.poll() {
+                Ok(done) => {
+                    settled.extend(done);
+                    failures = 0;
+                    self.poll_interval
+                }
+                Err(error) => {
+                    if self.all_expired() || self.failed_height_reads >= MAX_FAILED_HEIGHT_READS {
+                        return Err(error);
+                    }
+                    failures += 1;
+                    let delay =
+                        (self.poll_interval * 2u32.pow(failures.min(4))).min(MAX_RETRY_DELAY);
+                    warn!(
+                        "Reading transaction statuses failed, retrying in {:?}: {}",
+                        delay, error
+                    );
+                    delay
+                }
+            };
+            if self.pending.is_empty() {
+                return Ok(settled);
+            }
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Whether the chain passed the last valid height of every pending
+    /// signature, so none of them can land any more
+    fn all_expired(&self) -> bool {
+        self.block_height.is_some_and(|height| {
+            self.pending
+                .iter()
+                .all(|p| height > p.last_valid_block_height)
+        })
+    }
+}
+
+/// Outcome of a status that reached `commitment`; `None` while it has not
+fn classify(
+    status: Option<&TransactionStatus>,
+    commitment: CommitmentConfig,
+) -> Option<Confirmation> {
+    let status = status.filter(|s| s.satisfies_commitment(commitment))?;
+    Some(match &status.err {
+        Some(err) => Confirmation::Failed(err.clone()),
+        None => Confirmation::Confirmed { slot: status.slot },
+    })
+}
+
+/// Why `send_and_confirm` did not confirm a transaction
+#[derive(Debug)]
+pub enum ConfirmError {
+    /// Nothing was sent: the blockhash could not be fetched
+    Blockhash(ClientError),
+    /// The node refused the transaction, e.g. its simulation failed; it did not
+    /// land
+    Rejected(ClientError),
+    /// Landed and failed on chain
+    Failed {
+        signature: Signature,
+        error: TransactionError,
+    },
+    /// Every submission expired without landing
+    Expired(Vec<Signature>),
+    /// Statuses could not be read by the time its blockhash expired, or the
+    /// block height could not be read at all, so whether it landed is unknown
+    Unknown {
+        signature: Signature,
+        error: ClientError,
+    },
+}
+
+impl fmt::Display for ConfirmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfirmError::Blockhash(e) => write!(f, "failed to fetch blockhash: {}", e),
+            ConfirmError::Rejected(e) => write!(f, "transaction rejected: {}", e),
+            ConfirmError::Failed { signature, error } => {
+                write!(f, "transaction {} failed: {}", signature, error)
+            }
+            ConfirmError::Expired(signatures) => write!(
+                f,
+                "transaction expired {} times without landing (last signature {})",
+                signatures.len(),
+                signatures.last().map(|s| s.to_string()).unwrap_or_default()
+            ),
+            ConfirmError::Unknown { signature, error } => write!(
+                f,
+                "status of transaction {} is unknown: {}",
+                signature, error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfirmError {}
+
+/// Whether a failed send may still have reached the cluster: a transport error
+/// says nothing about it, while an RPC error response means the node refused it
+fn may_have_been_sent(error: &ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) | ClientErrorKind::Middleware(_)
+    )
+}
+
+/// Sign `instructions` with a fresh blockhash, send them and wait for the
+/// outcome. Only an expired transaction is re-signed and resent, so the same
+/// instructions can never land twice
+pub fn send_and_confirm<S: Signers + ?Sized>(
+    client: &dyn SolanaRpc,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &S,
+) -> Result<Signature, ConfirmError> {
+    let mut expired = Vec::new();
+    for attempt in 1..=MAX_SUBMISSIONS {
+        let (blockhash, last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .map_err(ConfirmError::Blockhash)?;
+        let tx = Transaction::new_signed_with_payer(instructions, Some(payer), signers, blockhash);
+        let signature = tx.signatures[0];
+
+        if let Err(e) = client.send_transaction(&tx) {
+            if !may_have_been_sent(&e) {
+                return Err(ConfirmError::Rejected(e));
+            }
+            warn!(
+                "Sending {} failed, tracking it until its blockhash expires: {}",
+                signature, e
+            );
+        }
+
+        let mut tracker = ConfirmationTracker::new(client);
+        tracker.track(signature, last_valid_block_height);
+        let outcome = tracker
+            .wait()
+            .map_err(|error| ConfirmError::Unknown { signature, error })?
+            .pop()
+            .map(|(_, confirmation)| confirmation)
+            .expect("the tracked signature settled");
+
+        match outcome {
+            Confirmation::Confirmed { slot } => {
+                info!("Transaction {} confirmed in slot {}", signature, slot);
+                return Ok(signature);
+            }
+            Confirmation::Failed(error) => return Err(ConfirmError::Failed { signature, error }),
+            Confirmation::Expired => {
+                warn!(
+                    "Transaction {} expired (submission {} of {})",
+                    signature, attempt, MAX_SUBMISSIONS
+                );
+                expired.push(signature);
+            }
+        }
+    }
+    Err(ConfirmError::Expired(expired))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::rpc::fake::{system_transfers, FakeRpc, Landing, RpcMethod};
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+    use solana_sdk::system_instruction;
+
+    fn transfer(rpc: &FakeRpc) -> Result<Signature, ConfirmError> {
+        let payer = Keypair::new();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+        send_and_confirm(rpc, &[ix], &payer.pubkey(), &[&payer])
+    }
+
+    #[test]
+    fn confirmed_on_first_submission() {
+        let rpc = FakeRpc::new();
+
+        let signature = transfer(&rpc).unwrap();
+
+        assert_eq!(rpc.sent().len(), 1);
+        assert_eq!(rpc.sent()[0].signatures[0], signature);
+    }
+
+    #[test]
+    fn expired_transaction_is_resigned_and_resent() {
+        let rpc = FakeRpc::new();
+        rpc.land_next(Landing::Dropped);
+
+        let signature = transfer(&rpc).unwrap();
+
+        let sent = rpc.sent();
+        assert_eq!(sent.len(), 2);
+        assert_ne!(
+            sent[0].message.recent_blockhash,
+            sent[1].message.recent_blockhash
+        );
+        assert_eq!(sent[1].signatures[0], signature);
+        assert_eq!(system_transfers(&sent[0]), system_transfers(&sent[1]));
+    }
+
+    #[test]
+    fn gives_up_after_max_submissions() {
+        let rpc = FakeRpc::new();
+        for _ in 0..MAX_SUBMISSIONS {
+            rpc.land_next(Landing::Dropped);
+        }
+
+        let err = transfer(&rpc).unwrap_err();
+
+        let sent: Vec<Signature> = rpc.sent().iter().map(|tx| tx.signatures[0]).collect();
+        assert!(matches!(err, ConfirmError::Expired(ref expired) if *expired == sent));
+        assert_eq!(sent.len(), MAX_SUBMISSIONS);
+    }
+
+    #[test]
+    fn on_chain_failure_is_not_resent() {
+        let rpc = FakeRpc::new();
+        rpc.land_next(Landing::Failed(TransactionError::AccountInUse));
+
+        let err = transfer(&rpc).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ConfirmError::Failed {
+                error: TransactionError::AccountInUse,
+                ..
+            }
+        ));
+        assert_eq!(rpc.sent().len(), 1);
+    }
+
+    #[test]
+    fn rejected_transaction_is_not_resent() {
+        let rpc = FakeRpc::new();
+        rpc.fail_next(RpcMethod::SendTransaction, "preflight check failed");
+
+        let err = transfer(&rpc).unwrap_err();
+
+        assert!(matches!(err, ConfirmError::Rejected(_)));
+        assert_eq!(rpc.sent().len(), 1);
+        assert_eq!(rpc.calls(RpcMethod::GetSignatureStatuses), 0);
+    }
+
+    #[test]
+    fn timed_out_send_is_tracked_until_expiry_before_resending() {
+        let rpc = FakeRpc::new();
+        rpc.time_out_next(RpcMethod::SendTransaction);
+
+        transfer(&rpc).unwrap();
+
+        assert_eq!(rpc.sent().len(), 2);
+        assert!(rpc.calls(RpcMethod::GetSignatureStatuses) >= 2);
+    }
+
+    #[test]
+    fn unreadable_status_after_expiry_is_unknown() {
+        let rpc = FakeRpc::new();
+        // The fake chain passes the blockhash at the first poll
+        rpc.fail_next(RpcMethod::GetSignatureStatuses, "rate limited");
+
+        let err = transfer(&rpc).unwrap_err();
+
+        assert!(matches!(err, ConfirmError::Unknown { .. }));
+        assert_eq!(rpc.sent().len(), 1);
+    }
+
+    /// Sign and send a transfer through `rpc`, tracked by `tracker`
+    fn send_tracked(rpc: &FakeRpc, tracker: &mut ConfirmationTracker) -> Signature {
+        let payer = Keypair::new();
+        let (blockhash, last_valid_block_height) = rpc
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .unwrap();
+        let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let tx =
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], blockhash);
+        let signature = rpc.send_transaction(&tx).unwrap();
+        tracker.track(signature, last_valid_block_height);
+        signature
+    }
+
+    #[test]
+    fn failed_reads_are_retried_while_the_blockhash_is_valid() {
+        let rpc = FakeRpc::new();
+        rpc.set_block_advance(1);
+        let mut tracker = ConfirmationTracker::new(&rpc).with_poll_interval(Duration::ZERO);
+        let signature = send_tracked(&rpc, &mut tracker);
+        rpc.fail_next(RpcMethod::GetBlockHeight, "connection reset");
+        rpc.fail_next(RpcMethod::GetSignatureStatuses, "rate limited");
+        rpc.fail_next(RpcMethod::GetSignatureStatuses, "rate limited");
+
+        let settled = tracker.wait().unwrap();
+
+        assert!(matches!(settled[..], [(s, Confirmation::Confirmed { .. })] if s == signature));
+        assert_eq!(rpc.calls(RpcMethod::GetSignatureStatuses), 3);
+    }
+
+    #[test]
+    fn failed_reads_are_retried_until_the_blockhash_expires() {
+        let rpc = FakeRpc::new();
+        rpc.set_block_advance(50);
+        let mut tracker = ConfirmationTracker::new(&rpc).with_poll_interval(Duration::ZERO);
+        rpc.land_next(Landing::Dropped);
+        send_tracked(&rpc, &mut tracker);
+        for _ in 0..10 {
+            rpc.fail_next(RpcMethod::GetSignatureStatuses, "rate limited");
+        }
+
+        let err = tracker.wait().unwrap_err();
+
+        assert!(err.to_string().contains("rate limited"));
+        // Heights 50, 100 and 150 are within the blockhash validity
+        assert_eq!(rpc.calls(RpcMethod::GetSignatureStatuses), 4);
+        assert!(!tracker.is_empty());
+    }
+
+    #[test]
+    fn unreadable_block_height_gives_up() {
+        let rpc = FakeRpc::new();
+        let mut tracker = ConfirmationTracker::new(&rpc).with_poll_interval(Duration::ZERO);
+        send_tracked(&rpc, &mut tracker);
+        for _ in 0..MAX_FAILED_HEIGHT_READS {
+            rpc.fail_next(RpcMethod::GetBlockHeight, "connection refused");
+        }
+
+        assert!(tracker.wait().is_err());
+        assert_eq!(
+            rpc.calls(RpcMethod::GetBlockHeight),
+            MAX_FAILED_HEIGHT_READS as usize
+        );
+        assert_eq!(rpc.calls(RpcMethod::GetSignatureStatuses), 0);
+    }
+
+    #[test]
+    fn tracker_settles_each_signature_independently() {
+        let rpc = FakeRpc::new();
+        let payer = Keypair::new();
+        let mut tracker = ConfirmationTracker::new(&rpc).with_poll_interval(Duration::ZERO);
+        let mut signatures = Vec::new();
+        for landing in [
+            Landing::Confirmed,
+            Landing::Dropped,
+            Landing::Failed(TransactionError::InsufficientFundsForFee),
+        ] {
+            rpc.land_next(landing);
+            let (blockhash, last_valid_block_height) = rpc
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+                .unwrap();
+            let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+            let tx = Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            );
+            signatures.push(rpc.send_transaction(&tx).unwrap());
+            tracker.track(signatures[signatures.len() - 1], last_valid_block_height);
+        }
+
+        let settled = tracker.wait().unwrap();
+
+        assert!(tracker.is_empty());
+        assert!(matches!(settled[0], (s, Confirmation::Confirmed { .. }) if s == signatures[0]));
+        assert_eq!(settled[1], (signatures[1], Confirmation::Expired));
+        assert_eq!(
+            settled[2],
+            (
+                signatures[2],
+                Confirmation::Failed(TransactionError::InsufficientFundsForFee)
+            )
+        );
+    }
+}
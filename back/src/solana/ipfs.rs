@@ -1,11 +1,24 @@
 use std::time::Duration;
 
-use base64::{engine::general_purpose, Engine as _};
+use crate::config::CONFIG;
+use crate::services::local_cache;
+use anyhow::Context;
+use async_trait::async_trait;
+use log::{info, warn};
+use reqwest::multipart::{Form, Part};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+/// pump.fun rejects larger images
+pub const MAX_IMAGE_BYTES: usize = 4 * 1024 * 1024;
+pub const CREATED_ON: &str = "https://pump.fun";
+/// Pins already made, keyed by provider and content hash
+const PIN_CACHE_NAMESPACE: &str = "ipfs_pins";
+const MAX_PIN_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(120);
 
 /// Metadata structure for a token, matching the format expected by Pump.fun.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,45 +75,724 @@ pub struct CreateTokenMetadata {
     pub metadata_uri: Option<String>,
 }
 
+impl From<pumpfun::utils::CreateTokenMetadata> for CreateTokenMetadata {
+    fn from(metadata: pumpfun::utils::CreateTokenMetadata) -> Self {
+        CreateTokenMetadata {
+            name: metadata.name,
+            symbol: metadata.symbol,
+            description: metadata.description,
+            file: metadata.file,
+            twitter: metadata.twitter,
+            telegram: metadata.telegram,
+            website: metadata.website,
+            metadata_uri: None,
+        }
+    }
+}
+
+/// Content pinned by a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedContent {
+    pub provider: String,
+    pub cid: String,
+    /// Gateway URI of the content
+    pub uri: String,
+    /// Hex sha256 of the pinned bytes
+    pub content_hash: String,
+    pub pinned_at: i64,
+}
+
+/// A service able to pin content on IPFS
+#[async_trait]
+pub trait IpfsUploader: Send + Sync {
+    /// Provider name, part of the pin cache key
+    fn name(&self) -> &'static str;
+
+    /// CID of the content if the provider already pins it
+    async fn find_pinned(&self, content_hash: &str, bytes: &[u8])
+        -> anyhow::Result<Option<String>>;
+
+    /// Pin the content and return its CID
+    async fn pin(
+        &self,
+        bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+        content_hash: &str,
+    ) -> anyhow::Result<String>;
+
+    /// Public URI of a CID
+    fn gateway_uri(&self, cid: &str) -> String {
+        format!("{}/{}", CONFIG.ipfs.gateway_url.trim_end_matches('/'), cid)
+    }
+}
+
+fn http_client() -> anyhow::Result<Client> {
+    Client::builder()
+        .timeout(UPLOAD_TIMEOUT)
+        .build()
+        .context("failed to build http client")
+}
+
+/// Pinata-style API authenticated with a JWT
+pub struct PinataUploader {
+    client: Client,
+    api_url: String,
+    jwt: String,
+}
+
+#[derive(Deserialize)]
+struct PinataPinResponse {
+    #[serde(rename = "IpfsHash")]
+    ipfs_hash: String,
+}
+
+#[derive(Deserialize)]
+struct PinataPinListRow {
+    ipfs_pin_hash: String,
+}
+
+#[derive(Deserialize)]
+struct PinataPinList {
+    rows: Vec<PinataPinListRow>,
+}
+
+impl PinataUploader {
+    pub fn new(api_url: &str, jwt: &str) -> anyhow::Result<Self> {
+        if jwt.is_empty() {
+            anyhow::bail!("Pinata JWT is not configured");
+        }
+        Ok(Self {
+            client: http_client()?,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            jwt: jwt.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl IpfsUploader for PinataUploader {
+    fn name(&self) -> &'static str {
+        "pinata"
+    }
+
+    async fn find_pinned(
+        &self,
+        content_hash: &str,
+        _bytes: &[u8],
+    ) -> anyhow::Result<Option<String>> {
+        // Every pin is tagged with the sha256 of its content
+        let filter = json!({ "sha256": { "value": content_hash, "op": "eq" } }).to_string();
+        let list = self
+            .client
+            .get(format!("{}/data/pinList", self.api_url))
+            .bearer_auth(&self.jwt)
+            .query(&[
+                ("status", "pinned"),
+                ("pageLimit", "1"),
+                ("metadata[keyvalues]", filter.as_str()),
+            ])
+            .send()
+            .await
+            .context("failed to query Pinata pins")?
+            .error_for_status()
+            .context("Pinata pin list request failed")?
+            .json::<PinataPinList>()
+            .await
+            .context("invalid Pinata pin list response")?;
+        Ok(list.rows.into_iter().next().map(|row| row.ipfs_pin_hash))
+    }
+
+    async fn pin(
+        &self,
+        bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+        content_hash: &str,
+    ) -> anyhow::Result<String> {
+        let part = Part::bytes(bytes)
+            .file_name(file_name.to_string())
+            .mime_str(mime)?;
+        let form = Form::new()
+            .part("file", part)
+            .text(
+                "pinataMetadata",
+                json!({ "name": file_name, "keyvalues": { "sha256": content_hash } }).to_string(),
+            )
+            .text("pinataOptions", json!({ "cidVersion": 1 }).to_string());
+        let response = self
+            .client
+            .post(format!("{}/pinning/pinFileToIPFS", self.api_url))
+            .bearer_auth(&self.jwt)
+            .multipart(form)
+            .send()
+            .await
+            .context("failed to upload to Pinata")?
+            .error_for_status()
+            .context("Pinata upload failed")?
+            .json::<PinataPinResponse>()
+            .await
+            .context("invalid Pinata upload response")?;
+        Ok(response.ipfs_hash)
+    }
+}
+
+/// IPFS HTTP API of a Kubo node (e.g. a local daemon)
+pub struct KuboUploader {
+    client: Client,
+    api_url: String,
+}
+
+#[derive(Deserialize)]
+struct KuboAddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+impl KuboUploader {
+    pub fn new(api_url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: http_client()?,
+            api_url: api_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    async fn add(
+        &self,
+        bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+        only_hash: bool,
+    ) -> anyhow::Result<String> {
+        let part = Part::bytes(bytes)
+            .file_name(file_name.to_string())
+            .mime_str(mime)?;
+        let response = self
+            .client
+            .post(format!("{}/api/v0/add", self.api_url))
+            .query(&[
+                ("pin", (!only_hash).to_string()),
+                ("only-hash", only_hash.to_string()),
+                ("cid-version", "1".to_string()),
+            ])
+            .multipart(Form::new().part("file", part))
+            .send()
+            .await
+            .context("failed to reach IPFS node")?
+            .error_for_status()
+            .context("IPFS add failed")?
+            .json::<KuboAddResponse>()
+            .await
+            .context("invalid IPFS add response")?;
+        Ok(response.hash)
+    }
+}
+
+#[async_trait]
+impl IpfsUploader for KuboUploader {
+    fn name(&self) -> &'static str {
+        "kubo"
+    }
+
+    async fn find_pinned(
+        &self,
+        _content_hash: &str,
+        bytes: &[u8],
+    ) -> anyhow::Result<Option<String>> {
+        // Same add parameters as `pin`, so the CID matches without storing anything
+        let cid = self
+            .add(bytes.to_vec(), "content", "application/octet-stream", true)
+            .await?;
+        let response = self
+            .client
+            .post(format!("{}/api/v0/pin/ls", self.api_url))
+            .query(&[("arg", cid.as_str()), ("type", "recursive")])
+            .send()
+            .await
+            .context("failed to reach IPFS node")?;
+        // Kubo answers with an error status when the CID is not pinned
+        Ok(response.status().is_success().then_some(cid))
+    }
+
+    async fn pin(
+        &self,
+        bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+        _content_hash: &str,
+    ) -> anyhow::Result<String> {
+        self.add(bytes, file_name, mime, false).await
+    }
+}
+
+/// Uploader selected by `IPFS_PROVIDER`
+pub fn uploader_from_config() -> anyhow::Result<Box<dyn IpfsUploader>> {
+    let ipfs = &CONFIG.ipfs;
+    match ipfs.provider.to_lowercase().as_str() {
+        "pinata" => Ok(Box::new(PinataUploader::new(
+            &ipfs.pinata_api_url,
+            ipfs.pinata_jwt.expose(),
+        )?)),
+        "kubo" => Ok(Box::new(KuboUploader::new(&ipfs.kubo_api_url)?)),
+        other => anyhow::bail!("unknown IPFS provider: {}", other),
+    }
+}
+
+/// Hex sha256 of the content
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// MIME type and file extension of a supported image, from its magic bytes
+pub fn sniff_image(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else {
+        None
+    }
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le_u24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+/// Width and height of a supported image, read from its header
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match sniff_image(bytes)?.0 {
+        "image/png" => {
+            let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+            let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+            Some((width, height))
+        }
+        "image/gif" => Some((le_u16(bytes, 6)?, le_u16(bytes, 8)?)),
+        "image/jpeg" => {
+            // Walk the segments up to the start-of-frame marker
+            let mut at = 2;
+            loop {
+                if *bytes.get(at)? != 0xFF {
+                    return None;
+                }
+                let marker = *bytes.get(at + 1)?;
+                let is_frame =
+                    (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+                if is_frame {
+                    return Some((be_u16(bytes, at + 7)?, be_u16(bytes, at + 5)?));
+                }
+                at += 2 + be_u16(bytes, at + 2)? as usize;
+            }
+        }
+        "image/webp" => match bytes.get(12..16)? {
+            b"VP8 " => Some((le_u16(bytes, 26)? & 0x3FFF, le_u16(bytes, 28)? & 0x3FFF)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le_u24(bytes, 24)? + 1, le_u24(bytes, 27)? + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Check the image is non-empty, small enough and in a supported format
+pub fn validate_image(bytes: &[u8]) -> anyhow::Result<(&'static str, &'static str)> {
+    if bytes.is_empty() {
+        anyhow::bail!("image is empty");
+    }
+    if bytes.len() > MAX_IMAGE_BYTES {
+        anyhow::bail!(
+            "image is {} bytes, max is {} bytes",
+            bytes.len(),
+            MAX_IMAGE_BYTES
+        );
+    }
+    sniff_image(bytes).context("unsupported image format (expected png, jpeg, gif or webp)")
+}
+
+fn record_pin(
+    uploader: &dyn IpfsUploader,
+    cache_key: &str,
+    cid: String,
+    hash: String,
+) -> PinnedContent {
+    let pinned = PinnedContent {
+        provider: uploader.name().to_string(),
+        uri: uploader.gateway_uri(&cid),
+        cid,
+        content_hash: hash,
+        pinned_at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = local_cache::store(PIN_CACHE_NAMESPACE, cache_key, &pinned) {
+        warn!("Failed to cache pin {}: {:#}", pinned.cid, e);
+    }
+    pinned
+}
+
+/// Pin content at most once per provider: known pins are reused, and the provider is
+/// asked whether it already holds the content before each attempt, so a retry after a
+/// lost response never pins it twice
+pub async fn pin_once(
+    uploader: &dyn IpfsUploader,
+    bytes: Vec<u8>,
+    file_name: &str,
+    mime: &str,
+) -> anyhow::Result<PinnedContent> {
+    let hash = content_hash(&bytes);
+    let cache_key = format!("{}-{}", uploader.name(), hash);
+    if let Some(pinned) = local_cache::load::<PinnedContent>(PIN_CACHE_NAMESPACE, &cache_key) {
+        info!("Reusing pin {} for {}", pinned.cid, file_name);
+        return Ok(pinned);
+    }
+
+    let mut last_error = None;
+    for attempt in 1..=MAX_PIN_ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(RETRY_DELAY * (attempt - 1)).await;
+        }
+        match uploader.find_pinned(&hash, &bytes).await {
+            Ok(Some(cid)) => {
+                info!("{} already pinned as {}", file_name, cid);
+                return Ok(record_pin(uploader, &cache_key, cid, hash));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                // Pinning blind could duplicate an earlier attempt
+                warn!("Pin lookup attempt {} failed: {:#}", attempt, e);
+                last_error = Some(e);
+                continue;
+            }
+        }
+        match uploader.pin(bytes.clone(), file_name, mime, &hash).await {
+            Ok(cid) => {
+                info!("Pinned {} on {} as {}", file_name, uploader.name(), cid);
+                return Ok(record_pin(uploader, &cache_key, cid, hash));
+            }
+            Err(e) => {
+                warn!("Pin attempt {} for {} failed: {:#}", attempt, file_name, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| anyhow::anyhow!("no pin attempt made"))
+        .context(format!(
+            "failed to pin {} after {} attempts",
+            file_name, MAX_PIN_ATTEMPTS
+        )))
+}
+
+/// Validate and pin a token image
+pub async fn upload_image(
+    bytes: Vec<u8>,
+    uploader: &dyn IpfsUploader,
+) -> anyhow::Result<PinnedContent> {
+    let (mime, extension) = validate_image(&bytes)?;
+    pin_once(uploader, bytes, &format!("image.{}", extension), mime).await
+}
+
+/// Pump.fun metadata JSON pointing at an already uploaded image
+pub fn build_token_metadata(metadata: &CreateTokenMetadata, image_uri: String) -> TokenMetadata {
+    TokenMetadata {
+        name: metadata.name.clone(),
+        symbol: metadata.symbol.clone(),
+        description: metadata.description.clone(),
+        image: image_uri,
+        show_name: true,
+        created_on: CREATED_ON.to_string(),
+        twitter: metadata.twitter.clone(),
+        telegram: metadata.telegram.clone(),
+        website: metadata.website.clone(),
+    }
+}
+
+/// Upload the image (unless `file` is already a URL), then the metadata JSON; an
+/// existing `metadata_uri` is returned as is
 pub async fn create_token_metadata(
     metadata: CreateTokenMetadata,
-    jwt_token: &str,
+    uploader: &dyn IpfsUploader,
 ) -> Result<TokenMetadataIPFS, anyhow::Error> {
-    let ipfs_url: String = if metadata.file.starts_with("http") || metadata.metadata_uri.is_some() {
-        metadata.file
+    let image_uri = if metadata.file.starts_with("http") || metadata.metadata_uri.is_some() {
+        metadata.file.clone()
     } else {
-        let base64_string = file_to_base64(&metadata.file).await?;
-        upload_base64_file(&base64_string, jwt_token).await?
+        let bytes = tokio::fs::read(&metadata.file)
+            .await
+            .with_context(|| format!("failed to read image {}", metadata.file))?;
+        upload_image(bytes, uploader).await?.uri
     };
 
-    // Private code
+    let token_metadata = build_token_metadata(&metadata, image_uri);
+    if let Some(metadata_uri) = metadata.metadata_uri {
+        return Ok(TokenMetadataIPFS {
+            metadata: token_metadata,
+            metadata_uri,
+        });
+    }
+
+    let pinned = pin_once(
+        uploader,
+        serde_json::to_vec(&token_metadata)?,
+        "metadata.json",
+        "application/json",
+    )
+    .await?;
+    Ok(TokenMetadataIPFS {
+        metadata: token_metadata,
+        metadata_uri: pinned.uri,
+    })
 }
 
-pub async fn upload_base64_file(
-    base64_string: &str,
-    jwt_token: &str,
-) -> Result<String, anyhow::Error> {
-    let _decoded_bytes = general_purpose::STANDARD.decode(base64_string)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
 
-    let client = Client::builder()
-        .timeout(Duration::from_secs(120)) 
-        .pool_max_idle_per_host(0) 
-        .pool_idle_timeout(None) 
-        .build()?;
+    const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
 
-    // let part = Part::bytes(decoded_bytes)
-    //     .file_name("file.png") 
-    //     .mime_str("image/png")?; 
+    /// Uploader whose lookups and pins fail on demand; a failed pin still lands,
+    /// like an upload whose response was lost
+    #[derive(Default)]
+    struct FakeUploader {
+        pinned: Mutex<Vec<String>>,
+        lookup_failures: Mutex<u32>,
+        lost_pins: Mutex<u32>,
+        pins: Mutex<u32>,
+    }
 
-    // let form = Form::new().part("file", part);
+    #[async_trait]
+    impl IpfsUploader for FakeUploader {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
 
-    // Private code
-}
+        async fn find_pinned(
+            &self,
+            content_hash: &str,
+            _bytes: &[u8],
+        ) -> anyhow::Result<Option<String>> {
+            let mut failures = self.lookup_failures.lock();
+            if *failures > 0 {
+                *failures -= 1;
+                anyhow::bail!("lookup timed out");
+            }
+            let cid = format!("cid-{}", content_hash);
+            Ok(self.pinned.lock().contains(&cid).then_some(cid))
+        }
+
+        async fn pin(
+            &self,
+            _bytes: Vec<u8>,
+            _file_name: &str,
+            _mime: &str,
+            content_hash: &str,
+        ) -> anyhow::Result<String> {
+            *self.pins.lock() += 1;
+            let cid = format!("cid-{}", content_hash);
+            self.pinned.lock().push(cid.clone());
+            let mut lost = self.lost_pins.lock();
+            if *lost > 0 {
+                *lost -= 1;
+                anyhow::bail!("connection reset");
+            }
+            Ok(cid)
+        }
+
+        fn gateway_uri(&self, cid: &str) -> String {
+            format!("https://gateway.test/{}", cid)
+        }
+    }
+
+    /// Content no other test pins, so the shared pin cache never answers for it
+    fn unique_content() -> Vec<u8> {
+        uuid::Uuid::new_v4().to_string().into_bytes()
+    }
+
+    #[test]
+    fn sniff_image_recognizes_supported_formats() {
+        assert_eq!(sniff_image(PNG_MAGIC), Some(("image/png", "png")));
+        assert_eq!(
+            sniff_image(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(("image/jpeg", "jpg"))
+        );
+        assert_eq!(sniff_image(b"GIF87a"), Some(("image/gif", "gif")));
+        assert_eq!(sniff_image(b"GIF89a"), Some(("image/gif", "gif")));
+        assert_eq!(
+            sniff_image(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(("image/webp", "webp"))
+        );
+    }
+
+    #[test]
+    fn sniff_image_rejects_other_and_truncated_content() {
+        assert_eq!(
+            sniff_image(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            None
+        );
+        assert_eq!(sniff_image(b"RIFF\0\0\0\0WAVE"), None);
+        assert_eq!(sniff_image(b"RIFF\0\0\0\0WEB"), None);
+        assert_eq!(sniff_image(&PNG_MAGIC[..7]), None);
+        assert_eq!(sniff_image(b""), None);
+    }
+
+    #[test]
+    fn validate_image_checks_size_and_format() {
+        assert_eq!(validate_image(PNG_MAGIC).unwrap(), ("image/png", "png"));
+        assert!(validate_image(b"")
+            .unwrap_err()
+            .to_string()
+            .contains("empty"));
+        assert!(validate_image(b"plain text")
+            .unwrap_err()
+            .to_string()
+            .contains("unsupported"));
+
+        let mut oversized = PNG_MAGIC.to_vec();
+        oversized.resize(MAX_IMAGE_BYTES + 1, 0);
+        assert!(validate_image(&oversized)
+            .unwrap_err()
+            .to_string()
+            .contains("max is"));
+        oversized.truncate(MAX_IMAGE_BYTES);
+        assert!(validate_image(&oversized).is_ok());
+    }
+
+    #[tokio::test]
+    async fn pin_once_retries_without_pinning_twice() {
+        let uploader = FakeUploader {
+            lost_pins: Mutex::new(1),
+            ..Default::default()
+        };
+        let bytes = unique_content();
+        let hash = content_hash(&bytes);
+
+        // The first pin lands but its response is lost; the retry finds it instead
+        // of pinning again
+        let pinned = pin_once(&uploader, bytes, "image.png", "image/png")
+            .await
+            .unwrap();
+
+        assert_eq!(*uploader.pins.lock(), 1);
+        assert_eq!(pinned.cid, format!("cid-{}", hash));
+        assert_eq!(pinned.uri, format!("https://gateway.test/cid-{}", hash));
+        assert_eq!(pinned.content_hash, hash);
+        assert_eq!(pinned.provider, "fake");
+    }
+
+    #[tokio::test]
+    async fn pin_once_reuses_cached_pins() {
+        let bytes = unique_content();
+        let first = pin_once(
+            &FakeUploader::default(),
+            bytes.clone(),
+            "a.png",
+            "image/png",
+        )
+        .await
+        .unwrap();
+
+        // A fresh provider that fails every lookup is never asked: the cache answers
+        let offline = FakeUploader {
+            lookup_failures: Mutex::new(u32::MAX),
+            ..Default::default()
+        };
+        let second = pin_once(&offline, bytes, "b.png", "image/png")
+            .await
+            .unwrap();
+
+        assert_eq!(second.cid, first.cid);
+        assert_eq!(second.pinned_at, first.pinned_at);
+        assert_eq!(*offline.pins.lock(), 0);
+        assert_eq!(*offline.lookup_failures.lock(), u32::MAX);
+    }
+
+    fn webp(chunk: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WEBP".to_vec();
+        bytes.extend_from_slice(chunk);
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn image_dimensions_reads_png_and_gif_headers() {
+        let mut png = PNG_MAGIC.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((640, 480)));
+        assert_eq!(image_dimensions(&png[..23]), None);
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&300u16.to_le_bytes());
+        gif.extend_from_slice(&200u16.to_le_bytes());
+        assert_eq!(image_dimensions(&gif), Some((300, 200)));
+    }
+
+    #[test]
+    fn image_dimensions_walks_jpeg_segments_to_the_frame() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP0 (JFIF) and a Huffman table, whose C4 marker is not a frame
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        jpeg.extend_from_slice(&[0; 14]);
+        jpeg.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x04, 0x00, 0x00]);
+        // Progressive SOF2: precision, height, width
+        jpeg.extend_from_slice(&[0xFF, 0xC2, 0x00, 0x11, 0x08]);
+        jpeg.extend_from_slice(&768u16.to_be_bytes());
+        jpeg.extend_from_slice(&1024u16.to_be_bytes());
+        assert_eq!(image_dimensions(&jpeg), Some((1024, 768)));
+
+        // Segments that do not start with a marker, or end before the frame
+        let mut broken = jpeg.clone();
+        broken[2] = 0x00;
+        assert_eq!(image_dimensions(&broken), None);
+        assert_eq!(image_dimensions(&jpeg[..24]), None);
+    }
+
+    #[test]
+    fn image_dimensions_reads_every_webp_variant() {
+        // Lossy: frame tag, start code, then 14-bit sizes whose top bits are scaling
+        let mut lossy = vec![0x30, 0x01, 0x00, 0x9D, 0x01, 0x2A];
+        lossy.extend_from_slice(&(400u16 | 0xC000).to_le_bytes());
+        lossy.extend_from_slice(&300u16.to_le_bytes());
+        assert_eq!(image_dimensions(&webp(b"VP8 ", &lossy)), Some((400, 300)));
+
+        // Lossless: signature byte, then width - 1 and height - 1 on 14 bits each
+        let bits: u32 = (500 - 1) | ((250 - 1) << 14);
+        let mut lossless = vec![0x2F];
+        lossless.extend_from_slice(&bits.to_le_bytes());
+        assert_eq!(
+            image_dimensions(&webp(b"VP8L", &lossless)),
+            Some((500, 250))
+        );
+
+        // Extended: flags, then width - 1 and height - 1 on 24 bits each
+        let mut extended = vec![0x10, 0, 0, 0];
+        extended.extend_from_slice(&(5000u32 - 1).to_le_bytes()[..3]);
+        extended.extend_from_slice(&(100u32 - 1).to_le_bytes()[..3]);
+        assert_eq!(
+            image_dimensions(&webp(b"VP8X", &extended)),
+            Some((5000, 100))
+        );
 
-async fn file_to_base64(file_path: &str) -> Result<String, anyhow::Error> {
-    let mut file = File::open(file_path).await?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).await?;
-    let base64_string = general_purpose::STANDARD.encode(&buffer);
-    Ok(base64_string)
+        assert_eq!(image_dimensions(&webp(b"ALPH", &extended)), None);
+        assert_eq!(image_dimensions(&webp(b"VP8X", &extended[..5])), None);
+    }
 }
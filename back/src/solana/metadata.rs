@@ -0,0 +1,125 @@
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+/// Metaplex Token Metadata program
+pub const METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Account key byte of a `MetadataV1` account
+const METADATA_V1_KEY: u8 = 4;
+
+/// Leading fields of a Metaplex metadata account (the rest is not needed here)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaplexMetadata {
+    pub update_authority: Pubkey,
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+pub fn metadata_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+fn read_pubkey(data: &[u8], offset: &mut usize) -> Option<Pubkey> {
+    let bytes: [u8; 32] = data.get(*offset..*offset + 32)?.try_into().ok()?;
+    *offset += 32;
+    Some(Pubkey::new_from_array(bytes))
+}
+
+/// Borsh string; Metaplex pads names/symbols/uris with NUL bytes
+fn read_string(data: &[u8], offset: &mut usize) -> Option<String> {
+    let len_bytes: [u8; 4] = data.get(*offset..*offset + 4)?.try_into().ok()?;
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    let bytes = data.get(*offset + 4..*offset + 4 + len)?;
+    *offset += 4 + len;
+    Some(
+        String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .trim()
+            .to_string(),
+    )
+}
+
+/// Decode the name, symbol and uri of a metadata account
+pub fn decode_metadata(data: &[u8]) -> Option<MetaplexMetadata> {
+    if *data.first()? != METADATA_V1_KEY {
+        return None;
+    }
+    let mut offset = 1;
+    Some(MetaplexMetadata {
+        update_authority: read_pubkey(data, &mut offset)?,
+        mint: read_pubkey(data, &mut offset)?,
+        name: read_string(data, &mut offset)?,
+        symbol: read_string(data, &mut offset)?,
+        uri: read_string(data, &mut offset)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Borsh string padded with NULs to `max_len`, as Metaplex stores it
+    fn padded(value: &str, max_len: usize) -> Vec<u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(max_len, 0);
+        let mut out = (max_len as u32).to_le_bytes().to_vec();
+        out.extend(bytes);
+        out
+    }
+
+    fn metadata_account(update_authority: &Pubkey, mint: &Pubkey) -> Vec<u8> {
+        let mut data = vec![METADATA_V1_KEY];
+        data.extend_from_slice(update_authority.as_ref());
+        data.extend_from_slice(mint.as_ref());
+        data.extend(padded("Pump Token", 32));
+        data.extend(padded("PUMP", 10));
+        data.extend(padded("https://ipfs.io/ipfs/Qm", 200));
+        // Seller fee, creators and the rest follow
+        data.extend_from_slice(&[0xf4, 0x01, 0, 1, 1]);
+        data
+    }
+
+    #[test]
+    fn decode_metadata_trims_padding() {
+        let (update_authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let metadata = decode_metadata(&metadata_account(&update_authority, &mint)).unwrap();
+        assert_eq!(
+            metadata,
+            MetaplexMetadata {
+                update_authority,
+                mint,
+                name: "Pump Token".to_string(),
+                symbol: "PUMP".to_string(),
+                uri: "https://ipfs.io/ipfs/Qm".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_metadata_rejects_other_and_truncated_accounts() {
+        let data = metadata_account(&Pubkey::new_unique(), &Pubkey::new_unique());
+        assert!(decode_metadata(&[]).is_none());
+
+        let mut other_key = data.clone();
+        other_key[0] = 1;
+        assert!(decode_metadata(&other_key).is_none());
+
+        // Cut inside the uri, inside a length prefix, and inside the mint
+        let uri_end = 1 + 32 + 32 + (4 + 32) + (4 + 10) + (4 + 200);
+        assert!(decode_metadata(&data[..uri_end]).is_some());
+        assert!(decode_metadata(&data[..uri_end - 1]).is_none());
+        assert!(decode_metadata(&data[..1 + 64 + 2]).is_none());
+        assert!(decode_metadata(&data[..40]).is_none());
+
+        // A length running past the end of the account
+        let mut long_name = data;
+        long_name[65..69].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode_metadata(&long_name).is_none());
+    }
+}
@@ -1,5 +1,10 @@
 pub mod buy;
+pub mod confirm;
 pub mod create;
 pub mod ipfs;
+pub mod metadata;
+pub mod pump;
+pub mod rpc;
 pub mod sell;
+pub mod token_account;
 pub mod tokens;
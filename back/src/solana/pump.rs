@@ -0,0 +1,130 @@
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+/// pump.fun bonding curve program
+pub const PUMP_PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+
+/// Anchor discriminators of the pump.fun instructions we care about
+pub const CREATE_DISCRIMINATOR: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+
+/// Account positions in buy/sell instructions
+pub const TRADE_MINT_INDEX: usize = 2;
+pub const TRADE_BONDING_CURVE_INDEX: usize = 3;
+
+/// Decoded pump.fun instruction
+#[derive(Debug, Clone, PartialEq)]
+pub enum PumpInstruction {
+    Create,
+    Buy { amount: u64, max_sol_cost: u64 },
+    Sell { amount: u64, min_sol_output: u64 },
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Decode raw pump.fun instruction data by discriminator
+pub fn decode_instruction(data: &[u8]) -> Option<PumpInstruction> {
+    let discriminator: [u8; 8] = data.get(..8)?.try_into().ok()?;
+    match discriminator {
+        CREATE_DISCRIMINATOR => Some(PumpInstruction::Create),
+        BUY_DISCRIMINATOR => Some(PumpInstruction::Buy {
+            amount: read_u64(data, 8)?,
+            max_sol_cost: read_u64(data, 16)?,
+        }),
+        SELL_DISCRIMINATOR => Some(PumpInstruction::Sell {
+            amount: read_u64(data, 8)?,
+            min_sol_output: read_u64(data, 16)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Seed of the per-mint bonding curve PDA
+pub const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+pub const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+
+/// On-chain state of a pump.fun bonding curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondingCurveAccount {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// Set once the curve is filled and liquidity has migrated
+    pub complete: bool,
+}
+
+pub fn bonding_curve_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BONDING_CURVE_SEED, mint.as_ref()], &PUMP_PROGRAM_ID).0
+}
+
+/// Decode bonding curve account data; None if it is not a bonding curve account
+pub fn decode_bonding_curve(data: &[u8]) -> Option<BondingCurveAccount> {
+    if data.get(..8)? != BONDING_CURVE_DISCRIMINATOR {
+        return None;
+    }
+    Some(BondingCurveAccount {
+        virtual_token_reserves: read_u64(data, 8)?,
+        virtual_sol_reserves: read_u64(data, 16)?,
+        real_token_reserves: read_u64(data, 24)?,
+        real_sol_reserves: read_u64(data, 32)?,
+        token_total_supply: read_u64(data, 40)?,
+        complete: *data.get(48)? != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve_account(complete: bool) -> Vec<u8> {
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for value in [
+            1_073_000_000_000_000u64,
+            30_000_000_000,
+            793_100_000_000_000,
+            2_500_000_000,
+            1_000_000_000_000_000,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(complete as u8);
+        // Newer curves carry the creator after the original fields
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data
+    }
+
+    #[test]
+    fn decode_bonding_curve_reads_reserves_and_status() {
+        assert_eq!(
+            decode_bonding_curve(&curve_account(false)),
+            Some(BondingCurveAccount {
+                virtual_token_reserves: 1_073_000_000_000_000,
+                virtual_sol_reserves: 30_000_000_000,
+                real_token_reserves: 793_100_000_000_000,
+                real_sol_reserves: 2_500_000_000,
+                token_total_supply: 1_000_000_000_000_000,
+                complete: false,
+            })
+        );
+        assert!(decode_bonding_curve(&curve_account(true)).unwrap().complete);
+    }
+
+    #[test]
+    fn decode_bonding_curve_rejects_other_and_truncated_accounts() {
+        let data = curve_account(false);
+        assert!(decode_bonding_curve(&data[..49]).is_some());
+        assert!(decode_bonding_curve(&data[..48]).is_none());
+        assert!(decode_bonding_curve(&data[..8]).is_none());
+        assert!(decode_bonding_curve(&[]).is_none());
+
+        let mut other = data;
+        other[..8].copy_from_slice(&BUY_DISCRIMINATOR);
+        assert!(decode_bonding_curve(&other).is_none());
+    }
+}
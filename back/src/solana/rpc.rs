@@ -0,0 +1,704 @@
+use solana_account_decoder_client_types::token::UiTokenAmount;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcSendTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_client::rpc_response::{
+    RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcSimulateTransactionResult,
+};
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionStatus,
+};
+
+/// The RPC calls the services make. Services take `&dyn SolanaRpc` so their logic
+/// can run against `FakeRpc` in unit tests; production code passes an `RpcClient`.
+pub trait SolanaRpc: Send + Sync {
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64>;
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>>;
+
+    fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: TokenAccountsFilter,
+    ) -> ClientResult<Vec<RpcKeyedAccount>>;
+
+    fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount>;
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64>;
+
+    /// Blockhash and the last block height at which it is still valid
+    fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<(Hash, u64)>;
+
+    fn get_block_height(&self) -> ClientResult<u64>;
+
+    /// Submit without waiting; track the signature with `confirm::ConfirmationTracker`
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature>;
+
+    /// One entry per signature, `None` for the ones the node has not seen
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>>;
+
+    fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<RpcSimulateTransactionResult>;
+
+    /// Signatures involving `address`, newest first
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>>;
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta>;
+}
+
+impl SolanaRpc for RpcClient {
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        RpcClient::get_balance(self, pubkey)
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts(self, pubkeys)
+    }
+
+    fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: TokenAccountsFilter,
+    ) -> ClientResult<Vec<RpcKeyedAccount>> {
+        RpcClient::get_token_accounts_by_owner(self, owner, filter)
+    }
+
+    fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount> {
+        RpcClient::get_token_account_balance(self, pubkey)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        RpcClient::get_minimum_balance_for_rent_exemption(self, data_len)
+    }
+
+    fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<(Hash, u64)> {
+        RpcClient::get_latest_blockhash_with_commitment(self, commitment)
+    }
+
+    fn get_block_height(&self) -> ClientResult<u64> {
+        RpcClient::get_block_height(self)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        // Preflight against the same commitment the blockhash is fetched at; the
+        // client default (finalized) may not know a confirmed blockhash yet
+        RpcClient::send_transaction_with_config(
+            self,
+            transaction,
+            RpcSendTransactionConfig {
+                preflight_commitment: Some(CommitmentLevel::Confirmed),
+                ..RpcSendTransactionConfig::default()
+            },
+        )
+    }
+
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        RpcClient::get_signature_statuses(self, signatures).map(|response| response.value)
+    }
+
+    fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<RpcSimulateTransactionResult> {
+        RpcClient::simulate_transaction(self, transaction).map(|response| response.value)
+    }
+
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        RpcClient::get_signatures_for_address_with_config(self, address, config)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        RpcClient::get_transaction_with_config(self, signature, config)
+    }
+}
+
+/// In-memory `SolanaRpc` for unit tests: serves balances, accounts, token
+/// accounts and transaction history set up by the test, records every transaction sent, lands it as
+/// scripted, and returns scripted failures
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use parking_lot::Mutex;
+    use solana_account_decoder_client_types::{ParsedAccount, UiAccount, UiAccountData};
+    use solana_client::client_error::{ClientError, ClientErrorKind};
+    use solana_sdk::transaction::TransactionError;
+    use solana_transaction_status_client_types::TransactionConfirmationStatus;
+    use std::collections::{HashMap, VecDeque};
+
+    /// Lamports per byte-year times the two-year exemption threshold
+    const RENT_PER_BYTE: u64 = 6_960;
+    /// Bytes of account metadata the runtime charges rent for
+    const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+    /// Blocks a blockhash stays valid for after it is handed out
+    const BLOCKHASH_VALIDITY: u64 = 150;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum RpcMethod {
+        GetBalance,
+        GetMultipleAccounts,
+        GetTokenAccountsByOwner,
+        GetTokenAccountBalance,
+        GetMinimumBalanceForRentExemption,
+        GetLatestBlockhash,
+        GetBlockHeight,
+        SendTransaction,
+        GetSignatureStatuses,
+        SimulateTransaction,
+        GetSignaturesForAddress,
+        GetTransaction,
+    }
+
+    /// What happens to the next transaction `send_transaction` accepts
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Landing {
+        Confirmed,
+        Failed(TransactionError),
+        /// Never lands, so its blockhash eventually expires
+        Dropped,
+    }
+
+    /// Token account served by `get_token_accounts_by_owner` as jsonParsed data
+    #[derive(Debug, Clone)]
+    pub struct FakeTokenAccount {
+        pub pubkey: Pubkey,
+        pub owner: Pubkey,
+        pub mint: Pubkey,
+        pub program_id: Pubkey,
+        pub amount: u64,
+        pub decimals: u8,
+        pub frozen: bool,
+        pub lamports: u64,
+    }
+
+    impl FakeTokenAccount {
+        /// Unfrozen legacy SPL Token account with 6 decimals
+        pub fn new(owner: Pubkey, mint: Pubkey, amount: u64) -> Self {
+            FakeTokenAccount {
+                pubkey: Pubkey::new_unique(),
+                owner,
+                mint,
+                program_id: spl_token::id(),
+                amount,
+                decimals: 6,
+                frozen: false,
+                lamports: 2_039_280,
+            }
+        }
+
+        fn token_amount(&self) -> UiTokenAmount {
+            let ui_amount = self.amount as f64 / 10f64.powi(self.decimals as i32);
+            UiTokenAmount {
+                ui_amount: Some(ui_amount),
+                decimals: self.decimals,
+                amount: self.amount.to_string(),
+                ui_amount_string: ui_amount.to_string(),
+            }
+        }
+
+        fn keyed(&self) -> RpcKeyedAccount {
+            let program = if self.program_id == spl_token_2022::id() {
+                "spl-token-2022"
+            } else {
+                "spl-token"
+            };
+            let parsed = serde_json::json!({
+                "type": "account",
+                "info": {
+                    "isNative": false,
+                    "mint": self.mint.to_string(),
+                    "owner": self.owner.to_string(),
+                    "state": if self.frozen { "frozen" } else { "initialized" },
+                    "tokenAmount": self.token_amount(),
+                }
+            });
+            RpcKeyedAccount {
+                pubkey: self.pubkey.to_string(),
+                account: UiAccount {
+                    lamports: self.lamports,
+                    data: UiAccountData::Json(ParsedAccount {
+                        program: program.to_string(),
+                        parsed,
+                        space: 165,
+                    }),
+                    owner: self.program_id.to_string(),
+                    executable: false,
+                    rent_epoch: 0,
+                    space: Some(165),
+                },
+            }
+        }
+    }
+
+    /// (from, to, lamports) of every system transfer in `transaction`
+    pub fn system_transfers(transaction: &Transaction) -> Vec<(Pubkey, Pubkey, u64)> {
+        /// SystemInstruction::Transfer, bincode-encoded as a u32 variant index
+        const TRANSFER_INDEX: [u8; 4] = [2, 0, 0, 0];
+
+        let keys = &transaction.message.account_keys;
+        transaction
+            .message
+            .instructions
+            .iter()
+            .filter(|ix| keys[ix.program_id_index as usize] == solana_sdk::system_program::id())
+            .filter(|ix| ix.data.len() == 12 && ix.data[..4] == TRANSFER_INDEX)
+            .map(|ix| {
+                let lamports = u64::from_le_bytes(ix.data[4..12].try_into().unwrap());
+                (
+                    keys[ix.accounts[0] as usize],
+                    keys[ix.accounts[1] as usize],
+                    lamports,
+                )
+            })
+            .collect()
+    }
+
+    #[derive(Default)]
+    struct State {
+        balances: HashMap<Pubkey, u64>,
+        accounts: HashMap<Pubkey, Account>,
+        token_accounts: Vec<FakeTokenAccount>,
+        block_height: u64,
+        block_advance: Option<u64>,
+        sent: Vec<Transaction>,
+        landings: VecDeque<Landing>,
+        statuses: HashMap<Signature, TransactionStatus>,
+        simulations: VecDeque<RpcSimulateTransactionResult>,
+        /// (address, signature, slot) in the order they were pushed
+        history: Vec<(Pubkey, Signature, u64)>,
+        transactions: HashMap<Signature, EncodedConfirmedTransactionWithStatusMeta>,
+        failures: HashMap<RpcMethod, VecDeque<ClientErrorKind>>,
+        calls: Vec<RpcMethod>,
+    }
+
+    #[derive(Default)]
+    pub struct FakeRpc {
+        state: Mutex<State>,
+    }
+
+    impl FakeRpc {
+        pub fn new() -> Self {
+            FakeRpc::default()
+        }
+
+        pub fn set_balance(&self, pubkey: &Pubkey, lamports: u64) {
+            self.state.lock().balances.insert(*pubkey, lamports);
+        }
+
+        pub fn set_account(&self, pubkey: &Pubkey, account: Account) {
+            self.state.lock().accounts.insert(*pubkey, account);
+        }
+
+        pub fn add_token_account(&self, account: FakeTokenAccount) -> Pubkey {
+            let pubkey = account.pubkey;
+            self.state.lock().token_accounts.push(account);
+            pubkey
+        }
+
+        /// Result returned by the next `simulate_transaction`; a successful
+        /// simulation without logs when none is queued
+        pub fn push_simulation(&self, result: RpcSimulateTransactionResult) {
+            self.state.lock().simulations.push_back(result);
+        }
+
+        /// Record `transaction` in the history of `address` under a new
+        /// signature, served by `get_transaction_with_config`
+        pub fn push_transaction(
+            &self,
+            address: &Pubkey,
+            transaction: EncodedConfirmedTransactionWithStatusMeta,
+        ) -> Signature {
+            let signature = Signature::new_unique();
+            let mut state = self.state.lock();
+            state.history.push((*address, signature, transaction.slot));
+            state.transactions.insert(signature, transaction);
+            signature
+        }
+
+        /// Make the next call of `method` fail with `message`; queued failures
+        /// are consumed one call at a time
+        pub fn fail_next(&self, method: RpcMethod, message: &str) {
+            self.push_failure(method, ClientErrorKind::Custom(message.to_string()));
+        }
+
+        /// Make the next call of `method` time out, leaving the caller unsure
+        /// whether the node received it
+        pub fn time_out_next(&self, method: RpcMethod) {
+            let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+            self.push_failure(method, ClientErrorKind::Io(timeout));
+        }
+
+        /// Blocks the chain moves on every `get_block_height`; by default past the
+        /// validity of every blockhash handed out so far
+        pub fn set_block_advance(&self, blocks: u64) {
+            self.state.lock().block_advance = Some(blocks);
+        }
+
+        /// How the next accepted transaction lands; `Landing::Confirmed` when
+        /// none is queued
+        pub fn land_next(&self, landing: Landing) {
+            self.state.lock().landings.push_back(landing);
+        }
+
+        /// Transactions passed to `send_transaction`, including the ones that
+        /// were scripted to fail
+        pub fn sent(&self) -> Vec<Transaction> {
+            self.state.lock().sent.clone()
+        }
+
+        /// How many times `method` was called
+        pub fn calls(&self, method: RpcMethod) -> usize {
+            self.state
+                .lock()
+                .calls
+                .iter()
+                .filter(|m| **m == method)
+                .count()
+        }
+
+        fn push_failure(&self, method: RpcMethod, kind: ClientErrorKind) {
+            self.state
+                .lock()
+                .failures
+                .entry(method)
+                .or_default()
+                .push_back(kind);
+        }
+
+        fn call(&self, method: RpcMethod) -> ClientResult<()> {
+            let mut state = self.state.lock();
+            state.calls.push(method);
+            match state.failures.get_mut(&method).and_then(|q| q.pop_front()) {
+                Some(kind) => Err(ClientError::from(kind)),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl SolanaRpc for FakeRpc {
+        fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+            self.call(RpcMethod::GetBalance)?;
+            Ok(self.state.lock().balances.get(pubkey).copied().unwrap_or(0))
+        }
+
+        fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+            self.call(RpcMethod::GetMultipleAccounts)?;
+            let state = self.state.lock();
+            Ok(pubkeys
+                .iter()
+                .map(|pubkey| state.accounts.get(pubkey).cloned())
+                .collect())
+        }
+
+        fn get_token_accounts_by_owner(
+            &self,
+            owner: &Pubkey,
+            filter: TokenAccountsFilter,
+        ) -> ClientResult<Vec<RpcKeyedAccount>> {
+            self.call(RpcMethod::GetTokenAccountsByOwner)?;
+            let state = self.state.lock();
+            Ok(state
+                .token_accounts
+                .iter()
+                .filter(|account| account.owner == *owner)
+                .filter(|account| match &filter {
+                    TokenAccountsFilter::Mint(mint) => account.mint == *mint,
+                    TokenAccountsFilter::ProgramId(program_id) => account.program_id == *program_id,
+                })
+                .map(FakeTokenAccount::keyed)
+                .collect())
+        }
+
+        fn get_token_account_balance(&self, pubkey: &Pubkey) -> ClientResult<UiTokenAmount> {
+            self.call(RpcMethod::GetTokenAccountBalance)?;
+            self.state
+                .lock()
+                .token_accounts
+                .iter()
+                .find(|account| account.pubkey == *pubkey)
+                .map(FakeTokenAccount::token_amount)
+                .ok_or_else(|| {
+                    ClientError::from(ClientErrorKind::Custom(format!(
+                        "could not find account {}",
+                        pubkey
+                    )))
+                })
+        }
+
+        fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+            self.call(RpcMethod::GetMinimumBalanceForRentExemption)?;
+            Ok((ACCOUNT_STORAGE_OVERHEAD + data_len as u64) * RENT_PER_BYTE)
+        }
+
+        /// A new blockhash on every call, so a re-signed transaction gets a new
+        /// signature
+        fn get_latest_blockhash_with_commitment(
+            &self,
+            _commitment: CommitmentConfig,
+        ) -> ClientResult<(Hash, u64)> {
+            self.call(RpcMethod::GetLatestBlockhash)?;
+            let block_height = self.state.lock().block_height;
+            Ok((Hash::new_unique(), block_height + BLOCKHASH_VALIDITY))
+        }
+
+        /// Unless `set_block_advance` says otherwise, every call moves the chain
+        /// past the validity of the blockhashes handed out so far, so a
+        /// transaction that has not landed expires at its first poll
+        fn get_block_height(&self) -> ClientResult<u64> {
+            self.call(RpcMethod::GetBlockHeight)?;
+            let mut state = self.state.lock();
+            state.block_height += state.block_advance.unwrap_or(BLOCKHASH_VALIDITY + 1);
+            Ok(state.block_height)
+        }
+
+        fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+            self.state.lock().sent.push(transaction.clone());
+            self.call(RpcMethod::SendTransaction)?;
+            let signature = transaction.signatures[0];
+            let mut state = self.state.lock();
+            let landing = state.landings.pop_front().unwrap_or(Landing::Confirmed);
+            let err = match landing {
+                Landing::Confirmed => None,
+                Landing::Failed(err) => Some(err),
+                Landing::Dropped => return Ok(signature),
+            };
+            let slot = state.block_height;
+            state.statuses.insert(
+                signature,
+                TransactionStatus {
+                    slot,
+                    confirmations: Some(1),
+                    status: err.clone().map_or(Ok(()), Err),
+                    err,
+                    confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+                },
+            );
+            Ok(signature)
+        }
+
+        fn get_signature_statuses(
+            &self,
+            signatures: &[Signature],
+        ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+            self.call(RpcMethod::GetSignatureStatuses)?;
+            let state = self.state.lock();
+            Ok(signatures
+                .iter()
+                .map(|signature| state.statuses.get(signature).cloned())
+                .collect())
+        }
+
+        fn simulate_transaction(
+            &self,
+            _transaction: &Transaction,
+        ) -> ClientResult<RpcSimulateTransactionResult> {
+            self.call(RpcMethod::SimulateTransaction)?;
+            Ok(self
+                .state
+                .lock()
+                .simulations
+                .pop_front()
+                .unwrap_or_else(|| {
+                    serde_json::from_value(serde_json::json!({ "err": null, "logs": [] }))
+                        .expect("valid simulation result")
+                }))
+        }
+
+        /// Newest first by slot, later pushes first within a slot; `before` and
+        /// `until` are exclusive, as on a node
+        fn get_signatures_for_address_with_config(
+            &self,
+            address: &Pubkey,
+            config: GetConfirmedSignaturesForAddress2Config,
+        ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+            self.call(RpcMethod::GetSignaturesForAddress)?;
+            let state = self.state.lock();
+            let mut history: Vec<&(Pubkey, Signature, u64)> = state
+                .history
+                .iter()
+                .rev()
+                .filter(|(owner, _, _)| owner == address)
+                .collect();
+            history.sort_by(|a, b| b.2.cmp(&a.2));
+            let start = match config.before {
+                Some(before) => history
+                    .iter()
+                    .position(|(_, signature, _)| *signature == before)
+                    .map_or(history.len(), |i| i + 1),
+                None => 0,
+            };
+            Ok(history[start..]
+                .iter()
+                .take_while(|(_, signature, _)| Some(*signature) != config.until)
+                .take(config.limit.unwrap_or(1000))
+                .map(
+                    |(_, signature, slot)| RpcConfirmedTransactionStatusWithSignature {
+                        signature: signature.to_string(),
+                        slot: *slot,
+                        err: None,
+                        memo: None,
+                        block_time: None,
+                        confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+                    },
+                )
+                .collect())
+        }
+
+        fn get_transaction_with_config(
+            &self,
+            signature: &Signature,
+            _config: RpcTransactionConfig,
+        ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+            self.call(RpcMethod::GetTransaction)?;
+            self.state
+                .lock()
+                .transactions
+                .get(signature)
+                .cloned()
+                .ok_or_else(|| {
+                    ClientError::from(ClientErrorKind::Custom(format!(
+                        "transaction {} not found",
+                        signature
+                    )))
+                })
+        }
+    }
+
+    mod tests {
+        use super::*;
+        use solana_sdk::signature::Keypair;
+        use solana_sdk::signer::Signer;
+        use solana_sdk::system_instruction;
+
+        fn transfer(rpc: &FakeRpc) -> Transaction {
+            let payer = Keypair::new();
+            let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+            let (blockhash, _) = rpc
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+                .unwrap();
+            Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], blockhash)
+        }
+
+        fn status(rpc: &FakeRpc, signature: &Signature) -> Option<TransactionStatus> {
+            rpc.get_signature_statuses(&[*signature]).unwrap().remove(0)
+        }
+
+        #[test]
+        fn fail_next_fails_one_call_of_that_method() {
+            let rpc = FakeRpc::new();
+            let wallet = Pubkey::new_unique();
+            rpc.set_balance(&wallet, 42);
+            rpc.fail_next(RpcMethod::GetBalance, "node is behind");
+            rpc.fail_next(RpcMethod::GetBalance, "rate limited");
+
+            // Other methods are unaffected
+            assert_eq!(
+                rpc.get_minimum_balance_for_rent_exemption(0).unwrap(),
+                890_880
+            );
+
+            let first = rpc.get_balance(&wallet).unwrap_err();
+            assert!(first.to_string().contains("node is behind"), "{}", first);
+            let second = rpc.get_balance(&wallet).unwrap_err();
+            assert!(second.to_string().contains("rate limited"), "{}", second);
+            assert_eq!(rpc.get_balance(&wallet).unwrap(), 42);
+            assert_eq!(rpc.calls(RpcMethod::GetBalance), 3);
+        }
+
+        #[test]
+        fn timed_out_send_is_recorded_and_lands_nothing() {
+            let rpc = FakeRpc::new();
+            rpc.time_out_next(RpcMethod::SendTransaction);
+
+            let tx = transfer(&rpc);
+            let error = rpc.send_transaction(&tx).unwrap_err();
+            let ClientErrorKind::Io(io) = error.kind() else {
+                panic!("expected an IO error, got {}", error);
+            };
+            assert_eq!(io.kind(), std::io::ErrorKind::TimedOut);
+            assert_eq!(rpc.sent(), [tx.clone()]);
+            assert!(status(&rpc, &tx.signatures[0]).is_none());
+
+            // The next attempt goes through
+            assert_eq!(rpc.send_transaction(&tx).unwrap(), tx.signatures[0]);
+            assert!(status(&rpc, &tx.signatures[0]).is_some());
+        }
+
+        #[test]
+        fn sent_transactions_land_as_scripted() {
+            let rpc = FakeRpc::new();
+            rpc.land_next(Landing::Failed(TransactionError::InsufficientFundsForFee));
+            rpc.land_next(Landing::Dropped);
+
+            let failed = rpc.send_transaction(&transfer(&rpc)).unwrap();
+            let dropped = rpc.send_transaction(&transfer(&rpc)).unwrap();
+            let confirmed = rpc.send_transaction(&transfer(&rpc)).unwrap();
+
+            let failed = status(&rpc, &failed).unwrap();
+            assert_eq!(failed.err, Some(TransactionError::InsufficientFundsForFee));
+            assert_eq!(
+                failed.status,
+                Err(TransactionError::InsufficientFundsForFee)
+            );
+            assert!(status(&rpc, &dropped).is_none());
+            let confirmed = status(&rpc, &confirmed).unwrap();
+            assert_eq!(confirmed.err, None);
+            assert_eq!(
+                confirmed.confirmation_status,
+                Some(TransactionConfirmationStatus::Confirmed)
+            );
+            assert_eq!(rpc.sent().len(), 3);
+        }
+
+        #[test]
+        fn blockhashes_expire_once_the_chain_moves_past_them() {
+            let rpc = FakeRpc::new();
+            let (_, last_valid) = rpc
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+                .unwrap();
+            assert!(rpc.get_block_height().unwrap() > last_valid);
+
+            rpc.set_block_advance(1);
+            let (_, last_valid) = rpc
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+                .unwrap();
+            assert!(rpc.get_block_height().unwrap() <= last_valid);
+        }
+    }
+}
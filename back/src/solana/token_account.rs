@@ -0,0 +1,489 @@
+use crate::solana::rpc::SolanaRpc;
+use anyhow::Context;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder_client_types::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_client::rpc_response::RpcKeyedAccount;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use std::fmt;
+use std::str::FromStr;
+
+/// Both token programs: legacy SPL Token and Token-2022
+pub fn token_program_ids() -> [Pubkey; 2] {
+    [spl_token::id(), spl_token_2022::id()]
+}
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    token_program_ids().contains(program_id)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAccountState {
+    Uninitialized,
+    Initialized,
+    Frozen,
+}
+
+/// Reason an account cannot be closed as-is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloseBlocker {
+    NonZeroBalance(u64),
+    Frozen,
+    /// Token-2022 transfer fees withheld in the account must be harvested to the mint first
+    WithheldTransferFees(u64),
+}
+
+impl fmt::Display for CloseBlocker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloseBlocker::NonZeroBalance(amount) => {
+                write!(f, "account still holds {} raw tokens", amount)
+            }
+            CloseBlocker::Frozen => write!(f, "account is frozen by the mint freeze authority"),
+            CloseBlocker::WithheldTransferFees(amount) => write!(
+                f,
+                "{} raw tokens of withheld transfer fees must be harvested to the mint first",
+                amount
+            ),
+        }
+    }
+}
+
+/// Token account fields, whatever encoding the RPC returned
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTokenAccount {
+    /// Owning token program (legacy SPL Token or Token-2022)
+    pub program_id: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Only known for jsonParsed data; the binary layout does not carry decimals
+    pub decimals: Option<u8>,
+    pub state: TokenAccountState,
+    pub delegate: Option<Pubkey>,
+    pub delegated_amount: u64,
+    pub close_authority: Option<Pubkey>,
+    pub is_native: bool,
+    /// Token-2022 transfer fees withheld in this account (0 for legacy accounts)
+    pub withheld_transfer_fees: u64,
+}
+
+impl DecodedTokenAccount {
+    pub fn ui_amount(&self) -> Option<f64> {
+        self.decimals
+            .map(|decimals| self.amount as f64 / 10f64.powi(decimals as i32))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.state == TokenAccountState::Frozen
+    }
+
+    /// Everything preventing a CloseAccount instruction from succeeding; pass
+    /// `after_burn` when the full balance is burned in the same transaction
+    pub fn close_blockers(&self, after_burn: bool) -> Vec<CloseBlocker> {
+        let mut blockers = Vec::new();
+        if self.is_frozen() {
+            blockers.push(CloseBlocker::Frozen);
+        }
+        if self.amount > 0 && !after_burn && !self.is_native {
+            blockers.push(CloseBlocker::NonZeroBalance(self.amount));
+        }
+        if self.withheld_transfer_fees > 0 {
+            blockers.push(CloseBlocker::WithheldTransferFees(
+                self.withheld_transfer_fees,
+            ));
+        }
+        blockers
+    }
+}
+
+fn parse_pubkey(value: &serde_json::Value, field: &str) -> anyhow::Result<Pubkey> {
+    let raw = value
+        .get(field)
+        .and_then(|v| v.as_str())
+        .with_context(|| format!("missing {}", field))?;
+    Pubkey::from_str(raw).with_context(|| format!("invalid {}", field))
+}
+
+fn parse_optional_pubkey(value: &serde_json::Value, field: &str) -> anyhow::Result<Option<Pubkey>> {
+    match value.get(field).and_then(|v| v.as_str()) {
+        Some(raw) => Ok(Some(
+            Pubkey::from_str(raw).with_context(|| format!("invalid {}", field))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn parse_raw_amount(value: Option<&serde_json::Value>) -> anyhow::Result<u64> {
+    match value.and_then(|v| v.get("amount")) {
+        Some(amount) => amount
+            .as_str()
+            .context("amount is not a string")?
+            .parse::<u64>()
+            .context("invalid amount"),
+        None => Ok(0),
+    }
+}
+
+fn parsed_withheld_fees(info: &serde_json::Value) -> u64 {
+    info.get("extensions")
+        .and_then(|e| e.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|ext| ext.get("extension").and_then(|n| n.as_str()) == Some("transferFeeAmount"))
+        .filter_map(|ext| ext.get("state")?.get("withheldAmount")?.as_u64())
+        .sum()
+}
+
+fn decode_parsed(
+    parsed: &serde_json::Value,
+    program_id: &Pubkey,
+) -> anyhow::Result<DecodedTokenAccount> {
+    let account_type = parsed.get("type").and_then(|t| t.as_str());
+    if account_type != Some("account") {
+        anyhow::bail!("not a token account (type: {:?})", account_type);
+    }
+    let info = parsed.get("info").context("missing info")?;
+
+    let token_amount = info.get("tokenAmount").context("missing tokenAmount")?;
+    let decimals = token_amount
+        .get("decimals")
+        .and_then(|d| d.as_u64())
+        .context("missing decimals")? as u8;
+
+    let state = match info.get("state").and_then(|s| s.as_str()) {
+        Some("initialized") => TokenAccountState::Initialized,
+        Some("frozen") => TokenAccountState::Frozen,
+        Some("uninitialized") => TokenAccountState::Uninitialized,
+        other => anyhow::bail!("unknown account state: {:?}", other),
+    };
+
+    Ok(DecodedTokenAccount {
+        program_id: *program_id,
+        mint: parse_pubkey(info, "mint")?,
+        owner: parse_pubkey(info, "owner")?,
+        amount: parse_raw_amount(Some(token_amount))?,
+        decimals: Some(decimals),
+        state,
+        delegate: parse_optional_pubkey(info, "delegate")?,
+        delegated_amount: parse_raw_amount(info.get("delegatedAmount"))?,
+        close_authority: parse_optional_pubkey(info, "closeAuthority")?,
+        is_native: info
+            .get("isNative")
+            .and_then(|n| n.as_bool())
+            .unwrap_or(false),
+        withheld_transfer_fees: parsed_withheld_fees(info),
+    })
+}
+
+/// Decode the raw token account layout; Token-2022 accounts may carry extensions after it
+pub fn decode_binary(bytes: &[u8], program_id: &Pubkey) -> anyhow::Result<DecodedTokenAccount> {
+    let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(bytes)
+        .map_err(|e| anyhow::anyhow!("invalid token account data: {}", e))?;
+    let account = state.base;
+
+    let withheld_transfer_fees = state
+        .get_extension::<TransferFeeAmount>()
+        .map(|ext| u64::from(ext.withheld_amount))
+        .unwrap_or(0);
+
+    let account_state = match account.state {
+        spl_token_2022::state::AccountState::Uninitialized => TokenAccountState::Uninitialized,
+        spl_token_2022::state::AccountState::Initialized => TokenAccountState::Initialized,
+        spl_token_2022::state::AccountState::Frozen => TokenAccountState::Frozen,
+    };
+
+    Ok(DecodedTokenAccount {
+        program_id: *program_id,
+        mint: account.mint,
+        owner: account.owner,
+        amount: account.amount,
+        decimals: None,
+        state: account_state,
+        is_native: account.is_native.is_some(),
+        delegate: Option::from(account.delegate),
+        delegated_amount: account.delegated_amount,
+        close_authority: Option::from(account.close_authority),
+        withheld_transfer_fees,
+    })
+}
+
+/// Decode token account data returned by the RPC (jsonParsed or base58/base64 binary)
+pub fn decode_token_account(
+    data: &UiAccountData,
+    program_id: &Pubkey,
+) -> anyhow::Result<DecodedTokenAccount> {
+    if !is_token_program(program_id) {
+        anyhow::bail!("account is not owned by a token program: {}", program_id);
+    }
+    match data {
+        UiAccountData::Json(parsed) => decode_parsed(&parsed.parsed, program_id),
+        binary => {
+            let bytes = binary
+                .decode()
+                .context("unsupported token account encoding")?;
+            decode_binary(&bytes, program_id)
+        }
+    }
+}
+
+/// Decode the token account of a `getTokenAccountsByOwner` entry
+pub fn decode_keyed_account(
+    keyed: &RpcKeyedAccount,
+) -> anyhow::Result<(Pubkey, DecodedTokenAccount)> {
+    let pubkey = Pubkey::from_str(&keyed.pubkey).context("invalid token account pubkey")?;
+    let program_id =
+        Pubkey::from_str(&keyed.account.owner).context("invalid token account owner program")?;
+    let decoded = decode_token_account(&keyed.account.data, &program_id)
+        .with_context(|| format!("failed to decode token account {}", pubkey))?;
+    Ok((pubkey, decoded))
+}
+
+/// Validate an explicit rent recipient (e.g. a treasury wallet)
+pub fn parse_rent_destination(destination: &str) -> anyhow::Result<Pubkey> {
+    let destination =
+        Pubkey::from_str(destination.trim()).context("invalid rent destination pubkey")?;
+    if destination == Pubkey::default() || is_token_program(&destination) {
+        anyhow::bail!("rent destination cannot be a program: {}", destination);
+    }
+    Ok(destination)
+}
+
+/// Account receiving the rent of closed token accounts: `destination` when given,
+/// the owner otherwise
+pub fn resolve_rent_destination(
+    destination: Option<&str>,
+    owner: &Pubkey,
+) -> anyhow::Result<Pubkey> {
+    match destination.filter(|d| !d.trim().is_empty()) {
+        Some(destination) => parse_rent_destination(destination),
+        None => Ok(*owner),
+    }
+}
+
+/// Decoded token account with its address and rent-exempt balance
+#[derive(Debug, Clone)]
+pub struct OwnedTokenAccount {
+    pub pubkey: Pubkey,
+    /// Lamports held by the account, returned to the destination on close
+    pub lamports: u64,
+    pub account: DecodedTokenAccount,
+}
+
+/// All decodable token accounts of `owner`; undecodable entries are logged and skipped
+pub fn fetch_owned_token_accounts(
+    client: &dyn SolanaRpc,
+    owner: &Pubkey,
+) -> anyhow::Result<Vec<OwnedTokenAccount>> {
+    let mut owned = Vec::new();
+    for keyed in fetch_token_accounts(client, owner)? {
+        match decode_keyed_account(&keyed) {
+            Ok((pubkey, account)) => owned.push(OwnedTokenAccount {
+                pubkey,
+                lamports: keyed.account.lamports,
+                account,
+            }),
+            Err(e) => warn!("Could not parse token account data: {:#}", e),
+        }
+    }
+    Ok(owned)
+}
+
+/// All token accounts of `owner` under both token programs
+pub fn fetch_token_accounts(
+    client: &dyn SolanaRpc,
+    owner: &Pubkey,
+) -> anyhow::Result<Vec<RpcKeyedAccount>> {
+    let mut accounts = Vec::new();
+    for program_id in token_program_ids() {
+        let program_accounts = client
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program_id))
+            .with_context(|| format!("failed to get token accounts for program {}", program_id))?;
+        accounts.extend(program_accounts);
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_account_decoder_client_types::{ParsedAccount, UiAccountEncoding};
+
+    const MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const OWNER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const DELEGATE: &str = "3fFZHN7NBiBCwRXm9i8cNxnSpLhEGjmmqoNSdBDFHpgR";
+
+    /// `getTokenAccountsByOwner` jsonParsed entry: frozen, delegated, with close authority
+    const PARSED_FIXTURE: &str = r#"{
+        "program": "spl-token",
+        "parsed": {
+            "info": {
+                "closeAuthority": "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+                "delegate": "3fFZHN7NBiBCwRXm9i8cNxnSpLhEGjmmqoNSdBDFHpgR",
+                "delegatedAmount": {
+                    "amount": "500000",
+                    "decimals": 6,
+                    "uiAmount": 0.5,
+                    "uiAmountString": "0.5"
+                },
+                "isNative": false,
+                "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "owner": "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+                "state": "frozen",
+                "tokenAmount": {
+                    "amount": "1500000",
+                    "decimals": 6,
+                    "uiAmount": 1.5,
+                    "uiAmountString": "1.5"
+                }
+            },
+            "type": "account"
+        },
+        "space": 165
+    }"#;
+
+    /// Same account as PARSED_FIXTURE, base64 encoded
+    const BINARY_FIXTURE: &str = "xvp6877brTo9ZfNqq8l0MbG75MLS9uDkfKYCA0UvXWF+jAiHYL/eHd3PMsF/IJuCQu5SqvEx+s2I0OosbQsG8mDjFgAAAAAAAQAAACeD515vnfzpYLkiBf1/QvbecRr1w10DfmoHenln8XrqAgAAAAAAAAAAAAAAACChBwAAAAAAAQAAAH6MCIdgv94d3c8ywX8gm4JC7lKq8TH6zYjQ6ixtCwby";
+
+    /// Empty initialized account without delegate or close authority
+    const EMPTY_BINARY_FIXTURE: &str = "xvp6877brTo9ZfNqq8l0MbG75MLS9uDkfKYCA0UvXWF+jAiHYL/eHd3PMsF/IJuCQu5SqvEx+s2I0OosbQsG8gAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+    fn pk(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    /// Token-2022 account (transfer fee extension) with 4200 raw tokens of withheld fees
+    const TOKEN_2022_PARSED_FIXTURE: &str = r#"{
+        "program": "spl-token-2022",
+        "parsed": {
+            "info": {
+                "extensions": [
+                    { "extension": "immutableOwner" },
+                    { "extension": "transferFeeAmount", "state": { "withheldAmount": 4200 } }
+                ],
+                "isNative": false,
+                "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "owner": "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+                "state": "initialized",
+                "tokenAmount": {
+                    "amount": "0",
+                    "decimals": 6,
+                    "uiAmount": 0.0,
+                    "uiAmountString": "0"
+                }
+            },
+            "type": "account"
+        },
+        "space": 182
+    }"#;
+
+    fn parsed_fixture() -> UiAccountData {
+        let parsed: ParsedAccount = serde_json::from_str(PARSED_FIXTURE).unwrap();
+        UiAccountData::Json(parsed)
+    }
+
+    #[test]
+    fn decodes_json_parsed_account() {
+        let decoded = decode_token_account(&parsed_fixture(), &spl_token::id()).unwrap();
+        assert_eq!(decoded.mint, pk(MINT));
+        assert_eq!(decoded.owner, pk(OWNER));
+        assert_eq!(decoded.amount, 1_500_000);
+        assert_eq!(decoded.decimals, Some(6));
+        assert_eq!(decoded.ui_amount(), Some(1.5));
+        assert_eq!(decoded.state, TokenAccountState::Frozen);
+        assert_eq!(decoded.delegate, Some(pk(DELEGATE)));
+        assert_eq!(decoded.delegated_amount, 500_000);
+        assert_eq!(decoded.close_authority, Some(pk(OWNER)));
+        assert!(!decoded.is_native);
+        assert_eq!(decoded.program_id, spl_token::id());
+        assert_eq!(
+            decoded.close_blockers(false),
+            vec![
+                CloseBlocker::Frozen,
+                CloseBlocker::NonZeroBalance(1_500_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_base64_account() {
+        let data = UiAccountData::Binary(BINARY_FIXTURE.to_string(), UiAccountEncoding::Base64);
+        let decoded = decode_token_account(&data, &spl_token::id()).unwrap();
+        let parsed = decode_token_account(&parsed_fixture(), &spl_token::id()).unwrap();
+        assert_eq!(
+            decoded,
+            DecodedTokenAccount {
+                decimals: None,
+                ..parsed
+            }
+        );
+        assert_eq!(decoded.ui_amount(), None);
+    }
+
+    #[test]
+    fn decodes_empty_account() {
+        let data =
+            UiAccountData::Binary(EMPTY_BINARY_FIXTURE.to_string(), UiAccountEncoding::Base64);
+        let decoded = decode_token_account(&data, &spl_token::id()).unwrap();
+        assert!(decoded.is_empty());
+        assert_eq!(decoded.state, TokenAccountState::Initialized);
+        assert_eq!(decoded.delegate, None);
+        assert_eq!(decoded.close_authority, None);
+        assert!(decoded.close_blockers(false).is_empty());
+    }
+
+    #[test]
+    fn decodes_token_2022_withheld_fees() {
+        let parsed: ParsedAccount = serde_json::from_str(TOKEN_2022_PARSED_FIXTURE).unwrap();
+        let decoded =
+            decode_token_account(&UiAccountData::Json(parsed), &spl_token_2022::id()).unwrap();
+        assert_eq!(decoded.program_id, spl_token_2022::id());
+        assert!(decoded.is_empty());
+        assert_eq!(decoded.withheld_transfer_fees, 4200);
+        assert_eq!(
+            decoded.close_blockers(false),
+            vec![CloseBlocker::WithheldTransferFees(4200)]
+        );
+    }
+
+    #[test]
+    fn rejects_non_token_program_owner() {
+        let owner = pk(OWNER);
+        assert!(decode_token_account(&parsed_fixture(), &owner).is_err());
+    }
+
+    #[test]
+    fn resolves_rent_destination() {
+        let owner = pk(OWNER);
+        assert_eq!(resolve_rent_destination(None, &owner).unwrap(), owner);
+        assert_eq!(resolve_rent_destination(Some("  "), &owner).unwrap(), owner);
+        assert_eq!(
+            resolve_rent_destination(Some(DELEGATE), &owner).unwrap(),
+            pk(DELEGATE)
+        );
+        assert!(resolve_rent_destination(Some("not-a-pubkey"), &owner).is_err());
+        let token_program = spl_token::id().to_string();
+        assert!(resolve_rent_destination(Some(&token_program), &owner).is_err());
+    }
+
+    #[test]
+    fn rejects_mint_accounts() {
+        let mut parsed: ParsedAccount = serde_json::from_str(PARSED_FIXTURE).unwrap();
+        parsed.parsed["type"] = serde_json::json!("mint");
+        assert!(decode_token_account(&UiAccountData::Json(parsed), &spl_token::id()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let data = UiAccountData::Binary("AAAA".to_string(), UiAccountEncoding::Base64);
+        assert!(decode_token_account(&data, &spl_token::id()).is_err());
+    }
+}
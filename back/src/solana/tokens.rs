@@ -1,5 +1,3 @@
-// use super::ipfs::CreateTokenMetadata;
-
 pub struct TokenCollection {
     pub tokens: Vec<CreateTokenMetadata>,
 }
@@ -7,6 +5,43 @@ pub struct TokenCollection {
 use std::sync::LazyLock;
 
 use pumpfun::utils::CreateTokenMetadata;
+use rand::Rng;
+use serde::Serialize;
+
+/// Summary of a built-in template, as listed to the UI
+#[derive(Serialize, Debug, Clone)]
+pub struct TokenTemplate {
+    pub name: String,
+    pub symbol: String,
+    pub description: String,
+}
+
+impl TokenCollection {
+    pub fn templates(&self) -> Vec<TokenTemplate> {
+        self.tokens
+            .iter()
+            .map(|t| TokenTemplate {
+                name: t.name.clone(),
+                symbol: t.symbol.clone(),
+                description: t.description.clone(),
+            })
+            .collect()
+    }
+
+    /// Template by symbol (case insensitive), or a random one for "random"
+    pub fn find(&self, key: &str) -> Option<&CreateTokenMetadata> {
+        if key.eq_ignore_ascii_case("random") {
+            if self.tokens.is_empty() {
+                return None;
+            }
+            let idx = rand::thread_rng().gen_range(0..self.tokens.len());
+            return self.tokens.get(idx);
+        }
+        self.tokens
+            .iter()
+            .find(|t| t.symbol.eq_ignore_ascii_case(key))
+    }
+}
 
 pub static TOKEN_COLLECTION: LazyLock<TokenCollection> = LazyLock::new(|| TokenCollection {
     tokens: vec![
@@ -0,0 +1,445 @@
+//! Local `solana-test-validator` with the fixtures from `tests/fixtures` loaded,
+//! plus the helpers the scenarios use to fund wallets, mint tokens and read exact
+//! balances and fees back from the ledger.
+
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiMessage, UiTransactionEncoding,
+};
+use std::collections::HashSet;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(90);
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+const BANK_LAMPORTS: u64 = 500_000_000_000;
+
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// A running validator; killed and its ledger removed on drop
+pub struct Validator {
+    child: Child,
+    ledger: PathBuf,
+    pub client: RpcClient,
+    bank: Keypair,
+}
+
+impl Validator {
+    /// Start the validator and point the service layer at it. `CONFIG` is read
+    /// once per process, so this must run before any service call.
+    pub fn start() -> Self {
+        let rpc_port = free_port();
+        let faucet_port = loop {
+            let port = free_port();
+            if port != rpc_port && port != rpc_port + 1 {
+                break port;
+            }
+        };
+        let ledger = std::env::temp_dir().join(format!("memecore-e2e-{}", std::process::id()));
+        let data_dir = ledger.join("app_data");
+
+        let binary = std::env::var("SOLANA_TEST_VALIDATOR")
+            .unwrap_or_else(|_| "solana-test-validator".to_string());
+        let mut command = Command::new(&binary);
+        command
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(&ledger)
+            .arg("--rpc-port")
+            .arg(rpc_port.to_string())
+            .arg("--faucet-port")
+            .arg(faucet_port.to_string());
+
+        for (program_id, path) in fixture_programs() {
+            command.arg("--bpf-program").arg(program_id).arg(path);
+        }
+        let accounts = fixtures_dir().join("accounts");
+        if has_json_files(&accounts) {
+            command.arg("--account-dir").arg(&accounts);
+        }
+
+        let child = command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("failed to start {}: {}", binary, e));
+
+        let rpc_url = format!("http://127.0.0.1:{}", rpc_port);
+        std::env::set_var("HELIUS_HTTPS", &rpc_url);
+        std::env::set_var("HELIUS_WS", format!("ws://127.0.0.1:{}", rpc_port + 1));
+        std::env::set_var("DATA_DIR", &data_dir);
+
+        let mut validator = Validator {
+            child,
+            ledger,
+            client: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            bank: Keypair::new(),
+        };
+        validator.wait_until_ready();
+        let bank = validator.bank.pubkey();
+        validator.airdrop(&bank, BANK_LAMPORTS);
+        validator
+    }
+
+    fn wait_until_ready(&mut self) {
+        let started = Instant::now();
+        loop {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                panic!(
+                    "solana-test-validator exited with {} (see {}/validator.log)",
+                    status,
+                    self.ledger.display()
+                );
+            }
+            if self.client.get_health().is_ok() && self.client.get_slot().unwrap_or(0) > 0 {
+                return;
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                panic!("solana-test-validator did not become healthy in time");
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+    }
+
+    pub fn airdrop(&self, to: &Pubkey, lamports: u64) {
+        let signature = self
+            .client
+            .request_airdrop(to, lamports)
+            .expect("airdrop request failed");
+        self.confirm(&signature);
+    }
+
+    fn confirm(&self, signature: &Signature) {
+        let started = Instant::now();
+        while !self.client.confirm_transaction(signature).unwrap_or(false) {
+            if started.elapsed() > CONFIRM_TIMEOUT {
+                panic!("transaction {} was not confirmed in time", signature);
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    /// New keypair holding exactly `lamports`
+    pub fn funded_wallet(&self, lamports: u64) -> Keypair {
+        let wallet = Keypair::new();
+        self.transfer(&wallet.pubkey(), lamports);
+        wallet
+    }
+
+    /// Send `lamports` from the bank wallet, so the fees never touch test wallets
+    pub fn transfer(&self, to: &Pubkey, lamports: u64) {
+        let ix = system_instruction::transfer(&self.bank.pubkey(), to, lamports);
+        self.send(&[ix], &[&self.bank]);
+    }
+
+    /// Send instructions paid for by the bank wallet
+    pub fn send(
+        &self,
+        instructions: &[solana_sdk::instruction::Instruction],
+        signers: &[&Keypair],
+    ) {
+        let blockhash = self
+            .client
+            .get_latest_blockhash()
+            .expect("failed to get blockhash");
+        let mut all_signers: Vec<&Keypair> = vec![&self.bank];
+        all_signers.extend(signers.iter().filter(|s| s.pubkey() != self.bank.pubkey()));
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.bank.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.client
+            .send_and_confirm_transaction(&tx)
+            .expect("setup transaction failed");
+    }
+
+    pub fn balance(&self, pubkey: &Pubkey) -> u64 {
+        self.client
+            .get_balance(pubkey)
+            .expect("failed to get balance")
+    }
+
+    pub fn account_exists(&self, pubkey: &Pubkey) -> bool {
+        self.client
+            .get_account_with_commitment(pubkey, CommitmentConfig::confirmed())
+            .expect("failed to get account")
+            .value
+            .is_some()
+    }
+
+    pub fn token_account_rent(&self) -> u64 {
+        self.client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+            .expect("failed to get rent")
+    }
+
+    /// New SPL Token mint with the bank as mint authority
+    pub fn create_mint(&self, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let rent = self
+            .client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+            .expect("failed to get rent");
+        let instructions = [
+            system_instruction::create_account(
+                &self.bank.pubkey(),
+                &mint.pubkey(),
+                rent,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.bank.pubkey(),
+                None,
+                decimals,
+            )
+            .expect("failed to build initialize_mint2"),
+        ];
+        self.send(&instructions, &[&mint]);
+        mint.pubkey()
+    }
+
+    /// New token account of `owner` for `mint`, holding `amount` raw units.
+    /// The bank pays the rent.
+    pub fn create_token_account(&self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let account = Keypair::new();
+        let mut instructions = vec![
+            system_instruction::create_account(
+                &self.bank.pubkey(),
+                &account.pubkey(),
+                self.token_account_rent(),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account3(
+                &spl_token::id(),
+                &account.pubkey(),
+                mint,
+                owner,
+            )
+            .expect("failed to build initialize_account3"),
+        ];
+        if amount > 0 {
+            instructions.push(
+                spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    mint,
+                    &account.pubkey(),
+                    &self.bank.pubkey(),
+                    &[],
+                    amount,
+                )
+                .expect("failed to build mint_to"),
+            );
+        }
+        self.send(&instructions, &[&account]);
+        account.pubkey()
+    }
+
+    /// Raw token amount of a token account, 0 when it does not exist
+    pub fn token_amount(&self, account: &Pubkey) -> u64 {
+        match self.client.get_token_account_balance(account) {
+            Ok(balance) => balance.amount.parse().expect("invalid token amount"),
+            Err(_) => 0,
+        }
+    }
+
+    pub fn mint_supply(&self, mint: &Pubkey) -> u64 {
+        self.client
+            .get_token_supply(mint)
+            .expect("failed to get token supply")
+            .amount
+            .parse()
+            .expect("invalid token supply")
+    }
+
+    /// Snapshot of `payer`'s signatures; transactions after it are what a service sent
+    pub fn tracker(&self, payer: &Pubkey) -> TxTracker<'_> {
+        TxTracker {
+            validator: self,
+            payer: *payer,
+            seen: self.signatures(payer),
+        }
+    }
+
+    fn signatures(&self, address: &Pubkey) -> HashSet<Signature> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        };
+        self.client
+            .get_signatures_for_address_with_config(address, config)
+            .expect("failed to get signatures")
+            .into_iter()
+            .filter_map(|s| Signature::from_str(&s.signature).ok())
+            .collect()
+    }
+
+    fn transaction(&self, signature: &Signature) -> EncodedConfirmedTransactionWithStatusMeta {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::JsonParsed),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        self.client
+            .get_transaction_with_config(signature, config)
+            .expect("failed to get transaction")
+    }
+}
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+/// Lamport changes of one transaction, by account
+pub struct TxEffects {
+    pub fee_payer: String,
+    pub fee: u64,
+    pub success: bool,
+    pub deltas: Vec<(String, i64)>,
+}
+
+impl TxEffects {
+    pub fn delta(&self, account: &Pubkey) -> i64 {
+        let account = account.to_string();
+        self.deltas
+            .iter()
+            .filter(|(key, _)| *key == account)
+            .map(|(_, delta)| *delta)
+            .sum()
+    }
+}
+
+pub struct TxTracker<'a> {
+    validator: &'a Validator,
+    payer: Pubkey,
+    seen: HashSet<Signature>,
+}
+
+impl TxTracker<'_> {
+    /// Transactions paid for by the tracked wallet since the snapshot
+    pub fn new_transactions(&self) -> Vec<TxEffects> {
+        let payer = self.payer.to_string();
+        self.validator
+            .signatures(&self.payer)
+            .difference(&self.seen)
+            .map(|signature| effects(&self.validator.transaction(signature)))
+            .filter(|effects| effects.fee_payer == payer)
+            .collect()
+    }
+
+    /// Total network fees the tracked wallet paid since the snapshot
+    pub fn fees_paid(&self) -> u64 {
+        self.new_transactions().iter().map(|tx| tx.fee).sum()
+    }
+}
+
+fn effects(tx: &EncodedConfirmedTransactionWithStatusMeta) -> TxEffects {
+    let mut account_keys = Vec::new();
+    if let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction {
+        if let UiMessage::Parsed(message) = &ui_tx.message {
+            account_keys = message
+                .account_keys
+                .iter()
+                .map(|k| k.pubkey.clone())
+                .collect();
+        }
+    }
+    let meta = tx
+        .transaction
+        .meta
+        .as_ref()
+        .expect("transaction without status meta");
+    let deltas = account_keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let pre = meta.pre_balances.get(i).copied().unwrap_or(0) as i64;
+            let post = meta.post_balances.get(i).copied().unwrap_or(0) as i64;
+            (key.clone(), post - pre)
+        })
+        .collect();
+    TxEffects {
+        fee_payer: account_keys.first().cloned().unwrap_or_default(),
+        fee: meta.fee,
+        success: meta.err.is_none(),
+        deltas,
+    }
+}
+
+pub fn private_key(keypair: &Keypair) -> String {
+    keypair.to_base58_string()
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    let ata_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap();
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ata_program,
+    )
+    .0
+}
+
+/// `tests/fixtures/programs/<program id>.so`
+fn fixture_programs() -> Vec<(String, PathBuf)> {
+    let dir = fixtures_dir().join("programs");
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "so"))
+        .filter_map(|path| {
+            let program_id = path.file_stem()?.to_str()?.to_string();
+            Pubkey::from_str(&program_id).ok()?;
+            Some((program_id, path))
+        })
+        .collect()
+}
+
+fn has_json_files(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .any(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        })
+        .unwrap_or(false)
+}
+
+fn free_port() -> u16 {
+    // The websocket listens on rpc port + 1, so keep the pair clear of each other
+    loop {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map(|addr| addr.port())
+            .expect("failed to find a free port");
+        if port < u16::MAX - 1 && TcpListener::bind(("127.0.0.1", port + 1)).is_ok() {
+            return port;
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! End-to-end tests of the service layer against a local `solana-test-validator`.
+//!
+//! Everything runs offline against a fresh validator. The pump.fun buy/sell needs
+//! dumped fixtures and runs from its own binary, `tests/e2e_trading`. The tests
+//! start a validator, so they are ignored by default:
+//!
+//! ```sh
+//! cargo test --test e2e -- --ignored --nocapture
+//! ```
+//!
+//! `CONFIG` is read once per process, so all scenarios share one validator and
+//! run in order from a single test.
+
+mod harness;
+mod token_accounts;
+mod transfers;
+
+use harness::Validator;
+
+macro_rules! scenario {
+    ($validator:expr, $path:path) => {{
+        eprintln!("e2e: {}", stringify!($path));
+        $path($validator).await;
+    }};
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "starts solana-test-validator"]
+async fn services_against_local_validator() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let validator = Validator::start();
+
+    scenario!(&validator, transfers::distribute_sol);
+    scenario!(&validator, transfers::refunds_to);
+    scenario!(&validator, transfers::refunds_amount_to);
+    scenario!(&validator, token_accounts::close_accounts);
+    scenario!(&validator, token_accounts::close_token_account);
+    scenario!(&validator, token_accounts::burn_tokens);
+}
@@ -0,0 +1,92 @@
+use crate::harness::{private_key, Validator};
+use app_lib::services::{burn_tokens, close_accounts, close_token_account};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+
+const WALLET_LAMPORTS: u64 = LAMPORTS_PER_SOL / 10;
+
+pub async fn close_accounts(validator: &Validator) {
+    let wallet = validator.funded_wallet(WALLET_LAMPORTS);
+    let empty: Vec<Pubkey> = (0..3)
+        .map(|_| {
+            let mint = validator.create_mint(6);
+            validator.create_token_account(&wallet.pubkey(), &mint, 0)
+        })
+        .collect();
+    let funded_mint = validator.create_mint(6);
+    let funded = validator.create_token_account(&wallet.pubkey(), &funded_mint, 1_000);
+    let collector = Pubkey::new_unique();
+    let rent = validator.token_account_rent();
+    let tracker = validator.tracker(&wallet.pubkey());
+
+    close_accounts::close_accounts(private_key(&wallet), Some(collector.to_string()))
+        .await
+        .expect("close_accounts failed");
+
+    for account in &empty {
+        assert!(!validator.account_exists(account), "{} still open", account);
+    }
+    assert!(
+        validator.account_exists(&funded),
+        "non-empty account was closed"
+    );
+    assert_eq!(validator.token_amount(&funded), 1_000);
+
+    assert_eq!(validator.balance(&collector), 3 * rent);
+    let transactions = tracker.new_transactions();
+    assert_eq!(transactions.len(), 1, "three closes fit in one batch");
+    assert_eq!(
+        validator.balance(&wallet.pubkey()),
+        WALLET_LAMPORTS - tracker.fees_paid()
+    );
+}
+
+pub async fn close_token_account(validator: &Validator) {
+    let wallet = validator.funded_wallet(WALLET_LAMPORTS);
+    let mint = validator.create_mint(9);
+    let account = validator.create_token_account(&wallet.pubkey(), &mint, 0);
+    let rent = validator.token_account_rent();
+    let tracker = validator.tracker(&wallet.pubkey());
+
+    close_token_account::close_token_account(private_key(&wallet), mint.to_string(), None, None)
+        .await
+        .expect("close_token_account failed");
+
+    // Without a rent destination the rent comes back to the wallet itself
+    assert!(!validator.account_exists(&account));
+    assert_eq!(
+        validator.balance(&wallet.pubkey()),
+        WALLET_LAMPORTS + rent - tracker.fees_paid()
+    );
+}
+
+pub async fn burn_tokens(validator: &Validator) {
+    let wallet = validator.funded_wallet(WALLET_LAMPORTS);
+    let mint = validator.create_mint(6);
+    let account = validator.create_token_account(&wallet.pubkey(), &mint, 1_000_000);
+    let supply_before = validator.mint_supply(&mint);
+    let tracker = validator.tracker(&wallet.pubkey());
+
+    burn_tokens::burn_tokens(private_key(&wallet), mint.to_string(), 25.0)
+        .await
+        .expect("burn_tokens failed");
+
+    assert_eq!(validator.token_amount(&account), 750_000);
+    assert_eq!(validator.mint_supply(&mint), supply_before - 250_000);
+    assert_eq!(
+        validator.balance(&wallet.pubkey()),
+        WALLET_LAMPORTS - tracker.fees_paid()
+    );
+
+    burn_tokens::burn_tokens(private_key(&wallet), mint.to_string(), 100.0)
+        .await
+        .expect("burn_tokens failed");
+
+    assert_eq!(validator.token_amount(&account), 0);
+    assert_eq!(validator.mint_supply(&mint), supply_before - 1_000_000);
+    assert!(
+        validator.account_exists(&account),
+        "burning must not close the account"
+    );
+}
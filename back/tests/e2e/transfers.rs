@@ -0,0 +1,92 @@
+use crate::harness::{private_key, Validator};
+use app_lib::services::{distribute_sol, refund_wallets};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+
+/// Lamports the refund services leave behind to pay the transfer fee
+const MIN_RESERVE: u64 = 5_000;
+
+pub async fn distribute_sol(validator: &Validator) {
+    let source = validator.funded_wallet(10 * LAMPORTS_PER_SOL);
+    // 12 destinations: one full batch of 10 transfers and one of 2
+    let destinations: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+    let tracker = validator.tracker(&source.pubkey());
+
+    distribute_sol::distribute_sol(
+        private_key(&source),
+        destinations.iter().map(|d| d.to_string()).collect(),
+        1.2,
+        None,
+    )
+    .await
+    .expect("distribute_sol failed");
+
+    let per_destination = 100_000_000;
+    for destination in &destinations {
+        assert_eq!(validator.balance(destination), per_destination);
+    }
+
+    let transactions = tracker.new_transactions();
+    assert_eq!(transactions.len(), 2, "expected two transfer batches");
+    assert!(transactions.iter().all(|tx| tx.success));
+    let fees = tracker.fees_paid();
+    assert_eq!(
+        validator.balance(&source.pubkey()),
+        10 * LAMPORTS_PER_SOL - 12 * per_destination - fees
+    );
+}
+
+pub async fn refunds_to(validator: &Validator) {
+    let wallets: Vec<_> = (0..3)
+        .map(|_| validator.funded_wallet(LAMPORTS_PER_SOL))
+        .collect();
+    let destination = Pubkey::new_unique();
+
+    refund_wallets::refunds_to(
+        wallets.iter().map(private_key).collect(),
+        destination.to_string(),
+        String::new(),
+        None,
+    )
+    .await
+    .expect("refunds_to failed");
+
+    // Each wallet sends everything but the fee reserve, which the fee consumes
+    for wallet in &wallets {
+        assert_eq!(validator.balance(&wallet.pubkey()), 0);
+    }
+    assert_eq!(
+        validator.balance(&destination),
+        3 * (LAMPORTS_PER_SOL - MIN_RESERVE)
+    );
+}
+
+pub async fn refunds_amount_to(validator: &Validator) {
+    let wallets: Vec<_> = (0..2)
+        .map(|_| validator.funded_wallet(LAMPORTS_PER_SOL))
+        .collect();
+    let trackers: Vec<_> = wallets
+        .iter()
+        .map(|w| validator.tracker(&w.pubkey()))
+        .collect();
+    let destination = Pubkey::new_unique();
+
+    refund_wallets::refunds_amount_to(
+        wallets.iter().map(private_key).collect(),
+        destination.to_string(),
+        0.25,
+        None,
+    )
+    .await
+    .expect("refunds_amount_to failed");
+
+    let amount = LAMPORTS_PER_SOL / 4;
+    assert_eq!(validator.balance(&destination), 2 * amount);
+    for (wallet, tracker) in wallets.iter().zip(&trackers) {
+        assert_eq!(
+            validator.balance(&wallet.pubkey()),
+            LAMPORTS_PER_SOL - amount - tracker.fees_paid()
+        );
+    }
+}
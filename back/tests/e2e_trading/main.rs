@@ -0,0 +1,28 @@
+//! End-to-end buy/sell against the pump.fun program on a local `solana-test-validator`.
+//!
+//! Needs the pump.fun fixtures, which are not committed (see
+//! `tests/fixtures/README.md`), and fails without them. It starts its own
+//! validator, so it lives in its own test binary and is ignored by default:
+//!
+//! ```sh
+//! tests/fixtures/dump.sh <MINT>
+//! cargo test --test e2e_trading -- --ignored --nocapture
+//! ```
+
+#[allow(dead_code)]
+#[path = "../e2e/harness.rs"]
+mod harness;
+mod trading;
+
+use harness::Validator;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "starts solana-test-validator, needs the dumped pump.fun fixtures"]
+async fn buy_then_sell_against_local_validator() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    // Check the fixtures before paying for a validator start
+    let mint = trading::fixture_mint();
+    let validator = Validator::start();
+
+    trading::buy_then_sell(mint, &validator).await;
+}
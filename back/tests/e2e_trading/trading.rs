@@ -0,0 +1,169 @@
+use crate::harness::{associated_token_address, fixtures_dir, private_key, TxEffects, Validator};
+use app_lib::services::common::{QuickBuyReq, QuickSellReq};
+use app_lib::services::{quick_buy, quick_sell};
+use app_lib::solana::pump::{
+    bonding_curve_pda, decode_bonding_curve, BondingCurveAccount, PUMP_PROGRAM_ID,
+};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use std::str::FromStr;
+
+const WALLET_LAMPORTS: u64 = 2 * LAMPORTS_PER_SOL;
+const BUY_LAMPORTS: u64 = LAMPORTS_PER_SOL / 10;
+
+/// Mint of the dumped pump.fun token; panics unless `tests/fixtures/dump.sh` has written
+/// it along with the program and the accounts a trade needs
+pub fn fixture_mint() -> Pubkey {
+    let dir = fixtures_dir();
+    let hint = "run tests/fixtures/dump.sh <MINT> first";
+    let mint = std::fs::read_to_string(dir.join("pump_mint.txt"))
+        .unwrap_or_else(|e| panic!("no pump_mint.txt fixture ({}), {}", e, hint));
+    let mint = Pubkey::from_str(mint.trim()).expect("invalid mint in pump_mint.txt");
+    let required = [
+        dir.join("programs").join(format!("{}.so", PUMP_PROGRAM_ID)),
+        dir.join("accounts").join(format!("{}.json", mint)),
+        dir.join("accounts")
+            .join(format!("{}.json", bonding_curve_pda(&mint))),
+    ];
+    for path in required {
+        assert!(
+            path.exists(),
+            "missing fixture {}, {}",
+            path.display(),
+            hint
+        );
+    }
+    mint
+}
+
+/// Curve state and the lamports and tokens it holds
+struct CurveSnapshot {
+    state: BondingCurveAccount,
+    lamports: u64,
+    tokens: u64,
+}
+
+fn curve_snapshot(validator: &Validator, curve: &Pubkey, curve_ata: &Pubkey) -> CurveSnapshot {
+    let account = validator
+        .client
+        .get_account(curve)
+        .expect("bonding curve fixture is not loaded");
+    CurveSnapshot {
+        state: decode_bonding_curve(&account.data).expect("not a bonding curve account"),
+        lamports: account.lamports,
+        tokens: validator.token_amount(curve_ata),
+    }
+}
+
+/// Lamports that went to accounts other than the wallet and the curve: protocol
+/// and creator fees, plus rent of accounts the trade opened
+fn paid_to_others(transactions: &[TxEffects], wallet: &Pubkey, curve: &Pubkey) -> i64 {
+    let excluded = [wallet.to_string(), curve.to_string()];
+    transactions
+        .iter()
+        .flat_map(|tx| tx.deltas.iter())
+        .filter(|(key, _)| !excluded.contains(key))
+        .map(|(_, delta)| *delta)
+        .filter(|delta| *delta > 0)
+        .sum()
+}
+
+pub async fn buy_then_sell(mint: Pubkey, validator: &Validator) {
+    let token_program = validator
+        .client
+        .get_account(&mint)
+        .expect("mint fixture is not loaded")
+        .owner;
+    let curve = bonding_curve_pda(&mint);
+    let curve_ata = associated_token_address(&curve, &mint, &token_program);
+
+    let wallet = validator.funded_wallet(WALLET_LAMPORTS);
+    let wallet_ata = associated_token_address(&wallet.pubkey(), &mint, &token_program);
+
+    // Buy
+    let before = curve_snapshot(validator, &curve, &curve_ata);
+    assert!(!before.state.complete, "fixture curve has already migrated");
+    let tracker = validator.tracker(&wallet.pubkey());
+
+    let res = quick_buy::quick_buy(QuickBuyReq {
+        pk: private_key(&wallet).into(),
+        amount_sol: BUY_LAMPORTS as f64 / LAMPORTS_PER_SOL as f64,
+        mint: mint.to_string(),
+        slippage_bps: Some(500),
+    })
+    .await
+    .expect("quick_buy failed");
+    assert!(res.ok, "quick_buy failed: {:?}", res.error);
+
+    let after = curve_snapshot(validator, &curve, &curve_ata);
+    let bought = validator.token_amount(&wallet_ata);
+    assert!(bought > 0, "no tokens received");
+    assert_eq!(
+        bought,
+        before.state.virtual_token_reserves - after.state.virtual_token_reserves
+    );
+    assert_eq!(bought, before.tokens - after.tokens);
+
+    let sol_in = after.lamports - before.lamports;
+    assert!(sol_in > 0 && sol_in <= BUY_LAMPORTS);
+    assert_eq!(
+        sol_in,
+        after.state.virtual_sol_reserves - before.state.virtual_sol_reserves
+    );
+    assert_eq!(
+        sol_in,
+        after.state.real_sol_reserves - before.state.real_sol_reserves
+    );
+
+    let transactions = tracker.new_transactions();
+    assert!(transactions.iter().all(|tx| tx.success));
+    let others = paid_to_others(&transactions, &wallet.pubkey(), &curve);
+    let fees = tracker.fees_paid();
+    let wallet_after_buy = validator.balance(&wallet.pubkey());
+    assert_eq!(
+        wallet_after_buy as i64,
+        WALLET_LAMPORTS as i64 - sol_in as i64 - others - fees as i64
+    );
+
+    // Sell everything back
+    let before = after;
+    let tracker = validator.tracker(&wallet.pubkey());
+
+    let res = quick_sell::quick_sell(QuickSellReq {
+        wallet: wallet.pubkey().to_string(),
+        pk: private_key(&wallet).into(),
+        percent: 100,
+        mint: mint.to_string(),
+        slippage_bps: Some(500),
+    })
+    .await;
+    assert!(res.ok, "quick_sell failed: {:?}", res.error);
+
+    let after = curve_snapshot(validator, &curve, &curve_ata);
+    assert_eq!(validator.token_amount(&wallet_ata), 0);
+    assert_eq!(
+        after.state.virtual_token_reserves - before.state.virtual_token_reserves,
+        bought
+    );
+    assert_eq!(after.tokens - before.tokens, bought);
+
+    let sol_out = before.lamports - after.lamports;
+    assert!(
+        sol_out > 0 && sol_out < sol_in,
+        "a round trip cannot profit"
+    );
+    assert_eq!(
+        sol_out,
+        before.state.real_sol_reserves - after.state.real_sol_reserves
+    );
+
+    let transactions = tracker.new_transactions();
+    assert!(transactions.iter().all(|tx| tx.success));
+    let others = paid_to_others(&transactions, &wallet.pubkey(), &curve);
+    let fees = tracker.fees_paid();
+    assert_eq!(
+        validator.balance(&wallet.pubkey()) as i64,
+        wallet_after_buy as i64 + sol_out as i64 - others - fees as i64
+    );
+}
@@ -0,0 +1,85 @@
+#!/usr/bin/env bash
+# Dump the pump.fun programs and every account a buy/sell of MINT touches into
+# tests/fixtures, so the end-to-end tests can run against an offline validator.
+#
+#   tests/fixtures/dump.sh <MINT> [EXTRA_ACCOUNT...]
+#
+# RPC_URL selects the cluster to dump from (mainnet-beta by default). Needs the
+# solana CLI and python3.
+set -euo pipefail
+
+MINT=${1:?usage: dump.sh <MINT> [EXTRA_ACCOUNT...]}
+shift
+RPC_URL=${RPC_URL:-https://api.mainnet-beta.solana.com}
+
+PUMP=6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P
+PUMP_FEES=pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ
+ATA_PROGRAM=ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL
+
+DIR=$(cd "$(dirname "$0")" && pwd)
+mkdir -p "$DIR/programs" "$DIR/accounts"
+
+pda() {
+    solana find-program-derived-address "$@" --output json |
+        python3 -c 'import json, sys; print(json.load(sys.stdin)["address"])'
+}
+
+account_field() {
+    solana account -u "$RPC_URL" "$1" --output json |
+        python3 -c 'import json, sys; print(json.load(sys.stdin)["account"]["'"$2"'"])'
+}
+
+# 32-byte pubkey at OFFSET of the account data
+account_pubkey_at() {
+    solana account -u "$RPC_URL" "$1" --output json | python3 -c '
+import base64, json, sys
+alphabet = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+data = base64.b64decode(json.load(sys.stdin)["account"]["data"][0])
+raw = data['"$2"':'"$2"' + 32]
+n, out = int.from_bytes(raw, "big"), ""
+while n:
+    n, r = divmod(n, 58)
+    out = alphabet[r] + out
+print("1" * (len(raw) - len(raw.lstrip(b"\0"))) + out)'
+}
+
+dump_account() {
+    echo "account $1"
+    # Accounts the program creates on first use (e.g. a fresh creator vault)
+    # may not exist yet; the validator then creates them just like mainnet
+    solana account -u "$RPC_URL" "$1" --output json --output-file "$DIR/accounts/$1.json" \
+        >/dev/null 2>&1 || echo "  not found, skipped"
+}
+
+for program in "$PUMP" "$PUMP_FEES"; do
+    echo "program $program"
+    solana program dump -u "$RPC_URL" "$program" "$DIR/programs/$program.so"
+done
+
+GLOBAL=$(pda "$PUMP" string:global)
+TOKEN_PROGRAM=$(account_field "$MINT" owner)
+CURVE=$(pda "$PUMP" string:bonding-curve "pubkey:$MINT")
+CURVE_ATA=$(pda "$ATA_PROGRAM" "pubkey:$CURVE" "pubkey:$TOKEN_PROGRAM" "pubkey:$MINT")
+# Global: discriminator, initialized, authority, then fee_recipient
+FEE_RECIPIENT=$(account_pubkey_at "$GLOBAL" 41)
+# Bonding curve: discriminator, five u64 reserves, complete, then creator
+CREATOR=$(account_pubkey_at "$CURVE" 49)
+
+ACCOUNTS=(
+    "$GLOBAL"
+    "$FEE_RECIPIENT"
+    "$MINT"
+    "$CURVE"
+    "$CURVE_ATA"
+    "$(pda "$PUMP" string:creator-vault "pubkey:$CREATOR")"
+    "$(pda "$PUMP" string:__event_authority)"
+    "$(pda "$PUMP" string:global_volume_accumulator)"
+    "$(pda "$PUMP_FEES" string:fee_config "pubkey:$PUMP")"
+    "$@"
+)
+for account in "${ACCOUNTS[@]}"; do
+    dump_account "$account"
+done
+
+echo "$MINT" >"$DIR/pump_mint.txt"
+echo "fixtures written to $DIR"